@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use lz_compression::lzw::decompress_lzw;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = decompress_lzw(data);
+});
@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use lz_compression::bitstream::BitReader;
+use lz_compression::huffman::{HuffmanDecoder, HUFFMAN_MAX_SYMBOLS};
+
+fuzz_target!(|data: &[u8]| {
+    let mut reader = BitReader::new(data);
+    let mut decoder = HuffmanDecoder::new();
+    //Bad input has to come back as an error, and anything that does decode has to be in the alphabet
+    if let Ok(symbols) = decoder.decode_all(&mut reader) {
+        assert!(symbols.iter().all(|&symbol| (symbol as usize) < HUFFMAN_MAX_SYMBOLS), "Decoded a symbol outside the alphabet");
+    }
+});
@@ -0,0 +1,31 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use lz_compression::lz77::{lz77_decompress, LZ77Data, LZ77Encoded};
+
+/// Turns the fuzzer's bytes into literals and matches. Each piece of data
+/// starts with a flag byte: if it's even, the next byte is a literal, and if
+/// it's odd, the next 4 bytes are a big endian `u16` length and offset.
+fn parse_data(mut bytes: &[u8]) -> Vec<LZ77Data> {
+    let mut data = Vec::new();
+
+    while let Some((flag, rest)) = bytes.split_first() {
+        if flag & 1 == 0 {
+            let Some((byte, rest)) = rest.split_first() else { break; };
+            data.push(LZ77Data::Literal(*byte));
+            bytes = rest;
+        } else {
+            if rest.len() < 4 { break; }
+            let length = u16::from_be_bytes([rest[0], rest[1]]) as usize;
+            let offset = u16::from_be_bytes([rest[2], rest[3]]) as usize;
+            data.push(LZ77Data::Match(length, offset));
+            bytes = &rest[4..];
+        }
+    }
+
+    data
+}
+
+fuzz_target!(|data: &[u8]| {
+    let _ = lz77_decompress(LZ77Encoded::new(parse_data(data)));
+});
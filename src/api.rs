@@ -0,0 +1,236 @@
+use core::fmt;
+use core::str::FromStr;
+use crate::bitstream::{BitWriter, BitReader};
+use crate::deflate::{deflate_compress, deflate_decompress};
+use crate::error::DecodeError;
+use crate::huffman::{compress_huffman, decompress_huffman, HUFFMAN_DEFAULT_CHUNK_SIZE};
+use crate::lz77::{LZ77Encoded, lz77_compress_simple, lz77_decompress, LZ77_DEFAULT_WINDOW_SIZE};
+use crate::lzw::{compress_lzw, decompress_lzw};
+use alloc::{vec::Vec, boxed::Box, string::String, format};
+
+pub(crate) const LZ77_DEFAULT_MIN_MATCH_LEN:usize = 3;
+pub(crate) const LZ77_DEFAULT_MAX_MATCH_LEN:usize = 256;
+pub(crate) const LZ77_DEFAULT_MAX_CHAIN_LEN:usize = 16;
+
+/// All the codecs, for when you just want to compress something and don't
+/// care about the details. The values are the ids containers use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    /// `compress_huffman`, with chunks of `HUFFMAN_DEFAULT_CHUNK_SIZE`
+    Huffman = 0,
+    /// `compress_lzw`
+    Lzw = 1,
+    /// `lz77_compress_simple` written with `LZ77Encoded::write`, with a window
+    /// size of `LZ77_DEFAULT_WINDOW_SIZE`
+    Lz77 = 2,
+    /// `deflate_compress`
+    Deflate = 3
+}
+
+impl Codec {
+    pub const ALL:[Codec; 4] = [Codec::Huffman, Codec::Lzw, Codec::Lz77, Codec::Deflate];
+
+    /// The codec with its default parameters, as something that can compress
+    /// and decompress.
+    pub fn boxed(&self) -> Box<dyn CompressDecompress> {
+        match self {
+            Codec::Huffman => Box::new(Huffman::default()),
+            Codec::Lzw => Box::new(Lzw),
+            Codec::Lz77 => Box::new(Lz77::default()),
+            Codec::Deflate => Box::new(Deflate)
+        }
+    }
+
+    /// The lowercase name, which is also what `from_str` takes.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Codec::Huffman => "huffman",
+            Codec::Lzw => "lzw",
+            Codec::Lz77 => "lz77",
+            Codec::Deflate => "deflate"
+        }
+    }
+}
+
+impl fmt::Display for Codec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Codec {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Codec::ALL
+            .into_iter()
+            .find(|codec| codec.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("Unknown codec [{name}], expected one of: huffman, lzw, lz77, deflate"))
+    }
+}
+
+/// Anything that can compress bytes, so code can be written for all the
+/// codecs at once (or a `Vec<Box<dyn Compressor>>` of them).
+pub trait Compressor {
+    fn compress(&self, input: &[u8]) -> Vec<u8>;
+}
+
+/// Anything that can decompress what its `Compressor` wrote.
+pub trait Decompressor {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>, DecodeError>;
+}
+
+/// Both at once, since `dyn Compressor + Decompressor` isn't allowed. It's
+/// implemented for everything that's both.
+pub trait CompressDecompress: Compressor + Decompressor {}
+
+impl<T: Compressor + Decompressor> CompressDecompress for T {}
+
+/// `compress_huffman` with chunks of `chunk_size`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Huffman {
+    pub chunk_size: usize
+}
+
+impl Default for Huffman {
+    fn default() -> Self {
+        Huffman { chunk_size: HUFFMAN_DEFAULT_CHUNK_SIZE }
+    }
+}
+
+impl Compressor for Huffman {
+    fn compress(&self, input: &[u8]) -> Vec<u8> {
+        compress_huffman(input, self.chunk_size)
+    }
+}
+
+impl Decompressor for Huffman {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        decompress_huffman(input)
+    }
+}
+
+/// `compress_lzw`, which doesn't have anything to set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Lzw;
+
+impl Compressor for Lzw {
+    fn compress(&self, input: &[u8]) -> Vec<u8> {
+        compress_lzw(input)
+    }
+}
+
+impl Decompressor for Lzw {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        Ok(decompress_lzw(input)?)
+    }
+}
+
+/// `lz77_compress_simple` written with `LZ77Encoded::write`. Decompressing
+/// needs the same `window_size` that was used to compress.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lz77 {
+    pub window_size: usize,
+    pub min_match_len: usize,
+    pub max_match_len: usize,
+    pub max_chain_len: usize
+}
+
+impl Default for Lz77 {
+    fn default() -> Self {
+        Lz77 {
+            window_size: LZ77_DEFAULT_WINDOW_SIZE,
+            min_match_len: LZ77_DEFAULT_MIN_MATCH_LEN,
+            max_match_len: LZ77_DEFAULT_MAX_MATCH_LEN,
+            max_chain_len: LZ77_DEFAULT_MAX_CHAIN_LEN
+        }
+    }
+}
+
+impl Compressor for Lz77 {
+    fn compress(&self, input: &[u8]) -> Vec<u8> {
+        let mut writer = BitWriter::new();
+        lz77_compress_simple(input, self.window_size, self.min_match_len, self.max_match_len, self.max_chain_len, self.max_match_len).write(&mut writer, self.window_size);
+        writer.get_bytes()
+    }
+}
+
+impl Decompressor for Lz77 {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        lz77_decompress(LZ77Encoded::read(&mut BitReader::new(input), self.window_size)?)
+    }
+}
+
+/// `deflate_compress`, which doesn't have anything to set either.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Deflate;
+
+impl Compressor for Deflate {
+    fn compress(&self, input: &[u8]) -> Vec<u8> {
+        deflate_compress(input)
+    }
+}
+
+impl Decompressor for Deflate {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        Ok(deflate_decompress(input)?)
+    }
+}
+
+/// Compresses bytes with a codec, using sane defaults for everything else.
+pub fn compress(codec: Codec, data: &[u8]) -> Vec<u8> {
+    codec.boxed().compress(data)
+}
+
+/// Decompresses bytes compressed with `compress` using the same codec.
+/// Malformed or cut off input gives an error instead.
+pub fn decompress(codec: Codec, data: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    codec.boxed().decompress(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::{compress, decompress, Codec};
+
+    #[test]
+    fn api_test() {
+        use std::{fs, time};
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+
+        for codec in Codec::ALL {
+            let start_time = time::Instant::now();
+            let compressed = compress(codec, &bytes);
+            println!("{codec:?} Compressed size: [{}] Time: [{:?}]", compressed.len(), start_time.elapsed());
+
+            assert!(compressed.len() < bytes.len(), "{codec:?} didn't compress lorem_ipsum at all");
+            assert!(decompress(codec, &compressed) == Ok(bytes.clone()), "Bytes different after compressing and decompressing with {codec:?}");
+            assert!(decompress(codec, &compress(codec, &[])) == Ok(Vec::new()), "Nothing wasn't empty after compressing and decompressing with {codec:?}");
+            assert!(codec.name().parse() == Ok(codec) && codec.name().to_uppercase().parse() == Ok(codec), "{codec:?} couldn't be parsed from its name");
+        }
+        assert!("zip".parse::<Codec>().is_err(), "Unknown codec was parsed");
+    }
+
+    #[test]
+    fn compressor_trait_test() {
+        use crate::api::{CompressDecompress, Huffman, Lzw, Lz77, Deflate};
+        use std::fs;
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+
+        let codecs:Vec<Box<dyn CompressDecompress>> = vec![
+            Box::new(Huffman { chunk_size: 1 << 12 }),
+            Box::new(Lzw),
+            Box::new(Lz77 { window_size: 1 << 12, min_match_len: 4, max_match_len: 64, max_chain_len: 4 }),
+            Box::new(Deflate),
+            Codec::Lz77.boxed()
+        ];
+        for (i, codec) in codecs.iter().enumerate() {
+            let compressed = codec.compress(&bytes);
+            println!("Compressor [{i}] Compressed size: [{}]", compressed.len());
+            assert!(compressed.len() < bytes.len(), "Compressor [{i}] didn't compress lorem_ipsum at all");
+            assert!(codec.decompress(&compressed) == Ok(bytes.clone()), "Bytes different after compressing and decompressing with compressor [{i}]");
+            assert!(codec.decompress(&codec.compress(&[])) == Ok(Vec::new()), "Nothing wasn't empty after compressing and decompressing with compressor [{i}]");
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use crate::api::Codec;
+use crate::checksum::Checksum;
+use crate::container::compress_and_pack_with_checksum;
+
+/// The extension added to compressed files.
+pub const COMPRESSED_EXTENSION:&str = "lz";
+
+/// Where the compressed version of a file goes, which is just the same path
+/// with `.lz` tacked on the end (so `file.ext` becomes `file.ext.lz`).
+pub fn compressed_path(path: &Path) -> PathBuf {
+    let mut compressed = path.as_os_str().to_owned();
+    compressed.push(".");
+    compressed.push(COMPRESSED_EXTENSION);
+    PathBuf::from(compressed)
+}
+
+/// Compresses a single file into a container next to it, returning the path
+/// of the compressed file.
+pub fn compress_file(path: &Path, codec: Codec, checksum: Checksum) -> io::Result<PathBuf> {
+    let bytes = fs::read(path)?;
+    let output_path = compressed_path(path);
+    fs::write(&output_path, compress_and_pack_with_checksum(codec, checksum, &bytes))?;
+
+    Ok(output_path)
+}
+
+/// Turns the given paths into a list of files to compress.
+///
+/// Directories are only allowed if `recursive` is set, in which case every
+/// file under them is added, skipping ones that are already compressed.
+pub fn collect_files(inputs: &[PathBuf], recursive: bool) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    for input in inputs {
+        if input.is_dir() {
+            if !recursive {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} is a directory, use --recursive to compress it", input.display())));
+            }
+            collect_dir(input, &mut files)?;
+        } else {
+            files.push(input.clone());
+        }
+    }
+
+    Ok(files)
+}
+
+fn collect_dir(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.path());
+
+    for entry in entries {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_dir(&path, files)?;
+        } else if path.extension().is_none_or(|ext| ext != COMPRESSED_EXTENSION) {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Compresses every file on its own, spread across `threads` threads (or as
+/// many as the machine has, if it's `0`). The results are in the same order
+/// as the files, so one file failing doesn't stop the rest.
+pub fn compress_files(files: &[PathBuf], codec: Codec, checksum: Checksum, threads: usize) -> Vec<io::Result<PathBuf>> {
+    let threads = if threads == 0 {thread::available_parallelism().map_or(1, |n| n.get())} else {threads};
+    let threads = threads.min(files.len()).max(1);
+
+    let next_file = AtomicUsize::new(0);
+    let results:Vec<Mutex<Option<io::Result<PathBuf>>>> = files.iter().map(|_| Mutex::new(None)).collect();
+
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| {
+                loop {
+                    let i = next_file.fetch_add(1, Ordering::Relaxed);
+                    if i >= files.len() {break;}
+                    *results[i].lock().unwrap() = Some(compress_file(&files[i], codec, checksum));
+                }
+            });
+        }
+    });
+
+    results
+        .into_iter()
+        .map(|result| result.into_inner().unwrap().expect("Every file gets compressed"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+    use crate::batch::{collect_files, compress_files, compressed_path};
+    use crate::api::Codec;
+    use crate::checksum::Checksum;
+    use crate::container::unpack;
+
+    #[test]
+    fn batch_compress_test() {
+        let dir = std::env::temp_dir().join(format!("lz_compression_batch_{}", std::process::id()));
+        let sub_dir = dir.join("sub");
+        fs::create_dir_all(&sub_dir).unwrap();
+
+        let contents = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let mut originals:Vec<(PathBuf, Vec<u8>)> = Vec::new();
+        for i in 0..6 {
+            let path = if i % 2 == 0 {dir.join(format!("file{i}.txt"))} else {sub_dir.join(format!("file{i}.txt"))};
+            let bytes = contents[..(i + 1) * 10000].to_vec();
+            fs::write(&path, &bytes).unwrap();
+            originals.push((path, bytes));
+        }
+
+        assert!(collect_files(std::slice::from_ref(&dir), false).is_err(), "Directory was accepted without recursive");
+        let files = collect_files(std::slice::from_ref(&dir), true).unwrap();
+        assert!(files.len() == originals.len(), "Found [{}] files instead of [{}]", files.len(), originals.len());
+
+        let results = compress_files(&files, Codec::Lzw, Checksum::Adler32, 3);
+        for (file, result) in files.iter().zip(results) {
+            assert!(result.as_ref().ok() == Some(&compressed_path(file)), "Compressing [{}] failed [{result:?}]", file.display());
+        }
+
+        for (path, bytes) in &originals {
+            let compressed = fs::read(compressed_path(path)).unwrap();
+            assert!(unpack(&compressed) == Ok((Codec::Lzw, bytes.clone())), "[{}] different after compressing and decompressing", path.display());
+        }
+
+        //Compressed files are skipped when collecting again
+        assert!(collect_files(std::slice::from_ref(&dir), true).unwrap().len() == originals.len(), "Compressed files were collected");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
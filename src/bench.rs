@@ -0,0 +1,109 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use crate::api::{compress, decompress, Codec};
+
+/// How one codec did on some bytes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchResult {
+    pub codec: Codec,
+    pub input_size: usize,
+    pub output_size: usize,
+    pub encode_time: Duration,
+    pub decode_time: Duration,
+    /// Whether decompressing gave back the original bytes. Anything else is a
+    /// bug, but a benchmark shouldn't `panic` over it.
+    pub roundtrip: bool
+}
+
+/// Megabytes (not mebibytes) per second, the same as the tests print.
+fn mb_per_sec(size: usize, time: Duration) -> f64 {
+    let secs = time.as_secs_f64();
+    if secs == 0.0 {0.0} else {size as f64 / 1_000_000.0 / secs}
+}
+
+impl BenchResult {
+    /// Input size over output size, so bigger is better.
+    pub fn ratio(&self) -> f64 {
+        if self.output_size == 0 {
+            return 0.0;
+        }
+        self.input_size as f64 / self.output_size as f64
+    }
+
+    /// Compression speed, in MB of input per second.
+    pub fn encode_mb_per_sec(&self) -> f64 {
+        mb_per_sec(self.input_size, self.encode_time)
+    }
+
+    /// Decompression speed, in MB of output per second.
+    pub fn decode_mb_per_sec(&self) -> f64 {
+        mb_per_sec(self.input_size, self.decode_time)
+    }
+
+    /// The header that goes above the rows `Display` prints.
+    pub fn table_header() -> String {
+        format!("{:<8} {:>12} {:>12} {:>8} {:>12} {:>12}", "Codec", "Input", "Output", "Ratio", "Encode MB/s", "Decode MB/s")
+    }
+}
+
+impl fmt::Display for BenchResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:<8} {:>12} {:>12} {:>8.3} {:>12.2} {:>12.2}", self.codec.name(), self.input_size, self.output_size, self.ratio(), self.encode_mb_per_sec(), self.decode_mb_per_sec())?;
+        if !self.roundtrip {
+            write!(f, " (roundtrip failed)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Compresses and decompresses `bytes` once with every codec, timing both.
+pub fn bench_bytes(bytes: &[u8]) -> Vec<BenchResult> {
+    Codec::ALL.into_iter().map(|codec| {
+        let start_time = Instant::now();
+        let compressed = compress(codec, bytes);
+        let encode_time = start_time.elapsed();
+
+        let start_time = Instant::now();
+        let decompressed = decompress(codec, &compressed);
+        let decode_time = start_time.elapsed();
+
+        BenchResult {
+            codec,
+            input_size: bytes.len(),
+            output_size: compressed.len(),
+            encode_time,
+            decode_time,
+            roundtrip: decompressed.as_deref() == Ok(bytes)
+        }
+    }).collect()
+}
+
+/// Benchmarks every codec on a file, see `bench_bytes`.
+pub fn run(path: &Path) -> io::Result<Vec<BenchResult>> {
+    Ok(bench_bytes(&fs::read(path)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::Codec;
+    use crate::bench::run;
+    use std::path::Path;
+
+    #[test]
+    fn bench_test() {
+        let results = run(Path::new("lorem_ipsum")).expect("File could not be opened and/or read");
+
+        println!("{}", crate::bench::BenchResult::table_header());
+        for result in &results {
+            println!("{result}");
+            assert!(result.roundtrip, "{} didn't roundtrip", result.codec);
+            assert!(result.ratio() > 1.0, "{} didn't compress lorem_ipsum at all [{}]", result.codec, result.ratio());
+        }
+        assert!(results.iter().map(|result| result.codec).eq(Codec::ALL), "Not every codec was benchmarked");
+
+        assert!(run(Path::new("not_a_file")).is_err(), "Benchmarking a file that doesn't exist worked");
+    }
+}
@@ -1,4 +1,6 @@
-use std::fmt::{self};
+use core::cmp::min;
+use core::fmt::{self};
+use alloc::{vec::Vec, borrow::Cow, string::String, format};
 
 const U64_MSB_MASK:u64 = 1 << 63;
 
@@ -8,12 +10,41 @@ pub struct BitWriter {
     bytes:Vec<u8>
 }
 
+/// Reads bits written by `BitWriter`, most significant bit first. It either
+/// borrows the bytes (`new`) or owns them (`from_vec`), and since an owned
+/// reader is a `BitReader<'static>`, it can be returned from a function
+/// without keeping the bytes alive somewhere else.
 pub struct BitReader<'a> {
     buffer:u64,
+    total_bits: usize,
     remaining_bits: usize,
     bits_in_buffer:usize,
     unused_bits_in_buffer:usize,
-    bytes:&'a [u8]
+    bytes:Cow<'a, [u8]>,
+    /// The next byte of `bytes` that goes into the buffer.
+    byte_pos:usize
+}
+
+#[cfg(feature = "std")]
+/// Estimates the best `k` for Rice coding `values`, going by their mean.
+/// 
+/// For geometrically distributed values (which is what Rice codes are made
+/// for) the best `k` can be worked out from the mean alone (Kiely's formula,
+/// `1 + log2(ln(golden ratio - 1) / ln(mean / (mean + 1)))` rounded down), so
+/// this doesn't need to try every `k`. Values that aren't geometric might do
+/// a bit better with a different `k`. An empty slice gives `0`.
+pub fn rice_parameter(values: &[u32]) -> usize {
+    if values.is_empty() {
+        return 0;
+    }
+
+    let mean = values.iter().map(|&value| value as f64).sum::<f64>() / values.len() as f64;
+    if mean == 0.0 {
+        return 0;
+    }
+    let golden_ratio = (1.0 + 5.0f64.sqrt()) / 2.0;
+    let k = 1.0 + ((golden_ratio - 1.0).ln() / (mean / (mean + 1.0)).ln()).log2().floor();
+    k.clamp(0.0, 31.0) as usize
 }
 
 impl fmt::Display for BitWriter {
@@ -38,6 +69,13 @@ impl fmt::Display for BitWriter {
     }
 }
 
+impl BitReader<'static> {
+    /// Same as `new`, but the reader owns the bytes.
+    pub fn from_vec(bytes: Vec<u8>) -> Self {
+        BitReader::from_cow(Cow::Owned(bytes))
+    }
+}
+
 impl<'a> Iterator for BitReader<'a>{
     type Item = bool;
 
@@ -48,7 +86,11 @@ impl<'a> Iterator for BitReader<'a>{
 
 impl<'a> BitReader<'a>{
     pub fn new(bytes: &'a [u8]) -> Self {
-        let mut br = BitReader { buffer: 0, remaining_bits: bytes.len() << 3, bits_in_buffer:0, unused_bits_in_buffer:64, bytes: bytes };
+        BitReader::from_cow(Cow::Borrowed(bytes))
+    }
+
+    fn from_cow(bytes: Cow<'a, [u8]>) -> Self {
+        let mut br = BitReader { buffer: 0, total_bits: bytes.len() << 3, remaining_bits: bytes.len() << 3, bits_in_buffer:0, unused_bits_in_buffer:64, bytes, byte_pos: 0 };
         br.refill();
 
         br
@@ -58,10 +100,31 @@ impl<'a> BitReader<'a>{
         self.remaining_bits
     }
 
+    /// The number of bits read (or emptied) since the reader was made.
+    pub fn bits_consumed(&self) -> usize {
+        self.total_bits - self.remaining_bits
+    }
+
+    /// Fills the buffer with as many whole bytes as fit. With at least `8`
+    /// bytes left, they're all loaded as one big endian `u64` and whatever
+    /// doesn't fit is cut off, otherwise it goes a byte at a time.
     fn refill(&mut self) {
-        while self.unused_bits_in_buffer >= 8 && self.bytes.len() > 0{
-            let byte = self.bytes[0];
-            self.bytes = &self.bytes[1..];
+        if self.unused_bits_in_buffer >= 8 && self.byte_pos + 8 <= self.bytes.len() {
+            let byte_num = self.unused_bits_in_buffer >> 3;
+            let filled_bits = self.bits_in_buffer + (byte_num << 3);
+            let word = u64::from_be_bytes(self.bytes[self.byte_pos..(self.byte_pos + 8)].try_into().unwrap());
+            let mask = if filled_bits == 64 {u64::MAX} else {!(u64::MAX >> filled_bits)};
+
+            self.buffer |= (word >> self.bits_in_buffer) & mask;
+            self.byte_pos += byte_num;
+            self.bits_in_buffer = filled_bits;
+            self.unused_bits_in_buffer -= byte_num << 3;
+            return;
+        }
+
+        while self.unused_bits_in_buffer >= 8 && self.byte_pos < self.bytes.len(){
+            let byte = self.bytes[self.byte_pos];
+            self.byte_pos += 1;
             self.bits_in_buffer += 8;
             self.unused_bits_in_buffer -= 8;
             self.buffer |= (byte as u64) << self.unused_bits_in_buffer;
@@ -69,9 +132,11 @@ impl<'a> BitReader<'a>{
         //println!("Bits in buffer: {}", self.bits_in_buffer);
     }
 
+    #[allow(dead_code)]
+    #[cfg(feature = "std")]
     fn print_buffer(&self) {
         let mut mask:u64 = 1 << 63;
-        for i in 0..self.bits_in_buffer{
+        for _ in 0..self.bits_in_buffer{
             print!("{}", if mask & self.buffer > 0 {1} else {0});
             mask >>= 1;
         }
@@ -98,7 +163,7 @@ impl<'a> BitReader<'a>{
     pub fn read_bits<T>(&mut self, bit_num:usize) -> Option<T> 
     where
     T: From<u64>{
-        let max_bits = std::mem::size_of::<T>() << 3;
+        let max_bits = core::mem::size_of::<T>() << 3;
         assert!(bit_num <= max_bits, "Can only read up to [{max_bits}] bits, attempted to read [{bit_num}] bits");
 
         if self.remaining_bits == 0 {
@@ -177,6 +242,10 @@ impl<'a> BitReader<'a>{
         Some(bits)
     }
 
+    /// Reads `bit_num` bits, most significant first. If there are fewer than
+    /// `bit_num` left, it reads all of them and they come back right aligned,
+    /// as if only that many were asked for, so the value can be smaller than
+    /// expected. Returns `None` once there's nothing left.
     pub fn read_bits_into_u32(&mut self, bit_num:usize) -> Option<u32> {
 
         assert!(bit_num <= 32, "Can only read up to 32 bits, attempted to read [{bit_num}] bits");
@@ -206,24 +275,159 @@ impl<'a> BitReader<'a>{
         Some(bits)
     }
 
-    pub fn empty_bits(&mut self, bit_num:usize){
-        
+    /// Same as `read_bits_into_u32`, but returns `None` (without reading
+    /// anything) if there aren't `bit_num` bits left, instead of the fewer
+    /// bits that are. Handy for headers, where a short value is just wrong.
+    pub fn read_bits_into_u32_exact(&mut self, bit_num:usize) -> Option<u32> {
         if bit_num > self.remaining_bits {
-            self.empty_bits(self.remaining_bits);
+            return None;
         }
-        if bit_num > self.bits_in_buffer{
-            self.empty_bits(self.bits_in_buffer);
-            self.empty_bits(bit_num - self.bits_in_buffer);
+        self.read_bits_into_u32(bit_num)
+    }
+
+    /// Reads a signed value written with `BitWriter::write_signed_bits`,
+    /// sign-extending it from bit `bit_num - 1`.
+    /// 
+    /// Unlike the unsigned reads, this returns `None` (without reading
+    /// anything) if there aren't `bit_num` bits left, since sign-extending
+    /// fewer bits would give the wrong value.
+    pub fn read_signed_bits(&mut self, bit_num:usize) -> Option<i32> {
+        assert!(bit_num <= 32, "Can only read up to 32 bits, attempted to read [{bit_num}] bits");
+
+        if bit_num > self.remaining_bits {
+            return None;
+        } else if bit_num == 0 {
+            return Some(0);
         }
 
-        self.buffer <<= bit_num;
-        self.bits_in_buffer -= bit_num;
-        self.unused_bits_in_buffer += bit_num;
-        self.remaining_bits -= bit_num;
+        //Shifting the sign bit up to the top and back down copies it into every bit above it
+        let bits = self.read_bits_into_u32(bit_num)?;
+        Some(((bits << (32 - bit_num)) as i32) >> (32 - bit_num))
+    }
+
+    /// Reads a number written with `BitWriter::write_elias_gamma`. Returns
+    /// `None` if the reader runs out or there are more zeros than a `u32`
+    /// could need, in which case some bits may have been read.
+    pub fn read_elias_gamma(&mut self) -> Option<u32> {
+        let mut zeros = 0;
+        while !self.read_bit()? {
+            zeros += 1;
+            if zeros > 31 {
+                return None;
+            }
+        }
+        self.read_elias_rest(zeros)
+    }
+
+    /// Reads a number written with `BitWriter::write_elias_delta`, returning
+    /// `None` under the same conditions as `read_elias_gamma`.
+    pub fn read_elias_delta(&mut self) -> Option<u32> {
+        let len = self.read_elias_gamma()? as usize;
+        if len > 32 {
+            return None;
+        }
+        self.read_elias_rest(len - 1)
+    }
+
+    /// Reads a value written with `BitWriter::write_rice` with the same `k`.
+    /// Returns `None` if the reader runs out, or the quotient is too big for
+    /// a `u32`, in which case some bits may have been read.
+    pub fn read_rice(&mut self, k: usize) -> Option<u32> {
+        assert!(k <= 32, "Rice parameter can be at most [32], given [{k}]");
+
+        let max_quotient = u32::MAX as u64 >> k;
+        let mut quotient:u64 = 0;
+        while self.read_bit()? {
+            quotient += 1;
+            if quotient > max_quotient {
+                return None;
+            }
+        }
+
+        if k > self.remaining_bits {
+            return None;
+        }
+        let remainder = self.read_bits_into_u32(k)? as u64;
+        u32::try_from((quotient << k) | remainder).ok()
+    }
+
+    /// Reads the `bit_num` bits after the leading `1` of an Elias code.
+    fn read_elias_rest(&mut self, bit_num:usize) -> Option<u32> {
+        if bit_num == 0 {
+            return Some(1);
+        } else if bit_num > self.remaining_bits {
+            return None;
+        }
+        Some((1 << bit_num) | self.read_bits_into_u32(bit_num)?)
+    }
+
+    /// Throws away the next `bit_num` bits, or all of the remaining bits if
+    /// there aren't that many left.
+    /// 
+    /// At most `bits_in_buffer` bits are emptied at once, with a refill in
+    /// between, so this works for any amount.
+    pub fn empty_bits(&mut self, bit_num:usize){
+        let mut bit_num = min(bit_num, self.remaining_bits);
+
+        while bit_num > 0 {
+            let bits_to_empty = min(bit_num, self.bits_in_buffer);
+            if bits_to_empty == 0 {
+                break;
+            }
+
+            //Shifting a u64 by 64 overflows
+            self.buffer = if bits_to_empty == 64 {0} else {self.buffer << bits_to_empty};
+            self.bits_in_buffer -= bits_to_empty;
+            self.unused_bits_in_buffer += bits_to_empty;
+            self.remaining_bits -= bits_to_empty;
+            bit_num -= bits_to_empty;
+
+            self.refill();
+        }
+    }
+
+    /// Skips the bits left in the current byte, so the next read starts on a
+    /// byte boundary.
+    pub fn align_to_byte(&mut self) {
+        let bit_num = self.remaining_bits & 0b111;
+        self.empty_bits(bit_num);
+    }
+
+    /// Copies whole bytes into `buf`, returning how many were copied (fewer
+    /// than `buf.len()` if the reader runs out). Returns `None` if the reader
+    /// isn't on a byte boundary, so `align_to_byte` first.
+    ///
+    /// Only the few bytes already in the buffer get read one at a time, the
+    /// rest are copied straight out of the underlying slice.
+    pub fn read_bytes_aligned(&mut self, buf: &mut [u8]) -> Option<usize> {
+        if self.remaining_bits & 0b111 != 0 {
+            return None;
+        }
+
+        let byte_num = min(buf.len(), self.remaining_bits >> 3);
+        let buffered_byte_num = min(byte_num, self.bits_in_buffer >> 3);
+        for byte in &mut buf[..buffered_byte_num] {
+            *byte = (self.buffer >> 56) as u8;
+            self.buffer <<= 8;
+        }
+        self.bits_in_buffer -= buffered_byte_num << 3;
+        self.unused_bits_in_buffer += buffered_byte_num << 3;
 
+        //Anything past the buffer only gets here once it's empty
+        let direct_byte_num = byte_num - buffered_byte_num;
+        buf[buffered_byte_num..byte_num].copy_from_slice(&self.bytes[self.byte_pos..(self.byte_pos + direct_byte_num)]);
+        self.byte_pos += direct_byte_num;
+
+        self.remaining_bits -= byte_num << 3;
         self.refill();
+
+        Some(byte_num)
     }
 
+    /// Same as `read_bits_into_u32`, except that if there are fewer than
+    /// `bit_num` bits left, they get shifted up to where they would've been
+    /// and the missing low bits are `0`, which is what a code that got cut
+    /// off by the end of the stream has to be compared against.
     pub fn read_bits_into_u32_with_shift(&mut self, bit_num:usize) -> Option<u32> {
         assert!(bit_num <= 32, "Can only read up to 32 bits, attempted to read [{bit_num}] bits");
 
@@ -241,6 +445,7 @@ impl<'a> BitReader<'a>{
         self.buffer <<= bit_num;
         self.bits_in_buffer -= bit_num;
         self.unused_bits_in_buffer += bit_num;
+        self.remaining_bits -= bit_num;
 
         //print!("Before refill: ");
         //self.print_buffer();
@@ -253,6 +458,8 @@ impl<'a> BitReader<'a>{
         Some(bits)
     }
 
+    /// Same as `read_bits_into_u32` without reading anything, so fewer than
+    /// `bit_num` bits at the end come back right aligned too.
     pub fn peek_bits_into_u32(&self, bit_num:usize) -> Option<u32> {
 
         assert!(bit_num <= 32, "Can only read up to 32 bits, attempted to read [{}] bits", bit_num);
@@ -268,6 +475,10 @@ impl<'a> BitReader<'a>{
         Some((self.buffer >> (64 - bit_num)) as u32)
     }
 
+    /// Same as `read_bits_into_u32_with_shift` without reading anything. This
+    /// is the one Huffman decoding peeks paths with, so near the end of the
+    /// stream the path is padded with zeros, and the decoder has to check
+    /// that the code it lands on isn't longer than `remaining_bits`.
     pub fn peek_bits_into_u32_with_shift(&self, bit_num:usize) -> Option<u32> {
         assert!(bit_num <= 32, "Can only read up to 32 bits, attempted to read [{}] bits", bit_num);
 
@@ -286,6 +497,12 @@ impl<'a> BitReader<'a>{
 
 }
 
+impl Default for BitWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl BitWriter {
     pub fn new() -> Self{
         BitWriter { bits_written_to_buffer: 0, buffer:0, bytes: Vec::new()}
@@ -295,6 +512,39 @@ impl BitWriter {
         (self.bytes.len() << 3) + self.bits_written_to_buffer
     }
 
+    /// Where the next bit will be written, for remembering a spot to
+    /// `patch_bits_u32` later. Same as `total_bits_written`.
+    pub fn bit_position(&self) -> usize {
+        self.total_bits_written()
+    }
+
+    /// Overwrites `bit_num` bits starting at `bit_position` with `data`, for
+    /// fields (like a length) that can't be known until after what comes
+    /// after them has been written. Just write a placeholder of the same size
+    /// first, then patch it.
+    /// 
+    /// The patched bits have to have been written already, whether they've
+    /// made it out to the bytes or are still in the buffer, so this `panic`s
+    /// if they go past `bit_position()`. Positions from before a
+    /// `take_bytes` don't line up anymore.
+    pub fn patch_bits_u32(&mut self, bit_position: usize, data: u32, bit_num: usize) {
+        assert!(bit_num <= 32, "Number of bits must less than 32, given [{}] bits", bit_num);
+        assert!(bit_position + bit_num <= self.total_bits_written(), "Can't patch bits [{bit_position}..{}] when only [{}] bits were written", bit_position + bit_num, self.total_bits_written());
+
+        let flushed_bits = self.bytes.len() << 3;
+        for i in 0..bit_num {
+            let bit = (data >> (bit_num - 1 - i)) & 1;
+            let pos = bit_position + i;
+            if pos < flushed_bits {
+                let mask = 1 << (7 - (pos & 0b111));
+                self.bytes[pos >> 3] = if bit == 1 {self.bytes[pos >> 3] | mask} else {self.bytes[pos >> 3] & !mask};
+            } else {
+                let mask = U64_MSB_MASK >> (pos - flushed_bits);
+                self.buffer = if bit == 1 {self.buffer | mask} else {self.buffer & !mask};
+            }
+        }
+    }
+
     fn flush(&mut self) {
         while self.bits_written_to_buffer >= 8{
             self.bytes.push( (self.buffer>>56) as u8);
@@ -305,7 +555,13 @@ impl BitWriter {
 
     pub fn write_bits_u16(&mut self, data: u16, bit_num:usize){
         assert!(bit_num <= 16, "Number of bits must less than 32, given [{}] bits", bit_num);
+        if bit_num == 0 {
+            return;
+        }
         
+        //flush() leaves less than a byte in the buffer, so there's always room
+        //for 16 more bits and the shift below can't go negative
+        debug_assert!(self.bits_written_to_buffer < 8, "Buffer wasn't flushed before writing, [{}] bits still in it", self.bits_written_to_buffer);
         let mask = if bit_num == 16 {u16::MAX} else {(1 << bit_num) - 1};
         self.buffer |= ((data & mask) as u64) << (64 - self.bits_written_to_buffer - bit_num);
         self.bits_written_to_buffer += bit_num;
@@ -313,13 +569,131 @@ impl BitWriter {
     }
     pub fn write_bits_u32(&mut self, data: u32, bit_num:usize){
         assert!(bit_num <= 32, "Number of bits must less than 32, given [{}] bits", bit_num);
+        if bit_num == 0 {
+            return;
+        }
         
+        //flush() leaves less than a byte in the buffer, so there's always room
+        //for 32 more bits and the shift below can't go negative
+        debug_assert!(self.bits_written_to_buffer < 8, "Buffer wasn't flushed before writing, [{}] bits still in it", self.bits_written_to_buffer);
         let mask = if bit_num == 32 {u32::MAX} else {(1 << bit_num) - 1};
         self.buffer |= ((data & mask) as u64) << (64 - self.bits_written_to_buffer - bit_num);
         self.bits_written_to_buffer += bit_num;
         self.flush();
     }
 
+    /// Writes a signed value as a `bit_num` bit two's complement number, by
+    /// just writing its lowest `bit_num` bits. Values that don't fit in
+    /// `bit_num` bits lose their top bits, same as the unsigned writes.
+    pub fn write_signed_bits(&mut self, value: i32, bit_num:usize){
+        self.write_bits_u32(value as u32, bit_num);
+    }
+
+    /// Writes `value` as a Golomb-Rice code with parameter `k`: the quotient
+    /// `value >> k` in unary (that many `1`s, then a `0`), then the bottom `k`
+    /// bits. Great for small, geometrically distributed values like the output
+    /// of a delta filter, as long as `k` fits them, see `rice_parameter`.
+    /// 
+    /// A `k` that's way too small makes for a lot of `1`s, so be careful.
+    pub fn write_rice(&mut self, value: u32, k: usize){
+        assert!(k <= 32, "Rice parameter can be at most [32], given [{k}]");
+
+        let mut quotient = (value as u64) >> k;
+        while quotient > 0 {
+            let ones = min(quotient, 32) as usize;
+            self.write_bits_u32(u32::MAX, ones);
+            quotient -= ones as u64;
+        }
+        self.write_bits_u16(0, 1);
+        self.write_bits_u32(value, k);
+    }
+
+    /// Writes `n` as an Elias gamma code: as many zeros as `n` has bits after
+    /// its top one, then `n` itself. Small numbers take very few bits (`1` is
+    /// just `1`), and there's no maximum to pick like with a fixed width.
+    /// 
+    /// `panic`s if `n` is `0`, since it has no top bit.
+    pub fn write_elias_gamma(&mut self, n: u32){
+        assert!(n >= 1, "Elias gamma codes start at 1, given [{n}]");
+
+        let bit_num = (u32::BITS - n.leading_zeros()) as usize;
+        self.write_bits_u32(0, bit_num - 1);
+        self.write_bits_u32(n, bit_num);
+    }
+
+    /// Writes `n` as an Elias delta code: the number of bits in `n` as a gamma
+    /// code, then `n` without its top bit. Takes a few more bits than gamma
+    /// for small numbers, but a lot fewer for big ones.
+    /// 
+    /// `panic`s if `n` is `0`, same as `write_elias_gamma`.
+    pub fn write_elias_delta(&mut self, n: u32){
+        assert!(n >= 1, "Elias delta codes start at 1, given [{n}]");
+
+        let bit_num = (u32::BITS - n.leading_zeros()) as usize;
+        self.write_elias_gamma(bit_num as u32);
+        self.write_bits_u32(n, bit_num - 1);
+    }
+
+    /// Writes up to 64 bits, by splitting them into two `u32` writes if needed.
+    pub fn write_bits_u64(&mut self, data: u64, bit_num:usize){
+        assert!(bit_num <= 64, "Number of bits must less than 64, given [{}] bits", bit_num);
+
+        if bit_num > 32 {
+            self.write_bits_u32((data >> 32) as u32, bit_num - 32);
+            self.write_bits_u32(data as u32, 32);
+        } else {
+            self.write_bits_u32(data as u32, bit_num);
+        }
+    }
+
+    /// Writes everything `other` has written onto the end of this writer, as
+    /// if it had been written here in the first place. Handy for splicing
+    /// together streams that were written separately, like one per thread.
+    /// 
+    /// If this writer is on a byte boundary, `other`'s bytes are just copied
+    /// over. Otherwise every byte gets shifted to line up with the bits
+    /// already in the buffer, which is slower.
+    pub fn append(&mut self, other: &BitWriter) {
+        if self.bits_written_to_buffer == 0 {
+            self.bytes.extend_from_slice(&other.bytes);
+        } else {
+            for byte in &other.bytes {
+                self.write_bits_u16(*byte as u16, 8);
+            }
+        }
+
+        if other.bits_written_to_buffer > 0 {
+            let bit_num = other.bits_written_to_buffer;
+            self.write_bits_u16((other.buffer >> (64 - bit_num)) as u16, bit_num);
+        }
+    }
+
+    /// Pads the current byte with zeros, so the next write starts on a byte
+    /// boundary.
+    pub fn align_to_byte(&mut self) {
+        if self.bits_written_to_buffer & 0b111 != 0 {
+            self.bits_written_to_buffer += 8 - (self.bits_written_to_buffer & 0b111);
+            self.flush();
+        }
+    }
+
+    /// Throws away everything written so far, so the writer can be reused
+    /// without having to grow a new `Vec` all over again.
+    pub fn clear(&mut self) {
+        self.bits_written_to_buffer = 0;
+        self.buffer = 0;
+        self.bytes.clear();
+    }
+
+    /// Takes out every full byte written so far, leaving only the bits that
+    /// don't make a full byte yet. Handy for passing bytes along as they're
+    /// written, instead of keeping all of them around.
+    /// 
+    /// Note that `total_bits_written` only counts what's left afterwards.
+    pub fn take_bytes(&mut self) -> Vec<u8> {
+        core::mem::take(&mut self.bytes)
+    }
+
     pub fn get_bytes(&self) -> Vec<u8> {
         let mut bytes = self.bytes.clone();
         if self.bits_written_to_buffer > 0 {
@@ -342,7 +716,6 @@ mod tests {
         let val_num = 8192;
         let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(2123);
 
-        let mut bit_num:usize = 0;
         let mut vals:Vec<u32> = Vec::with_capacity(val_num);
         let mut val_sizes:Vec<usize> = Vec::with_capacity(val_num);
         for _ in 0..val_num{
@@ -365,4 +738,511 @@ mod tests {
             assert!(read_val == vals[i], "Val at position [{i}] was read/written incorrectly, {read_val} -> {}",vals[i]);
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn empty_bits_test() {
+        use rand::prelude::*;
+
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(4567);
+        let bytes:Vec<u8> = (0..64).map(|_| rng.gen::<u8>()).collect();
+        let bit_at = |pos:usize| (bytes[pos >> 3] >> (7 - (pos & 0b111))) & 1;
+        let bits_at = |pos:usize, bit_num:usize| (pos..(pos + bit_num)).fold(0u32, |bits, i| (bits << 1) | bit_at(i) as u32);
+
+        //Amounts that straddle the edge of the 64 bit buffer, and one that's exactly the whole buffer
+        let mut reader = BitReader::new(&bytes);
+        let mut pos = 0;
+        for (empty_num, read_num) in [(3, 5), (62, 9), (64, 1), (1, 32), (70, 7), (0, 3), (129, 16)] {
+            reader.empty_bits(empty_num);
+            pos += empty_num;
+            assert!(reader.remaining_bits() == (bytes.len() << 3) - pos, "Wrong number of remaining bits after emptying [{empty_num}] bits");
+            let read_val = reader.read_bits_into_u32(read_num).unwrap();
+            assert!(read_val == bits_at(pos, read_num), "Wrong bits read at position [{pos}] after emptying [{empty_num}] bits, [{read_val:b}] instead of [{:b}]", bits_at(pos, read_num));
+            pos += read_num;
+        }
+
+        //Emptying more bits than are left empties everything
+        reader.empty_bits(10000);
+        assert!(reader.remaining_bits() == 0, "Bits left after emptying more than there were");
+        assert!(reader.read_bit().is_none(), "Bit read after emptying everything");
+        reader.empty_bits(1);
+        assert!(reader.remaining_bits() == 0, "Emptying with nothing left changed the remaining bits");
+
+        //Emptying everything in one go, from the very start
+        let mut reader = BitReader::new(&bytes);
+        reader.empty_bits(bytes.len() << 3);
+        assert!(reader.remaining_bits() == 0, "Bits left after emptying exactly all of them");
+    }
+
+    #[test]
+    fn write_wide_bits_test() {
+        use rand::prelude::*;
+
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(8910);
+
+        //Full 32 bit writes starting at every offset within a byte, one after
+        //the other, so the buffer never lines up with a byte boundary
+        for offset in 0..8 {
+            let vals:Vec<u32> = (0..4096).map(|_| rng.gen::<u32>()).collect();
+
+            let mut writer = BitWriter::new();
+            writer.write_bits_u32(u32::MAX, offset);
+            for val in &vals {
+                writer.write_bits_u32(*val, 32);
+                writer.write_bits_u16(0b101, 3);
+            }
+            assert!(writer.total_bits_written() == offset + vals.len() * 35, "Wrong number of bits written at offset [{offset}]");
+            let bytes = writer.get_bytes();
+
+            let mut reader = BitReader::new(&bytes);
+            reader.empty_bits(offset);
+            for (i, val) in vals.iter().enumerate() {
+                let read_val = reader.read_bits_into_u32(32).unwrap();
+                assert!(read_val == *val, "32 bit val [{i}] at offset [{offset}] was read/written incorrectly, {read_val} -> {val}");
+                assert!(reader.read_bits_into_u16(3).unwrap() == 0b101, "Bits after 32 bit val [{i}] at offset [{offset}] were read/written incorrectly");
+            }
+        }
+
+        //Same thing with 64 bit writes, which get split in two
+        let vals:Vec<u64> = (0..4096).map(|_| rng.gen::<u64>()).collect();
+        let mut writer = BitWriter::new();
+        for val in &vals {
+            writer.write_bits_u16(1, 1);
+            writer.write_bits_u64(*val, 64);
+        }
+        let bytes = writer.get_bytes();
+
+        let mut reader = BitReader::new(&bytes);
+        for (i, val) in vals.iter().enumerate() {
+            assert!(reader.read_bit() == Some(true), "Bit before 64 bit val [{i}] was read/written incorrectly");
+            let read_val = (reader.read_bits_into_u32(32).unwrap() as u64) << 32 | reader.read_bits_into_u32(32).unwrap() as u64;
+            assert!(read_val == *val, "64 bit val [{i}] was read/written incorrectly, {read_val} -> {val}");
+        }
+    }
+
+    #[test]
+    fn clear_test() {
+        let mut writer = BitWriter::new();
+        for i in 0..1000 {
+            writer.write_bits_u32(i, 13);
+        }
+        //Bits left in the buffer too, not just full bytes
+        writer.write_bits_u16(0b111, 3);
+        writer.clear();
+        assert!(writer.total_bits_written() == 0 && writer.get_bytes().is_empty(), "Bits left after clearing");
+
+        writer.write_bits_u16(0b101, 3);
+        writer.write_bits_u32(0xABCD, 16);
+        let mut fresh_writer = BitWriter::new();
+        fresh_writer.write_bits_u16(0b101, 3);
+        fresh_writer.write_bits_u32(0xABCD, 16);
+        assert!(writer.get_bytes() == fresh_writer.get_bytes(), "Writing after clearing different from writing to a new writer [{:?}] [{:?}]", writer.get_bytes(), fresh_writer.get_bytes());
+        assert!(writer.total_bits_written() == 19, "Wrong number of bits written after clearing [{}]", writer.total_bits_written());
+    }
+
+    #[test]
+    fn align_to_byte_test() {
+        let mut writer = BitWriter::new();
+        writer.write_bits_u16(0b101, 3);
+        writer.align_to_byte();
+        writer.write_bits_u16(0xA5, 8);
+        writer.write_bits_u16(0b1, 1);
+        writer.align_to_byte();
+        //Already aligned, so this does nothing
+        writer.align_to_byte();
+        writer.write_bits_u16(0x3C, 8);
+        let bytes = writer.get_bytes();
+        assert!(bytes == [0b1010_0000, 0xA5, 0b1000_0000, 0x3C], "Wrong bytes after aligning [{bytes:?}]");
+
+        let mut reader = BitReader::new(&bytes);
+        assert!(reader.bits_consumed() == 0, "Bits consumed before reading anything");
+        assert!(reader.read_bits_into_u8(3) == Some(0b101), "Wrong 3 bit value read");
+        assert!(reader.bits_consumed() == 3, "Wrong number of bits consumed [{}] after reading 3 bits", reader.bits_consumed());
+        reader.align_to_byte();
+        assert!(reader.bits_consumed() == 8, "Wrong number of bits consumed [{}] after aligning", reader.bits_consumed());
+        assert!(reader.read_bits_into_u8(8) == Some(0xA5), "Wrong byte read after aligning");
+        reader.align_to_byte();
+        assert!(reader.bits_consumed() == 16, "Aligning on a byte boundary skipped bits");
+        assert!(reader.read_bit() == Some(true), "Wrong bit read");
+        reader.align_to_byte();
+        assert!(reader.read_bits_into_u8(8) == Some(0x3C), "Wrong byte read after aligning again");
+        assert!(reader.bits_consumed() == 32 && reader.remaining_bits() == 0, "Wrong number of bits consumed [{}] at the end", reader.bits_consumed());
+
+        let mut reader = BitReader::new(&bytes);
+        assert!(reader.read_bits_into_u32_with_shift(12) == Some(0b1010_0000_1010), "Wrong bits read with shift");
+        assert!(reader.bits_consumed() == 12, "Wrong number of bits consumed [{}] after reading with shift", reader.bits_consumed());
+    }
+
+    #[test]
+    fn read_bytes_aligned_test() {
+        use rand::prelude::*;
+
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(2123);
+        let block:Vec<u8> = (0..5000).map(|_| rng.gen::<u8>()).collect();
+
+        let mut writer = BitWriter::new();
+        writer.write_bits_u16(0b101, 3);
+        writer.align_to_byte();
+        for byte in &block {
+            writer.write_bits_u16(*byte as u16, 8);
+        }
+        writer.write_bits_u16(0b11011, 5);
+        let bytes = writer.get_bytes();
+
+        let mut reader = BitReader::new(&bytes);
+        assert!(reader.read_bits_into_u8(3) == Some(0b101), "Wrong bits read before the block");
+        let mut buf = vec![0u8; block.len()];
+        assert!(reader.read_bytes_aligned(&mut buf).is_none(), "Bytes read without being aligned");
+        assert!(reader.bits_consumed() == 3, "Failed aligned read still read bits");
+
+        reader.align_to_byte();
+        //A few bytes first, so the rest starts partway through the buffer
+        assert!(reader.read_bytes_aligned(&mut buf[..3]) == Some(3), "Wrong number of bytes read");
+        assert!(reader.read_bytes_aligned(&mut buf[3..]) == Some(block.len() - 3), "Wrong number of bytes read");
+        assert!(buf == block, "Block different after reading it aligned");
+        assert!(reader.bits_consumed() == 8 + block.len() * 8, "Wrong number of bits consumed [{}] after the block", reader.bits_consumed());
+        assert!(reader.read_bits_into_u8(5) == Some(0b11011), "Wrong bits read after the block");
+
+        //Asking for more than is left
+        let mut reader = BitReader::new(&bytes);
+        reader.empty_bits(8);
+        let mut buf = vec![0u8; bytes.len()];
+        assert!(reader.read_bytes_aligned(&mut buf) == Some(bytes.len() - 1), "Wrong number of bytes read when asking for too many");
+        assert!(buf[..(bytes.len() - 1)] == bytes[1..], "Bytes different after reading all of them aligned");
+        assert!(reader.remaining_bits() == 0 && reader.read_bytes_aligned(&mut buf) == Some(0), "Bytes read after running out");
+    }
+
+    #[test]
+    fn signed_bits_test() {
+        use rand::prelude::*;
+
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(2123);
+
+        //Random values that fit in random widths, including the most negative and most positive ones
+        let mut vals = Vec::new();
+        for _ in 0..4096 {
+            let bit_num = rng.gen_range(1..=32);
+            let min_val = i32::MIN >> (32 - bit_num);
+            let max_val = i32::MAX >> (32 - bit_num);
+            let val = match rng.gen_range(0..8) {
+                0 => min_val,
+                1 => max_val,
+                _ => rng.gen_range(min_val..=max_val)
+            };
+            vals.push((val, bit_num));
+        }
+
+        let mut writer = BitWriter::new();
+        for (val, bit_num) in &vals {
+            writer.write_signed_bits(*val, *bit_num);
+        }
+        let bytes = writer.get_bytes();
+
+        let mut reader = BitReader::new(&bytes);
+        for (val, bit_num) in &vals {
+            let read_val = reader.read_signed_bits(*bit_num);
+            assert!(read_val == Some(*val), "Signed val with [{bit_num}] bits was read/written incorrectly, {val} -> {read_val:?}");
+        }
+
+        //Full width, and values that don't fit losing their top bits
+        let mut writer = BitWriter::new();
+        writer.write_signed_bits(i32::MIN, 32);
+        writer.write_signed_bits(-1, 32);
+        writer.write_signed_bits(-1, 1);
+        writer.write_signed_bits(5, 3);
+        writer.write_signed_bits(-9, 4);
+        let bytes = writer.get_bytes();
+
+        let mut reader = BitReader::new(&bytes);
+        assert!(reader.read_signed_bits(32) == Some(i32::MIN), "Most negative 32 bit val read/written incorrectly");
+        assert!(reader.read_signed_bits(32) == Some(-1), "-1 with 32 bits read/written incorrectly");
+        assert!(reader.read_signed_bits(1) == Some(-1), "-1 with 1 bit read/written incorrectly");
+        assert!(reader.read_signed_bits(3) == Some(-3), "5 with 3 bits didn't wrap around to -3");
+        assert!(reader.read_signed_bits(4) == Some(7), "-9 with 4 bits didn't wrap around to 7");
+
+        //Not enough bits left to sign-extend
+        let remaining_bits = reader.remaining_bits();
+        assert!(reader.read_signed_bits(remaining_bits + 1).is_none(), "Signed val read with too few bits left");
+        assert!(reader.remaining_bits() == remaining_bits, "Failed signed read still read bits");
+    }
+
+    #[test]
+    fn patch_bits_test() {
+        let payload = b"Some payload whose length isn't known yet";
+
+        let mut writer = BitWriter::new();
+        writer.write_bits_u16(0b101, 3);
+        let header_pos = writer.bit_position();
+        writer.write_bits_u32(0, 32);
+        for byte in payload {
+            writer.write_bits_u16(*byte as u16, 8);
+        }
+        //Doesn't fill the last byte, so it's still in the buffer
+        let tail_pos = writer.bit_position();
+        writer.write_bits_u16(0, 4);
+
+        let payload_len = (tail_pos - header_pos - 32) as u32;
+        writer.patch_bits_u32(header_pos, payload_len, 32);
+        writer.patch_bits_u32(tail_pos, 0b1011, 4);
+
+        let bytes = writer.get_bytes();
+        let mut reader = BitReader::new(&bytes);
+        assert!(reader.read_bits_into_u32(3) == Some(0b101), "Bits before the patch changed");
+        assert!(reader.read_bits_into_u32(32) == Some(payload_len), "Patched length read wrong");
+        for byte in payload {
+            assert!(reader.read_bits_into_u8(8) == Some(*byte), "Payload changed by patching");
+        }
+        assert!(reader.read_bits_into_u8(4) == Some(0b1011), "Patched buffered bits read wrong");
+
+        let patched = std::panic::catch_unwind(|| {
+            let mut writer = BitWriter::new();
+            writer.write_bits_u16(0, 8);
+            writer.patch_bits_u32(4, 0, 8);
+        });
+        assert!(patched.is_err(), "Patching bits that weren't written yet worked");
+    }
+
+    #[test]
+    fn elias_test() {
+        use rand::prelude::*;
+
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(2057);
+        let vals:Vec<u32> = (1..=100000).chain((0..1000).map(|_| rng.gen_range(100000..=u32::MAX))).chain([u32::MAX, 1 << 31]).collect();
+
+        let mut gamma_writer = BitWriter::new();
+        let mut delta_writer = BitWriter::new();
+        for val in &vals {
+            gamma_writer.write_elias_gamma(*val);
+            delta_writer.write_elias_delta(*val);
+        }
+        println!("Gamma bits: [{}] Delta bits: [{}]", gamma_writer.total_bits_written(), delta_writer.total_bits_written());
+        assert!(delta_writer.total_bits_written() < gamma_writer.total_bits_written(), "Delta codes took more bits than gamma codes");
+
+        let gamma_bytes = gamma_writer.get_bytes();
+        let delta_bytes = delta_writer.get_bytes();
+        let mut gamma_reader = BitReader::new(&gamma_bytes);
+        let mut delta_reader = BitReader::new(&delta_bytes);
+        for val in &vals {
+            assert!(gamma_reader.read_elias_gamma() == Some(*val), "Gamma code for [{val}] read wrong");
+            assert!(delta_reader.read_elias_delta() == Some(*val), "Delta code for [{val}] read wrong");
+        }
+
+        //1 is a single bit, and 5 is 00101 as a gamma code and 01101 as a delta code
+        let mut writer = BitWriter::new();
+        writer.write_elias_gamma(1);
+        writer.write_elias_gamma(5);
+        writer.write_elias_delta(5);
+        assert!(writer.total_bits_written() == 11 && writer.get_bytes() == [0b10010101, 0b10100000], "Wrong codes written [{writer}]");
+
+        //Running out, and more zeros than any u32 has
+        assert!(BitReader::new(&[0b00000001]).read_elias_gamma().is_none(), "Truncated gamma code read");
+        assert!(BitReader::new(&[0; 5]).read_elias_gamma().is_none(), "Gamma code with too many zeros read");
+        assert!(BitReader::new(&[0b00000100, 0b00100000]).read_elias_delta().is_none(), "Delta code longer than 32 bits read");
+    }
+
+    #[test]
+    fn rice_test() {
+        use rand::prelude::*;
+
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(2058);
+        for k in [0, 1, 3, 8, 16, 31, 32] {
+            let max_val = if k >= 20 {u32::MAX} else {(1 << (k + 4)) - 1};
+            let vals:Vec<u32> = (0..2000).map(|_| rng.gen_range(0..=max_val)).chain([0, max_val]).collect();
+
+            let mut writer = BitWriter::new();
+            for val in &vals {
+                writer.write_rice(*val, k);
+            }
+            let bytes = writer.get_bytes();
+            let mut reader = BitReader::new(&bytes);
+            for val in &vals {
+                let read = reader.read_rice(k);
+                assert!(read == Some(*val), "Rice code for [{val}] with k [{k}] read as [{read:?}]");
+            }
+        }
+
+        //Running out, and a quotient too big for a u32
+        assert!(BitReader::new(&[0b11111111]).read_rice(2).is_none(), "Truncated Rice code read");
+        assert!(BitReader::new(&[0b10000000]).read_rice(8).is_none(), "Rice code missing its remainder read");
+        assert!(BitReader::new(&[0xFF; 8]).read_rice(31).is_none(), "Rice code with too big a quotient read");
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn rice_parameter_test() {
+        use crate::bitstream::rice_parameter;
+        use rand::prelude::*;
+
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(2058);
+        let rice_bits = |vals: &[u32], k: usize| {
+            let mut writer = BitWriter::new();
+            for val in vals {
+                writer.write_rice(*val, k);
+            }
+            writer.total_bits_written()
+        };
+
+        //Geometric values with a bunch of different means
+        for mean in [0.5, 3.0, 20.0, 150.0, 5000.0] {
+            let p:f64 = 1.0 / (mean + 1.0);
+            let vals:Vec<u32> = (0..20000).map(|_| (rng.gen::<f64>().ln() / (1.0 - p).ln()).floor() as u32).collect();
+
+            let k = rice_parameter(&vals);
+            let best_k = (0..20).min_by_key(|k| rice_bits(&vals, *k)).unwrap();
+            let (bits, best_bits) = (rice_bits(&vals, k), rice_bits(&vals, best_k));
+            println!("Mean: [{mean}] Estimated k: [{k}] Best k: [{best_k}] Bits: [{bits}] Best bits: [{best_bits}]");
+
+            assert!(bits as f64 <= best_bits as f64 * 1.03, "Estimated k [{k}] took [{bits}] bits, but k [{best_k}] only took [{best_bits}]");
+        }
+
+        assert!(rice_parameter(&[]) == 0 && rice_parameter(&[0; 10]) == 0, "Nothing or zeros didn't give a k of 0");
+    }
+
+    #[test]
+    fn append_test() {
+        use rand::prelude::*;
+
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(2064);
+        let vals:Vec<(u32, usize)> = (0..4096).map(|_| {
+            let bit_num = rng.gen_range(1..=32);
+            (rng.gen::<u32>() & (u32::MAX >> (32 - bit_num)), bit_num)
+        }).collect();
+
+        //Split in a bunch of places, so the first half ends on every bit offset
+        for split in [0, 1, 2, 3, 7, 100, 1001, 2048, 4095, 4096] {
+            let mut first = BitWriter::new();
+            let mut second = BitWriter::new();
+            for (i, &(val, bit_num)) in vals.iter().enumerate() {
+                let writer = if i < split {&mut first} else {&mut second};
+                writer.write_bits_u32(val, bit_num);
+            }
+            first.append(&second);
+
+            let total_bits:usize = vals.iter().map(|(_, bit_num)| bit_num).sum();
+            assert!(first.total_bits_written() == total_bits, "Wrong number of bits after appending at [{split}] [{}]", first.total_bits_written());
+
+            let bytes = first.get_bytes();
+            let mut reader = BitReader::new(&bytes);
+            for &(val, bit_num) in &vals {
+                assert!(reader.read_bits_into_u32(bit_num) == Some(val), "Value different after appending at [{split}]");
+            }
+        }
+
+        //Lined up on a byte boundary, it's just the bytes stuck together
+        let mut first = BitWriter::new();
+        first.write_bits_u16(0xAB, 8);
+        let mut second = BitWriter::new();
+        second.write_bits_u16(0b101, 3);
+        first.append(&second);
+        first.append(&BitWriter::new());
+        assert!(first.get_bytes() == [0xAB, 0b10100000] && first.total_bits_written() == 11, "Byte aligned append wrong [{:02x?}]", first.get_bytes());
+    }
+    #[test]
+    fn refill_test() {
+        use rand::prelude::*;
+        use std::cmp::min;
+
+        //The bit at `pos`, read straight out of the bytes
+        fn bit_at(bytes: &[u8], pos: usize) -> u32 {
+            ((bytes[pos >> 3] >> (7 - (pos & 0b111))) & 1) as u32
+        }
+
+        //Lengths that aren't multiples of 8, so the last few bytes always go in one at a time
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(2077);
+        for len in (1..=40).chain([1001, 4099]) {
+            let mut bytes = vec![0u8; len];
+            rng.fill_bytes(&mut bytes);
+
+            let mut reader = BitReader::new(&bytes);
+            let mut pos = 0;
+            while pos < len << 3 {
+                let bit_num = min(rng.gen_range(0..=32), (len << 3) - pos);
+                let expected = (pos..(pos + bit_num)).fold(0, |val, i| (val << 1) | bit_at(&bytes, i));
+                if bit_num > 0 {
+                    assert!(reader.peek_bits_into_u32(bit_num) == Some(expected), "Peeked [{bit_num}] bits at [{pos}] wrong in [{len}] bytes");
+                }
+                assert!(reader.read_bits_into_u32(bit_num) == Some(expected), "Read [{bit_num}] bits at [{pos}] wrong in [{len}] bytes");
+                pos += bit_num;
+                assert!(reader.bits_consumed() == pos, "Reader at [{}] instead of [{pos}] in [{len}] bytes", reader.bits_consumed());
+
+                //Sometimes skip to a byte boundary and copy some bytes out
+                if rng.gen_bool(0.1) {
+                    reader.align_to_byte();
+                    pos = pos.div_ceil(8) << 3;
+                    let mut buf = vec![0u8; rng.gen_range(0..20)];
+                    let byte_num = reader.read_bytes_aligned(&mut buf).unwrap();
+                    assert!(buf[..byte_num] == bytes[(pos >> 3)..((pos >> 3) + byte_num)], "Copied bytes at [{pos}] wrong in [{len}] bytes");
+                    pos += byte_num << 3;
+                }
+            }
+            assert!(reader.read_bit().is_none(), "Bits left over in [{len}] bytes");
+        }
+    }
+
+    #[test]
+    fn end_of_stream_test() {
+        use rand::prelude::*;
+
+        //Every way of asking for more bits than are left, from every position in the last few bytes
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(2090);
+        for len in [1, 3, 8, 13] {
+            let mut bytes = vec![0u8; len];
+            rng.fill_bytes(&mut bytes);
+            let total_bits = len << 3;
+
+            for pos in total_bits.saturating_sub(33)..=total_bits {
+                let remaining = total_bits - pos;
+                for bit_num in 0..=32 {
+                    let mut reader = BitReader::new(&bytes);
+                    reader.empty_bits(pos);
+
+                    //The bits that are actually left, read straight out of the bytes
+                    let left = (remaining > 0).then(|| (pos..(pos + bit_num.min(remaining))).fold(0u32, |val, i| (val << 1) | ((bytes[i >> 3] >> (7 - (i & 0b111))) & 1) as u32));
+                    let (right_aligned, shifted) = match left {
+                        Some(bits) if bit_num > remaining => (Some(bits), Some(bits << (bit_num - remaining))),
+                        bits => (bits, bits)
+                    };
+
+                    assert!(reader.peek_bits_into_u32(bit_num) == right_aligned, "Peeked [{bit_num}] bits with [{remaining}] left in [{len}] bytes wrong");
+                    assert!(reader.peek_bits_into_u32_with_shift(bit_num) == shifted, "Peeked [{bit_num}] bits with shift with [{remaining}] left in [{len}] bytes wrong");
+                    assert!(reader.remaining_bits() == remaining, "Peeking read bits with [{remaining}] left in [{len}] bytes");
+
+                    let mut shift_reader = BitReader::new(&bytes);
+                    shift_reader.empty_bits(pos);
+                    assert!(shift_reader.read_bits_into_u32_with_shift(bit_num) == shifted, "Read [{bit_num}] bits with shift with [{remaining}] left in [{len}] bytes wrong");
+                    assert!(reader.read_bits_into_u32(bit_num) == right_aligned, "Read [{bit_num}] bits with [{remaining}] left in [{len}] bytes wrong");
+
+                    let consumed = if remaining == 0 {0} else {bit_num.min(remaining)};
+                    assert!(reader.remaining_bits() == remaining - consumed && shift_reader.remaining_bits() == remaining - consumed, "Wrong number of bits read with [{remaining}] left in [{len}] bytes");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn owned_reader_test() {
+        use crate::huffman::{HuffmanEncoder, HuffmanDecoder, HUFFMAN_MAX_SYMBOLS, HUFFMAN_DEFAULT_CHUNK_SIZE};
+        use std::fs;
+
+        //The compressed bytes only live inside the reader, which couldn't be returned if it borrowed them
+        fn compressed_reader(bytes: &[u8]) -> BitReader<'static> {
+            let mut writer = BitWriter::new();
+            HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS).encode_all_bytes(bytes, HUFFMAN_DEFAULT_CHUNK_SIZE, &mut writer);
+            BitReader::from_vec(writer.get_bytes())
+        }
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let mut reader = compressed_reader(&bytes);
+        assert!(HuffmanDecoder::new().decode_all_bytes(&mut reader) == Ok(bytes), "Bytes different after decoding from an owned reader");
+
+        //Everything else reads the same as a borrowed reader
+        let bytes:Vec<u8> = (0..=255u8).collect();
+        let mut owned = BitReader::from_vec(bytes.clone());
+        let mut borrowed = BitReader::new(&bytes);
+        assert!(owned.read_bits_into_u32(13) == borrowed.read_bits_into_u32(13), "Owned and borrowed readers read different bits");
+        owned.align_to_byte();
+        borrowed.align_to_byte();
+        let (mut owned_buf, mut borrowed_buf) = ([0u8; 200], [0u8; 200]);
+        assert!(owned.read_bytes_aligned(&mut owned_buf) == borrowed.read_bytes_aligned(&mut borrowed_buf) && owned_buf == borrowed_buf, "Owned and borrowed readers copied different bytes");
+        assert!(owned.bits_consumed() == borrowed.bits_consumed() && owned.remaining_bits() == borrowed.remaining_bits(), "Owned and borrowed readers are at different positions");
+    }
+}
@@ -0,0 +1,136 @@
+use alloc::{vec::Vec, vec};
+/// The biggest block `bwt_transform` takes. The sort needs a few `usize`s of
+/// memory per byte and `O(n log² n)` time, so blocks should stay around the
+/// size bzip2 uses (900KB) anyway.
+pub const BWT_MAX_BLOCK_SIZE:usize = 1 << 20;
+
+/// Sorts every rotation of the block, returning where each sorted rotation
+/// starts. It's a suffix array built with prefix doubling, except it wraps
+/// around the end instead of stopping there.
+///
+/// Rotations that are equal (which only happens if the block repeats itself)
+/// end up in no particular order, which doesn't matter since their rows in
+/// the rotation matrix are the same.
+fn sort_rotations(block: &[u8]) -> Vec<usize> {
+    let n = block.len();
+    let mut rotations:Vec<usize> = (0..n).collect();
+    let mut rank:Vec<usize> = block.iter().map(|&byte| byte as usize).collect();
+    let mut new_rank = vec![0; n];
+    let mut len = 1;
+
+    loop {
+        //Rotations are sorted by their first 2 * len bytes, using the ranks of their first len bytes
+        let key = |i: usize| (rank[i], rank[(i + len) % n]);
+        rotations.sort_unstable_by_key(|&i| key(i));
+
+        new_rank[rotations[0]] = 0;
+        for pair in rotations.windows(2) {
+            new_rank[pair[1]] = new_rank[pair[0]] + (key(pair[0]) != key(pair[1])) as usize;
+        }
+        core::mem::swap(&mut rank, &mut new_rank);
+
+        len *= 2;
+        if rank[rotations[n - 1]] == n - 1 || len >= n {
+            break;
+        }
+    }
+
+    rotations
+}
+
+/// Burrows-Wheeler transform. Takes the last byte of every rotation of the
+/// block, in sorted order, which groups bytes with similar contexts together.
+/// Returns those bytes, and the primary index (the row the unrotated block
+/// ended up in), which `bwt_inverse` needs to undo it.
+///
+/// The output doesn't compress any better on its own, it's meant to go
+/// through `mtf_encode` and then `HuffmanEncoder` like bzip2 does.
+///
+/// Blocks can be at most `BWT_MAX_BLOCK_SIZE` bytes, so split bigger buffers up.
+pub fn bwt_transform(block: &[u8]) -> (Vec<u8>, usize) {
+    assert!(block.len() <= BWT_MAX_BLOCK_SIZE, "Block of [{}] bytes is bigger than the max of [{BWT_MAX_BLOCK_SIZE}]", block.len());
+    if block.is_empty() {
+        return (Vec::new(), 0);
+    }
+
+    let n = block.len();
+    let rotations = sort_rotations(block);
+    let primary_index = rotations.iter().position(|&start| start == 0).expect("The unrotated block is one of the rotations");
+    let transformed = rotations.iter().map(|&start| block[(start + n - 1) % n]).collect();
+
+    (transformed, primary_index)
+}
+
+/// The inverse of `bwt_transform`, given the primary index it returned.
+///
+/// Since each byte of `transformed` is the one right before the first byte of
+/// its row, the block can be rebuilt backwards by going from each row to the
+/// row starting one byte earlier (the LF mapping), starting at the primary index.
+pub fn bwt_inverse(transformed: &[u8], primary_index: usize) -> Vec<u8> {
+    if transformed.is_empty() {
+        return Vec::new();
+    }
+    assert!(primary_index < transformed.len(), "Primary index [{primary_index}] out of range for a block of [{}] bytes", transformed.len());
+
+    //Where each byte's rows start in the sorted first column
+    let mut starts = [0usize; 256];
+    for &byte in transformed {
+        starts[byte as usize] += 1;
+    }
+    let mut total = 0;
+    for start in starts.iter_mut() {
+        let count = *start;
+        *start = total;
+        total += count;
+    }
+
+    //The n-th time a byte shows up in the last column is the n-th time it shows up in the first column
+    let mut seen = [0usize; 256];
+    let lf:Vec<usize> = transformed.iter().map(|&byte| {
+        let row = starts[byte as usize] + seen[byte as usize];
+        seen[byte as usize] += 1;
+        row
+    }).collect();
+
+    let mut block = vec![0u8; transformed.len()];
+    let mut row = primary_index;
+    for byte in block.iter_mut().rev() {
+        *byte = transformed[row];
+        row = lf[row];
+    }
+
+    block
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bwt::{bwt_transform, bwt_inverse};
+
+    #[test]
+    fn bwt_test() {
+        use std::fs;
+
+        assert!(bwt_transform(b"banana") == (b"nnbaaa".to_vec(), 3), "banana transformed wrong [{:?}]", bwt_transform(b"banana"));
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        for block in bytes.chunks(1 << 16) {
+            let (transformed, primary_index) = bwt_transform(block);
+            assert!(transformed.len() == block.len(), "Transformed length [{}] different from block length [{}]", transformed.len(), block.len());
+            assert!(bwt_inverse(&transformed, primary_index) == block, "Block different after transforming and inverting");
+        }
+    }
+
+    #[test]
+    fn bwt_edge_test() {
+        assert!(bwt_transform(&[]) == (Vec::new(), 0), "Empty block didn't transform to nothing");
+        assert!(bwt_inverse(&[], 0).is_empty(), "Empty block didn't invert to nothing");
+        assert!(bwt_transform(b"x") == (b"x".to_vec(), 0), "Single byte didn't transform to itself");
+        assert!(bwt_inverse(b"x", 0) == b"x", "Single byte didn't invert to itself");
+
+        //Blocks that repeat themselves have rotations that are equal
+        for block in [vec![0u8; 1 << 16], b"abababab".to_vec(), b"abcabcab".to_vec(), (0..=255).collect()] {
+            let (transformed, primary_index) = bwt_transform(&block);
+            assert!(bwt_inverse(&transformed, primary_index) == block, "Block different after transforming and inverting [{:?}]", &block[..block.len().min(16)]);
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use core::fmt;
+use core::str::FromStr;
+use alloc::{string::String, format};
+
+/// The reversed IEEE polynomial, the one zlib, gzip and PNG use.
+const CRC32_POLYNOMIAL:u32 = 0xEDB88320;
+const CRC32_TABLE:[u32; 256] = crc32_table();
+
+/// Builds the table of the CRC of every byte, so the CRC can be done a byte at
+/// a time instead of a bit at a time.
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut byte = 0;
+    while byte < 256 {
+        let mut crc = byte as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {(crc >> 1) ^ CRC32_POLYNOMIAL} else {crc >> 1};
+            bit += 1;
+        }
+        table[byte] = crc;
+        byte += 1;
+    }
+
+    table
+}
+
+/// The CRC-32 of some bytes, using the IEEE polynomial.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = u32::MAX;
+    for byte in bytes {
+        crc = (crc >> 8) ^ CRC32_TABLE[((crc as u8) ^ *byte) as usize];
+    }
+
+    !crc
+}
+
+/// The largest prime smaller than `65536`, which both Adler-32 sums are
+/// taken modulo.
+const ADLER32_MODULUS:u32 = 65521;
+/// The most bytes that can be added to the sums before they have to be
+/// reduced, without them overflowing a `u32`.
+const ADLER32_MAX_RUN:usize = 5552;
+
+/// Adler-32, which can be fed a chunk at a time as bytes get compressed.
+#[derive(Debug, Clone, Copy)]
+pub struct Adler32 {
+    a: u32,
+    b: u32
+}
+
+impl Default for Adler32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Adler32 {
+    pub fn new() -> Self {
+        Adler32 { a: 1, b: 0 }
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        for run in bytes.chunks(ADLER32_MAX_RUN) {
+            for byte in run {
+                self.a += *byte as u32;
+                self.b += self.a;
+            }
+            self.a %= ADLER32_MODULUS;
+            self.b %= ADLER32_MODULUS;
+        }
+    }
+
+    pub fn finalize(self) -> u32 {
+        (self.b << 16) | self.a
+    }
+}
+
+/// The Adler-32 of some bytes, all at once.
+pub fn adler32(bytes: &[u8]) -> u32 {
+    let mut adler = Adler32::new();
+    adler.update(bytes);
+    adler.finalize()
+}
+
+/// Which checksum protects some bytes. The values are the ids containers use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Checksum {
+    /// Nothing at all, for when speed matters more than catching corruption.
+    /// The checksum is always `0`.
+    None = 0,
+    Crc32 = 1,
+    Adler32 = 2
+}
+
+impl Checksum {
+    pub const ALL:[Checksum; 3] = [Checksum::None, Checksum::Crc32, Checksum::Adler32];
+
+    /// The checksum of some bytes.
+    pub fn compute(&self, bytes: &[u8]) -> u32 {
+        match self {
+            Checksum::None => 0,
+            Checksum::Crc32 => crc32(bytes),
+            Checksum::Adler32 => adler32(bytes)
+        }
+    }
+
+    /// The lowercase name, which is also what `from_str` takes.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Checksum::None => "none",
+            Checksum::Crc32 => "crc32",
+            Checksum::Adler32 => "adler32"
+        }
+    }
+}
+
+impl fmt::Display for Checksum {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Checksum {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Checksum::ALL
+            .into_iter()
+            .find(|checksum| checksum.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("Unknown checksum [{name}], expected one of: none, crc32, adler32"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::checksum::{crc32, adler32};
+
+    #[test]
+    fn crc32_test() {
+        assert!(crc32(b"") == 0, "CRC-32 of nothing should be 0, got [{:08x}]", crc32(b""));
+        assert!(crc32(b"123456789") == 0xCBF43926, "CRC-32 check value wrong, got [{:08x}]", crc32(b"123456789"));
+        assert!(crc32(b"The quick brown fox jumps over the lazy dog") == 0x414FA339, "CRC-32 of the quick brown fox wrong, got [{:08x}]", crc32(b"The quick brown fox jumps over the lazy dog"));
+    }
+
+    #[test]
+    fn adler32_test() {
+        assert!(adler32(b"") == 1, "Adler-32 of nothing should be 1, got [{:08x}]", adler32(b""));
+        assert!(adler32(b"Wikipedia") == 0x11E60398, "Adler-32 of Wikipedia wrong, got [{:08x}]", adler32(b"Wikipedia"));
+        //Enough 0xFF bytes that the sums would overflow without reducing them
+        assert!(adler32(&[0xFF; 100000]) == 0x149A302C, "Adler-32 of a long run wrong, got [{:08x}]", adler32(&[0xFF; 100000]));
+    }
+
+    #[test]
+    fn adler32_streaming_test() {
+        use crate::checksum::Adler32;
+        use std::fs;
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+
+        for slice_size in [1, 7, 1000, 5552, 65536] {
+            let mut adler = Adler32::new();
+            for slice in bytes.chunks(slice_size) {
+                adler.update(slice);
+            }
+            assert!(adler.finalize() == adler32(&bytes), "Adler-32 fed in slices of [{slice_size}] different from all at once");
+        }
+    }
+}
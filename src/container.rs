@@ -0,0 +1,236 @@
+use core::fmt;
+use crate::api::{compress, decompress, Codec};
+use crate::checksum::Checksum;
+use crate::error::DecodeError;
+use alloc::vec::Vec;
+
+/// The bytes every container starts with. The last one is the version.
+pub const CONTAINER_MAGIC:[u8; 4] = *b"LZC\x02";
+/// The size of the header: the magic, the codec id, the checksum id, the
+/// original length as a `u64` and the checksum as a `u32`, both little endian.
+pub const CONTAINER_HEADER_SIZE:usize = 4 + 1 + 1 + 8 + 4;
+
+impl TryFrom<u8> for Codec {
+    type Error = ContainerError;
+
+    fn try_from(id: u8) -> Result<Self, Self::Error> {
+        match id {
+            0 => Ok(Codec::Huffman),
+            1 => Ok(Codec::Lzw),
+            2 => Ok(Codec::Lz77),
+            3 => Ok(Codec::Deflate),
+            _ => Err(ContainerError::UnknownCodec(id))
+        }
+    }
+}
+
+impl TryFrom<u8> for Checksum {
+    type Error = ContainerError;
+
+    fn try_from(id: u8) -> Result<Self, Self::Error> {
+        match id {
+            0 => Ok(Checksum::None),
+            1 => Ok(Checksum::Crc32),
+            2 => Ok(Checksum::Adler32),
+            _ => Err(ContainerError::UnknownChecksum(id))
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ContainerError {
+    /// There aren't even enough bytes for the header.
+    TooShort,
+    /// The bytes don't start with `CONTAINER_MAGIC`.
+    BadMagic,
+    UnknownCodec(u8),
+    UnknownChecksum(u8),
+    /// The payload couldn't be decompressed.
+    Decode(DecodeError),
+    /// The payload decompressed to a different number of bytes than the
+    /// header says it should.
+    LengthMismatch { expected: usize, actual: usize },
+    /// The decompressed bytes don't have the checksum the header says they
+    /// should.
+    ChecksumMismatch { checksum: Checksum, expected: u32, actual: u32 }
+}
+
+impl fmt::Display for ContainerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ContainerError::TooShort => write!(f, "Container is too short to have a header"),
+            ContainerError::BadMagic => write!(f, "Container doesn't start with the magic number"),
+            ContainerError::UnknownCodec(id) => write!(f, "Unknown codec id {id}"),
+            ContainerError::UnknownChecksum(id) => write!(f, "Unknown checksum id {id}"),
+            ContainerError::Decode(err) => write!(f, "{err}"),
+            ContainerError::LengthMismatch { expected, actual } => write!(f, "Decompressed {actual} bytes, expected {expected}"),
+            ContainerError::ChecksumMismatch { checksum, expected, actual } => write!(f, "{checksum} mismatch, expected {expected:08x}, got {actual:08x}")
+        }
+    }
+}
+
+impl From<DecodeError> for ContainerError {
+    fn from(err: DecodeError) -> Self {
+        ContainerError::Decode(err)
+    }
+}
+
+/// Wraps already compressed bytes in a container, with a header that has the
+/// codec, the length of the original bytes and their CRC-32.
+pub fn pack(codec: Codec, original: &[u8], compressed: &[u8]) -> Vec<u8> {
+    pack_with_checksum(codec, Checksum::Crc32, original, compressed)
+}
+
+/// Same as `pack`, but with any checksum (or none at all).
+pub fn pack_with_checksum(codec: Codec, checksum: Checksum, original: &[u8], compressed: &[u8]) -> Vec<u8> {
+    let mut packed = Vec::with_capacity(CONTAINER_HEADER_SIZE + compressed.len());
+    packed.extend_from_slice(&CONTAINER_MAGIC);
+    packed.push(codec as u8);
+    packed.push(checksum as u8);
+    packed.extend_from_slice(&(original.len() as u64).to_le_bytes());
+    packed.extend_from_slice(&checksum.compute(original).to_le_bytes());
+    packed.extend_from_slice(compressed);
+
+    packed
+}
+
+/// Compresses bytes with `api::compress` and wraps them in a container.
+pub fn compress_and_pack(codec: Codec, original: &[u8]) -> Vec<u8> {
+    compress_and_pack_with_checksum(codec, Checksum::Crc32, original)
+}
+
+/// Same as `compress_and_pack`, but with any checksum (or none at all).
+pub fn compress_and_pack_with_checksum(codec: Codec, checksum: Checksum, original: &[u8]) -> Vec<u8> {
+    pack_with_checksum(codec, checksum, original, &compress(codec, original))
+}
+
+/// Decompresses the payload of a container with `api::decompress` and checks
+/// it against the length and checksum in the header. With `Checksum::None`
+/// only the length gets checked.
+pub fn unpack(bytes: &[u8]) -> Result<(Codec, Vec<u8>), ContainerError> {
+    if bytes.len() < CONTAINER_HEADER_SIZE {
+        return Err(ContainerError::TooShort);
+    }
+    if bytes[0..4] != CONTAINER_MAGIC {
+        return Err(ContainerError::BadMagic);
+    }
+
+    let codec = Codec::try_from(bytes[4])?;
+    let checksum = Checksum::try_from(bytes[5])?;
+    let expected_len = u64::from_le_bytes(bytes[6..14].try_into().unwrap()) as usize;
+    let expected_checksum = u32::from_le_bytes(bytes[14..18].try_into().unwrap());
+    let payload = &bytes[CONTAINER_HEADER_SIZE..];
+
+    let decompressed = decompress(codec, payload)?;
+
+    if decompressed.len() != expected_len {
+        return Err(ContainerError::LengthMismatch { expected: expected_len, actual: decompressed.len() });
+    }
+    let actual_checksum = checksum.compute(&decompressed);
+    if checksum != Checksum::None && actual_checksum != expected_checksum {
+        return Err(ContainerError::ChecksumMismatch { checksum, expected: expected_checksum, actual: actual_checksum });
+    }
+
+    Ok((codec, decompressed))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::container::{pack, unpack, ContainerError};
+    use crate::api::Codec;
+
+    #[test]
+    fn container_test() {
+        use crate::container::compress_and_pack;
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+
+        for codec in Codec::ALL {
+            let packed = compress_and_pack(codec, &bytes);
+            assert!(unpack(&packed) == Ok((codec, bytes.clone())), "Bytes different after packing and unpacking with {codec:?}");
+            assert!(unpack(&compress_and_pack(codec, &[])) == Ok((codec, Vec::new())), "Nothing wasn't empty after packing and unpacking with {codec:?}");
+        }
+    }
+
+    #[test]
+    fn container_corrupt_test() {
+        use crate::container::CONTAINER_HEADER_SIZE;
+        use crate::lzw::compress_lzw;
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let packed = pack(Codec::Lzw, &bytes, &compress_lzw(&bytes));
+
+        //Flipping a payload byte either breaks the LZW codes or decompresses to
+        //the wrong bytes, which only the checksum can catch. The last byte is
+        //skipped since it's partly padding.
+        let small_bytes = &bytes[..4096];
+        let small_packed = pack(Codec::Lzw, small_bytes, &compress_lzw(small_bytes));
+        let mut checksum_mismatches = 0;
+        for i in CONTAINER_HEADER_SIZE..(small_packed.len() - 1) {
+            let mut corrupted = small_packed.clone();
+            corrupted[i] ^= 0x01;
+            match unpack(&corrupted) {
+                Ok(_) => panic!("Flipped payload byte [{i}] wasn't caught"),
+                Err(ContainerError::ChecksumMismatch { .. }) => checksum_mismatches += 1,
+                Err(_) => {}
+            }
+        }
+        println!("Flipped bytes caught by the checksum: [{checksum_mismatches}] out of [{}]", small_packed.len() - CONTAINER_HEADER_SIZE - 1);
+        assert!(checksum_mismatches > 0, "No flipped payload bytes were caught by the checksum");
+
+        let mut corrupted = packed.clone();
+        corrupted[CONTAINER_HEADER_SIZE - 1] ^= 0xFF;
+        assert!(matches!(unpack(&corrupted), Err(ContainerError::ChecksumMismatch { .. })), "Corrupted CRC wasn't caught");
+
+        let mut corrupted = packed.clone();
+        corrupted[0] = b'X';
+        assert!(unpack(&corrupted) == Err(ContainerError::BadMagic), "Bad magic number wasn't caught");
+
+        let mut corrupted = packed.clone();
+        corrupted[4] = 200;
+        assert!(unpack(&corrupted) == Err(ContainerError::UnknownCodec(200)), "Unknown codec wasn't caught");
+
+        assert!(unpack(&packed[..10]) == Err(ContainerError::TooShort), "Truncated header wasn't caught");
+    }
+
+    #[test]
+    fn container_checksum_test() {
+        use crate::container::{compress_and_pack_with_checksum, pack_with_checksum, CONTAINER_HEADER_SIZE};
+        use crate::checksum::Checksum;
+        use crate::lzw::compress_lzw;
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+
+        for checksum in Checksum::ALL {
+            let packed = compress_and_pack_with_checksum(Codec::Deflate, checksum, &bytes);
+            assert!(unpack(&packed) == Ok((Codec::Deflate, bytes.clone())), "Bytes different after packing and unpacking with {checksum:?}");
+        }
+
+        //Same as container_corrupt_test, but with Adler-32
+        let small_bytes = &bytes[..4096];
+        let small_packed = pack_with_checksum(Codec::Lzw, Checksum::Adler32, small_bytes, &compress_lzw(small_bytes));
+        let mut checksum_mismatches = 0;
+        for i in CONTAINER_HEADER_SIZE..(small_packed.len() - 1) {
+            let mut corrupted = small_packed.clone();
+            corrupted[i] ^= 0x01;
+            match unpack(&corrupted) {
+                Ok(_) => panic!("Flipped payload byte [{i}] wasn't caught"),
+                Err(ContainerError::ChecksumMismatch { checksum, .. }) => {
+                    assert!(checksum == Checksum::Adler32, "Mismatch reported for [{checksum:?}] instead of Adler-32");
+                    checksum_mismatches += 1;
+                },
+                Err(_) => {}
+            }
+        }
+        assert!(checksum_mismatches > 0, "No flipped payload bytes were caught by the checksum");
+
+        //Without a checksum, a corrupted checksum field doesn't matter
+        let mut unchecked = pack_with_checksum(Codec::Lzw, Checksum::None, small_bytes, &compress_lzw(small_bytes));
+        unchecked[CONTAINER_HEADER_SIZE - 1] ^= 0xFF;
+        assert!(unpack(&unchecked) == Ok((Codec::Lzw, small_bytes.to_vec())), "Container without a checksum was checked");
+
+        unchecked[5] = 200;
+        assert!(unpack(&unchecked) == Err(ContainerError::UnknownChecksum(200)), "Unknown checksum wasn't caught");
+    }
+}
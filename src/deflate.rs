@@ -1,7 +1,97 @@
-use crate::huffman::{HuffmanEncoder, HuffmanDecoder, HuffmanSymbol};
-use crate::lz77::{lz77_compress_simple, lz77_decompress};
+use core::fmt;
+use core::cmp::{min, max};
+use crate::huffman::{HuffmanEncoder, HuffmanSymbol};
+use crate::lz77::{LZ77Data, LZ77Encoded, lz77_compress_cost_aware};
+use alloc::{vec::Vec, vec};
 
-fn extra_bits_for_length_symbol(symbol: HuffmanSymbol) -> usize {
+/// The longest match DEFLATE can encode with a single length symbol.
+pub const DEFLATE_MAX_MATCH_LEN:usize = 258;
+/// The shortest match DEFLATE can encode.
+pub const DEFLATE_MIN_MATCH_LEN:usize = 3;
+/// The furthest back a DEFLATE match can point.
+pub const DEFLATE_WINDOW_SIZE:usize = 32768;
+/// The number of symbols in the literal/length alphabet.
+pub const DEFLATE_LIT_LEN_SYMBOLS:usize = 286;
+/// The number of symbols in the distance alphabet.
+pub const DEFLATE_DIST_SYMBOLS:usize = 30;
+/// The number of tokens in each block, each of which gets its own Huffman
+/// tables.
+pub const DEFLATE_BLOCK_SIZE:usize = 1 << 16;
+pub(crate) const DEFLATE_MAX_CHAIN_LEN:usize = 32;
+const DEFLATE_MAX_CODE_LEN:usize = 15;
+const END_OF_BLOCK:HuffmanSymbol = 256;
+/// The number of symbols in the alphabet the code lengths of dynamic blocks
+/// are written with.
+const DEFLATE_CODE_LEN_SYMBOLS:usize = 19;
+const DEFLATE_CODE_LEN_MAX_CODE_LEN:usize = 7;
+/// The order the code lengths of the code length alphabet are written in,
+/// so the ones that are usually `0` come last and can be left off.
+const DEFLATE_CODE_LEN_ORDER:[usize; DEFLATE_CODE_LEN_SYMBOLS] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+/// The most bytes a single stored block can hold, since its length is a `u16`.
+pub const DEFLATE_MAX_STORED_LEN:usize = u16::MAX as usize;
+
+/// The kinds of blocks, with the values their 2-bit `BTYPE` header has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockType {
+    /// The bytes are copied as is, for when compressing them doesn't help.
+    Stored = 0,
+    /// Huffman coded with the code lengths RFC 1951 picked, so no table has
+    /// to be written. Good for small blocks, where the table would be most of
+    /// the block.
+    Fixed = 1,
+    /// Huffman coded, with the code lengths written at the start of the block.
+    Dynamic = 2
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum DeflateError {
+    /// A match is shorter than DEFLATE's minimum match length.
+    MatchTooShort(usize),
+    /// A match points further back than DEFLATE's window, or further back
+    /// than what's been decompressed so far.
+    OffsetTooLarge(usize),
+    /// A symbol was decoded that isn't in the alphabet it was decoded from.
+    InvalidSymbol(HuffmanSymbol),
+    /// The compressed bytes ended before the final block did.
+    UnexpectedEnd,
+    /// A block's type was `3`, which RFC 1951 doesn't use.
+    InvalidBlockType(u8),
+    /// A dynamic block's code lengths don't make a valid code.
+    InvalidCodeLengths,
+    /// Bits were read that aren't the start of any code.
+    InvalidCode,
+    /// A stored block's length doesn't match the complement after it.
+    StoredLengthMismatch
+}
+
+impl fmt::Display for DeflateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DeflateError::MatchTooShort(length) => write!(f, "Match of length {length} is too short for DEFLATE"),
+            DeflateError::OffsetTooLarge(offset) => write!(f, "Match with offset {offset} is outside DEFLATE's window"),
+            DeflateError::InvalidSymbol(symbol) => write!(f, "Invalid symbol {symbol}"),
+            DeflateError::UnexpectedEnd => write!(f, "Compressed bytes ended before the final block"),
+            DeflateError::InvalidBlockType(block_type) => write!(f, "Invalid block type {block_type}"),
+            DeflateError::InvalidCodeLengths => write!(f, "Invalid code lengths in a dynamic block"),
+            DeflateError::InvalidCode => write!(f, "Invalid Huffman code"),
+            DeflateError::StoredLengthMismatch => write!(f, "Stored block length doesn't match its complement")
+        }
+    }
+}
+
+/// A single DEFLATE token, before it gets Huffman coded.
+///
+/// Matches are stored as their length symbol and the value of its extra bits,
+/// then their distance symbol and the value of its extra bits, the same as
+/// they'd get written out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeflateToken {
+    Literal(u8),
+    Match(HuffmanSymbol, u16, HuffmanSymbol, u16),
+    EndOfBlock
+}
+
+pub fn extra_bits_for_length_symbol(symbol: HuffmanSymbol) -> usize {
     match symbol {
         257..=264 => 0,
         265..=268 => 1,
@@ -13,7 +103,7 @@ fn extra_bits_for_length_symbol(symbol: HuffmanSymbol) -> usize {
     }
 }
 
-fn extra_bits_for_dist_symbol(symbol: HuffmanSymbol) -> usize {
+pub fn extra_bits_for_dist_symbol(symbol: HuffmanSymbol) -> usize {
     match symbol {
         0..=3 => 0,
         4 | 5 => 1,
@@ -33,18 +123,18 @@ fn extra_bits_for_dist_symbol(symbol: HuffmanSymbol) -> usize {
     }
 }
 
-
 fn data_from_extra_length_bits(symbol: HuffmanSymbol, extra_bits: u16) -> usize {
     let symbol = symbol as usize;
     let extra_bits = extra_bits as usize;
 
     match symbol {
         257..=264 => symbol - 254,
-        265..=268 => 11 + (symbol - 265) << 1 + extra_bits,
-        269..=272 => 19 + (symbol - 269) << 2 + extra_bits,
-        273..=276 => 35 + (symbol - 273) << 3 + extra_bits,
-        277..=280 => 67 + (symbol - 277) << 4 + extra_bits,
-        281..=284 => 131 + (symbol - 281) << 5 + extra_bits,
+        265..=268 => 11 + ((symbol - 265) << 1) + extra_bits,
+        269..=272 => 19 + ((symbol - 269) << 2) + extra_bits,
+        273..=276 => 35 + ((symbol - 273) << 3) + extra_bits,
+        277..=280 => 67 + ((symbol - 277) << 4) + extra_bits,
+        281..=284 => 131 + ((symbol - 281) << 5) + extra_bits,
+        285 => 258,
         _ => 0 //This should never happen
     }
 }
@@ -55,19 +145,849 @@ fn data_from_extra_dist_bits(symbol: HuffmanSymbol, extra_bits: u16) -> usize {
 
     match symbol {
         0..=3 => symbol + 1,
-        4 | 5 => 5 + (symbol - 4) << 1 + extra_bits,
-        6 | 7 => 9 + (symbol - 6) << 2 + extra_bits,
-        8 | 9 => 17 + (symbol - 8) << 3 + extra_bits,
-        10 | 11 => 33 + (symbol - 10) << 4 + extra_bits,
-        12 | 13 => 65 + (symbol - 12) << 5 + extra_bits,
-        14 | 15 => 129 + (symbol - 14) << 6 + extra_bits,
-        16 | 17 => 257 + (symbol - 16) << 7 + extra_bits,
-        18 | 19 => 513 + (symbol - 18) << 8 + extra_bits, 
-        20 | 21 => 1025 + (symbol - 20) << 9 + extra_bits,
-        22 | 23 => 2049 + (symbol - 22) << 10 + extra_bits,
-        24 | 25 => 4097 + (symbol - 24) << 11 + extra_bits,
-        26 | 27 => 8193 + (symbol - 26) << 12 + extra_bits,
-        28 | 29 => 16385 + (symbol - 28) << 13 + extra_bits,
+        4 | 5 => 5 + ((symbol - 4) << 1) + extra_bits,
+        6 | 7 => 9 + ((symbol - 6) << 2) + extra_bits,
+        8 | 9 => 17 + ((symbol - 8) << 3) + extra_bits,
+        10 | 11 => 33 + ((symbol - 10) << 4) + extra_bits,
+        12 | 13 => 65 + ((symbol - 12) << 5) + extra_bits,
+        14 | 15 => 129 + ((symbol - 14) << 6) + extra_bits,
+        16 | 17 => 257 + ((symbol - 16) << 7) + extra_bits,
+        18 | 19 => 513 + ((symbol - 18) << 8) + extra_bits,
+        20 | 21 => 1025 + ((symbol - 20) << 9) + extra_bits,
+        22 | 23 => 2049 + ((symbol - 22) << 10) + extra_bits,
+        24 | 25 => 4097 + ((symbol - 24) << 11) + extra_bits,
+        26 | 27 => 8193 + ((symbol - 26) << 12) + extra_bits,
+        28 | 29 => 16385 + ((symbol - 28) << 13) + extra_bits,
         _ => 0 //This should never happen
     }
-}
\ No newline at end of file
+}
+
+/// The length symbol for a given length. The length has to be between 3 and
+/// 258.
+fn length_symbol(length: usize) -> HuffmanSymbol {
+    let symbol = match length {
+        3..=10 => length + 254,
+        11..=18 => 265 + ((length - 11) >> 1),
+        19..=34 => 269 + ((length - 19) >> 2),
+        35..=66 => 273 + ((length - 35) >> 3),
+        67..=130 => 277 + ((length - 67) >> 4),
+        131..=257 => 281 + ((length - 131) >> 5),
+        258 => 285,
+        _ => panic!("Length [{length}] can't be encoded in DEFLATE")
+    };
+
+    symbol as HuffmanSymbol
+}
+
+/// The distance symbol for a given distance. The distance has to be between
+/// 1 and 32768.
+fn dist_symbol(dist: usize) -> HuffmanSymbol {
+    let symbol = match dist {
+        1..=4 => dist - 1,
+        5..=8 => 4 + ((dist - 5) >> 1),
+        9..=16 => 6 + ((dist - 9) >> 2),
+        17..=32 => 8 + ((dist - 17) >> 3),
+        33..=64 => 10 + ((dist - 33) >> 4),
+        65..=128 => 12 + ((dist - 65) >> 5),
+        129..=256 => 14 + ((dist - 129) >> 6),
+        257..=512 => 16 + ((dist - 257) >> 7),
+        513..=1024 => 18 + ((dist - 513) >> 8),
+        1025..=2048 => 20 + ((dist - 1025) >> 9),
+        2049..=4096 => 22 + ((dist - 2049) >> 10),
+        4097..=8192 => 24 + ((dist - 4097) >> 11),
+        8193..=16384 => 26 + ((dist - 8193) >> 12),
+        16385..=32768 => 28 + ((dist - 16385) >> 13),
+        _ => panic!("Distance [{dist}] can't be encoded in DEFLATE")
+    };
+
+    symbol as HuffmanSymbol
+}
+
+/// Makes a match token out of a length and offset that DEFLATE can encode.
+fn match_token(length: usize, offset: usize) -> DeflateToken {
+    let length_symbol = length_symbol(length);
+    let length_extra = (length - data_from_extra_length_bits(length_symbol, 0)) as u16;
+    let dist_symbol = dist_symbol(offset);
+    let dist_extra = (offset - data_from_extra_dist_bits(dist_symbol, 0)) as u16;
+
+    debug_assert!(length_extra < 1 << extra_bits_for_length_symbol(length_symbol));
+    debug_assert!(dist_extra < 1 << extra_bits_for_dist_symbol(dist_symbol));
+
+    DeflateToken::Match(length_symbol, length_extra, dist_symbol, dist_extra)
+}
+
+/// Converts already-found LZ77 literals and matches into DEFLATE tokens,
+/// without having to find all the matches again. The tokens end with an
+/// `EndOfBlock`.
+///
+/// Matches longer than 258 bytes get split into several matches with the same
+/// offset, which works since the matches can overlap the bytes they encode.
+/// Matches that are too short or point too far back for DEFLATE return an
+/// error, so use a window of at most `DEFLATE_WINDOW_SIZE` when compressing.
+pub fn lz77_to_deflate_tokens(encoded: &LZ77Encoded) -> Result<Vec<DeflateToken>, DeflateError> {
+    let mut tokens = Vec::with_capacity(encoded.data().len() + 1);
+
+    for data in encoded.data() {
+        match *data {
+            LZ77Data::Literal(byte) => tokens.push(DeflateToken::Literal(byte)),
+            LZ77Data::Match(length, offset) => {
+                if length < DEFLATE_MIN_MATCH_LEN {
+                    return Err(DeflateError::MatchTooShort(length));
+                }
+                if offset == 0 || offset > DEFLATE_WINDOW_SIZE {
+                    return Err(DeflateError::OffsetTooLarge(offset));
+                }
+
+                let mut remaining = length;
+                while remaining > DEFLATE_MAX_MATCH_LEN {
+                    //Don't leave a piece that's too short to be a match
+                    let piece_len = if remaining - DEFLATE_MAX_MATCH_LEN < DEFLATE_MIN_MATCH_LEN {remaining - DEFLATE_MIN_MATCH_LEN} else {DEFLATE_MAX_MATCH_LEN};
+                    tokens.push(match_token(piece_len, offset));
+                    remaining -= piece_len;
+                }
+                tokens.push(match_token(remaining, offset));
+            }
+        }
+    }
+
+    tokens.push(DeflateToken::EndOfBlock);
+
+    Ok(tokens)
+}
+
+/// Converts DEFLATE tokens back into LZ77 literals and matches, stopping at
+/// the first `EndOfBlock`. The result can be decompressed with
+/// `lz77_decompress`.
+pub fn deflate_tokens_to_lz77(tokens: &[DeflateToken]) -> LZ77Encoded {
+    let mut data = Vec::with_capacity(tokens.len());
+
+    for token in tokens {
+        match *token {
+            DeflateToken::Literal(byte) => data.push(LZ77Data::Literal(byte)),
+            DeflateToken::Match(length_symbol, length_extra, dist_symbol, dist_extra) => {
+                let length = data_from_extra_length_bits(length_symbol, length_extra);
+                let offset = data_from_extra_dist_bits(dist_symbol, dist_extra);
+                data.push(LZ77Data::Match(length, offset));
+            }
+            DeflateToken::EndOfBlock => break
+        }
+    }
+
+    LZ77Encoded::new(data)
+}
+
+impl DeflateToken {
+    /// The symbol from the literal/length alphabet for this token.
+    pub fn lit_len_symbol(&self) -> HuffmanSymbol {
+        match *self {
+            DeflateToken::Literal(byte) => byte as HuffmanSymbol,
+            DeflateToken::Match(length_symbol, _, _, _) => length_symbol,
+            DeflateToken::EndOfBlock => END_OF_BLOCK
+        }
+    }
+
+    /// The number of bytes this token decompresses to.
+    pub fn decompressed_len(&self) -> usize {
+        match *self {
+            DeflateToken::Literal(_) => 1,
+            DeflateToken::Match(length_symbol, length_extra, _, _) => data_from_extra_length_bits(length_symbol, length_extra),
+            DeflateToken::EndOfBlock => 0
+        }
+    }
+}
+
+/// Writes bits the way RFC 1951 packs them, starting from the least
+/// significant bit of each byte. `BitWriter` goes the other way, so it can't
+/// be used here.
+struct DeflateWriter {
+    bytes: Vec<u8>,
+    buffer: u64,
+    bits_in_buffer: usize
+}
+
+impl DeflateWriter {
+    fn new() -> Self {
+        DeflateWriter { bytes: Vec::new(), buffer: 0, bits_in_buffer: 0 }
+    }
+
+    /// Writes the lowest `bit_num` bits of `data`, least significant first.
+    /// This is how everything but the Huffman codes gets written.
+    fn write_bits(&mut self, data: u32, bit_num: usize) {
+        debug_assert!(bit_num <= 32);
+        self.buffer |= (data as u64 & ((1 << bit_num) - 1)) << self.bits_in_buffer;
+        self.bits_in_buffer += bit_num;
+
+        while self.bits_in_buffer >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bits_in_buffer -= 8;
+        }
+    }
+
+    fn bits_written(&self) -> usize {
+        self.bytes.len() * 8 + self.bits_in_buffer
+    }
+
+    /// Writes everything another writer wrote, right after what's already here.
+    fn append(&mut self, other: DeflateWriter) {
+        if self.bits_in_buffer == 0 {
+            self.bytes.extend_from_slice(&other.bytes);
+        } else {
+            for byte in &other.bytes {
+                self.write_bits(*byte as u32, 8);
+            }
+        }
+        self.write_bits(other.buffer as u32, other.bits_in_buffer);
+    }
+
+    /// Pads with zeros up to the next byte boundary.
+    fn align_to_byte(&mut self) {
+        if self.bits_in_buffer > 0 {
+            self.write_bits(0, 8 - self.bits_in_buffer);
+        }
+    }
+
+    /// Writes a Huffman code, which goes most significant bit first.
+    fn write_code(&mut self, code: u32, len: usize) {
+        self.write_bits(code.reverse_bits() >> (32 - len), len);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.align_to_byte();
+        self.bytes
+    }
+}
+
+/// Reads bits packed the way `DeflateWriter` writes them.
+struct DeflateReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    buffer: u64,
+    bits_in_buffer: usize
+}
+
+impl<'a> DeflateReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        DeflateReader { bytes, pos: 0, buffer: 0, bits_in_buffer: 0 }
+    }
+
+    fn read_bits(&mut self, bit_num: usize) -> Result<u32, DeflateError> {
+        while self.bits_in_buffer < bit_num {
+            let byte = *self.bytes.get(self.pos).ok_or(DeflateError::UnexpectedEnd)?;
+            self.buffer |= (byte as u64) << self.bits_in_buffer;
+            self.bits_in_buffer += 8;
+            self.pos += 1;
+        }
+
+        let bits = (self.buffer & ((1 << bit_num) - 1)) as u32;
+        self.buffer >>= bit_num;
+        self.bits_in_buffer -= bit_num;
+        Ok(bits)
+    }
+
+    /// Skips to the next byte boundary and hands back the whole bytes that
+    /// were already buffered, so the next `len` bytes can be copied directly.
+    fn read_aligned_bytes(&mut self, len: usize) -> Result<&'a [u8], DeflateError> {
+        self.pos -= self.bits_in_buffer / 8;
+        self.buffer = 0;
+        self.bits_in_buffer = 0;
+
+        let bytes = self.bytes.get(self.pos..(self.pos + len)).ok_or(DeflateError::UnexpectedEnd)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    /// The number of bytes read so far, counting a partly read byte as read.
+    fn bytes_read(&self) -> usize {
+        self.pos - self.bits_in_buffer / 8
+    }
+}
+
+/// A Huffman code read from a block's code lengths, decoded a bit at a time.
+///
+/// `counts[len]` is the number of codes of each length, and `symbols` has
+/// the symbols sorted by code, which is all that's needed since the codes are
+/// canonical.
+struct DeflateDecodeTable {
+    counts: [u16; DEFLATE_MAX_CODE_LEN + 1],
+    symbols: Vec<HuffmanSymbol>
+}
+
+impl DeflateDecodeTable {
+    /// Returns an error if there are more codes than fit in the lengths.
+    /// Codes that don't use up every length are fine, since RFC 1951 allows
+    /// a single distance code (and no distance codes at all).
+    fn new(lengths: &[u8]) -> Result<Self, DeflateError> {
+        let mut counts = [0u16; DEFLATE_MAX_CODE_LEN + 1];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+        counts[0] = 0;
+
+        let mut left:i32 = 1;
+        for count in &counts[1..] {
+            left = (left << 1) - *count as i32;
+            if left < 0 {
+                return Err(DeflateError::InvalidCodeLengths);
+            }
+        }
+
+        let mut symbols:Vec<(u8, HuffmanSymbol)> = lengths.iter().enumerate().filter(|(_, len)| **len > 0).map(|(symbol, len)| (*len, symbol as HuffmanSymbol)).collect();
+        symbols.sort_unstable();
+
+        Ok(DeflateDecodeTable { counts, symbols: symbols.into_iter().map(|(_, symbol)| symbol).collect() })
+    }
+
+    fn decode(&self, reader: &mut DeflateReader) -> Result<HuffmanSymbol, DeflateError> {
+        //Codes of each length come right after the codes of the length before, so only the first code of each length matters
+        let mut code:i32 = 0;
+        let mut first:i32 = 0;
+        let mut index:i32 = 0;
+
+        for count in &self.counts[1..] {
+            code |= reader.read_bits(1)? as i32;
+            let count = *count as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        Err(DeflateError::InvalidCode)
+    }
+}
+
+/// The canonical codes for some code lengths, assigned the way RFC 1951 does:
+/// shorter codes come first, and codes of the same length go in symbol order.
+fn canonical_codes(lengths: &[u8]) -> Vec<u32> {
+    let mut counts = [0u32; DEFLATE_MAX_CODE_LEN + 1];
+    for &len in lengths {
+        counts[len as usize] += 1;
+    }
+    counts[0] = 0;
+
+    let mut next_code = [0u32; DEFLATE_MAX_CODE_LEN + 1];
+    let mut code = 0;
+    for len in 1..=DEFLATE_MAX_CODE_LEN {
+        code = (code + counts[len - 1]) << 1;
+        next_code[len] = code;
+    }
+
+    lengths.iter().map(|&len| {
+        let code = next_code[len as usize];
+        next_code[len as usize] += 1;
+        code
+    }).collect()
+}
+
+/// Builds a Huffman code for some symbols and returns the length of every
+/// symbol's code, `0` for the ones that aren't used.
+///
+/// Decoders want codes that use up every length, which takes at least two
+/// symbols, so if there aren't two, symbols `0` and `1` get added (zlib does
+/// the same thing).
+fn build_code_lengths(encoder: &mut HuffmanEncoder, symbols: &[HuffmanSymbol], symbol_num: usize) -> Vec<u8> {
+    let mut used = vec![false; symbol_num];
+    for symbol in symbols {
+        used[*symbol as usize] = true;
+    }
+
+    encoder.build_frequency_table(symbols);
+    let mut used_num = used.iter().filter(|used| **used).count();
+    for (symbol, used) in used.iter().enumerate().take(2) {
+        if used_num < 2 && !used {
+            encoder.scan_symbol(symbol as HuffmanSymbol);
+            used_num += 1;
+        }
+    }
+    encoder.build_huffman_table();
+
+    let mut lengths = vec![0u8; symbol_num];
+    for (symbol, level) in encoder.iter() {
+        lengths[symbol as usize] = level as u8;
+    }
+    lengths
+}
+
+/// Compresses bytes into a raw RFC 1951 DEFLATE stream, which anything that
+/// reads DEFLATE (zlib, gzip, ...) can decompress once it's wrapped in their
+/// headers. See the `gzip` module for that.
+///
+/// LZ77 goes first (skipping matches that would cost more than their
+/// literals, see `lz77_compress_cost_aware`), then the literals, lengths and distances are Huffman
+/// coded using a literal/length alphabet and a distance alphabet, with the
+/// extra bits written raw after each symbol. Every `DEFLATE_BLOCK_SIZE`
+/// tokens get their own dynamic block, with code lengths from
+/// `HuffmanEncoder` limited to 15 bits.
+///
+/// Each block is also written with the fixed codes, and whichever of the two
+/// is smaller gets used, which is usually the fixed one for small inputs. If
+/// both would end up bigger than the bytes the block holds (which is what
+/// happens with random data), the bytes get stored as they are instead, so
+/// the output is never more than a few bytes per `DEFLATE_MAX_STORED_LEN`
+/// bigger than the input.
+pub fn deflate_compress(bytes: &[u8]) -> Vec<u8> {
+    let encoded = lz77_compress_cost_aware(bytes, DEFLATE_WINDOW_SIZE, DEFLATE_MIN_MATCH_LEN, DEFLATE_MAX_MATCH_LEN, DEFLATE_MAX_CHAIN_LEN);
+    deflate_compress_lz77(bytes, &encoded)
+}
+
+/// Does the rest of `deflate_compress` once the bytes have been parsed.
+/// `encoded` has to be `bytes` parsed with at most a `DEFLATE_WINDOW_SIZE`
+/// window, and matches of `DEFLATE_MIN_MATCH_LEN` to `DEFLATE_MAX_MATCH_LEN`.
+fn deflate_compress_lz77(bytes: &[u8], encoded: &LZ77Encoded) -> Vec<u8> {
+    let mut tokens = lz77_to_deflate_tokens(encoded).expect("LZ77 data was compressed to fit DEFLATE");
+    tokens.pop(); //Each block gets its own end of block
+
+    let mut writer = DeflateWriter::new();
+    let mut lit_len_encoder = HuffmanEncoder::new(DEFLATE_LIT_LEN_SYMBOLS);
+    let mut dist_encoder = HuffmanEncoder::new(DEFLATE_DIST_SYMBOLS);
+    let mut code_len_encoder = HuffmanEncoder::new(DEFLATE_CODE_LEN_SYMBOLS);
+    lit_len_encoder.set_max_code_len(DEFLATE_MAX_CODE_LEN);
+    dist_encoder.set_max_code_len(DEFLATE_MAX_CODE_LEN);
+    code_len_encoder.set_max_code_len(DEFLATE_CODE_LEN_MAX_CODE_LEN);
+    let mut encoders = (lit_len_encoder, dist_encoder, code_len_encoder);
+    let fixed_codes = FixedCodes::new();
+
+    let mut blocks = tokens.chunks(DEFLATE_BLOCK_SIZE).peekable();
+    if blocks.peek().is_none() {
+        write_fixed_block(&[], true, &fixed_codes, &mut writer);
+    }
+    let mut pos = 0;
+    while let Some(block) = blocks.next() {
+        let final_block = blocks.peek().is_none();
+        let block_len:usize = block.iter().map(|token| token.decompressed_len()).sum();
+
+        let mut dynamic_writer = DeflateWriter::new();
+        write_dynamic_block(block, final_block, &mut encoders, &mut dynamic_writer);
+        let mut fixed_writer = DeflateWriter::new();
+        write_fixed_block(block, final_block, &fixed_codes, &mut fixed_writer);
+        let coded_writer = if fixed_writer.bits_written() <= dynamic_writer.bits_written() {fixed_writer} else {dynamic_writer};
+
+        if stored_block_bits(block_len) < coded_writer.bits_written() {
+            write_stored_blocks(&bytes[pos..(pos + block_len)], final_block, &mut writer);
+        } else {
+            writer.append(coded_writer);
+        }
+        pos += block_len;
+    }
+
+    writer.finish()
+}
+
+/// The most bits storing some bytes can take, counting the padding before
+/// each stored block as a whole byte.
+fn stored_block_bits(len: usize) -> usize {
+    let block_num = max(len.div_ceil(DEFLATE_MAX_STORED_LEN), 1);
+    block_num * (3 + 7 + 32) + len * 8
+}
+
+/// Writes bytes as stored blocks, split up into `DEFLATE_MAX_STORED_LEN`
+/// bytes at most. Each one is the block header, padding to the next byte,
+/// then the length and its complement, then the bytes themselves.
+fn write_stored_blocks(bytes: &[u8], final_block: bool, writer: &mut DeflateWriter) {
+    let mut blocks = bytes.chunks(DEFLATE_MAX_STORED_LEN).peekable();
+
+    while let Some(block) = blocks.next() {
+        writer.write_bits((final_block && blocks.peek().is_none()) as u32, 1);
+        writer.write_bits(BlockType::Stored as u32, 2);
+        writer.align_to_byte();
+        writer.write_bits(block.len() as u32, 16);
+        writer.write_bits(!(block.len() as u16) as u32, 16);
+        writer.bytes.extend_from_slice(block);
+    }
+}
+
+/// Run-length encodes the code lengths with the code length alphabet, as
+/// `(symbol, extra bits)`. `16` repeats the last length 3 to 6 times, `17`
+/// is 3 to 10 zeros and `18` is 11 to 138 zeros.
+fn run_length_code_lengths(lengths: &[u8]) -> Vec<(HuffmanSymbol, u16)> {
+    let mut runs = Vec::new();
+    let mut pos = 0;
+
+    while pos < lengths.len() {
+        let len = lengths[pos];
+        let run = lengths[pos..].iter().take_while(|other| **other == len).count();
+
+        if len == 0 && run >= 11 {
+            let run = min(run, 138);
+            runs.push((18, (run - 11) as u16));
+            pos += run;
+        } else if len == 0 && run >= 3 {
+            runs.push((17, (run - 3) as u16));
+            pos += run;
+        } else if len != 0 && run >= 4 {
+            //The first one has to be written as is, so there's something to repeat
+            runs.push((len as HuffmanSymbol, 0));
+            let repeat = min(run - 1, 6);
+            runs.push((16, (repeat - 3) as u16));
+            pos += 1 + repeat;
+        } else {
+            runs.push((len as HuffmanSymbol, 0));
+            pos += 1;
+        }
+    }
+
+    runs
+}
+
+fn write_dynamic_block(tokens: &[DeflateToken], final_block: bool, encoders: &mut (HuffmanEncoder, HuffmanEncoder, HuffmanEncoder), writer: &mut DeflateWriter) {
+    let (lit_len_encoder, dist_encoder, code_len_encoder) = encoders;
+
+    let mut lit_len_symbols:Vec<HuffmanSymbol> = tokens.iter().map(|token| token.lit_len_symbol()).collect();
+    lit_len_symbols.push(END_OF_BLOCK);
+    let dist_symbols:Vec<HuffmanSymbol> = tokens.iter().filter_map(|token| match *token {
+        DeflateToken::Match(_, _, dist_symbol, _) => Some(dist_symbol),
+        _ => None
+    }).collect();
+
+    let lit_len_lengths = build_code_lengths(lit_len_encoder, &lit_len_symbols, DEFLATE_LIT_LEN_SYMBOLS);
+    let dist_lengths = build_code_lengths(dist_encoder, &dist_symbols, DEFLATE_DIST_SYMBOLS);
+    let lit_len_codes = canonical_codes(&lit_len_lengths);
+    let dist_codes = canonical_codes(&dist_lengths);
+
+    //Trailing unused symbols don't get written
+    let lit_len_num = max(lit_len_lengths.iter().rposition(|len| *len > 0).unwrap_or(0) + 1, 257);
+    let dist_num = dist_lengths.iter().rposition(|len| *len > 0).unwrap_or(0) + 1;
+
+    let mut all_lengths = lit_len_lengths[..lit_len_num].to_vec();
+    all_lengths.extend_from_slice(&dist_lengths[..dist_num]);
+    let runs = run_length_code_lengths(&all_lengths);
+    let run_symbols:Vec<HuffmanSymbol> = runs.iter().map(|(symbol, _)| *symbol).collect();
+    let code_len_lengths = build_code_lengths(code_len_encoder, &run_symbols, DEFLATE_CODE_LEN_SYMBOLS);
+    let code_len_codes = canonical_codes(&code_len_lengths);
+    let code_len_num = max(DEFLATE_CODE_LEN_ORDER.iter().rposition(|symbol| code_len_lengths[*symbol] > 0).unwrap_or(0) + 1, 4);
+
+    writer.write_bits(final_block as u32, 1);
+    writer.write_bits(BlockType::Dynamic as u32, 2);
+    writer.write_bits((lit_len_num - 257) as u32, 5);
+    writer.write_bits((dist_num - 1) as u32, 5);
+    writer.write_bits((code_len_num - 4) as u32, 4);
+    for symbol in &DEFLATE_CODE_LEN_ORDER[..code_len_num] {
+        writer.write_bits(code_len_lengths[*symbol] as u32, 3);
+    }
+    for (symbol, extra) in runs {
+        writer.write_code(code_len_codes[symbol as usize], code_len_lengths[symbol as usize] as usize);
+        match symbol {
+            16 => writer.write_bits(extra as u32, 2),
+            17 => writer.write_bits(extra as u32, 3),
+            18 => writer.write_bits(extra as u32, 7),
+            _ => {}
+        }
+    }
+
+    write_tokens(tokens, &lit_len_codes, &lit_len_lengths, &dist_codes, &dist_lengths, writer);
+}
+
+/// The fixed blocks' codes, worked out once from `fixed_code_lengths`.
+struct FixedCodes {
+    lit_len_lengths: Vec<u8>,
+    lit_len_codes: Vec<u32>,
+    dist_lengths: Vec<u8>,
+    dist_codes: Vec<u32>
+}
+
+impl FixedCodes {
+    fn new() -> Self {
+        let (lit_len_lengths, dist_lengths) = fixed_code_lengths();
+        FixedCodes {
+            lit_len_codes: canonical_codes(&lit_len_lengths),
+            dist_codes: canonical_codes(&dist_lengths),
+            lit_len_lengths,
+            dist_lengths
+        }
+    }
+}
+
+/// Writes a block with the fixed codes, which is just the header and the
+/// tokens, since there's no table.
+fn write_fixed_block(tokens: &[DeflateToken], final_block: bool, codes: &FixedCodes, writer: &mut DeflateWriter) {
+    writer.write_bits(final_block as u32, 1);
+    writer.write_bits(BlockType::Fixed as u32, 2);
+    write_tokens(tokens, &codes.lit_len_codes, &codes.lit_len_lengths, &codes.dist_codes, &codes.dist_lengths, writer);
+}
+
+/// Writes the tokens of a block and its end of block with the given codes.
+fn write_tokens(tokens: &[DeflateToken], lit_len_codes: &[u32], lit_len_lengths: &[u8], dist_codes: &[u32], dist_lengths: &[u8], writer: &mut DeflateWriter) {
+    for token in tokens {
+        let lit_len_symbol = token.lit_len_symbol() as usize;
+        writer.write_code(lit_len_codes[lit_len_symbol], lit_len_lengths[lit_len_symbol] as usize);
+        if let DeflateToken::Match(length_symbol, length_extra, dist_symbol, dist_extra) = *token {
+            writer.write_bits(length_extra as u32, extra_bits_for_length_symbol(length_symbol));
+            writer.write_code(dist_codes[dist_symbol as usize], dist_lengths[dist_symbol as usize] as usize);
+            writer.write_bits(dist_extra as u32, extra_bits_for_dist_symbol(dist_symbol));
+        }
+    }
+    writer.write_code(lit_len_codes[END_OF_BLOCK as usize], lit_len_lengths[END_OF_BLOCK as usize] as usize);
+}
+
+/// The code lengths of the literal/length and distance codes of fixed
+/// blocks, from RFC 1951. There are 288 and 32 of them, two more than can
+/// actually be used in each.
+fn fixed_code_lengths() -> (Vec<u8>, Vec<u8>) {
+    let mut lit_len_lengths = vec![8u8; 288];
+    lit_len_lengths[144..256].fill(9);
+    lit_len_lengths[256..280].fill(7);
+
+    (lit_len_lengths, vec![5u8; 32])
+}
+
+/// Reads the code lengths at the start of a dynamic block.
+fn read_dynamic_tables(reader: &mut DeflateReader) -> Result<(DeflateDecodeTable, DeflateDecodeTable), DeflateError> {
+    let lit_len_num = reader.read_bits(5)? as usize + 257;
+    let dist_num = reader.read_bits(5)? as usize + 1;
+    let code_len_num = reader.read_bits(4)? as usize + 4;
+    if lit_len_num > DEFLATE_LIT_LEN_SYMBOLS || dist_num > DEFLATE_DIST_SYMBOLS {
+        return Err(DeflateError::InvalidCodeLengths);
+    }
+
+    let mut code_len_lengths = [0u8; DEFLATE_CODE_LEN_SYMBOLS];
+    for symbol in &DEFLATE_CODE_LEN_ORDER[..code_len_num] {
+        code_len_lengths[*symbol] = reader.read_bits(3)? as u8;
+    }
+    let code_len_table = DeflateDecodeTable::new(&code_len_lengths)?;
+
+    let mut lengths = Vec::with_capacity(lit_len_num + dist_num);
+    while lengths.len() < lit_len_num + dist_num {
+        let (len, repeat) = match code_len_table.decode(reader)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => (*lengths.last().ok_or(DeflateError::InvalidCodeLengths)?, reader.read_bits(2)? as usize + 3),
+            17 => (0, reader.read_bits(3)? as usize + 3),
+            _ => (0, reader.read_bits(7)? as usize + 11)
+        };
+        if lengths.len() + repeat > lit_len_num + dist_num {
+            return Err(DeflateError::InvalidCodeLengths);
+        }
+        lengths.extend(core::iter::repeat_n(len, repeat));
+    }
+    if lengths[END_OF_BLOCK as usize] == 0 {
+        return Err(DeflateError::InvalidCodeLengths);
+    }
+
+    Ok((DeflateDecodeTable::new(&lengths[..lit_len_num])?, DeflateDecodeTable::new(&lengths[lit_len_num..])?))
+}
+
+/// Decompresses a raw RFC 1951 DEFLATE stream, like the ones
+/// `deflate_compress` makes. Stored, fixed and dynamic blocks all work, so
+/// streams from zlib or gzip are fine too.
+pub fn deflate_decompress(bytes: &[u8]) -> Result<Vec<u8>, DeflateError> {
+    deflate_decompress_with_size(bytes).map(|(decompressed, _)| decompressed)
+}
+
+/// Same as `deflate_decompress`, but also returns the number of bytes the
+/// stream took up, so whatever comes after it (like a gzip trailer) can be
+/// found.
+pub fn deflate_decompress_with_size(bytes: &[u8]) -> Result<(Vec<u8>, usize), DeflateError> {
+    let mut reader = DeflateReader::new(bytes);
+    let mut decompressed = Vec::new();
+
+    loop {
+        let final_block = reader.read_bits(1)? == 1;
+        let (lit_len_table, dist_table) = match reader.read_bits(2)? {
+            0 => {
+                let header = reader.read_aligned_bytes(4)?;
+                let len = u16::from_le_bytes([header[0], header[1]]);
+                let len_complement = u16::from_le_bytes([header[2], header[3]]);
+                if len != !len_complement {
+                    return Err(DeflateError::StoredLengthMismatch);
+                }
+                decompressed.extend_from_slice(reader.read_aligned_bytes(len as usize)?);
+
+                if final_block {
+                    break;
+                }
+                continue;
+            },
+            1 => {
+                let (lit_len_lengths, dist_lengths) = fixed_code_lengths();
+                (DeflateDecodeTable::new(&lit_len_lengths)?, DeflateDecodeTable::new(&dist_lengths)?)
+            },
+            2 => read_dynamic_tables(&mut reader)?,
+            block_type => return Err(DeflateError::InvalidBlockType(block_type as u8))
+        };
+
+        loop {
+            let symbol = lit_len_table.decode(&mut reader)?;
+            match symbol {
+                0..=255 => decompressed.push(symbol as u8),
+                END_OF_BLOCK => break,
+                257..=285 => {
+                    let length_extra = reader.read_bits(extra_bits_for_length_symbol(symbol))? as u16;
+                    let length = data_from_extra_length_bits(symbol, length_extra);
+
+                    let dist_symbol = dist_table.decode(&mut reader)?;
+                    if dist_symbol as usize >= DEFLATE_DIST_SYMBOLS {
+                        return Err(DeflateError::InvalidSymbol(dist_symbol));
+                    }
+                    let dist_extra = reader.read_bits(extra_bits_for_dist_symbol(dist_symbol))? as u16;
+                    let offset = data_from_extra_dist_bits(dist_symbol, dist_extra);
+
+                    if offset > decompressed.len() {
+                        return Err(DeflateError::OffsetTooLarge(offset));
+                    }
+                    let start_pos = decompressed.len() - offset;
+                    for i in 0..length {
+                        decompressed.push(decompressed[start_pos + i]);
+                    }
+                },
+                _ => return Err(DeflateError::InvalidSymbol(symbol))
+            }
+        }
+
+        if final_block {
+            break;
+        }
+    }
+
+    Ok((decompressed, reader.bytes_read()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lz77::{lz77_compress_simple, lz77_decompress};
+
+    #[test]
+    fn deflate_token_conversion() {
+        use crate::deflate::{lz77_to_deflate_tokens, deflate_tokens_to_lz77, DeflateToken, DEFLATE_WINDOW_SIZE, DEFLATE_MAX_MATCH_LEN};
+        use std::fs;
+
+        let mut bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        //Add a long run so some matches have to be split up
+        bytes.extend(vec![0x41u8; 4000]);
+
+        let lz77_encoded = lz77_compress_simple(&bytes, DEFLATE_WINDOW_SIZE, 3, usize::MAX, 16, usize::MAX);
+        let tokens = lz77_to_deflate_tokens(&lz77_encoded).unwrap();
+
+        assert!(tokens.last() == Some(&DeflateToken::EndOfBlock), "Tokens didn't end with an end of block");
+        assert!(tokens.iter().filter(|token| token.lit_len_symbol() == 285).count() >= 4000 / DEFLATE_MAX_MATCH_LEN, "Long run wasn't split into max length matches");
+
+        let decompressed = lz77_decompress(deflate_tokens_to_lz77(&tokens)).unwrap();
+        assert!(decompressed == bytes, "Bytes different after converting to DEFLATE tokens and back");
+    }
+
+    #[test]
+    fn deflate_compress_decompress() {
+        use crate::deflate::{deflate_compress, deflate_decompress};
+        use std::{fs, time};
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+
+        let start_time = time::Instant::now();
+        let compressed = deflate_compress(&bytes);
+        println!("DEFLATE compression time: {:?}", start_time.elapsed());
+        println!("Original size: {} Compressed size: {} Compression ratio: {:.3}", bytes.len(), compressed.len(), bytes.len() as f64 / compressed.len() as f64);
+
+        let start_time = time::Instant::now();
+        let decompressed = deflate_decompress(&compressed).unwrap();
+        println!("DEFLATE decompression time: {:?}", start_time.elapsed());
+        assert!(decompressed == bytes, "Bytes different after DEFLATE compression and decompression");
+
+        //Runs, more than one block, and nothing at all
+        let mut bytes = vec![0x41u8; 100000];
+        bytes.extend(0..=255u8);
+        bytes.extend((0..200000u32).map(|i| (i.wrapping_mul(2654435761) >> 24) as u8));
+        assert!(deflate_decompress(&deflate_compress(&bytes)).unwrap() == bytes, "Bytes different after DEFLATE compression and decompression of multiple blocks");
+        assert!(deflate_decompress(&deflate_compress(&[])).unwrap().is_empty(), "Nothing wasn't empty after DEFLATE compression and decompression");
+    }
+
+    #[test]
+    fn deflate_cost_aware_test() {
+        use crate::deflate::{deflate_compress_lz77, deflate_decompress, DEFLATE_WINDOW_SIZE, DEFLATE_MIN_MATCH_LEN, DEFLATE_MAX_MATCH_LEN, DEFLATE_MAX_CHAIN_LEN};
+        use crate::lz77::lz77_compress_cost_aware;
+        use std::fs;
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let simple = lz77_compress_simple(&bytes, DEFLATE_WINDOW_SIZE, DEFLATE_MIN_MATCH_LEN, DEFLATE_MAX_MATCH_LEN, DEFLATE_MAX_CHAIN_LEN, DEFLATE_MAX_MATCH_LEN);
+        let cost_aware = lz77_compress_cost_aware(&bytes, DEFLATE_WINDOW_SIZE, DEFLATE_MIN_MATCH_LEN, DEFLATE_MAX_MATCH_LEN, DEFLATE_MAX_CHAIN_LEN);
+        let simple_compressed = deflate_compress_lz77(&bytes, &simple);
+        let cost_aware_compressed = deflate_compress_lz77(&bytes, &cost_aware);
+        println!("Simple: [{}] data [{}] bytes Cost aware: [{}] data [{}] bytes", simple.data().len(), simple_compressed.len(), cost_aware.data().len(), cost_aware_compressed.len());
+
+        assert!(cost_aware_compressed.len() < simple_compressed.len(), "Cost aware parse compressed to [{}] bytes, simple parse to [{}]", cost_aware_compressed.len(), simple_compressed.len());
+        assert!(deflate_decompress(&cost_aware_compressed).unwrap() == bytes, "Bytes different after cost aware DEFLATE compression and decompression");
+    }
+
+    #[test]
+    fn deflate_stored_test() {
+        use crate::deflate::{deflate_compress, deflate_decompress, BlockType};
+        use rand::prelude::*;
+        use std::fs;
+
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(2123);
+        let mut random = vec![0u8; 1 << 16];
+        rng.fill_bytes(&mut random);
+
+        let compressed = deflate_compress(&random);
+        println!("Random: [{}] -> [{}]", random.len(), compressed.len());
+        assert!(compressed.len() <= random.len() + 16, "Random bytes expanded to [{}]", compressed.len());
+        assert!(compressed[0] & 0b110 == (BlockType::Stored as u8) << 1, "Random bytes weren't stored [{:#010b}]", compressed[0]);
+        assert!(deflate_decompress(&compressed).unwrap() == random, "Random bytes different after DEFLATE compression and decompression");
+
+        //Stored blocks in between dynamic ones, which won't start on a byte boundary
+        let text = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let mut mixed = text[..70000].to_vec();
+        for _ in 0..3 {
+            rng.fill_bytes(&mut random);
+            mixed.extend_from_slice(&random);
+        }
+        mixed.extend_from_slice(&text[..70000]);
+        let compressed = deflate_compress(&mixed);
+        println!("Mixed: [{}] -> [{}]", mixed.len(), compressed.len());
+        assert!(deflate_decompress(&compressed).unwrap() == mixed, "Mixed bytes different after DEFLATE compression and decompression");
+    }
+
+    #[test]
+    fn deflate_fixed_test() {
+        use crate::deflate::{deflate_compress, deflate_decompress, BlockType};
+        use std::fs;
+
+        //A final fixed block is 1, then 01 (LSB first), then the codes
+        let bytes = b"Hello, hello, hello!";
+        let compressed = deflate_compress(bytes);
+        println!("Tiny: [{}] -> [{}]", bytes.len(), compressed.len());
+        assert!(compressed[0] & 0b111 == 1 | (BlockType::Fixed as u8) << 1, "Tiny input wasn't a final fixed block [{:#010b}]", compressed[0]);
+        assert!(deflate_decompress(&compressed).unwrap() == bytes, "Tiny input different after DEFLATE compression and decompression");
+        assert!(compressed.len() < bytes.len(), "Tiny input expanded to [{}]", compressed.len());
+
+        //Nothing is a single fixed block with only an end of block, which is what zlib writes too
+        assert!(deflate_compress(&[]) == [0x03, 0x00], "Nothing compressed to [{:02x?}]", deflate_compress(&[]));
+
+        //Big inputs still get dynamic blocks
+        let text = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let compressed = deflate_compress(&text);
+        assert!(compressed[0] & 0b110 == (BlockType::Dynamic as u8) << 1, "lorem_ipsum wasn't dynamic [{:#010b}]", compressed[0]);
+        assert!(deflate_decompress(&compressed).unwrap() == text, "lorem_ipsum different after DEFLATE compression and decompression");
+    }
+
+    #[test]
+    fn deflate_token_tables() {
+        use crate::deflate::{length_symbol, dist_symbol, data_from_extra_length_bits, data_from_extra_dist_bits, extra_bits_for_length_symbol, extra_bits_for_dist_symbol};
+
+        for length in 3..=258 {
+            let symbol = length_symbol(length);
+            let extra = length - data_from_extra_length_bits(symbol, 0);
+            assert!(extra < 1 << extra_bits_for_length_symbol(symbol), "Length [{length}] has too many extra bits for symbol [{symbol}]");
+            assert!(data_from_extra_length_bits(symbol, extra as u16) == length, "Length [{length}] doesn't map back from symbol [{symbol}]");
+        }
+
+        for dist in 1..=32768 {
+            let symbol = dist_symbol(dist);
+            let extra = dist - data_from_extra_dist_bits(symbol, 0);
+            assert!(extra < 1 << extra_bits_for_dist_symbol(symbol), "Distance [{dist}] has too many extra bits for symbol [{symbol}]");
+            assert!(data_from_extra_dist_bits(symbol, extra as u16) == dist, "Distance [{dist}] doesn't map back from symbol [{symbol}]");
+        }
+    }
+
+    #[test]
+    fn deflate_token_conversion_errors() {
+        use crate::deflate::{lz77_to_deflate_tokens, DeflateError};
+        use crate::lz77::{LZ77Data, LZ77Encoded};
+
+        let encoded = LZ77Encoded::new(vec![LZ77Data::Literal(0x41), LZ77Data::Match(3, 40000)]);
+        assert!(lz77_to_deflate_tokens(&encoded) == Err(DeflateError::OffsetTooLarge(40000)), "Offset outside the window didn't error");
+
+        let encoded = LZ77Encoded::new(vec![LZ77Data::Literal(0x41), LZ77Data::Match(2, 1)]);
+        assert!(lz77_to_deflate_tokens(&encoded) == Err(DeflateError::MatchTooShort(2)), "Match that's too short didn't error");
+    }
+}
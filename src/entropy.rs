@@ -0,0 +1,258 @@
+use crate::bitstream::{BitWriter, BitReader};
+use crate::huffman::HuffmanSymbol;
+use alloc::{vec::Vec, vec};
+
+/// The biggest total a `FrequencyModel` scales its frequencies down to. The
+/// range coder needs this to fit in its bottom `16` bits.
+pub const ENTROPY_MAX_TOTAL:u32 = 1 << 16;
+/// The number of bits used to write the number of symbols in a model, the
+/// symbols themselves, and each of their frequencies (minus one).
+const ENTROPY_MODEL_FIELD_BITS:usize = 16;
+/// The number of bits used to write how many symbols `entropy_encode` wrote.
+const ENTROPY_COUNT_BITS:usize = 32;
+
+/// How often each symbol shows up, as far as an entropy coder is concerned.
+///
+/// Symbols take up `freq` out of `total`, starting at `cum_freq` (the sum of
+/// the frequencies of every symbol before them). Symbols with a frequency of
+/// `0` can't be encoded.
+pub trait Model {
+    fn freq(&self, symbol: HuffmanSymbol) -> u32;
+    fn cum_freq(&self, symbol: HuffmanSymbol) -> u32;
+    fn total(&self) -> u32;
+    /// The number of symbols in the alphabet, which go from `0` up to
+    /// `num_symbols() - 1`.
+    fn num_symbols(&self) -> usize;
+
+    /// The symbol whose range has `target` in it, which is what decoders need
+    /// to go back the other way. `target` has to be less than `total`.
+    ///
+    /// The default just binary searches `cum_freq`.
+    fn symbol_at(&self, target: u32) -> HuffmanSymbol {
+        let (mut low, mut high) = (0, self.num_symbols());
+        while high - low > 1 {
+            let mid = (low + high) / 2;
+            if self.cum_freq(mid as HuffmanSymbol) <= target {low = mid;} else {high = mid;}
+        }
+        low as HuffmanSymbol
+    }
+}
+
+/// Something that can turn symbols into bits and back using a `Model`.
+///
+/// Neither the model nor the number of symbols gets written, so the decoder
+/// has to be given the same ones, see `entropy_encode` for something that
+/// writes both.
+pub trait EntropyCoder {
+    /// Encodes every symbol. `panic`s if one of them has a frequency of `0`.
+    fn encode<M: Model>(&mut self, model: &M, symbols: &[HuffmanSymbol], writer: &mut BitWriter);
+    /// Decodes `count` symbols, or returns `None` if the reader runs out first.
+    fn decode<M: Model>(&mut self, model: &M, reader: &mut BitReader, count: usize) -> Option<Vec<HuffmanSymbol>>;
+}
+
+/// The simplest model there is: every symbol gets how many times it showed up
+/// (order-0), and it never changes (static).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrequencyModel {
+    freqs: Vec<u32>,
+    /// `cum_freqs[i]` is the sum of `freqs[..i]`, with the total at the end.
+    cum_freqs: Vec<u32>
+}
+
+impl FrequencyModel {
+    /// Makes a model out of frequencies for the symbols `0` up to
+    /// `freqs.len() - 1`, scaling them down if they add up to more than
+    /// `ENTROPY_MAX_TOTAL`. Symbols that showed up at all always keep a
+    /// frequency of at least `1`.
+    ///
+    /// `panic`s if there are `ENTROPY_MAX_TOTAL` or more symbols, since they
+    /// couldn't all fit.
+    pub fn from_freqs(freqs: &[u64]) -> Self {
+        assert!(freqs.len() < ENTROPY_MAX_TOTAL as usize, "Model can have less than [{ENTROPY_MAX_TOTAL}] symbols, given [{}] symbols", freqs.len());
+
+        let total:u64 = freqs.iter().sum();
+        //Leave room for every symbol getting rounded up to 1
+        let scaled_total = (ENTROPY_MAX_TOTAL as usize - freqs.len()) as u64;
+        let freqs:Vec<u32> = freqs.iter().map(|&freq| {
+            if total <= ENTROPY_MAX_TOTAL as u64 || freq == 0 {freq as u32} else {(freq * scaled_total / total).max(1) as u32}
+        }).collect();
+
+        let mut cum_freqs = Vec::with_capacity(freqs.len() + 1);
+        let mut cum_freq = 0;
+        cum_freqs.push(cum_freq);
+        for freq in &freqs {
+            cum_freq += freq;
+            cum_freqs.push(cum_freq);
+        }
+
+        FrequencyModel { freqs, cum_freqs }
+    }
+
+    /// Counts the symbols to make a model for an alphabet of `num_symbols`.
+    pub fn from_symbols(symbols: &[HuffmanSymbol], num_symbols: usize) -> Self {
+        let mut freqs = vec![0; num_symbols];
+        for &symbol in symbols {
+            freqs[symbol as usize] += 1;
+        }
+        Self::from_freqs(&freqs)
+    }
+
+    /// Writes the number of symbols with a frequency, then each of those
+    /// symbols and its frequency.
+    pub fn write(&self, writer: &mut BitWriter) {
+        let used = self.freqs.iter().filter(|&&freq| freq > 0).count();
+        writer.write_bits_u32(used as u32, ENTROPY_MODEL_FIELD_BITS);
+        for (symbol, &freq) in self.freqs.iter().enumerate().filter(|(_, &freq)| freq > 0) {
+            writer.write_bits_u32(symbol as u32, ENTROPY_MODEL_FIELD_BITS);
+            writer.write_bits_u32(freq - 1, ENTROPY_MODEL_FIELD_BITS);
+        }
+    }
+
+    /// Reads a model written with `write` for an alphabet of `num_symbols`.
+    /// Returns `None` if the reader runs out, a symbol is past the alphabet,
+    /// or the frequencies add up to 0 or more than `ENTROPY_MAX_TOTAL`.
+    pub fn read(reader: &mut BitReader, num_symbols: usize) -> Option<Self> {
+        let used = reader.read_bits_into_u32_exact(ENTROPY_MODEL_FIELD_BITS)? as usize;
+        let mut freqs = vec![0; num_symbols];
+        for _ in 0..used {
+            let symbol = reader.read_bits_into_u32_exact(ENTROPY_MODEL_FIELD_BITS)? as usize;
+            let freq = reader.read_bits_into_u32_exact(ENTROPY_MODEL_FIELD_BITS)? as u64 + 1;
+            *freqs.get_mut(symbol)? = freq;
+        }
+
+        let total = freqs.iter().sum::<u64>();
+        if total == 0 || total > ENTROPY_MAX_TOTAL as u64 {
+            return None;
+        }
+        Some(Self::from_freqs(&freqs))
+    }
+}
+
+impl Model for FrequencyModel {
+    fn freq(&self, symbol: HuffmanSymbol) -> u32 {
+        self.freqs[symbol as usize]
+    }
+
+    fn cum_freq(&self, symbol: HuffmanSymbol) -> u32 {
+        self.cum_freqs[symbol as usize]
+    }
+
+    fn total(&self) -> u32 {
+        *self.cum_freqs.last().unwrap()
+    }
+
+    fn num_symbols(&self) -> usize {
+        self.freqs.len()
+    }
+}
+
+/// Encodes symbols with any `EntropyCoder`, writing the number of symbols and
+/// a `FrequencyModel` of them first so `entropy_decode` doesn't need to be
+/// told anything but the alphabet size. With no symbols there's no model to
+/// write, so only the count of 0 is written.
+pub fn entropy_encode<C: EntropyCoder>(coder: &mut C, symbols: &[HuffmanSymbol], num_symbols: usize, writer: &mut BitWriter) {
+    writer.write_bits_u32(symbols.len() as u32, ENTROPY_COUNT_BITS);
+    if symbols.is_empty() {
+        return;
+    }
+    let model = FrequencyModel::from_symbols(symbols, num_symbols);
+    model.write(writer);
+    coder.encode(&model, symbols, writer);
+}
+
+/// Decodes symbols written with `entropy_encode` and the same kind of coder.
+/// Returns `None` if the reader runs out or the model is corrupt.
+pub fn entropy_decode<C: EntropyCoder>(coder: &mut C, reader: &mut BitReader, num_symbols: usize) -> Option<Vec<HuffmanSymbol>> {
+    let count = reader.read_bits_into_u32_exact(ENTROPY_COUNT_BITS)? as usize;
+    if count == 0 {
+        return Some(Vec::new());
+    }
+    let model = FrequencyModel::read(reader, num_symbols)?;
+    coder.decode(&model, reader, count)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::entropy::{FrequencyModel, Model, ENTROPY_MAX_TOTAL};
+
+    #[test]
+    fn entropy_model_test() {
+        use crate::bitstream::{BitWriter, BitReader};
+
+        let model = FrequencyModel::from_symbols(&[1, 3, 3, 0, 3], 5);
+        assert!((0..5).map(|symbol| model.freq(symbol)).eq([1, 1, 0, 3, 0]), "Wrong frequencies [{model:?}]");
+        assert!((0..5).map(|symbol| model.cum_freq(symbol)).eq([0, 1, 2, 2, 5]), "Wrong cumulative frequencies [{model:?}]");
+        assert!(model.total() == 5, "Wrong total [{}]", model.total());
+        assert!((0..5).map(|target| model.symbol_at(target)).eq([0, 1, 3, 3, 3]), "Wrong symbols found from cumulative frequencies");
+
+        let mut writer = BitWriter::new();
+        model.write(&mut writer);
+        let bytes = writer.get_bytes();
+        assert!(FrequencyModel::read(&mut BitReader::new(&bytes), 5) == Some(model), "Model different after writing and reading");
+        assert!(FrequencyModel::read(&mut BitReader::new(&bytes), 2).is_none(), "Model with symbols past the alphabet was read");
+
+        //No symbols at all means a total of 0, which nothing can be decoded with
+        let mut writer = BitWriter::new();
+        FrequencyModel::from_symbols(&[], 5).write(&mut writer);
+        assert!(FrequencyModel::read(&mut BitReader::new(&writer.get_bytes()), 5).is_none(), "Model with a total of 0 was read");
+
+        //Way too many symbols get scaled down, without losing any
+        let model = FrequencyModel::from_freqs(&[1, 1_000_000, 0, 3_000_000]);
+        println!("Scaled model: {model:?}");
+        assert!(model.total() <= ENTROPY_MAX_TOTAL, "Scaled total too big [{}]", model.total());
+        assert!(model.freq(0) == 1 && model.freq(2) == 0 && model.freq(3) > model.freq(1), "Frequencies scaled wrong [{model:?}]");
+    }
+
+    #[test]
+    fn entropy_lz77_test() {
+        use crate::bitstream::{BitWriter, BitReader};
+        use crate::entropy::{entropy_encode, entropy_decode};
+        use crate::huffman::{HuffmanEncoder, HuffmanSymbol, HUFFMAN_MAX_SYMBOLS};
+        use crate::lz77::{lz77_compress_simple, lz77_decompress, LZ77Data, LZ77Encoded};
+        use crate::range::RangeCoder;
+        use std::fs;
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let encoded = lz77_compress_simple(&bytes, 0xFFFF, 3, 256, 16, 256);
+
+        //Literals are bytes, matches are their length past 256 followed by the two bytes of the offset
+        let symbols:Vec<HuffmanSymbol> = encoded.data().iter().flat_map(|data| match *data {
+            LZ77Data::Literal(val) => vec![val as HuffmanSymbol],
+            LZ77Data::Match(length, offset) => vec![256 + length as HuffmanSymbol - 3, (offset >> 8) as HuffmanSymbol, (offset & 0xFF) as HuffmanSymbol]
+        }).collect();
+        let to_data = |symbols: &[HuffmanSymbol]| {
+            let mut data = Vec::new();
+            let mut symbols = symbols.iter().map(|&symbol| symbol as usize);
+            while let Some(symbol) = symbols.next() {
+                if symbol < 256 {
+                    data.push(LZ77Data::Literal(symbol as u8));
+                } else {
+                    let offset = (symbols.next().unwrap() << 8) | symbols.next().unwrap();
+                    data.push(LZ77Data::Match(symbol - 256 + 3, offset));
+                }
+            }
+            LZ77Encoded::new(data)
+        };
+
+        let mut huffman_writer = BitWriter::new();
+        entropy_encode(&mut HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS), &symbols, HUFFMAN_MAX_SYMBOLS, &mut huffman_writer);
+        let huffman_bytes = huffman_writer.get_bytes();
+        let mut range_writer = BitWriter::new();
+        entropy_encode(&mut RangeCoder::new(), &symbols, HUFFMAN_MAX_SYMBOLS, &mut range_writer);
+        let range_bytes = range_writer.get_bytes();
+        println!("Symbols: [{}] Huffman bytes: [{}] Range coder bytes: [{}]", symbols.len(), huffman_bytes.len(), range_bytes.len());
+
+        let huffman_decoded = entropy_decode(&mut HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS), &mut BitReader::new(&huffman_bytes), HUFFMAN_MAX_SYMBOLS);
+        assert!(huffman_decoded.as_ref() == Some(&symbols), "LZ77 symbols different after going through Huffman");
+        let range_decoded = entropy_decode(&mut RangeCoder::new(), &mut BitReader::new(&range_bytes), HUFFMAN_MAX_SYMBOLS);
+        assert!(range_decoded.as_ref() == Some(&symbols), "LZ77 symbols different after going through the range coder");
+
+        let mut writer = BitWriter::new();
+        entropy_encode(&mut RangeCoder::new(), &[], HUFFMAN_MAX_SYMBOLS, &mut writer);
+        assert!(entropy_decode(&mut RangeCoder::new(), &mut BitReader::new(&writer.get_bytes()), HUFFMAN_MAX_SYMBOLS) == Some(Vec::new()), "No symbols different after going through the range coder");
+
+        assert!(lz77_decompress(to_data(&huffman_decoded.unwrap())).unwrap() == bytes, "LZ77 with Huffman resulted in different bytes");
+        assert!(lz77_decompress(to_data(&range_decoded.unwrap())).unwrap() == bytes, "LZ77 with the range coder resulted in different bytes");
+        assert!(range_bytes.len() <= huffman_bytes.len(), "Range coder did worse than Huffman, {} > {}", range_bytes.len(), huffman_bytes.len());
+    }
+}
@@ -0,0 +1,69 @@
+use core::fmt;
+use crate::lzw::LzwError;
+use crate::deflate::DeflateError;
+use crate::huffman::HuffmanError;
+use crate::rle::RleError;
+
+/// The errors that can happen while decoding any of the codecs.
+#[derive(Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The decoded bytes don't fit in the output they're being written to.
+    OutputTooLarge,
+    /// The bytes ended before everything in them was read.
+    UnexpectedEnd,
+    /// A BWT block's primary index isn't one of its rows.
+    BadBwtIndex(usize),
+    /// An LZ77 match's offset is `0` or reaches back before the first byte.
+    BadMatchOffset(usize),
+    /// An LZ77 stream says its match lengths take more than `32` bits, which
+    /// `LZ77Encoded::write` never does.
+    BadMatchLengthBits(usize),
+    /// An LZ77 stream's literals and matches don't add up to the decompressed
+    /// size in its header. If they go past it, `actual` is as far as they got.
+    BadDecompressedSize { expected: usize, actual: usize },
+    Huffman(HuffmanError),
+    Lzw(LzwError),
+    Deflate(DeflateError),
+    Rle(RleError)
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::OutputTooLarge => write!(f, "Decoded bytes don't fit in the output"),
+            DecodeError::UnexpectedEnd => write!(f, "Bytes ended before they were all decoded"),
+            DecodeError::BadBwtIndex(index) => write!(f, "BWT primary index [{index}] is outside its block"),
+            DecodeError::BadMatchOffset(offset) => write!(f, "LZ77 match offset [{offset}] reaches outside the decoded bytes"),
+            DecodeError::BadMatchLengthBits(bits) => write!(f, "LZ77 match lengths can't take [{bits}] bits"),
+            DecodeError::BadDecompressedSize { expected, actual } => write!(f, "LZ77 literals and matches add up to [{actual}] bytes, the header says [{expected}]"),
+            DecodeError::Huffman(err) => write!(f, "Huffman error: {err}"),
+            DecodeError::Lzw(err) => write!(f, "LZW error: {err}"),
+            DecodeError::Deflate(err) => write!(f, "DEFLATE error: {err}"),
+            DecodeError::Rle(err) => write!(f, "RLE error: {err}")
+        }
+    }
+}
+
+impl From<HuffmanError> for DecodeError {
+    fn from(err: HuffmanError) -> Self {
+        DecodeError::Huffman(err)
+    }
+}
+
+impl From<LzwError> for DecodeError {
+    fn from(err: LzwError) -> Self {
+        DecodeError::Lzw(err)
+    }
+}
+
+impl From<DeflateError> for DecodeError {
+    fn from(err: DeflateError) -> Self {
+        DecodeError::Deflate(err)
+    }
+}
+
+impl From<RleError> for DecodeError {
+    fn from(err: RleError) -> Self {
+        DecodeError::Rle(err)
+    }
+}
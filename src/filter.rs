@@ -0,0 +1,90 @@
+/// Delta filter. Every byte gets replaced by how much it changed since the
+/// byte before it, in place, wrapping around on overflow. The first byte is
+/// left as is.
+///
+/// Data that changes slowly (like sensor logs or scanlines) turns into
+/// mostly values near `0`, which `HuffmanEncoder` gives short codes.
+pub fn delta_encode(bytes: &mut [u8]) {
+    delta_encode_stride(bytes, 1);
+}
+
+/// The inverse of `delta_encode`.
+pub fn delta_decode(bytes: &mut [u8]) {
+    delta_decode_stride(bytes, 1);
+}
+
+/// Same as `delta_encode`, except every byte is compared with the one
+/// `stride` bytes before it, for interleaved channels like the `3` bytes of
+/// RGB pixels. The first `stride` bytes are left as is.
+pub fn delta_encode_stride(bytes: &mut [u8], stride: usize) {
+    assert!(stride > 0, "Stride can't be 0");
+
+    //Backwards, so every byte is still the original when the one after it needs it
+    for i in (stride..bytes.len()).rev() {
+        bytes[i] = bytes[i].wrapping_sub(bytes[i - stride]);
+    }
+}
+
+/// The inverse of `delta_encode_stride`, given the same stride.
+pub fn delta_decode_stride(bytes: &mut [u8], stride: usize) {
+    assert!(stride > 0, "Stride can't be 0");
+
+    for i in stride..bytes.len() {
+        bytes[i] = bytes[i].wrapping_add(bytes[i - stride]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::filter::{delta_encode, delta_decode, delta_encode_stride, delta_decode_stride};
+
+    #[test]
+    fn delta_test() {
+        use std::fs;
+
+        let mut bytes = vec![10, 12, 15, 15, 3, 255, 0];
+        delta_encode(&mut bytes);
+        assert!(bytes == [10, 2, 3, 0, 244, 252, 1], "Wrong deltas [{bytes:?}]");
+        delta_decode(&mut bytes);
+        assert!(bytes == [10, 12, 15, 15, 3, 255, 0], "Bytes different after delta encoding and decoding [{bytes:?}]");
+
+        let original = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let mut bytes = original.clone();
+        delta_encode(&mut bytes);
+        delta_decode(&mut bytes);
+        assert!(bytes == original, "lorem_ipsum different after delta encoding and decoding");
+
+        let mut nothing:[u8; 0] = [];
+        delta_encode(&mut nothing);
+        delta_decode(&mut nothing);
+    }
+
+    #[test]
+    fn delta_stride_test() {
+        use crate::huffman::{HuffmanEncoder, HUFFMAN_MAX_SYMBOLS};
+        use crate::bitstream::BitWriter;
+
+        //A gradient of RGB pixels, where each channel changes at its own pace
+        let original:Vec<u8> = (0..30000u32).flat_map(|i| [(i / 7) as u8, (i * 3 / 11) as u8, (1000 - i / 31) as u8]).collect();
+        let mut bytes = original.clone();
+        delta_encode_stride(&mut bytes, 3);
+        let small = bytes.iter().filter(|byte| (**byte as i8).abs() <= 1).count();
+        assert!(small * 10 >= bytes.len() * 9, "Only [{small}] out of [{}] deltas were small", bytes.len());
+
+        let encoded_size = |bytes: &[u8]| {
+            let mut writer = BitWriter::new();
+            HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS).encode_all_bytes(bytes, bytes.len(), &mut writer);
+            writer.get_bytes().len()
+        };
+        println!("Huffman size without delta: [{}] with delta: [{}]", encoded_size(&original), encoded_size(&bytes));
+        assert!(encoded_size(&bytes) < encoded_size(&original), "Delta didn't help Huffman");
+
+        delta_decode_stride(&mut bytes, 3);
+        assert!(bytes == original, "RGB bytes different after delta encoding and decoding with a stride of 3");
+
+        //Strides longer than the bytes leave them alone
+        let mut bytes = vec![1, 2];
+        delta_encode_stride(&mut bytes, 3);
+        assert!(bytes == [1, 2], "Stride longer than the bytes changed them");
+    }
+}
@@ -0,0 +1,444 @@
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use crate::api::{compress, decompress, Codec};
+use crate::bitstream::{BitWriter, BitReader};
+use crate::checksum::crc32;
+use crate::error::DecodeError;
+
+/// The bytes every frame file starts with, followed by `FRAME_VERSION`.
+pub const FRAME_MAGIC:[u8; 3] = *b"LZF";
+pub const FRAME_VERSION:u8 = 1;
+/// The bytes every frame file ends with, right after the trailer.
+pub const FRAME_INDEX_MAGIC:[u8; 4] = *b"LZFX";
+/// The size of the file header: the magic and the version.
+pub const FRAME_FILE_HEADER_SIZE:usize = 3 + 1;
+/// The size of each frame's header: the codec id, then the compressed length,
+/// the uncompressed length and the CRC-32 of the uncompressed bytes, all
+/// `u32`s in little endian.
+pub const FRAME_HEADER_SIZE:usize = 1 + 4 + 4 + 4;
+/// The size of the trailer after the index: the number of frames as a `u32`,
+/// the offset of the index as a `u64`, both little endian, and then
+/// `FRAME_INDEX_MAGIC`.
+pub const FRAME_TRAILER_SIZE:usize = 4 + 8 + 4;
+/// The most bytes the varint length `write` puts before a payload can take,
+/// which is enough for any `u64`.
+const FRAME_MAX_VARINT_LEN:usize = 10;
+
+#[derive(Debug)]
+pub enum FrameError {
+    Io(io::Error),
+    /// The file doesn't start with `FRAME_MAGIC`.
+    BadMagic,
+    UnsupportedVersion(u8),
+    /// The file doesn't end with a trailer, probably because the writer was
+    /// never finished.
+    MissingIndex,
+    UnknownCodec(u8),
+    /// Asked for a frame past the last one.
+    FrameOutOfRange { frame: usize, frame_num: usize },
+    /// The payload couldn't be decompressed.
+    Decode(DecodeError),
+    /// The payload decompressed to a different number of bytes than the frame
+    /// header says it should.
+    LengthMismatch { expected: usize, actual: usize },
+    /// The decompressed bytes don't have the CRC-32 the frame header says they
+    /// should.
+    ChecksumMismatch { expected: u32, actual: u32 }
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FrameError::Io(err) => write!(f, "{err}"),
+            FrameError::BadMagic => write!(f, "Frame file doesn't start with the magic number"),
+            FrameError::UnsupportedVersion(version) => write!(f, "Unsupported frame format version {version}"),
+            FrameError::MissingIndex => write!(f, "Frame file doesn't end with an index"),
+            FrameError::UnknownCodec(id) => write!(f, "Unknown codec id {id}"),
+            FrameError::FrameOutOfRange { frame, frame_num } => write!(f, "Frame {frame} asked for, but there are only {frame_num} frames"),
+            FrameError::Decode(err) => write!(f, "{err}"),
+            FrameError::LengthMismatch { expected, actual } => write!(f, "Decompressed {actual} bytes, expected {expected}"),
+            FrameError::ChecksumMismatch { expected, actual } => write!(f, "CRC-32 mismatch, expected {expected:08x}, got {actual:08x}")
+        }
+    }
+}
+
+impl From<io::Error> for FrameError {
+    fn from(err: io::Error) -> Self {
+        FrameError::Io(err)
+    }
+}
+
+impl From<DecodeError> for FrameError {
+    fn from(err: DecodeError) -> Self {
+        FrameError::Decode(err)
+    }
+}
+
+/// What a frame's header says about it, plus where it starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameInfo {
+    /// Where the frame header starts, from the start of the file.
+    pub offset: u64,
+    pub codec: Codec,
+    pub compressed_len: usize,
+    pub uncompressed_len: usize,
+    pub crc: u32
+}
+
+/// Writes a frame file, where each call to `write_frame` compresses its bytes
+/// into a frame of their own with its own CRC-32. That makes it good for logs
+/// and other data that only ever gets appended to, since every frame can be
+/// read (and checked) on its own.
+///
+/// The file looks like this:
+/// - `FRAME_MAGIC` and `FRAME_VERSION`
+/// - The frames, each a header (see `FRAME_HEADER_SIZE`) followed by the
+///   compressed bytes
+/// - The index, the offset of every frame as a little endian `u64`
+/// - The trailer (see `FRAME_TRAILER_SIZE`), so readers can find the index
+///   from the end of the file
+///
+/// WARNING: Remember to `finish` the writer, otherwise there's no index and
+/// `FrameReader` can't read the file!
+pub struct FrameWriter<W: Write>{
+    inner: W,
+    codec: Codec,
+    offsets: Vec<u64>,
+    pos: u64
+}
+
+impl<W: Write> FrameWriter<W>{
+    /// Starts a frame file, writing its header right away. Every frame is
+    /// compressed with `codec` until `set_codec` changes it.
+    pub fn new(mut inner: W, codec: Codec) -> io::Result<Self> {
+        inner.write_all(&FRAME_MAGIC)?;
+        inner.write_all(&[FRAME_VERSION])?;
+
+        Ok(FrameWriter { inner, codec, offsets: Vec::new(), pos: FRAME_FILE_HEADER_SIZE as u64 })
+    }
+
+    /// Changes the codec used for the frames written after this. Each frame
+    /// says which codec it uses, so they can be mixed freely.
+    pub fn set_codec(&mut self, codec: Codec) {
+        self.codec = codec;
+    }
+
+    /// The number of frames written so far.
+    pub fn frame_num(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Compresses `bytes` into a new frame, returning its number.
+    ///
+    /// `panic`s if `bytes` or the compressed bytes are `4 GiB` or larger,
+    /// since the lengths are written as `u32`s.
+    pub fn write_frame(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        let compressed = compress(self.codec, bytes);
+        assert!(bytes.len() <= u32::MAX as usize && compressed.len() <= u32::MAX as usize, "Frame is too large to write [{}] bytes", bytes.len());
+
+        self.inner.write_all(&[self.codec as u8])?;
+        self.inner.write_all(&(compressed.len() as u32).to_le_bytes())?;
+        self.inner.write_all(&(bytes.len() as u32).to_le_bytes())?;
+        self.inner.write_all(&crc32(bytes).to_le_bytes())?;
+        self.inner.write_all(&compressed)?;
+
+        self.offsets.push(self.pos);
+        self.pos += (FRAME_HEADER_SIZE + compressed.len()) as u64;
+
+        Ok(self.offsets.len() - 1)
+    }
+
+    /// Writes the index and trailer, and gives back the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let index_offset = self.pos;
+        for offset in &self.offsets {
+            self.inner.write_all(&offset.to_le_bytes())?;
+        }
+        self.inner.write_all(&(self.offsets.len() as u32).to_le_bytes())?;
+        self.inner.write_all(&index_offset.to_le_bytes())?;
+        self.inner.write_all(&FRAME_INDEX_MAGIC)?;
+        self.inner.flush()?;
+
+        Ok(self.inner)
+    }
+}
+
+/// Reads frames out of a file written by `FrameWriter`, in any order. Only the
+/// index is read up front, each frame is only read when it's asked for.
+pub struct FrameReader<R: Read + Seek>{
+    inner: R,
+    offsets: Vec<u64>
+}
+
+impl<R: Read + Seek> FrameReader<R>{
+    /// Checks the file header and reads the index.
+    pub fn new(mut inner: R) -> Result<Self, FrameError> {
+        let mut header = [0u8; FRAME_FILE_HEADER_SIZE];
+        inner.seek(SeekFrom::Start(0))?;
+        inner.read_exact(&mut header).map_err(|_| FrameError::BadMagic)?;
+        if header[..3] != FRAME_MAGIC {
+            return Err(FrameError::BadMagic);
+        }
+        if header[3] != FRAME_VERSION {
+            return Err(FrameError::UnsupportedVersion(header[3]));
+        }
+
+        let file_len = inner.seek(SeekFrom::End(0))?;
+        if file_len < (FRAME_FILE_HEADER_SIZE + FRAME_TRAILER_SIZE) as u64 {
+            return Err(FrameError::MissingIndex);
+        }
+        let mut trailer = [0u8; FRAME_TRAILER_SIZE];
+        inner.seek(SeekFrom::Start(file_len - FRAME_TRAILER_SIZE as u64))?;
+        inner.read_exact(&mut trailer)?;
+        if trailer[12..16] != FRAME_INDEX_MAGIC {
+            return Err(FrameError::MissingIndex);
+        }
+
+        let frame_num = u32::from_le_bytes(trailer[0..4].try_into().unwrap()) as u64;
+        let index_offset = u64::from_le_bytes(trailer[4..12].try_into().unwrap());
+        //The index has to fit exactly between the frames and the trailer
+        if index_offset.checked_add(frame_num * 8) != Some(file_len - FRAME_TRAILER_SIZE as u64) {
+            return Err(FrameError::MissingIndex);
+        }
+
+        let mut index = vec![0u8; (frame_num * 8) as usize];
+        inner.seek(SeekFrom::Start(index_offset))?;
+        inner.read_exact(&mut index)?;
+        let offsets = index.chunks_exact(8).map(|offset| u64::from_le_bytes(offset.try_into().unwrap())).collect();
+
+        Ok(FrameReader { inner, offsets })
+    }
+
+    pub fn frame_num(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Reads a frame's header, without reading or decompressing the rest of
+    /// it.
+    pub fn frame_info(&mut self, frame: usize) -> Result<FrameInfo, FrameError> {
+        let offset = *self.offsets.get(frame).ok_or(FrameError::FrameOutOfRange { frame, frame_num: self.offsets.len() })?;
+
+        let mut header = [0u8; FRAME_HEADER_SIZE];
+        self.inner.seek(SeekFrom::Start(offset))?;
+        self.inner.read_exact(&mut header)?;
+
+        Ok(FrameInfo {
+            offset,
+            codec: Codec::try_from(header[0]).map_err(|_| FrameError::UnknownCodec(header[0]))?,
+            compressed_len: u32::from_le_bytes(header[1..5].try_into().unwrap()) as usize,
+            uncompressed_len: u32::from_le_bytes(header[5..9].try_into().unwrap()) as usize,
+            crc: u32::from_le_bytes(header[9..13].try_into().unwrap())
+        })
+    }
+
+    /// Seeks to a frame, decompresses it and checks it against the length and
+    /// CRC-32 in its header.
+    pub fn read_frame(&mut self, frame: usize) -> Result<Vec<u8>, FrameError> {
+        let info = self.frame_info(frame)?;
+
+        //frame_info leaves the reader right at the start of the payload. The
+        //length comes from the file, so only what's really there gets allocated
+        let mut compressed = Vec::new();
+        (&mut self.inner).take(info.compressed_len as u64).read_to_end(&mut compressed)?;
+        if compressed.len() != info.compressed_len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        let decompressed = decompress(info.codec, &compressed)?;
+
+        if decompressed.len() != info.uncompressed_len {
+            return Err(FrameError::LengthMismatch { expected: info.uncompressed_len, actual: decompressed.len() });
+        }
+        let actual_crc = crc32(&decompressed);
+        if actual_crc != info.crc {
+            return Err(FrameError::ChecksumMismatch { expected: info.crc, actual: actual_crc });
+        }
+
+        Ok(decompressed)
+    }
+
+    /// Gives back the inner reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+/// Writes `payload` with its length in front, so it can be read back with
+/// `read` no matter what comes after it. Unlike a frame file, there's no
+/// codec id or checksum, it's just a way of putting several codecs' outputs
+/// back to back in one stream.
+///
+/// The length is a varint (`7` bits at a time, lowest first, with the top bit
+/// set on every byte but the last). The writer is aligned to a byte first, so
+/// the length and the payload are whole bytes.
+pub fn write(writer: &mut BitWriter, payload: &[u8]) {
+    writer.align_to_byte();
+
+    let mut len = payload.len() as u64;
+    while len >= 0x80 {
+        writer.write_bits_u16((len as u16 & 0x7F) | 0x80, 8);
+        len >>= 7;
+    }
+    writer.write_bits_u16(len as u16, 8);
+
+    for &byte in payload {
+        writer.write_bits_u16(byte as u16, 8);
+    }
+}
+
+/// Reads a payload written by `write`, leaving the reader right after it.
+/// Returns `None` if the reader runs out first, or the length is too long to
+/// be a varint.
+pub fn read(reader: &mut BitReader) -> Option<Vec<u8>> {
+    reader.align_to_byte();
+
+    let mut len:u64 = 0;
+    for i in 0..FRAME_MAX_VARINT_LEN {
+        let byte = reader.read_bits_into_u8(8)?;
+        len |= ((byte & 0x7F) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            let len = usize::try_from(len).ok()?;
+            if len > reader.remaining_bits() >> 3 {
+                return None;
+            }
+
+            let mut payload = vec![0u8; len];
+            reader.read_bytes_aligned(&mut payload)?;
+            return Some(payload);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Cursor;
+    use crate::api::Codec;
+    use crate::frame::{FrameWriter, FrameReader, FrameError};
+
+    #[test]
+    fn frame_payload_test() {
+        use crate::bitstream::{BitWriter, BitReader};
+        use crate::frame::{read, write};
+        use crate::huffman::{HuffmanEncoder, HuffmanDecoder, HUFFMAN_MAX_SYMBOLS, HUFFMAN_DEFAULT_CHUNK_SIZE};
+        use crate::lzw::{compress_lzw, decompress_lzw};
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+
+        //Three codecs' outputs back to back, the last one long enough for a 3 byte length
+        let mut huffman_writer = BitWriter::new();
+        HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS).encode_all_bytes(&bytes[..5000], HUFFMAN_DEFAULT_CHUNK_SIZE, &mut huffman_writer);
+        let payloads = [huffman_writer.get_bytes(), Vec::new(), compress_lzw(&bytes)];
+
+        let mut writer = BitWriter::new();
+        //Not on a byte boundary to start with
+        writer.write_bits_u16(0b101, 3);
+        for payload in &payloads {
+            write(&mut writer, payload);
+        }
+        let framed = writer.get_bytes();
+
+        let mut reader = BitReader::new(&framed);
+        assert!(reader.read_bits_into_u8(3) == Some(0b101), "Bits before the payloads read wrong");
+        for (i, payload) in payloads.iter().enumerate() {
+            assert!(read(&mut reader).as_ref() == Some(payload), "Payload [{i}] different after writing and reading");
+        }
+        assert!(reader.remaining_bits() == 0 && read(&mut reader).is_none(), "Payload read past the end");
+
+        let mut reader = BitReader::new(&framed);
+        reader.read_bits_into_u8(3);
+        assert!(HuffmanDecoder::new().decode_all_bytes(&mut BitReader::new(&read(&mut reader).unwrap())) == Ok(bytes[..5000].to_vec()), "Framed Huffman different after decoding");
+        read(&mut reader);
+        assert!(decompress_lzw(&read(&mut reader).unwrap()) == Ok(bytes.clone()), "Framed LZW different after decoding");
+
+        //A length that runs past the end, and one that never ends
+        let mut reader = BitReader::new(&framed[..framed.len() - 1]);
+        reader.read_bits_into_u8(3);
+        read(&mut reader);
+        read(&mut reader);
+        assert!(read(&mut reader).is_none(), "Truncated payload was read");
+        assert!(read(&mut BitReader::new(&[0xFF; 11])).is_none(), "Endless varint was read");
+    }
+
+    #[test]
+    fn frame_test() {
+        use crate::checksum::crc32;
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let frames:Vec<&[u8]> = vec![&bytes[..1000], &bytes[1000..21000], &[], &bytes[21000..61000], &bytes[61000..]];
+
+        let mut writer = FrameWriter::new(Vec::new(), Codec::Lzw).unwrap();
+        for (i, frame) in frames.iter().enumerate() {
+            //Every codec gets a turn
+            writer.set_codec(Codec::ALL[i % Codec::ALL.len()]);
+            assert!(writer.write_frame(frame).unwrap() == i, "Frame [{i}] got the wrong number");
+        }
+        let file = writer.finish().unwrap();
+        println!("Frame file size: [{}] Original size: [{}]", file.len(), bytes.len());
+
+        let mut reader = FrameReader::new(Cursor::new(&file)).unwrap();
+        assert!(reader.frame_num() == frames.len(), "Read [{}] frames instead of [{}]", reader.frame_num(), frames.len());
+
+        //Seeking straight to a frame in the middle
+        let info = reader.frame_info(3).unwrap();
+        assert!(info.crc == crc32(frames[3]) && info.uncompressed_len == frames[3].len() && info.codec == Codec::ALL[3], "Wrong frame info [{info:?}]");
+        assert!(reader.read_frame(3).unwrap() == frames[3], "Frame [3] different after writing and reading");
+
+        for (i, frame) in frames.iter().enumerate().rev() {
+            assert!(reader.read_frame(i).unwrap() == *frame, "Frame [{i}] different after writing and reading");
+        }
+        assert!(matches!(reader.read_frame(frames.len()), Err(FrameError::FrameOutOfRange { .. })), "Frame past the end was read");
+
+        let empty = FrameWriter::new(Vec::new(), Codec::Lz77).unwrap().finish().unwrap();
+        assert!(FrameReader::new(Cursor::new(&empty)).unwrap().frame_num() == 0, "Frames found in an empty frame file");
+    }
+
+    #[test]
+    fn frame_corrupt_test() {
+        use crate::frame::FRAME_HEADER_SIZE;
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+
+        let mut writer = FrameWriter::new(Vec::new(), Codec::Lzw).unwrap();
+        for frame in bytes[..8192].chunks(2048) {
+            writer.write_frame(frame).unwrap();
+        }
+        let file = writer.finish().unwrap();
+        let second_frame = FrameReader::new(Cursor::new(&file)).unwrap().frame_info(1).unwrap();
+
+        //Flipping a byte in the second frame's payload only breaks that frame. The last byte is skipped since it's partly padding.
+        let payload_start = second_frame.offset as usize + FRAME_HEADER_SIZE;
+        let mut checksum_mismatches = 0;
+        for i in payload_start..(payload_start + second_frame.compressed_len - 1) {
+            let mut corrupted = file.clone();
+            corrupted[i] ^= 0x01;
+
+            let mut reader = FrameReader::new(Cursor::new(&corrupted)).unwrap();
+            match reader.read_frame(1) {
+                Ok(_) => panic!("Flipped payload byte [{i}] wasn't caught"),
+                Err(FrameError::ChecksumMismatch { .. }) => checksum_mismatches += 1,
+                Err(_) => {}
+            }
+            assert!(reader.read_frame(0).unwrap() == bytes[..2048] && reader.read_frame(2).unwrap() == bytes[4096..6144], "Flipping a byte in one frame broke another");
+        }
+        assert!(checksum_mismatches > 0, "No flipped payload bytes were caught by the checksum");
+
+        let mut corrupted = file.clone();
+        corrupted[0] = b'X';
+        assert!(matches!(FrameReader::new(Cursor::new(&corrupted)), Err(FrameError::BadMagic)), "Bad magic number wasn't caught");
+
+        let mut corrupted = file.clone();
+        corrupted[3] = 200;
+        assert!(matches!(FrameReader::new(Cursor::new(&corrupted)), Err(FrameError::UnsupportedVersion(200))), "Unsupported version wasn't caught");
+
+        assert!(matches!(FrameReader::new(Cursor::new(&file[..(file.len() - 1)])), Err(FrameError::MissingIndex)), "Missing index wasn't caught");
+
+        //A huge compressed length only reads to the end of the file
+        let mut corrupted = file.clone();
+        let last_frame = FrameReader::new(Cursor::new(&file)).unwrap().frame_info(3).unwrap();
+        corrupted[(last_frame.offset as usize + 1)..(last_frame.offset as usize + 5)].copy_from_slice(&u32::MAX.to_le_bytes());
+        let mut reader = FrameReader::new(Cursor::new(&corrupted)).unwrap();
+        assert!(matches!(reader.read_frame(3), Err(FrameError::Io(_))), "Compressed length past the end of the file wasn't caught");
+    }
+}
@@ -0,0 +1,226 @@
+use core::fmt;
+use crate::checksum::crc32;
+use crate::deflate::{DeflateError, deflate_compress, deflate_decompress_with_size};
+use alloc::vec::Vec;
+
+const GZIP_MAGIC:[u8; 2] = [0x1F, 0x8B];
+/// The only compression method gzip has, which is DEFLATE.
+const GZIP_METHOD_DEFLATE:u8 = 8;
+/// "Unknown", since the output doesn't depend on the OS.
+const GZIP_OS_UNKNOWN:u8 = 255;
+const GZIP_HEADER_LEN:usize = 10;
+const GZIP_TRAILER_LEN:usize = 8;
+
+const GZIP_FLAG_HCRC:u8 = 1 << 1;
+const GZIP_FLAG_EXTRA:u8 = 1 << 2;
+const GZIP_FLAG_NAME:u8 = 1 << 3;
+const GZIP_FLAG_COMMENT:u8 = 1 << 4;
+/// Flags that are reserved, which a decoder has to reject.
+const GZIP_FLAG_RESERVED:u8 = 0b1110_0000;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum GzipError {
+    /// The bytes ended in the middle of the header or trailer.
+    UnexpectedEnd,
+    /// The bytes don't start with `1f 8b`.
+    BadMagic,
+    /// The compression method isn't DEFLATE.
+    UnsupportedMethod(u8),
+    /// Some of the reserved flags are set.
+    UnsupportedFlags(u8),
+    Deflate(DeflateError),
+    /// The CRC-32 of the decompressed bytes isn't the one in the trailer.
+    ChecksumMismatch { expected: u32, actual: u32 },
+    /// The length of the decompressed bytes (mod 2^32) isn't the one in the
+    /// trailer.
+    LengthMismatch { expected: u32, actual: u32 }
+}
+
+impl fmt::Display for GzipError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GzipError::UnexpectedEnd => write!(f, "Bytes ended in the middle of a gzip header or trailer"),
+            GzipError::BadMagic => write!(f, "Not a gzip file"),
+            GzipError::UnsupportedMethod(method) => write!(f, "Unsupported compression method {method}"),
+            GzipError::UnsupportedFlags(flags) => write!(f, "Unsupported flags {flags:#04x}"),
+            GzipError::Deflate(err) => write!(f, "DEFLATE error: {err}"),
+            GzipError::ChecksumMismatch { expected, actual } => write!(f, "CRC-32 mismatch: expected {expected:#010x}, got {actual:#010x}"),
+            GzipError::LengthMismatch { expected, actual } => write!(f, "Length mismatch: expected {expected}, got {actual}")
+        }
+    }
+}
+
+impl From<DeflateError> for GzipError {
+    fn from(err: DeflateError) -> Self {
+        GzipError::Deflate(err)
+    }
+}
+
+/// Compresses bytes into a single gzip member, which `gunzip` (or anything
+/// else that reads `.gz` files) can decompress.
+///
+/// The header doesn't have a file name or modification time, so the same
+/// bytes always compress to the same output.
+pub fn gzip_compress(bytes: &[u8]) -> Vec<u8> {
+    let mut compressed = Vec::with_capacity(GZIP_HEADER_LEN + bytes.len() / 2 + GZIP_TRAILER_LEN);
+    compressed.extend_from_slice(&GZIP_MAGIC);
+    compressed.push(GZIP_METHOD_DEFLATE);
+    compressed.push(0); //Flags
+    compressed.extend_from_slice(&0u32.to_le_bytes()); //Modification time
+    compressed.push(0); //Extra flags
+    compressed.push(GZIP_OS_UNKNOWN);
+
+    compressed.extend_from_slice(&deflate_compress(bytes));
+
+    compressed.extend_from_slice(&crc32(bytes).to_le_bytes());
+    compressed.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    compressed
+}
+
+/// Reads a `u32`, little endian like everything else in gzip.
+fn read_u32(bytes: &[u8], pos: usize) -> Result<u32, GzipError> {
+    let word = bytes.get(pos..(pos + 4)).ok_or(GzipError::UnexpectedEnd)?;
+    Ok(u32::from_le_bytes(word.try_into().unwrap()))
+}
+
+/// Skips a zero-terminated string (the file name or comment), returning the
+/// position right after it.
+fn skip_zero_terminated(bytes: &[u8], pos: usize) -> Result<usize, GzipError> {
+    let len = bytes.get(pos..).and_then(|rest| rest.iter().position(|byte| *byte == 0)).ok_or(GzipError::UnexpectedEnd)?;
+    Ok(pos + len + 1)
+}
+
+/// Reads a gzip header, returning where the DEFLATE stream starts. The
+/// extra field, file name and comment are all skipped.
+fn read_header(bytes: &[u8]) -> Result<usize, GzipError> {
+    let header = bytes.get(..GZIP_HEADER_LEN).ok_or(GzipError::UnexpectedEnd)?;
+    if header[..2] != GZIP_MAGIC {
+        return Err(GzipError::BadMagic);
+    }
+    if header[2] != GZIP_METHOD_DEFLATE {
+        return Err(GzipError::UnsupportedMethod(header[2]));
+    }
+    let flags = header[3];
+    if flags & GZIP_FLAG_RESERVED != 0 {
+        return Err(GzipError::UnsupportedFlags(flags));
+    }
+
+    let mut pos = GZIP_HEADER_LEN;
+    if flags & GZIP_FLAG_EXTRA != 0 {
+        let len = bytes.get(pos..(pos + 2)).ok_or(GzipError::UnexpectedEnd)?;
+        pos += 2 + u16::from_le_bytes([len[0], len[1]]) as usize;
+    }
+    if flags & GZIP_FLAG_NAME != 0 {
+        pos = skip_zero_terminated(bytes, pos)?;
+    }
+    if flags & GZIP_FLAG_COMMENT != 0 {
+        pos = skip_zero_terminated(bytes, pos)?;
+    }
+    if flags & GZIP_FLAG_HCRC != 0 {
+        pos += 2;
+    }
+
+    if pos > bytes.len() {
+        return Err(GzipError::UnexpectedEnd);
+    }
+    Ok(pos)
+}
+
+/// Decompresses a gzip file, checking the CRC-32 and length of every member
+/// against its trailer. Files with more than one member (like ones made with
+/// `cat a.gz b.gz`) decompress to all of their members one after the other.
+pub fn gzip_decompress(bytes: &[u8]) -> Result<Vec<u8>, GzipError> {
+    let mut decompressed = Vec::new();
+    let mut pos = 0;
+
+    loop {
+        pos += read_header(&bytes[pos..])?;
+        let (member, deflate_len) = deflate_decompress_with_size(&bytes[pos..])?;
+        pos += deflate_len;
+
+        let expected = read_u32(bytes, pos)?;
+        let actual = crc32(&member);
+        if expected != actual {
+            return Err(GzipError::ChecksumMismatch { expected, actual });
+        }
+        let expected = read_u32(bytes, pos + 4)?;
+        let actual = member.len() as u32;
+        if expected != actual {
+            return Err(GzipError::LengthMismatch { expected, actual });
+        }
+        pos += GZIP_TRAILER_LEN;
+
+        decompressed.extend_from_slice(&member);
+        if pos == bytes.len() {
+            break;
+        }
+    }
+
+    Ok(decompressed)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gzip::{gzip_compress, gzip_decompress};
+
+    #[test]
+    fn gzip_test() {
+        use std::fs;
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let compressed = gzip_compress(&bytes);
+        println!("lorem_ipsum: [{}] -> [{}]", bytes.len(), compressed.len());
+        assert!(gzip_decompress(&compressed) == Ok(bytes.clone()), "lorem_ipsum different after compressing and decompressing");
+
+        //Two members one after the other decompress to both of them
+        let mut twice = compressed.clone();
+        twice.extend_from_slice(&gzip_compress(b"more bytes"));
+        let mut expected = bytes;
+        expected.extend_from_slice(b"more bytes");
+        assert!(gzip_decompress(&twice) == Ok(expected), "Two members different after decompressing");
+
+        assert!(gzip_decompress(&gzip_compress(&[])) == Ok(Vec::new()), "Nothing wasn't empty after compressing and decompressing");
+    }
+
+    #[test]
+    fn gzip_system_fixture_test() {
+        use std::fs;
+
+        //Made with `gzip -9 -n -c lorem_ipsum > lorem_ipsum.gz`, which uses dynamic blocks
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let fixture = fs::read("lorem_ipsum.gz").expect("File could not be opened and/or read");
+        assert!(gzip_decompress(&fixture) == Ok(bytes), "System gzip fixture different after decompressing");
+
+        //`printf 'hello hello hello' | gzip -n`, which is small enough to get a fixed block
+        let fixed = [0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x90, 0x00, 0x80, 0x88, 0xf9, 0xe5, 0x11, 0x00, 0x00, 0x00];
+        assert!(gzip_decompress(&fixed) == Ok(b"hello hello hello".to_vec()), "Fixed block fixture different after decompressing [{:?}]", gzip_decompress(&fixed));
+
+        //zlib at level 0, which only writes stored blocks
+        let stored = [0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x03, 0x01, 0x0c, 0x00, 0xf3, 0xff, 0x73, 0x74, 0x6f, 0x72, 0x65, 0x64, 0x20, 0x62, 0x79, 0x74, 0x65, 0x73, 0x78, 0x77, 0xa6, 0x0f, 0x0c, 0x00, 0x00, 0x00];
+        assert!(gzip_decompress(&stored) == Ok(b"stored bytes".to_vec()), "Stored block fixture different after decompressing [{:?}]", gzip_decompress(&stored));
+    }
+
+    #[test]
+    fn gzip_corrupt_test() {
+        use crate::gzip::GzipError;
+
+        let compressed = gzip_compress(b"Some bytes to compress, some bytes to compress");
+
+        let mut bad_crc = compressed.clone();
+        let crc_pos = bad_crc.len() - 8;
+        bad_crc[crc_pos] ^= 1;
+        assert!(matches!(gzip_decompress(&bad_crc), Err(GzipError::ChecksumMismatch {..})), "Wrong CRC-32 wasn't caught");
+
+        let mut bad_len = compressed.clone();
+        let len_pos = bad_len.len() - 4;
+        bad_len[len_pos] ^= 1;
+        assert!(matches!(gzip_decompress(&bad_len), Err(GzipError::LengthMismatch {..})), "Wrong length wasn't caught");
+
+        let mut bad_magic = compressed.clone();
+        bad_magic[1] = 0;
+        assert!(gzip_decompress(&bad_magic) == Err(GzipError::BadMagic), "Wrong magic wasn't caught");
+
+        assert!(gzip_decompress(&compressed[..(compressed.len() - 2)]) == Err(GzipError::UnexpectedEnd), "Truncated trailer wasn't caught");
+        assert!(gzip_decompress(&compressed[..5]) == Err(GzipError::UnexpectedEnd), "Truncated header wasn't caught");
+    }
+}
@@ -1,18 +1,70 @@
-use std::collections::{BinaryHeap};
-use std::cmp::{Ordering, min, max};
-use std::fmt::{self};
+use alloc::collections::{BinaryHeap, BTreeSet};
+use core::cmp::{Ordering, min, max};
+use core::fmt::{self};
+#[cfg(feature = "std")]
+use std::io::{self, Read, Write};
+#[cfg(feature = "std")]
+use std::sync::Mutex;
+#[cfg(feature = "std")]
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+#[cfg(feature = "std")]
+use std::thread;
 use crate::bitstream::{BitWriter, BitReader};
+use crate::entropy::{EntropyCoder, Model};
+use crate::error::DecodeError;
+use alloc::{vec::Vec, boxed::Box, vec, string::String, format};
 
+/// The default alphabet size, which is enough for bytes plus some extra
+/// symbols (like DEFLATE's lengths).
 pub const HUFFMAN_MAX_SYMBOLS:usize = 512;
-/// The number of bits needed to write the number of symbols.
+/// The biggest alphabet an encoder can be made with. Every symbol still fits
+/// in the default maximum code length of `12`.
+pub const HUFFMAN_MAX_ALPHABET_SIZE:usize = 1 << 12;
+/// The number of bits used to write symbols and the number of symbols in a
+/// table, for any alphabet up to `HUFFMAN_MAX_SYMBOLS`. Bigger alphabets use
+/// more, see `symbol_bits`.
 const HUFFMAN_MAX_SYMBOLS_SIZE:usize = 9;
 pub const HUFFMAN_CHUNK_SIZE_BITS:usize = 32;
+/// The chunk count `HuffmanWriter` writes, since it can't know how many
+/// chunks there'll be. Streams that have it are read until there aren't
+/// enough bits left for another chunk, so nothing can come after them.
+pub const HUFFMAN_UNKNOWN_CHUNK_NUM:u32 = 0;
 const MAX_CODE_LEN:usize = 12;
-const CODE_MASK:u32 = (1 << MAX_CODE_LEN) - 1;
+/// The number of bits used to write the max level of a table.
+const HUFFMAN_MAX_LEVEL_SIZE:usize = 5;
+/// The largest maximum code length an encoder can be configured with. It has
+/// to fit in `HUFFMAN_MAX_LEVEL_SIZE` bits, and past this the decoder's maps
+/// get way too big to be useful anyway.
+pub const HUFFMAN_MAX_CODE_LEN_LIMIT:usize = 24;
 pub const HUFFMAN_DEFAULT_CHUNK_SIZE:usize = 1 << 18;
+/// The most symbols a decoder believes a chunk has unless it's told
+/// otherwise with `set_max_chunk_size`, so a corrupt chunk size can't make it
+/// allocate gigabytes. That's `128` MB of symbols.
+pub const HUFFMAN_DEFAULT_MAX_CHUNK_SIZE:usize = 1 << 26;
+/// How many times a byte has to show up before `encode_order1` gives the
+/// bytes after it their own table. Anything rarer shares one, since a table
+/// costs more than it saves on only a few bytes.
+pub const HUFFMAN_ORDER1_MIN_CONTEXT_LEN:usize = 128;
+/// The version of the table format `write_huffman_table` writes, which it
+/// writes before every table. Bump it whenever the format changes, so old
+/// streams give a `HuffmanError::UnsupportedTableVersion` instead of
+/// decoding into garbage.
+pub const HUFFMAN_TABLE_VERSION:u8 = 2;
+/// The number of bits used to write how wide each symbol of a raw chunk is,
+/// see `encode_chunk`.
+const HUFFMAN_RAW_BITS_SIZE:usize = 5;
+/// The number of bytes a single entry of the decoder's symbol and level maps
+/// takes up.
+pub const HUFFMAN_DECODE_ENTRY_SIZE:usize = core::mem::size_of::<HuffmanSymbol>() + core::mem::size_of::<usize>();
 
 pub type HuffmanSymbol = u16;
+/// The type codes are built in. `u32` is plenty for the default limits, but
+/// the `huffman-path-u64` feature switches it to a `u64` if you need more room
+/// for the shifts.
+#[cfg(not(feature = "huffman-path-u64"))]
 pub type HuffmanPath = u32;
+#[cfg(feature = "huffman-path-u64")]
+pub type HuffmanPath = u64;
 
 /// A simple struct that `HuffmanTable`s use. Pretty much just a tuple.
 /// 
@@ -41,6 +93,9 @@ enum HuffmanNodeData {
 #[derive(Debug)]
 struct HuffmanNode {
     freq: u64,
+    /// The smallest symbol under the node, which breaks ties between nodes
+    /// with the same frequency.
+    min_symbol: HuffmanSymbol,
     data: HuffmanNodeData
 }
 
@@ -51,9 +106,13 @@ struct HuffmanNode {
 pub struct HuffmanEncoder{
     freq_table: Vec<u64>,
     max_symbols: usize,
-    max_symbols_size: usize,
+    symbol_bits: usize,
+    max_code_len: usize,
     table: HuffmanTable,
-    code_map: HuffmanCodeMap
+    code_map: HuffmanCodeMap,
+    byte_align_chunks: bool,
+    reuse_identical_chunks: bool,
+    auto_global_table: bool
 }
 
 #[derive(Debug, Clone)]
@@ -63,9 +122,142 @@ pub struct HuffmanEncoderIter<'a>{
 }
 
 pub struct HuffmanDecoder{
+    max_symbols: usize,
+    symbol_bits: usize,
     table: HuffmanTable,
+    code_len: usize,
     symbol_map: Vec<HuffmanSymbol>,
-    level_map: Vec<usize>
+    level_map: Vec<usize>,
+    /// How wide each symbol is if the last table read said the chunk was
+    /// written raw.
+    raw_bits: Option<usize>,
+    max_chunk_size: usize,
+    /// The longest codes a table can have before `read_huffman_table` turns
+    /// it down, see `set_max_code_len`.
+    max_code_len: usize
+}
+
+/// What encoding a chunk with `encode_chunk_with_stats` did.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HuffmanStats {
+    /// The number of different symbols in the chunk, which is the number of
+    /// entries in its table.
+    pub num_symbols: usize,
+    /// The average number of bits per symbol, not counting the table.
+    pub avg_code_len: f64,
+    pub min_level: usize,
+    pub max_level: usize,
+    /// The number of symbols in the chunk. For bytes, that's the number of
+    /// bytes.
+    pub input_bytes: usize,
+    /// Everything written for the chunk, table and chunk size included.
+    pub output_bits: usize,
+    /// Whether the chunk was written raw instead, because the table and
+    /// codes would've been bigger than the symbols themselves.
+    pub raw: bool
+}
+
+#[cfg(feature = "std")]
+/// Reads bytes encoded with `encode_all_bytes`, decoding a chunk at a time as
+/// they're needed, so it can be used anywhere a `Read` can.
+pub struct HuffmanReader<'a>{
+    decoder: HuffmanDecoder,
+    reader: BitReader<'a>,
+    /// Read on the first `read`, so a header that's cut off is an error
+    /// there instead of in `new`.
+    stream: Option<ChunkStream>,
+    chunk: Vec<HuffmanSymbol>,
+    bytes: Vec<u8>,
+    bytes_read: usize
+}
+
+#[cfg(feature = "std")]
+/// Huffman encodes everything written to it a chunk at a time, passing the
+/// encoded bytes along to `inner` as soon as each chunk is done. The output is
+/// the same as `encode_all_bytes` with the same chunk size, without byte
+/// aligned or reused chunks.
+/// 
+/// WARNING: Remember to `finish` the writer, otherwise the last chunk never
+/// gets written!
+pub struct HuffmanWriter<W: Write>{
+    encoder: HuffmanEncoder,
+    writer: BitWriter,
+    inner: W,
+    chunk_size: usize,
+    chunk: Vec<HuffmanSymbol>,
+    chunks_written: usize
+}
+
+/// The flags at the start of everything `encode_all` writes, and how many
+/// chunks have been decoded so far.
+struct ChunkStream {
+    byte_align_chunks: bool,
+    reuse_identical_chunks: bool,
+    /// Whether there's one table for every chunk, read before the first one.
+    global_table: bool,
+    chunk_num: usize,
+    chunks_read: usize
+}
+
+/// A node in an `AdaptiveHuffmanTree`.
+#[derive(Debug, Clone, Copy)]
+enum AdaptiveNodeKind {
+    /// The "not yet transmitted" leaf, which symbols that haven't been seen
+    /// yet are sent through.
+    Nyt,
+    Leaf(HuffmanSymbol),
+    /// The left and right children.
+    Node(usize, usize)
+}
+
+#[derive(Debug)]
+struct AdaptiveNode {
+    weight: u64,
+    parent: Option<usize>,
+    kind: AdaptiveNodeKind
+}
+
+/// The tree `encode_adaptive` and `decode_adaptive` both update as they go,
+/// using the FGK algorithm.
+/// 
+/// Nodes are numbered by their index, with the root at `0`, and the tree
+/// always keeps the sibling property: weights never go up as the index goes
+/// up, and siblings are next to each other.
+struct AdaptiveHuffmanTree {
+    nodes: Vec<AdaptiveNode>,
+    /// Which node each symbol's leaf is at, if it's been seen yet.
+    leaves: Vec<Option<usize>>,
+    nyt: usize,
+    symbol_bits: usize
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum HuffmanError {
+    /// The decode table size given can't fit the maps needed for the
+    /// smallest usable maximum code length.
+    TableSizeTooSmall { table_size: usize, required: usize },
+    /// A symbol was decoded that doesn't fit in a byte, so the stream is
+    /// corrupt (or wasn't made from bytes).
+    SymbolNotByte(HuffmanSymbol),
+    /// A table was written with a version of the format this can't read, see
+    /// `HUFFMAN_TABLE_VERSION`.
+    UnsupportedTableVersion(u8),
+    /// A remapped table's symbols run past the end of the alphabet (or the
+    /// reader), see `encode_chunk_remapped`, a raw chunk's symbols are
+    /// wider than the alphabet's (or `0` bits wide), or an adaptive stream
+    /// adds a symbol that's outside the alphabet or already in the tree.
+    BadSymbolMap,
+    /// A chunk says it has more symbols than the decoder's `max_chunk_size`.
+    ChunkTooLarge { chunk_size: usize, max_chunk_size: usize },
+    /// The reader ran out in the middle of a table or chunk.
+    UnexpectedEnd,
+    /// A table's codes are longer than the decoder allows (see
+    /// `HuffmanDecoder::set_max_code_len`), or there are too many short ones
+    /// for them to all be different.
+    BadCodeLengths,
+    /// A chunk has a code that none of the table's symbols have, which a
+    /// table whose codes don't use up every path leaves room for.
+    UnknownCode
 }
 
 /// A `Vec` of `HuffmanTableData`. Its `len()` equals the number of symbols 
@@ -78,9 +270,99 @@ type HuffmanTable = Vec<HuffmanTableData>;
 /// something that shouldn't happen in normal use, returns a `None`.
 type HuffmanCodeMap = Vec<Option<(HuffmanPath,usize)>>;
 
+impl fmt::Display for HuffmanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HuffmanError::TableSizeTooSmall { table_size, required } => write!(f, "Decode table size of [{table_size}] bytes is too small, needs at least [{required}] bytes"),
+            HuffmanError::SymbolNotByte(symbol) => write!(f, "Decoded symbol [{symbol}] doesn't fit in a byte"),
+            HuffmanError::UnsupportedTableVersion(version) => write!(f, "Huffman table version [{version}] isn't supported, expected [{HUFFMAN_TABLE_VERSION}]"),
+            HuffmanError::BadSymbolMap => write!(f, "Huffman table has symbols outside the alphabet"),
+            HuffmanError::ChunkTooLarge { chunk_size, max_chunk_size } => write!(f, "Huffman chunk of [{chunk_size}] symbols is larger than the maximum of [{max_chunk_size}]"),
+            HuffmanError::UnexpectedEnd => write!(f, "Bytes ended in the middle of a Huffman table or chunk"),
+            HuffmanError::BadCodeLengths => write!(f, "Huffman table's code lengths don't make a valid code"),
+            HuffmanError::UnknownCode => write!(f, "Huffman chunk has a code that isn't in its table")
+        }
+    }
+}
+
+/// So `HuffmanReader` and `decode_all_to` can pass table errors along as
+/// `InvalidData`.
+#[cfg(feature = "std")]
+impl From<HuffmanError> for io::Error {
+    fn from(err: HuffmanError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err.to_string())
+    }
+}
+
+/// The number of bits used to write a symbol (and the number of symbols in a
+/// table) for an alphabet of `max_symbols`. Alphabets up to
+/// `HUFFMAN_MAX_SYMBOLS` all use `HUFFMAN_MAX_SYMBOLS_SIZE` bits, so they're
+/// all written the same way.
+fn symbol_bits(max_symbols: usize) -> usize {
+    max(HUFFMAN_MAX_SYMBOLS_SIZE, (usize::BITS - (max_symbols - 1).leading_zeros()) as usize)
+}
+
+/// The number of bits it takes to tell `n` things apart, or `log2(n)` rounded
+/// up, without going through floats (which `no_std` doesn't have `log2` for).
+fn ceil_log2(n: usize) -> usize {
+    n.next_power_of_two().trailing_zeros() as usize
+}
+
+/// Returns the size in bytes of the decoder's symbol and level maps when
+/// decoding codes of at most `max_code_len` bits.
+pub fn decode_table_size(max_code_len: usize) -> usize {
+    (1 << max_code_len) * HUFFMAN_DECODE_ENTRY_SIZE
+}
+
+/// Returns the largest maximum code length whose decode maps fit in
+/// `table_size` bytes, or `None` if not even a code length of `1` fits.
+pub fn max_code_len_for_table_size(table_size: usize) -> Option<usize> {
+    (1..=HUFFMAN_MAX_CODE_LEN_LIMIT).rev().find(|len| decode_table_size(*len) <= table_size)
+}
+
+/// Huffman compresses bytes in chunks of `chunk_size`, which is just
+/// `HuffmanEncoder::encode_all_bytes` with the default settings. See
+/// `encode_all` for how to pick a chunk size.
+/// 
+/// `panic`s if `chunk_size` is `0`.
+pub fn compress_huffman(bytes: &[u8], chunk_size: usize) -> Vec<u8> {
+    let mut writer = BitWriter::new();
+    HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS).encode_all_bytes(bytes, chunk_size, &mut writer);
+    writer.get_bytes()
+}
+
+/// Decompresses bytes compressed with `compress_huffman`, whatever chunk
+/// size it used. Returns an error for the same things `decode_all_bytes`
+/// does.
+pub fn decompress_huffman(bytes: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    HuffmanDecoder::new().decode_all_bytes(&mut BitReader::new(bytes))
+}
+
+/// Reads `bit_num` bits, or returns `HuffmanError::UnexpectedEnd` without
+/// reading anything if there aren't that many left, instead of the fewer
+/// bits `read_bits_into_u32` would give back.
+fn read_bits(reader: &mut BitReader, bit_num: usize) -> Result<u32, HuffmanError> {
+    reader.read_bits_into_u32_exact(bit_num).ok_or(HuffmanError::UnexpectedEnd)
+}
+
+/// Reads the Elias gamma coded gap between two symbols of a remapped table.
+/// A gap can't be more than `1 << symbol_bits`, so more zeros than
+/// `symbol_bits` in front of it is a `HuffmanError::BadSymbolMap`, and
+/// running out is a `HuffmanError::UnexpectedEnd`.
+fn read_symbol_gap(reader: &mut BitReader, symbol_bits: usize) -> Result<u32, HuffmanError> {
+    let mut zeros = 0;
+    while !reader.read_bit().ok_or(HuffmanError::UnexpectedEnd)? {
+        zeros += 1;
+        if zeros > symbol_bits {
+            return Err(HuffmanError::BadSymbolMap);
+        }
+    }
+    Ok((1 << zeros) | read_bits(reader, zeros)?)
+}
+
 impl PartialEq for HuffmanTableData {
     fn eq(&self, other: &HuffmanTableData) -> bool{
-        self == other
+        self.level == other.level
     }
 }
 
@@ -98,7 +380,7 @@ impl Ord for HuffmanTableData {
 
 impl PartialEq for HuffmanNode {
     fn eq(&self, other: &HuffmanNode) -> bool{
-        self.freq == other.freq
+        self.freq == other.freq && self.min_symbol == other.min_symbol
     }
 }
 
@@ -111,8 +393,11 @@ impl PartialOrd for HuffmanNode {
 }
 
 impl Ord for HuffmanNode {
+    /// Backwards, so the `BinaryHeap` pops the least frequent node first.
+    /// Ties go to the node with the smallest symbol, so the tree (and the
+    /// output) doesn't depend on what order the heap got the nodes in.
     fn cmp(&self, other: &Self) -> Ordering{
-        other.freq.cmp(&self.freq)
+        other.freq.cmp(&self.freq).then(other.min_symbol.cmp(&self.min_symbol))
     }
 }
 
@@ -136,6 +421,7 @@ impl HuffmanNode {
     pub fn leaf(symbol: HuffmanSymbol, freq: u64) -> Self{
         HuffmanNode{
             freq,
+            min_symbol:symbol,
             data:HuffmanNodeData::Leaf(symbol)
         }
     }
@@ -145,10 +431,26 @@ impl HuffmanNode {
     pub fn node(left: HuffmanNode, right: HuffmanNode) -> Self{
         HuffmanNode {
             freq:left.freq + right.freq,
+            min_symbol:min(left.min_symbol, right.min_symbol),
             data:HuffmanNodeData::Node(Box::new(left), Box::new(right))
         }
     }
 
+    /// Builds a Huffman tree out of leaves with a `BinaryHeap`, joining the
+    /// two least frequent nodes until there's only one left, which is the
+    /// root. `None` if there aren't any leaves.
+    fn tree<I: IntoIterator<Item = HuffmanNode>>(leaves: I) -> Option<HuffmanNode> {
+        let mut node_heap:BinaryHeap<HuffmanNode> = leaves.into_iter().collect();
+
+        while node_heap.len() > 1{
+            let left = node_heap.pop().unwrap();
+            let right = node_heap.pop().unwrap();
+            node_heap.push(HuffmanNode::node(left, right));
+        }
+
+        node_heap.pop()
+    }
+
     fn leaves_helper(&self, leaves: &mut HuffmanTable, level: usize){
         match &self.data{
             HuffmanNodeData::Node(left, right) => {
@@ -178,23 +480,102 @@ impl HuffmanNode {
 }
 
 impl HuffmanEncoder {
+    /// Makes an encoder for symbols from `0` up to `max_symbols - 1`.
+    /// 
+    /// Alphabets bigger than `HUFFMAN_MAX_SYMBOLS` write their tables with more
+    /// bits, so they need a decoder made with `HuffmanDecoder::with_max_symbols`
+    /// and the same `max_symbols`.
+    /// 
+    /// `panic`s if `max_symbols` is `0`, since there'd be nothing to encode, or
+    /// if it's bigger than `HUFFMAN_MAX_ALPHABET_SIZE`.
     pub fn new(max_symbols: usize) -> Self{
-
-        let max_symbols =  min(max_symbols, HUFFMAN_MAX_SYMBOLS);
+        assert!(max_symbols > 0, "Encoder needs at least one symbol, given [{max_symbols}] symbols");
+        assert!(max_symbols <= HUFFMAN_MAX_ALPHABET_SIZE, "Encoder can have at most [{HUFFMAN_MAX_ALPHABET_SIZE}] symbols, given [{max_symbols}] symbols");
 
         let mut encoder = HuffmanEncoder {
             freq_table:Vec::with_capacity(max_symbols),
-            max_symbols:max_symbols,
-            max_symbols_size:((max_symbols as f32).log2().ceil() as usize),
+            max_symbols,
+            symbol_bits:symbol_bits(max_symbols),
+            max_code_len:MAX_CODE_LEN,
             table:Vec::with_capacity(max_symbols),
-            code_map:vec![None; max_symbols]
+            code_map:vec![None; max_symbols],
+            byte_align_chunks:false,
+            reuse_identical_chunks:false,
+            auto_global_table:false
         };
         encoder.freq_table.resize(max_symbols, 0);
 
         encoder
     }
 
-    pub fn iter(&self) -> HuffmanEncoderIter {
+    pub fn max_code_len(&self) -> usize {
+        self.max_code_len
+    }
+
+    /// Sets the maximum length of the codes the encoder produces. Longer codes
+    /// compress slightly better, but the decoder's maps grow with 
+    /// `1 << max_code_len`.
+    /// 
+    /// `panic`s if `max_code_len` is larger than `HUFFMAN_MAX_CODE_LEN_LIMIT`
+    /// or too small to give every possible symbol a code.
+    pub fn set_max_code_len(&mut self, max_code_len: usize) {
+        assert!(max_code_len <= HUFFMAN_MAX_CODE_LEN_LIMIT, "Maximum code length can be at most [{HUFFMAN_MAX_CODE_LEN_LIMIT}], given [{max_code_len}]");
+        assert!(max_code_len >= self.min_code_len(), "Maximum code length of [{max_code_len}] is too small for [{}] symbols", self.max_symbols);
+
+        self.max_code_len = max_code_len;
+    }
+
+    /// The smallest maximum code length that can still give every symbol in
+    /// the alphabet a code.
+    fn min_code_len(&self) -> usize {
+        max(ceil_log2(self.max_symbols), 1)
+    }
+
+    /// Sets the maximum code length to the largest one whose decode maps fit
+    /// in `table_size` bytes, returning the chosen length.
+    /// 
+    /// Returns an error if even the smallest code length that can represent
+    /// the whole alphabet doesn't fit.
+    pub fn fit_max_code_len_to_table_size(&mut self, table_size: usize) -> Result<usize, HuffmanError> {
+        let min_code_len = self.min_code_len();
+
+        match max_code_len_for_table_size(table_size) {
+            Some(max_code_len) if max_code_len >= min_code_len => {
+                self.max_code_len = max_code_len;
+                Ok(max_code_len)
+            },
+            _ => Err(HuffmanError::TableSizeTooSmall { table_size, required: decode_table_size(min_code_len) })
+        }
+    }
+
+    /// If this is set, `encode_all` pads every chunk so it starts on a byte
+    /// boundary. That way you can seek straight to a chunk if you know where
+    /// it starts, at the cost of a few bits per chunk. `decode_all` figures out
+    /// whether the chunks are aligned by itself.
+    pub fn set_byte_align_chunks(&mut self, byte_align_chunks: bool) {
+        self.byte_align_chunks = byte_align_chunks;
+    }
+
+    /// If this is set, `encode_all` checks whether each chunk is the same as
+    /// the one before it, and if it is, writes a single bit instead of the
+    /// whole chunk. Each chunk gets an extra bit either way, plus the number of
+    /// chunks gets written at the start. Great for files with lots of
+    /// repeated blocks (like zero-filled regions).
+    pub fn set_reuse_identical_chunks(&mut self, reuse_identical_chunks: bool) {
+        self.reuse_identical_chunks = reuse_identical_chunks;
+    }
+
+    /// If this is set, `encode_all` first works out roughly how big the
+    /// output would be with a table per chunk and with one table for the
+    /// whole input, and goes with whichever is smaller. Tables per chunk win
+    /// when the data changes from chunk to chunk, but when it doesn't they're
+    /// the same table written over and over. It costs building a table for
+    /// every chunk an extra time.
+    pub fn set_auto_global_table(&mut self, auto_global_table: bool) {
+        self.auto_global_table = auto_global_table;
+    }
+
+    pub fn iter(&self) -> HuffmanEncoderIter<'_> {
         HuffmanEncoderIter { curr_symbol: 0, table_ref: &self.table }
     }
 
@@ -210,6 +591,13 @@ impl HuffmanEncoder {
         }
     }
 
+    /// The frequency table, as built by `build_frequency_table` and the
+    /// `scan_*` functions. `frequency_table()[i]` is how many times symbol
+    /// `i` has been seen.
+    pub fn frequency_table(&self) -> &[u64] {
+        &self.freq_table
+    }
+
     pub fn scan_symbol(&mut self, symbol: HuffmanSymbol) {
         self.freq_table[symbol as usize] += 1;
     }
@@ -218,29 +606,54 @@ impl HuffmanEncoder {
         self.freq_table[byte as usize] += 1;
     }
 
+    /// Adds every symbol from an iterator to the frequency table, so symbols
+    /// made on the fly (like by `mtf_encode` or the delta filter) don't have
+    /// to be collected first.
+    /// 
+    /// Like `scan_symbol`, this adds to what's already in the table, so call
+    /// `build_frequency_table(&[])` first to start over.
+    pub fn scan_symbols<I: IntoIterator<Item = HuffmanSymbol>>(&mut self, symbols: I) {
+        for symbol in symbols {
+            self.freq_table[symbol as usize] += 1;
+        }
+    }
+
+    /// Installs a known distribution instead of counting the symbols, and
+    /// builds the table from it. `freqs[i]` is how often symbol `i` shows up,
+    /// and only the proportions matter, so percentages or counts from some
+    /// sample data both work. Symbols past the end of `freqs` get `0`.
+    /// 
+    /// Symbols with a frequency of `0` don't get a code, so give anything that
+    /// could show up at least `1`. Use it with `encode_all_with_fixed_table`.
+    /// 
+    /// `panic`s if `freqs` has more than `max_symbols` frequencies.
+    pub fn set_frequencies(&mut self, freqs: &[u64]) {
+        assert!(freqs.len() <= self.max_symbols, "Given [{}] frequencies, but the encoder only has [{}] symbols", freqs.len(), self.max_symbols);
+
+        self.freq_table.fill(0);
+        self.freq_table[..freqs.len()].copy_from_slice(freqs);
+        self.build_huffman_table();
+    }
+
     /// Builds a huffman table.
     /// 
     /// Creates a frequency table using `build_frequency_table()`, builds a Huffman
     /// tree out of `HuffmanNode`s using the frequency table with a `BinaryHeap`, 
     /// then turns that huffman tree into a `HuffmanTable`.
+    /// 
+    /// Frequencies that are the same are broken by symbol, so the same
+    /// frequencies always give the same table, and the same input always
+    /// compresses to the same bytes.
+    /// 
+    /// If no symbols were scanned, the table is left empty. If only one symbol
+    /// was scanned, it gets a level of `1`, so it's encoded as a single bit.
     pub fn build_huffman_table(&mut self) {
-        let mut node_heap:BinaryHeap<HuffmanNode> = BinaryHeap::new();
-        let mut symbol_num = 0;
-        for byte in 0..self.max_symbols {
-            if self.freq_table[byte] > 0{
-                node_heap.push(HuffmanNode::leaf(byte as u16, self.freq_table[byte]));
-                symbol_num += 1;
-            }
-        }
-
-        while node_heap.len() > 1{
-            let left = node_heap.pop().unwrap();
-            let right = node_heap.pop().unwrap();
-            node_heap.push(HuffmanNode::node(left, right));
-        }
+        let leaves = (0..self.max_symbols).filter(|&symbol| self.freq_table[symbol] > 0).map(|symbol| HuffmanNode::leaf(symbol as HuffmanSymbol, self.freq_table[symbol]));
 
         self.table.clear();
-        node_heap.pop().unwrap().leaves(&mut self.table);
+        if let Some(root) = HuffmanNode::tree(leaves) {
+            root.leaves(&mut self.table);
+        }
         self.table.sort();
         self.limit_huffman_table_code_sizes();
         self.build_huffman_code_map();
@@ -256,35 +669,37 @@ impl HuffmanEncoder {
     /// flattened symbols, and redistributing the entropy to other symbols by 
     /// increasing some of their levels.
     /// 
-    /// I'm not sure what happens when the `max_code_length` is too small, so just
-    /// in case it panics if the `max_code_length` isn't enough to store all the
-    /// symbols in the `HuffmanTable`
+    /// There are only `1 << max_code_len` codes of the maximum length, so that's
+    /// how many symbols the table can have. `new` and `set_max_code_len` make
+    /// sure that's never less than `max_symbols`, so this should never `panic`.
     fn limit_huffman_table_code_sizes(&mut self){
 
-        assert!((self.table.len() as f32).log2().ceil() as usize <= MAX_CODE_LEN, "Maximum code length of [{}] not large enough to store all [{}] symbols, needs length of at least [{}]", MAX_CODE_LEN, self.table.len(), (self.table.len() as f32).log2().ceil() as i32);
+        let max_code_len = self.max_code_len;
+
+        assert!(self.table.len() <= 1 << max_code_len, "Maximum code length of [{}] not large enough to store all [{}] symbols", max_code_len, self.table.len());
 
         let mut k = 0;
-        let k_max:usize = (1 << MAX_CODE_LEN) - 1;
+        let k_max:usize = 1 << max_code_len;
 
         for i in 0..self.table.len(){
-            self.table[i].level = min(self.table[i].level, MAX_CODE_LEN);
-            k += 1 << (MAX_CODE_LEN - self.table[i].level);
+            self.table[i].level = min(self.table[i].level, max_code_len);
+            k += 1 << (max_code_len - self.table[i].level);
         }
 
         for i in (0..self.table.len()).rev(){
 
             if k <= k_max { break; }
 
-            while self.table[i].level < MAX_CODE_LEN {
+            while self.table[i].level < max_code_len {
                 self.table[i].level += 1;
-                k -= 1 << (MAX_CODE_LEN - self.table[i].level);
+                k -= 1 << (max_code_len - self.table[i].level);
             }
 
         }
         
         for i in 0..self.table.len(){
-            while k + (1 << (MAX_CODE_LEN - self.table[i].level)) <= k_max {
-                k += 1 << (MAX_CODE_LEN - self.table[i].level);
+            while self.table[i].level > 1 && k + (1 << (max_code_len - self.table[i].level)) <= k_max {
+                k += 1 << (max_code_len - self.table[i].level);
                 self.table[i].level -= 1;
             }
         }
@@ -292,34 +707,77 @@ impl HuffmanEncoder {
 
     /// Writes a `HuffmanTable` to a given `BitWriter`.
     /// 
-    /// First writes `HUFFMAN_MAX_SYMBOLS_SIZE` bits denoting the number of symbols in
-    /// the `HuffmanTable` (`huffman_table.len()`) and `HUFFMAN_MAX_SYMBOLS_SIZE` bits
-    /// denoting the number of bits used to encode a level 
-    /// (`bits_per_level`). If there's only one symbol, write `1` instead.
+    /// First writes `HUFFMAN_TABLE_VERSION` as a byte, so a decoder can tell
+    /// if it knows how to read the rest, and a `0` bit saying the chunk isn't
+    /// raw (see `encode_chunk`). Then writes `symbol_bits` bits denoting the number of symbols in
+    /// the `HuffmanTable` (`huffman_table.len()`) and `HUFFMAN_MAX_LEVEL_SIZE` bits
+    /// denoting the max level, which gives the number of bits used to encode a
+    /// level (`bits_per_level`). If there's only one symbol, write `1` instead.
     /// 
-    /// For each symbol in the `HuffmanTable`, write `HUFFMAN_MAX_SYMBOLS_SIZE` bits
+    /// For each symbol in the `HuffmanTable`, write `symbol_bits` bits
     /// denoting the symbol itself, and `bits_per_level` bits denoting the level
     /// of the symbol. This is better than writing the code itself, since the codes
     /// can get quite long.
-    fn write_huffman_table(&mut self, writer: &mut BitWriter) {
+    /// 
+    /// An empty table (from an empty chunk) is written as a symbol count of `0`
+    /// and a max level of `0`, with no symbols following. A table with every
+    /// symbol of an alphabet of `1 << symbol_bits` symbols also has a count of
+    /// `0`, but its max level isn't.
+    pub fn write_huffman_table(&mut self, writer: &mut BitWriter) {
 
-        assert!(self.table.len() <= HUFFMAN_MAX_SYMBOLS, "The given Huffman table has too many symbols");
+        assert!(self.table.len() <= self.max_symbols, "The given Huffman table has too many symbols");
 
-        writer.write_bits_u32(self.table.len() as u32, HUFFMAN_MAX_SYMBOLS_SIZE);
+        writer.write_bits_u16(HUFFMAN_TABLE_VERSION as u16, 8);
+        writer.write_bits_u16(0, 1);
+        writer.write_bits_u32((self.table.len() & ((1 << self.symbol_bits) - 1)) as u32, self.symbol_bits);
 
-        let max_level = self.table.iter().max().unwrap().level; //Is this really necessary? I guess every little bit helps...
-        writer.write_bits_u32(max_level as u32, 4);
-        let bits_per_level = max((max_level as f32).log2().ceil() as usize, 1);
+        let max_level = self.table.iter().max().map_or(0, |data| data.level); //Is this really necessary? I guess every little bit helps...
+        writer.write_bits_u32(max_level as u32, HUFFMAN_MAX_LEVEL_SIZE);
+        let bits_per_level = max(ceil_log2(max_level), 1);
         //println!("Symbol num: {} Max level: {max_level} Bits per level: {bits_per_level}", self.table.len());
 
         for data in &self.table{
             let symbol = data.symbol;
             let level = data.level;
-            writer.write_bits_u32(symbol as u32, HUFFMAN_MAX_SYMBOLS_SIZE);
+            writer.write_bits_u32(symbol as u32, self.symbol_bits);
             writer.write_bits_u32(level as u32 - 1, bits_per_level);
         }
     }
 
+    /// Same as `write_huffman_table`, but for a table of only a few symbols
+    /// out of a big alphabet (like just the letters out of all the bytes).
+    /// Instead of writing every symbol next to its level, the symbols that
+    /// are there get written once, in order, as Elias gamma coded gaps from
+    /// the one before (starting from `-1`), then the levels of those symbols
+    /// in the same order. Symbols that are close together take a bit or two
+    /// each instead of `symbol_bits`.
+    ///
+    /// The decoder gets the order of the codes from the symbols, so the table
+    /// has to be sorted by symbol within each level first, which
+    /// `encode_chunk_remapped` does.
+    pub fn write_remapped_huffman_table(&mut self, writer: &mut BitWriter) {
+        assert!(self.table.len() <= self.max_symbols, "The given Huffman table has too many symbols");
+
+        writer.write_bits_u16(HUFFMAN_TABLE_VERSION as u16, 8);
+        writer.write_bits_u32((self.table.len() & ((1 << self.symbol_bits) - 1)) as u32, self.symbol_bits);
+
+        let max_level = self.table.iter().max().map_or(0, |data| data.level);
+        writer.write_bits_u32(max_level as u32, HUFFMAN_MAX_LEVEL_SIZE);
+        let bits_per_level = max(ceil_log2(max_level), 1);
+
+        let mut by_symbol = self.table.clone();
+        by_symbol.sort_by_key(|data| data.symbol);
+        let mut last_symbol:i64 = -1;
+        for data in &by_symbol {
+            writer.write_elias_gamma((data.symbol as i64 - last_symbol) as u32);
+            last_symbol = data.symbol as i64;
+        }
+        for data in &by_symbol {
+            writer.write_bits_u32(data.level as u32 - 1, bits_per_level);
+        }
+    }
+
+    #[cfg(feature = "std")]
     /// Prints the encoder's `HuffmanTable`
     pub fn print_huffman_table(&self) {
         for data in &self.table{
@@ -352,11 +810,12 @@ impl HuffmanEncoder {
                 code += 1;
             }
 
-            self.code_map[symbol as usize] = Some((code, level as usize));
+            self.code_map[symbol as usize] = Some((code, level));
         }
 
     }
 
+    #[cfg(feature = "std")]
     /// Prints the encoder's Huffman code map
     pub fn print_huffman_code_map(&self) {
         for symbol in 0..self.max_symbols{
@@ -370,14 +829,56 @@ impl HuffmanEncoder {
         }
     }
 
+    /// Gives the codes as a Graphviz `digraph`, so the tree can be looked at
+    /// with `dot -Tpng`. It's the tree the codes actually make, after
+    /// `limit_huffman_table_code_sizes`, not the one `build_huffman_table`
+    /// started with, which is what you want when a code length looks off.
+    ///
+    /// Each node is named after the path to it (`n` for the root, `n01` for
+    /// left then right), with `0` going left. Leaves are boxes labeled with
+    /// their symbol in hex and their level. An empty table is just an empty
+    /// graph.
+    pub fn dot_graph(&self) -> String {
+        let mut dot = String::from("digraph huffman {\n");
+        let mut nodes = BTreeSet::new();
+
+        for data in &self.table {
+            let Some((code, level)) = self.code_map[data.symbol as usize] else {continue};
+            let mut name = String::from("n");
+            if nodes.is_empty() {
+                dot.push_str("    n [label=\"\"];\n");
+                nodes.insert(name.clone());
+            }
+
+            for i in (0..level).rev() {
+                let bit = if (code & (1 << i)) > 0 {'1'} else {'0'};
+                let parent = name.clone();
+                name.push(bit);
+                if nodes.contains(&name) {continue;}
+
+                if i == 0 {
+                    dot.push_str(&format!("    {name} [label=\"{:x} ({level})\", shape=box];\n", data.symbol));
+                } else {
+                    dot.push_str(&format!("    {name} [label=\"\"];\n"));
+                }
+                dot.push_str(&format!("    {parent} -> {name} [label=\"{bit}\"];\n"));
+                nodes.insert(name.clone());
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
     /// Encodes a single given symbol. `panic`s if the symbol isn't in the code
     /// map, which should never happen.
     /// 
     /// WARNING: After encoding symbols, remember to `finish` the encoder to 
     /// add the proper padding!
+    #[allow(clippy::unnecessary_cast)] // HuffmanPath is already a u64 with huffman-path-u64
     pub fn encode_symbol(&mut self, symbol:HuffmanSymbol, writer: &mut BitWriter) {
         if let Some((code, length)) = self.code_map[symbol as usize]{
-            writer.write_bits_u32(code, length);
+            writer.write_bits_u64(code as u64, length);
         } else {
             panic!("Encoded symbol not found, this should never happen...");
         }
@@ -387,39 +888,384 @@ impl HuffmanEncoder {
     /// 
     /// WARNING: After encoding symbols, remember to `finish` the encoder to
     /// add the proper padding!
+    #[allow(clippy::unnecessary_cast)] // HuffmanPath is already a u64 with huffman-path-u64
     pub fn encode_symbols(&mut self, symbols: &[HuffmanSymbol], writer: &mut BitWriter) {
         writer.write_bits_u32(symbols.len() as u32, HUFFMAN_CHUNK_SIZE_BITS);
         //println!("Encoded symbol num written: {}", symbols.len());
         for symbol in symbols {
             if let Some((code, length)) = self.code_map[*symbol as usize]{
-                writer.write_bits_u32(code, length);
+                writer.write_bits_u64(code as u64, length);
                 //self.last_length = length;
             }
         }
     }
 
+    /// Encodes a chunk, table and all.
+    /// 
+    /// If the table and codes would take up more than the symbols do as they
+    /// are, like with random or already compressed bytes, the chunk is written
+    /// raw instead so it doesn't get any bigger: the version, a `1` bit,
+    /// `HUFFMAN_RAW_BITS_SIZE` bits giving how wide each symbol is (just
+    /// enough for the biggest one), the chunk size, then the symbols.
     pub fn encode_chunk(&mut self, chunk: &[HuffmanSymbol], writer: &mut BitWriter){
 
         self.build_frequency_table(chunk);
         self.build_huffman_table();
+        if self.is_raw_smaller(chunk.len()) {
+            self.write_raw_chunk(chunk, writer);
+            return;
+        }
         self.write_huffman_table(writer);
         self.encode_symbols(chunk, writer);
     }
 
+    /// How wide each symbol of a raw chunk is, which is just enough for the
+    /// biggest one in the table.
+    fn raw_symbol_bits(&self) -> usize {
+        let max_symbol = self.table.iter().map(|data| data.symbol as usize).max().unwrap_or(0);
+        max(ceil_log2(max_symbol + 1), 1)
+    }
+
+    /// Whether a chunk of `chunk_len` symbols is smaller written raw than with
+    /// the current table. The version and raw bit are the same either way, so
+    /// they're left out.
+    fn is_raw_smaller(&self, chunk_len: usize) -> bool {
+        self.raw_bits(chunk_len) < self.table_bits() + self.code_bits()
+    }
+
+    /// The size of the current table, not counting the version and raw bit.
+    fn table_bits(&self) -> u64 {
+        let max_level = self.table.iter().max().map_or(0, |data| data.level);
+        let bits_per_level = max(ceil_log2(max_level), 1);
+        (self.symbol_bits + HUFFMAN_MAX_LEVEL_SIZE + self.table.len() * (self.symbol_bits + bits_per_level)) as u64
+    }
+
+    /// The size of the codes for everything counted in the frequency table.
+    fn code_bits(&self) -> u64 {
+        self.table.iter().map(|data| self.freq_table[data.symbol as usize] * data.level as u64).sum()
+    }
+
+    /// The size of a raw chunk of `chunk_len` symbols with the current table,
+    /// not counting the version and raw bit.
+    fn raw_bits(&self, chunk_len: usize) -> u64 {
+        HUFFMAN_RAW_BITS_SIZE as u64 + (chunk_len * self.raw_symbol_bits()) as u64
+    }
+
+    /// Whether one table for all the `chunks` comes out smaller than a table
+    /// for each of them. Chunks that get reused are left out, since they're a
+    /// bit either way.
+    fn is_global_table_smaller(&mut self, symbols: &[HuffmanSymbol], chunk_size: usize) -> bool {
+        let table_header_bits = 8 + 1;
+        let mut per_chunk_bits = 0;
+        let mut global_freqs = vec![0; self.max_symbols];
+        let mut last_chunk:Option<&[HuffmanSymbol]> = None;
+        for chunk in symbols.chunks(chunk_size) {
+            if self.reuse_identical_chunks && last_chunk == Some(chunk) {continue;}
+            last_chunk = Some(chunk);
+
+            self.build_frequency_table(chunk);
+            self.build_huffman_table();
+            per_chunk_bits += table_header_bits + min(self.raw_bits(chunk.len()), self.table_bits() + self.code_bits());
+            for (global_freq, freq) in global_freqs.iter_mut().zip(&self.freq_table) {
+                *global_freq += freq;
+            }
+        }
+
+        //Each chunk has its size written either way
+        self.set_frequencies(&global_freqs);
+        let global_bits = table_header_bits + self.table_bits() + self.code_bits();
+        global_bits < per_chunk_bits
+    }
+
+    fn write_raw_chunk(&self, chunk: &[HuffmanSymbol], writer: &mut BitWriter) {
+        let raw_bits = self.raw_symbol_bits();
+        writer.write_bits_u16(HUFFMAN_TABLE_VERSION as u16, 8);
+        writer.write_bits_u16(1, 1);
+        writer.write_bits_u32(raw_bits as u32, HUFFMAN_RAW_BITS_SIZE);
+        writer.write_bits_u32(chunk.len() as u32, HUFFMAN_CHUNK_SIZE_BITS);
+        for &symbol in chunk {
+            writer.write_bits_u32(symbol as u32, raw_bits);
+        }
+    }
+
+    /// Same as `encode_chunk`, but the table is written with
+    /// `write_remapped_huffman_table`, which is a lot smaller when only a few
+    /// symbols of the alphabet show up. Decode it with
+    /// `HuffmanDecoder::decode_chunk_remapped`.
+    ///
+    /// The symbols that show up are in effect renumbered `0..k`, and since
+    /// that keeps them in order, they get the same codes dense indices would,
+    /// so they're encoded as they are.
+    pub fn encode_chunk_remapped(&mut self, chunk: &[HuffmanSymbol], writer: &mut BitWriter){
+        self.build_frequency_table(chunk);
+        self.build_huffman_table();
+        self.table.sort_by_key(|data| (data.level, data.symbol));
+        self.build_huffman_code_map();
+        self.write_remapped_huffman_table(writer);
+        self.encode_symbols(chunk, writer);
+    }
+
+    /// Same as `encode_chunk`, but also returns some stats about how it went,
+    /// which is handy for picking a chunk size or deciding whether Huffman is
+    /// worth it for some data at all.
+    pub fn encode_chunk_with_stats(&mut self, chunk: &[HuffmanSymbol], writer: &mut BitWriter) -> HuffmanStats {
+        let bits_before = writer.total_bits_written();
+        self.encode_chunk(chunk, writer);
+
+        let code_bits = self.code_bits();
+
+        HuffmanStats {
+            num_symbols: self.table.len(),
+            avg_code_len: if chunk.is_empty() {0.0} else {code_bits as f64 / chunk.len() as f64},
+            min_level: self.table.iter().map(|data| data.level).min().unwrap_or(0),
+            max_level: self.table.iter().map(|data| data.level).max().unwrap_or(0),
+            input_bytes: chunk.len(),
+            output_bits: writer.total_bits_written() - bits_before,
+            raw: self.is_raw_smaller(chunk.len())
+        }
+    }
+
+    /// Encodes symbols with adaptive Huffman coding (FGK), which builds the
+    /// tree as it goes instead of counting the symbols first. No table gets
+    /// written, and neither does the number of symbols, so the decoder needs to
+    /// be told that.
+    /// 
+    /// Each symbol's code is its path in the tree so far, and symbols that
+    /// haven't been seen yet are written as the path to the NYT ("not yet
+    /// transmitted") node followed by the raw symbol. It's slower and usually
+    /// a bit worse than `encode_chunk` on big inputs, but on small ones the
+    /// table `encode_chunk` writes can be a good chunk of the output.
+    /// 
+    /// `panic`s if a symbol is past the encoder's `max_symbols`.
+    pub fn encode_adaptive(&mut self, symbols: &[HuffmanSymbol], writer: &mut BitWriter) {
+        let mut tree = AdaptiveHuffmanTree::new(self.max_symbols);
+        for &symbol in symbols {
+            assert!((symbol as usize) < self.max_symbols, "Symbol [{symbol}] out of range for an encoder with [{}] symbols", self.max_symbols);
+            tree.encode(symbol, writer);
+        }
+    }
+
+    /// Encodes bytes with an order-1 model, where each byte gets coded with a
+    /// table for the byte before it (the first byte's context is `0`). Text
+    /// has a lot of letters that mostly follow certain other letters, so this
+    /// usually beats `encode_all` by a fair bit, even with all the tables.
+    /// 
+    /// Contexts that show up less than `HUFFMAN_ORDER1_MIN_CONTEXT_LEN` times
+    /// share a single table instead. The number of bytes gets written first,
+    /// then a bit for each context saying whether it has its own table, then
+    /// the shared table, then the other tables in order, then the codes.
+    /// 
+    /// Decode with `HuffmanDecoder::decode_order1`.
+    #[allow(clippy::unnecessary_cast)] // HuffmanPath is already a u64 with huffman-path-u64
+    pub fn encode_order1(&mut self, bytes: &[u8], writer: &mut BitWriter) {
+        assert!(self.max_symbols >= 256, "Order-1 encoding needs an encoder with at least [256] symbols, given [{}] symbols", self.max_symbols);
+
+        let contexts = || core::iter::once(0).chain(bytes.iter().copied()).zip(bytes.iter().copied());
+        let mut context_lens = [0usize; 256];
+        for (context, _) in contexts() {
+            context_lens[context as usize] += 1;
+        }
+        let own_table = context_lens.map(|len| len >= HUFFMAN_ORDER1_MIN_CONTEXT_LEN);
+
+        writer.write_bits_u32(bytes.len() as u32, HUFFMAN_CHUNK_SIZE_BITS);
+        for has_table in own_table {
+            writer.write_bits_u16(has_table as u16, 1);
+        }
+
+        //Index 0 is the shared table, and context i uses i + 1 if it has its own
+        let mut code_maps = Vec::new();
+        for table_context in core::iter::once(None).chain((0..256).filter(|&context| own_table[context]).map(Some)) {
+            self.build_frequency_table(&[]);
+            for (context, byte) in contexts() {
+                let context = context as usize;
+                if table_context == Some(context) || (table_context.is_none() && !own_table[context]) {
+                    self.scan_byte(byte);
+                }
+            }
+            self.build_huffman_table();
+            self.write_huffman_table(writer);
+            code_maps.push(self.code_map.clone());
+        }
+
+        let mut table_indices = [0; 256];
+        let mut next_index = 1;
+        for context in 0..256 {
+            if own_table[context] {
+                table_indices[context] = next_index;
+                next_index += 1;
+            }
+        }
+
+        for (context, byte) in contexts() {
+            let (code, length) = code_maps[table_indices[context as usize]][byte as usize].expect("Every byte was scanned into its context's table");
+            writer.write_bits_u64(code as u64, length);
+        }
+    }
+
+    /// Encodes all the symbols, splitting them into chunks of `chunk_size`.
+    /// 
+    /// The symbols can be anything from `0` up to the encoder's
+    /// `max_symbols - 1`, not just bytes, so with the default
+    /// `HUFFMAN_MAX_SYMBOLS` that's 9 bit symbols (like audio samples), and
+    /// a bigger alphabet goes up to 12 bit symbols, since encoders can't be
+    /// made with more than `HUFFMAN_MAX_ALPHABET_SIZE`. Decode them with
+    /// `HuffmanDecoder::decode_all`, which gives them back as they are.
+    /// `encode_all_bytes` is just this for bytes.
+    /// 
+    /// Each chunk gets its own table, so smaller chunks follow the data more
+    /// closely when it changes as it goes, but every table costs some bits
+    /// (a few hundred for bytes), and really small chunks end up mostly
+    /// tables. Bigger chunks only write a few tables, but one table has to do
+    /// for everything in them, and the decoder holds a whole chunk at a time.
+    /// Decoders turn down chunks bigger than `HUFFMAN_DEFAULT_MAX_CHUNK_SIZE`
+    /// unless they're told otherwise with `set_max_chunk_size`.
+    /// `HUFFMAN_DEFAULT_CHUNK_SIZE` is a decent middle ground. A chunk size
+    /// bigger than the input just makes it a single chunk.
+    /// 
+    /// An empty input is still written as a single empty chunk, so that the
+    /// decoder always has something well-defined to read.
+    /// 
+    /// The first bit written says whether the chunks are byte aligned, the
+    /// second whether identical chunks are reused, and the third whether
+    /// there's one table for all the chunks (see `set_auto_global_table`).
+    /// The number of chunks comes next, so the decoder knows exactly where the
+    /// stream ends, then the one table if there is one. If identical chunks
+    /// are reused each chunk starts with a bit that's set if it's the same as
+    /// the chunk before it. With one table, each chunk is just its size and
+    /// codes.
+    /// 
+    /// `panic`s if `chunk_size` is `0`.
     pub fn encode_all(&mut self, bytes: &[HuffmanSymbol], chunk_size: usize, writer: &mut BitWriter) {
-        let chunk_size = min(chunk_size, bytes.len() as usize);
+        assert!(chunk_size > 0, "Chunk size must be at least 1, given [{chunk_size}]");
+        let global_table = self.auto_global_table && !bytes.is_empty() && self.is_global_table_smaller(bytes, min(chunk_size, bytes.len()));
+        writer.write_bits_u16(self.byte_align_chunks as u16, 1);
+        writer.write_bits_u16(self.reuse_identical_chunks as u16, 1);
+        writer.write_bits_u16(global_table as u16, 1);
+
+        if bytes.is_empty() {
+            writer.write_bits_u32(1, HUFFMAN_CHUNK_SIZE_BITS);
+            if self.byte_align_chunks {writer.align_to_byte();}
+            if self.reuse_identical_chunks {writer.write_bits_u16(0, 1);}
+            self.encode_chunk(bytes, writer);
+            return;
+        }
+
+        let chunk_size = min(chunk_size, bytes.len());
+        writer.write_bits_u32(bytes.len().div_ceil(chunk_size) as u32, HUFFMAN_CHUNK_SIZE_BITS);
+        if global_table {
+            //is_global_table_smaller left the table for everything built
+            if self.byte_align_chunks {writer.align_to_byte();}
+            self.write_huffman_table(writer);
+        }
         for i in (0..bytes.len()).step_by(chunk_size){
             let chunk = &bytes[i..min(bytes.len(),i+chunk_size)];
-            self.encode_chunk(chunk ,writer);
+            if self.byte_align_chunks {writer.align_to_byte();}
+            if self.reuse_identical_chunks {
+                let same_as_last = i >= chunk_size && bytes[(i - chunk_size)..i] == *chunk;
+                writer.write_bits_u16(same_as_last as u16, 1);
+                if same_as_last {continue;}
+            }
+            if global_table {
+                self.encode_symbols(chunk, writer);
+            } else {
+                self.encode_chunk(chunk ,writer);
+            }
             //println!("Number of symbols: [{}] Smallest code length: [{}] Largest code length: [{}]", huffman_table.len(),  huffman_table.iter().min().unwrap().level,  huffman_table.iter().max().unwrap().level);
         }
     }
 
+    #[cfg(feature = "std")]
+    /// Same as `encode_all`, but the chunks are encoded on `threads` threads
+    /// (or as many as the machine has, if it's `0`), returning the encoded
+    /// bytes.
+    /// 
+    /// Each chunk is encoded into its own `BitWriter` and the bytes are stuck
+    /// together in order afterwards. For that to work each chunk has to start
+    /// on a byte boundary, so chunks are always byte aligned, as if
+    /// `set_byte_align_chunks(true)` was used. That costs up to 7 bits of
+    /// padding per chunk. The output is exactly what `encode_all` gives with
+    /// byte aligned chunks, so it decodes with `decode_all` as usual. Every
+    /// chunk always gets its own table, even with `set_auto_global_table`.
+    /// 
+    /// `panic`s if `chunk_size` is `0`.
+    pub fn encode_all_parallel(&self, symbols: &[HuffmanSymbol], chunk_size: usize, threads: usize) -> Vec<u8> {
+        assert!(chunk_size > 0, "Chunk size must be at least 1, given [{chunk_size}]");
+        //Same as encode_all, an empty input is still a single empty chunk
+        let chunks:Vec<&[HuffmanSymbol]> = if symbols.is_empty() {vec![symbols]} else {symbols.chunks(min(chunk_size, symbols.len())).collect()};
+
+        let mut header = BitWriter::new();
+        header.write_bits_u16(1, 1);
+        header.write_bits_u16(self.reuse_identical_chunks as u16, 1);
+        header.write_bits_u16(0, 1);
+        header.write_bits_u32(chunks.len() as u32, HUFFMAN_CHUNK_SIZE_BITS);
+        header.align_to_byte();
+
+        let threads = if threads == 0 {thread::available_parallelism().map_or(1, |n| n.get())} else {threads};
+        let threads = threads.min(chunks.len());
+
+        let next_chunk = AtomicUsize::new(0);
+        let encoded_chunks:Vec<Mutex<Vec<u8>>> = chunks.iter().map(|_| Mutex::new(Vec::new())).collect();
+
+        thread::scope(|scope| {
+            for _ in 0..threads {
+                scope.spawn(|| {
+                    let mut encoder = HuffmanEncoder::new(self.max_symbols);
+                    encoder.max_code_len = self.max_code_len;
+                    let mut writer = BitWriter::new();
+
+                    loop {
+                        let i = next_chunk.fetch_add(1, AtomicOrdering::Relaxed);
+                        if i >= chunks.len() {break;}
+
+                        writer.clear();
+                        let same_as_last = self.reuse_identical_chunks && i > 0 && chunks[i - 1] == chunks[i];
+                        if self.reuse_identical_chunks {writer.write_bits_u16(same_as_last as u16, 1);}
+                        if !same_as_last {encoder.encode_chunk(chunks[i], &mut writer);}
+                        *encoded_chunks[i].lock().unwrap() = writer.get_bytes();
+                    }
+                });
+            }
+        });
+
+        let mut encoded = header.get_bytes();
+        for encoded_chunk in encoded_chunks {
+            encoded.extend_from_slice(&encoded_chunk.into_inner().unwrap());
+        }
+
+        encoded
+    }
+
+    /// Same as `encode_all`, for bytes.
     pub fn encode_all_bytes(&mut self, bytes: &[u8], chunk_size: usize, writer: &mut BitWriter) {
         let symbols = HuffmanEncoder::bytes_to_symbols(bytes);
         self.encode_all(&symbols, chunk_size, writer);
     }
 
+    /// Encodes all the symbols against the table from `set_frequencies`
+    /// instead of building one per chunk. The table is written once up front,
+    /// then the number of chunks, then each chunk as its size and codes. Decode
+    /// it with `HuffmanDecoder::decode_all_fixed_table`.
+    /// 
+    /// When the data really does follow the distribution, this skips counting
+    /// every chunk and only pays for one table, which adds up with small
+    /// chunks or lots of small files. When it doesn't, the codes are worse
+    /// than a table of its own would give.
+    /// 
+    /// `panic`s if `chunk_size` is `0`, or if a symbol has no code because its
+    /// frequency was `0`.
+    pub fn encode_all_with_fixed_table(&mut self, symbols: &[HuffmanSymbol], chunk_size: usize, writer: &mut BitWriter) {
+        assert!(chunk_size > 0, "Chunk size must be at least 1, given [{chunk_size}]");
+
+        self.write_huffman_table(writer);
+        writer.write_bits_u32(symbols.len().div_ceil(chunk_size) as u32, HUFFMAN_CHUNK_SIZE_BITS);
+        for chunk in symbols.chunks(chunk_size) {
+            writer.write_bits_u32(chunk.len() as u32, HUFFMAN_CHUNK_SIZE_BITS);
+            for &symbol in chunk {
+                self.encode_symbol(symbol, writer);
+            }
+        }
+    }
+
     pub fn bytes_to_symbols(bytes: &[u8]) -> Vec<HuffmanSymbol>{
         let mut symbols = Vec::with_capacity(bytes.len());
         for byte in bytes{
@@ -447,32 +1293,195 @@ impl<'a> Iterator for HuffmanEncoderIter<'a> {
     }
 }
 
+impl Default for HuffmanDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl HuffmanDecoder{
+    /// Makes a decoder for anything encoded with an alphabet of up to
+    /// `HUFFMAN_MAX_SYMBOLS`.
     pub fn new() -> Self {
+        Self::with_max_symbols(HUFFMAN_MAX_SYMBOLS)
+    }
+
+    /// Makes a decoder for an encoder made with `HuffmanEncoder::new(max_symbols)`.
+    /// Only needed for alphabets bigger than `HUFFMAN_MAX_SYMBOLS`, since
+    /// everything up to that is written the same way.
+    /// 
+    /// `panic`s under the same conditions as `HuffmanEncoder::new`.
+    pub fn with_max_symbols(max_symbols: usize) -> Self {
+        assert!(max_symbols > 0, "Decoder needs at least one symbol, given [{max_symbols}] symbols");
+        assert!(max_symbols <= HUFFMAN_MAX_ALPHABET_SIZE, "Decoder can have at most [{HUFFMAN_MAX_ALPHABET_SIZE}] symbols, given [{max_symbols}] symbols");
+
         HuffmanDecoder { 
-            table: HuffmanTable::with_capacity(HUFFMAN_MAX_SYMBOLS), 
+            max_symbols,
+            symbol_bits: symbol_bits(max_symbols),
+            table: HuffmanTable::with_capacity(max_symbols), 
+            code_len: MAX_CODE_LEN,
             symbol_map: vec![0; 1 << MAX_CODE_LEN], 
-            level_map: vec![0; 1 << MAX_CODE_LEN]
+            level_map: vec![0; 1 << MAX_CODE_LEN],
+            raw_bits: None,
+            max_chunk_size: HUFFMAN_DEFAULT_MAX_CHUNK_SIZE,
+            max_code_len: MAX_CODE_LEN
+        }
+    }
+
+    /// Sets the longest codes a table can have before it's turned down with
+    /// `HuffmanError::BadCodeLengths`. Every table fills maps of
+    /// `1 << max_code_len` entries, so a corrupt stream full of tables with
+    /// long codes can take forever to decode. It starts at the same `12` an
+    /// encoder does, so only raise it to match an encoder's
+    /// `set_max_code_len`.
+    /// 
+    /// `panic`s if `max_code_len` is larger than `HUFFMAN_MAX_CODE_LEN_LIMIT`.
+    pub fn set_max_code_len(&mut self, max_code_len: usize) {
+        assert!(max_code_len <= HUFFMAN_MAX_CODE_LEN_LIMIT, "Maximum code length can be at most [{HUFFMAN_MAX_CODE_LEN_LIMIT}], given [{max_code_len}]");
+        self.max_code_len = max_code_len;
+    }
+
+    /// Sets the most symbols a chunk can have before `decode_chunk` gives up
+    /// on it with `HuffmanError::ChunkTooLarge`, instead of allocating room
+    /// for them. Raise it from `HUFFMAN_DEFAULT_MAX_CHUNK_SIZE` if you encode
+    /// bigger chunks than that, or lower it if memory is tight.
+    pub fn set_max_chunk_size(&mut self, max_chunk_size: usize) {
+        self.max_chunk_size = max_chunk_size;
+    }
+
+    /// Returns `chunk_size` if it's no more than `max_chunk_size`.
+    fn check_chunk_size(&self, chunk_size: usize) -> Result<usize, HuffmanError> {
+        if chunk_size > self.max_chunk_size {
+            return Err(HuffmanError::ChunkTooLarge { chunk_size, max_chunk_size: self.max_chunk_size });
         }
+        Ok(chunk_size)
     }
 
-    pub fn read_huffman_table(&mut self, reader: &mut BitReader) {
+    /// Reads a table written with `write_huffman_table`, returning
+    /// `HuffmanError::UnsupportedTableVersion` if it was written with a
+    /// different version of the format, and `HuffmanError::UnexpectedEnd` if
+    /// the reader runs out in the middle of it.
+    /// 
+    /// If the chunk was written raw there's no table, just the width of its
+    /// symbols, and the decoder reads them as they are until the next table.
+    pub fn read_huffman_table(&mut self, reader: &mut BitReader) -> Result<(), HuffmanError> {
+
+        let version = read_bits(reader, 8)? as u8;
+        if version != HUFFMAN_TABLE_VERSION {
+            return Err(HuffmanError::UnsupportedTableVersion(version));
+        }
+        self.raw_bits = None;
+        if reader.read_bit().ok_or(HuffmanError::UnexpectedEnd)? {
+            let raw_bits = read_bits(reader, HUFFMAN_RAW_BITS_SIZE)? as usize;
+            //The encoder always uses at least 1 bit, and 0 would let a huge chunk come out of nothing
+            if raw_bits == 0 || raw_bits > self.symbol_bits {
+                return Err(HuffmanError::BadSymbolMap);
+            }
+            self.raw_bits = Some(raw_bits);
+            return Ok(());
+        }
 
-        let symbol_num = reader.read_bits_into_u32(HUFFMAN_MAX_SYMBOLS_SIZE).unwrap() as usize;
-        let max_level = reader.read_bits_into_u32(4).unwrap() as i32;
-        let bits_per_level = max((max_level as f32).log2().ceil() as usize,1);
+        let mut symbol_num = read_bits(reader, self.symbol_bits)? as usize;
+        let max_level = read_bits(reader, HUFFMAN_MAX_LEVEL_SIZE)? as i32;
+        //Checked before anything's allocated, since the maps are 1 << max_level long
+        if max_level as usize > self.max_code_len {
+            return Err(HuffmanError::BadCodeLengths);
+        }
+        //A count of 0 with codes means every symbol is there, see write_huffman_table
+        if symbol_num == 0 && max_level > 0 {
+            symbol_num = 1 << self.symbol_bits;
+        }
+        let bits_per_level = max(ceil_log2(max_level as usize),1);
         //println!("Preliminary data read\nSymbol num: [{symbol_num}] Max level: [{max_level}] Bits per level: [{bits_per_level}]");
 
         self.table.clear();
         for _ in 0..symbol_num{
-            let symbol = reader.read_bits_into_u32(HUFFMAN_MAX_SYMBOLS_SIZE).unwrap() as HuffmanSymbol;
-            let level = reader.read_bits_into_u32(bits_per_level).unwrap() as usize + 1;
+            let symbol = read_bits(reader, self.symbol_bits)? as HuffmanSymbol;
+            let level = read_bits(reader, bits_per_level)? as usize + 1;
             self.table.push(HuffmanTableData{ symbol, level });
         }
         //println!("Huffman table read: {:?}", self.table);
 
+        self.code_len = max(max_level as usize, 1);
+        self.check_code_lengths()?;
+        self.fill_huffman_symbol_and_level_maps();
+
+        Ok(())
+    }
+
+    /// Reads a table written by `HuffmanEncoder::write_remapped_huffman_table`.
+    /// Returns the same errors as `read_huffman_table`, including
+    /// `HuffmanError::UnexpectedEnd` if the reader runs out in the middle of
+    /// it.
+    pub fn read_remapped_huffman_table(&mut self, reader: &mut BitReader) -> Result<(), HuffmanError> {
+        let version = read_bits(reader, 8)? as u8;
+        if version != HUFFMAN_TABLE_VERSION {
+            return Err(HuffmanError::UnsupportedTableVersion(version));
+        }
+
+        let mut symbol_num = read_bits(reader, self.symbol_bits)? as usize;
+        let max_level = read_bits(reader, HUFFMAN_MAX_LEVEL_SIZE)? as usize;
+        if max_level > self.max_code_len {
+            return Err(HuffmanError::BadCodeLengths);
+        }
+        if symbol_num == 0 && max_level > 0 {
+            symbol_num = 1 << self.symbol_bits;
+        }
+        let bits_per_level = max(ceil_log2(max_level), 1);
+
+        self.table.clear();
+        let mut symbol:i64 = -1;
+        for _ in 0..symbol_num {
+            symbol += read_symbol_gap(reader, self.symbol_bits)? as i64;
+            if symbol >= 1 << self.symbol_bits {
+                return Err(HuffmanError::BadSymbolMap);
+            }
+            self.table.push(HuffmanTableData { symbol: symbol as HuffmanSymbol, level: 0 });
+        }
+        for data in self.table.iter_mut() {
+            data.level = read_bits(reader, bits_per_level)? as usize + 1;
+        }
+        //Stable, so symbols stay in order within each level, like the encoder sorted them
+        self.table.sort();
+
+        self.raw_bits = None;
+        self.code_len = max(max_level, 1);
+        self.check_code_lengths()?;
         self.fill_huffman_symbol_and_level_maps();
 
+        Ok(())
+    }
+
+    /// Decodes a chunk written by `HuffmanEncoder::encode_chunk_remapped`.
+    pub fn decode_chunk_remapped(&mut self, reader: &mut BitReader) -> Result<Vec<HuffmanSymbol>, HuffmanError> {
+        self.read_remapped_huffman_table(reader)?;
+        self.decode_chunk(reader)
+    }
+
+    /// Makes sure the table read can be turned into maps: every level is
+    /// between `1` and `code_len` and in order, like the encoder sorts them,
+    /// and there aren't more codes of a length than there's room for (the
+    /// Kraft inequality). Otherwise filling the maps would run off the end.
+    fn check_code_lengths(&self) -> Result<(), HuffmanError> {
+        let mut last_level = 1;
+        let mut used_paths:u64 = 0;
+        for data in &self.table {
+            if data.level < last_level || data.level > self.code_len {
+                return Err(HuffmanError::BadCodeLengths);
+            }
+            last_level = data.level;
+            used_paths += 1 << (self.code_len - data.level);
+        }
+
+        if used_paths > 1 << self.code_len {
+            return Err(HuffmanError::BadCodeLengths);
+        }
+        Ok(())
+    }
+
+    /// The size in bytes of the decoder's symbol and level maps.
+    pub fn table_size(&self) -> usize {
+        self.symbol_map.len() * HUFFMAN_DECODE_ENTRY_SIZE
     }
 
     /// Fills a symbol and level maps.
@@ -488,11 +1497,21 @@ impl HuffmanDecoder{
     /// this allows us to read the maximum path length of bis from the buffer, 
     /// making decompression much easier. This is why limiting the maximum path 
     /// length is so important.
+    /// 
+    /// The maps are sized to the longest code in the table, so a table built
+    /// with a smaller maximum code length also decodes with smaller maps.
     fn fill_huffman_symbol_and_level_maps(&mut self) {
         //let mut map:HuffmanSymbolMap = vec![HuffmanTableData { symbol:0, level:0 }; 1 << max_level];
+        let map_len = 1 << self.code_len;
+        self.symbol_map.resize(map_len, 0);
+        self.symbol_map.shrink_to(map_len);
+        self.level_map.resize(map_len, 0);
+        self.level_map.shrink_to(map_len);
 
         let mut code:HuffmanPath = 0;
         let mut last_level = 0;
+        //Paths past the last code don't lead anywhere, and shouldn't keep what the last table had there
+        let mut unused_start = 0;
 
         for data in &self.table{
             let symbol = data.symbol;
@@ -510,25 +1529,82 @@ impl HuffmanDecoder{
             }
 
             //let reversed_code = reverse_u32(code);
-            let start_code = (code << (MAX_CODE_LEN- level)) as usize;
-            let end_code = (start_code | ((1 << (MAX_CODE_LEN - level))-1)) as usize;
+            let fill_bits:HuffmanPath = (1 << (self.code_len - level)) - 1;
+            let start_code = (code << (self.code_len - level)) as usize;
+            let end_code = start_code | fill_bits as usize;
             //println!("{} {level} {code:b} {start_code:064b} {end_code:064b}", self.max_code_length);
             self.symbol_map[start_code..=end_code].fill(symbol);
             self.level_map[start_code..=end_code].fill(level);
+            unused_start = end_code + 1;
+        }
+        self.level_map[unused_start..].fill(0);
+
+    }
+
+    /// Decodes a single symbol, or returns `None` if there isn't a whole one
+    /// left in the reader, in which case nothing is read.
+    /// 
+    /// Near the end of the stream the peeked path gets padded with zeros, so
+    /// the code it lands on can be longer than what's actually left. That
+    /// only happens with truncated or corrupt input, same as landing on a path
+    /// no code uses.
+    pub fn decode_one(&mut self, reader: &mut BitReader) -> Option<HuffmanSymbol> {
+        if let Some(raw_bits) = self.raw_bits {
+            if raw_bits > reader.remaining_bits() {
+                return None;
+            }
+            return reader.read_bits_into_u32(raw_bits).map(|symbol| symbol as HuffmanSymbol);
+        }
+        let path = reader.peek_bits_into_u32_with_shift(self.code_len)? as usize;
+        let level = self.level_map[path];
+
+        if level == 0 || level > reader.remaining_bits() {
+            return None;
         }
+        reader.empty_bits(level);
 
+        Some(self.symbol_map[path])
     }
 
-    pub fn decode_one(&mut self, reader: &mut BitReader) -> HuffmanSymbol {
-        let path = reader.peek_bits_into_u32_with_shift(MAX_CODE_LEN).unwrap();
+    /// Decodes up to `count` symbols lazily, one `decode_one` at a time, so
+    /// they can go through iterator adapters instead of all being decoded
+    /// at once like `decode_chunk` does. Stops early if the reader runs out.
+    pub fn symbols<'r, 'a>(&'r mut self, reader: &'r mut BitReader<'a>, count: usize) -> impl Iterator<Item = HuffmanSymbol> + use<'r, 'a> {
+        (0..count).map_while(move |_| self.decode_one(reader))
+    }
 
-        reader.empty_bits(self.level_map[path as usize] as usize);
+    /// Same as `decode_chunk`, but the symbols go straight into `out` instead
+    /// of a new `Vec`, returning how many there were. Returns
+    /// `DecodeError::OutputTooLarge` if the chunk has more symbols than `out`
+    /// can hold (after its length has been read), and
+    /// `DecodeError::UnexpectedEnd` if the reader runs out first.
+    pub fn decode_chunk_into(&mut self, reader: &mut BitReader, out: &mut [HuffmanSymbol]) -> Result<usize, DecodeError> {
+        let chunk_size = reader.read_bits_into_u32_exact(HUFFMAN_CHUNK_SIZE_BITS).ok_or(DecodeError::UnexpectedEnd)? as usize;
+        let out = out.get_mut(..chunk_size).ok_or(DecodeError::OutputTooLarge)?;
+
+        for slot in out {
+            *slot = self.decode_one(reader).ok_or(DecodeError::UnexpectedEnd)?;
+        }
 
-        self.symbol_map[path as usize]
+        Ok(chunk_size)
     }
 
-    pub fn decode_chunk(&mut self, reader: &mut BitReader) -> Vec<HuffmanSymbol> {
-        let chunk_size = reader.read_bits_into_u32(HUFFMAN_CHUNK_SIZE_BITS).unwrap() as usize;
+    /// Decodes a chunk with the last table read, returning
+    /// `HuffmanError::ChunkTooLarge` before anything is allocated if it says
+    /// it has more symbols than `max_chunk_size`, and
+    /// `HuffmanError::UnexpectedEnd` if it's cut off. That includes one cut
+    /// off in the middle of its last code, which the zeros the peeked path
+    /// gets padded with could otherwise turn into some other symbol. A code
+    /// the table doesn't have gives `HuffmanError::UnknownCode`.
+    pub fn decode_chunk(&mut self, reader: &mut BitReader) -> Result<Vec<HuffmanSymbol>, HuffmanError> {
+        let chunk_size = self.check_chunk_size(read_bits(reader, HUFFMAN_CHUNK_SIZE_BITS)? as usize)?;
+        if let Some(raw_bits) = self.raw_bits {
+            //Checked up front, since the last read would otherwise just come back short
+            if chunk_size.saturating_mul(raw_bits) > reader.remaining_bits() {
+                return Err(HuffmanError::UnexpectedEnd);
+            }
+            return (0..chunk_size).map(|_| reader.read_bits_into_u32(raw_bits).map(|symbol| symbol as HuffmanSymbol).ok_or(HuffmanError::UnexpectedEnd)).collect();
+        }
         let mut decoded = Vec::with_capacity(chunk_size);
         //println!("Encoded symbol num read: {}", chunk_size);
         //println!("Symbol map generated");
@@ -555,65 +1631,477 @@ impl HuffmanDecoder{
         while bytes_to_decode > 0 {
             //println!("{:011b} {}",path, bits_to_read);
             //let bit = bitstream.read_bit().unwrap();
-            let path = reader.peek_bits_into_u32_with_shift(MAX_CODE_LEN).unwrap();
+            let path = reader.peek_bits_into_u32_with_shift(self.code_len).ok_or(HuffmanError::UnexpectedEnd)?;
             //println!("{:011b}",path);
             //let data = symbol_map[path as usize];
             let symbol = self.symbol_map[path as usize];
             let level = self.level_map[path as usize];
+            if level == 0 {
+                return Err(HuffmanError::UnknownCode);
+            }
+            if level > reader.remaining_bits() {
+                return Err(HuffmanError::UnexpectedEnd);
+            }
 
             decoded.push(symbol);
             reader.empty_bits(level);
             bytes_to_decode -= 1;
         }
 
-        decoded
+        Ok(decoded)
+
+    }
+    /// Decodes bytes encoded with `HuffmanEncoder::encode_order1`. Returns
+    /// `HuffmanError::UnexpectedEnd` if the reader runs out in the middle of
+    /// the tables or codes, `SymbolNotByte` if one of them isn't a byte, and
+    /// the usual table and chunk size errors otherwise.
+    pub fn decode_order1(&mut self, reader: &mut BitReader) -> Result<Vec<u8>, HuffmanError> {
+        let len = self.check_chunk_size(read_bits(reader, HUFFMAN_CHUNK_SIZE_BITS)? as usize)?;
+        let mut own_table = [false; 256];
+        for has_table in &mut own_table {
+            *has_table = reader.read_bit().ok_or(HuffmanError::UnexpectedEnd)?;
+        }
+
+        let mut decoders = Vec::new();
+        let mut table_indices = [0; 256];
+        for context in core::iter::once(None).chain((0..256).filter(|&context| own_table[context]).map(Some)) {
+            let mut decoder = HuffmanDecoder::with_max_symbols(self.max_symbols);
+            decoder.read_huffman_table(reader)?;
+            if let Some(context) = context {
+                table_indices[context] = decoders.len();
+            }
+            decoders.push(decoder);
+        }
+
+        let mut decoded = Vec::with_capacity(len);
+        let mut context = 0;
+        for _ in 0..len {
+            let symbol = decoders[table_indices[context as usize]].decode_one(reader).ok_or(HuffmanError::UnexpectedEnd)?;
+            context = u8::try_from(symbol).map_err(|_| HuffmanError::SymbolNotByte(symbol))?;
+            decoded.push(context);
+        }
+
+        Ok(decoded)
+    }
 
+    /// Decodes `count` symbols encoded with `encode_adaptive`. Returns
+    /// `HuffmanError::UnexpectedEnd` if the reader runs out first.
+    pub fn decode_adaptive(&mut self, reader: &mut BitReader, count: usize) -> Result<Vec<HuffmanSymbol>, HuffmanError> {
+        let mut tree = AdaptiveHuffmanTree::new(self.max_symbols);
+        (0..count).map(|_| tree.decode(reader)).collect()
     }
-    /// Decodes all the chunks found in the bit reader
+
+    /// Decodes all the chunks found in the bit reader. It reads exactly as
+    /// many chunks as the stream starts by saying it has, so anything after
+    /// them (padding or another stream) is left alone. Only `HuffmanWriter`
+    /// streams don't say, and those have to be the last thing in the reader.
+    /// 
+    /// The symbols come back as they were given to `encode_all`, so ones
+    /// bigger than a byte are fine, as long as the decoder's `max_symbols`
+    /// matches the encoder's (see `with_max_symbols`).
     /// 
-    /// WARNING: I don't know what this does if the encoded bytes weren't created
-    /// using my `compress_huffman` function. Therefore, I'd advise you don't
-    /// use it on anything not created using this function.
-    pub fn decode_all(&mut self, reader: &mut BitReader) -> Vec<HuffmanSymbol> {
+    /// Returns an error if the stream is cut off or corrupt, like a table with
+    /// the wrong version or a chunk bigger than the decoder allows.
+    pub fn decode_all(&mut self, reader: &mut BitReader) -> Result<Vec<HuffmanSymbol>, HuffmanError> {
+        let mut decoded = Vec::new();
+        self.decode_chunks(reader, |chunk| {
+            decoded.extend_from_slice(chunk);
+            Ok::<(), HuffmanError>(())
+        })?;
+
+        Ok(decoded)
+    }
+
+    /// Same as `decode_all`, but every symbol has to be a byte. Returns
+    /// `HuffmanError::SymbolNotByte` if one isn't, which only happens with a
+    /// corrupt stream.
+    pub fn decode_all_bytes(&mut self, reader: &mut BitReader) -> Result<Vec<u8>, DecodeError> {
+        let mut decoded = Vec::new();
+        self.decode_chunks(reader, |chunk| {
+            decoded.extend_from_slice(&HuffmanDecoder::symbols_to_bytes(chunk)?);
+            Ok::<(), DecodeError>(())
+        })?;
+
+        Ok(decoded)
+    }
+
+    /// Decodes symbols encoded with `HuffmanEncoder::encode_all_with_fixed_table`,
+    /// reading the one table and then every chunk with it. Returns
+    /// `HuffmanError::UnexpectedEnd` if the reader runs out anywhere in them.
+    pub fn decode_all_fixed_table(&mut self, reader: &mut BitReader) -> Result<Vec<HuffmanSymbol>, DecodeError> {
+        self.read_huffman_table(reader)?;
+        let chunk_num = read_bits(reader, HUFFMAN_CHUNK_SIZE_BITS)?;
+
         let mut decoded = Vec::new();
-        while reader.remaining_bits() > HUFFMAN_CHUNK_SIZE_BITS {
-            self.read_huffman_table(reader);
-            decoded.append(&mut self.decode_chunk(reader));
+        for _ in 0..chunk_num {
+            let chunk_size = self.check_chunk_size(read_bits(reader, HUFFMAN_CHUNK_SIZE_BITS)? as usize)?;
+            let chunk_start = decoded.len();
+            decoded.extend(self.symbols(reader, chunk_size));
+            if decoded.len() - chunk_start < chunk_size {
+                return Err(HuffmanError::UnexpectedEnd.into());
+            }
         }
 
-        decoded
+        Ok(decoded)
     }
 
-    pub fn decode_all_bytes(&mut self, reader: &mut BitReader) -> Vec<u8> {
-        HuffmanDecoder::symbols_to_bytes(&self.decode_all(reader))
+    #[cfg(feature = "std")]
+    /// Same as `decode_all_bytes`, but each chunk gets written to `out` as
+    /// soon as it's decoded, so only one chunk is ever held in memory.
+    /// Symbols that aren't bytes give an `InvalidData` error.
+    pub fn decode_all_to<W: Write>(&mut self, reader: &mut BitReader, out: &mut W) -> io::Result<()> {
+        self.decode_chunks(reader, |chunk| {
+            let bytes = HuffmanDecoder::symbols_to_bytes(chunk).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+            out.write_all(&bytes)
+        })
     }
 
-    pub fn symbols_to_bytes(symbols: &[HuffmanSymbol]) -> Vec<u8> {
-        let mut bytes = Vec::with_capacity(symbols.len());
-        let max_symbol = u8::MAX as HuffmanSymbol;
+    /// Decodes the chunks one at a time, handing each one to `handle_chunk`
+    /// before moving on to the next.
+    fn decode_chunks<F, E>(&mut self, reader: &mut BitReader, mut handle_chunk: F) -> Result<(), E>
+    where
+    F: FnMut(&[HuffmanSymbol]) -> Result<(), E>,
+    E: From<HuffmanError> {
+        let mut stream = HuffmanDecoder::read_chunk_stream(reader)?;
+        //Kept around in case the next chunk is the same
+        let mut chunk = Vec::new();
+
+        while self.decode_next_chunk(reader, &mut stream, &mut chunk)? {
+            handle_chunk(&chunk)?;
+        }
 
-        for symbol in symbols{
-            assert!(*symbol <= max_symbol, "Symbols cannot be directly converted to bytes, found symbol with val {}",*symbol);
-            bytes.push(*symbol as u8);
+        Ok(())
+    }
+
+    /// Reads the flags and chunk count at the start of a stream, returning
+    /// `HuffmanError::UnexpectedEnd` if they're cut off. Only a count that's
+    /// actually `HUFFMAN_UNKNOWN_CHUNK_NUM` means there's no count.
+    fn read_chunk_stream(reader: &mut BitReader) -> Result<ChunkStream, HuffmanError> {
+        let byte_align_chunks = read_bits(reader, 1)? == 1;
+        let reuse_identical_chunks = read_bits(reader, 1)? == 1;
+        let global_table = read_bits(reader, 1)? == 1;
+        //Without a chunk count, just keep going until there can't be any chunks left
+        let chunk_num = match read_bits(reader, HUFFMAN_CHUNK_SIZE_BITS)? {
+            HUFFMAN_UNKNOWN_CHUNK_NUM => usize::MAX,
+            chunk_num => chunk_num as usize
+        };
+        if byte_align_chunks {reader.align_to_byte();}
+
+        Ok(ChunkStream { byte_align_chunks, reuse_identical_chunks, global_table, chunk_num, chunks_read: 0 })
+    }
+
+    /// Decodes the next chunk into `chunk`, which is left alone if the chunk
+    /// is the same as the last one. Returns `false` once there are no chunks
+    /// left.
+    fn decode_next_chunk(&mut self, reader: &mut BitReader, stream: &mut ChunkStream, chunk: &mut Vec<HuffmanSymbol>) -> Result<bool, HuffmanError> {
+        if stream.chunks_read >= stream.chunk_num {
+            return Ok(false);
         }
+        //Only a stream without a chunk count ends when the reader does, otherwise there should've been more chunks
+        if stream.chunk_num == usize::MAX && reader.remaining_bits() <= HUFFMAN_CHUNK_SIZE_BITS {
+            return Ok(false);
+        }
+        if reader.remaining_bits() == 0 {
+            return Err(HuffmanError::UnexpectedEnd);
+        }
+
+        if stream.global_table && stream.chunks_read == 0 {
+            self.read_huffman_table(reader)?;
+            if stream.byte_align_chunks {reader.align_to_byte();}
+        }
+        if !(stream.reuse_identical_chunks && reader.read_bit() == Some(true)) {
+            if !stream.global_table {self.read_huffman_table(reader)?;}
+            *chunk = self.decode_chunk(reader)?;
+        }
+        stream.chunks_read += 1;
+        if stream.byte_align_chunks {reader.align_to_byte();}
+
+        Ok(true)
+    }
+
+    /// Turns symbols back into bytes, returning `HuffmanError::SymbolNotByte`
+    /// for the first one that doesn't fit in a byte.
+    pub fn symbols_to_bytes(symbols: &[HuffmanSymbol]) -> Result<Vec<u8>, DecodeError> {
+        symbols.iter().map(|symbol| u8::try_from(*symbol).map_err(|_| DecodeError::Huffman(HuffmanError::SymbolNotByte(*symbol)))).collect()
+    }
 
-        bytes
+    /// Uses a table that's already known instead of reading one, like the one
+    /// an encoder built from the same frequencies.
+    fn set_huffman_table(&mut self, table: &[HuffmanTableData]) {
+        self.table.clear();
+        self.table.extend_from_slice(table);
+        self.raw_bits = None;
+        self.code_len = max(table.iter().map(|data| data.level).max().unwrap_or(0), 1);
+        self.fill_huffman_symbol_and_level_maps();
     }
 
 }
 
-/// Encodes a slice of bytes using Huffman encoding.
-/// 
-/// This encoding uses chunking, which can result in better compression.
-/// `chunk_size` denotes the size of each chunk. If you don't want any
-/// chunking, set `chunk_size` to `usize::MAX`. Otherwise, I've found
-/// that `1 << 18`, or roughly 256 KB, is a good size for chunks.
+/// Huffman as an `EntropyCoder`. The table is built from the model's
+/// frequencies instead of being written, and symbols go through the code map
+/// one at a time like `encode_symbol`.
 /// 
-/// `max_path_size` denotes the maximum length of the Huffman paths of each
-/// symbol. This is necessary for making decompression fast. Note that using
-/// larger `max_path_size`s results in decompression taking up much more space.
-/// Therefore, it's advised to make `max_path_size` as small as possible.
-/// If you're unsure what to set this to, I've found that `11` is a good length.
+/// The encoder's `max_symbols` and `max_code_len` have to match on both ends.
+impl EntropyCoder for HuffmanEncoder {
+    fn encode<M: Model>(&mut self, model: &M, symbols: &[HuffmanSymbol], writer: &mut BitWriter) {
+        self.build_model_table(model);
+        for &symbol in symbols {
+            self.encode_symbol(symbol, writer);
+        }
+    }
+
+    fn decode<M: Model>(&mut self, model: &M, reader: &mut BitReader, count: usize) -> Option<Vec<HuffmanSymbol>> {
+        self.build_model_table(model);
+        let mut decoder = HuffmanDecoder::with_max_symbols(self.max_symbols);
+        decoder.set_huffman_table(&self.table);
+
+        let decoded:Vec<HuffmanSymbol> = decoder.symbols(reader, count).collect();
+        (decoded.len() == count).then_some(decoded)
+    }
+}
+
+impl HuffmanEncoder {
+    /// Builds the table and code map from a model's frequencies.
+    fn build_model_table<M: Model>(&mut self, model: &M) {
+        assert!(model.num_symbols() <= self.max_symbols, "Model has [{}] symbols, but the encoder only has [{}]", model.num_symbols(), self.max_symbols);
+
+        self.freq_table.fill(0);
+        for symbol in 0..model.num_symbols() {
+            self.freq_table[symbol] = model.freq(symbol as HuffmanSymbol) as u64;
+        }
+        self.build_huffman_table();
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a> HuffmanReader<'a>{
+    pub fn new(reader: BitReader<'a>) -> Self {
+        HuffmanReader { decoder: HuffmanDecoder::new(), reader, stream: None, chunk: Vec::new(), bytes: Vec::new(), bytes_read: 0 }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a> Read for HuffmanReader<'a>{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let stream = match &mut self.stream {
+            Some(stream) => stream,
+            None => self.stream.insert(HuffmanDecoder::read_chunk_stream(&mut self.reader)?)
+        };
+        while self.bytes_read == self.bytes.len() {
+            if !self.decoder.decode_next_chunk(&mut self.reader, stream, &mut self.chunk)? {
+                return Ok(0);
+            }
+            self.bytes = HuffmanDecoder::symbols_to_bytes(&self.chunk).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+            self.bytes_read = 0;
+        }
+
+        let byte_num = min(buf.len(), self.bytes.len() - self.bytes_read);
+        buf[..byte_num].copy_from_slice(&self.bytes[self.bytes_read..(self.bytes_read + byte_num)]);
+        self.bytes_read += byte_num;
+
+        Ok(byte_num)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> HuffmanWriter<W>{
+    pub fn new(inner: W) -> Self {
+        HuffmanWriter::with_chunk_size(inner, HUFFMAN_DEFAULT_CHUNK_SIZE)
+    }
+
+    /// `chunk_size` works the same as in `encode_all`. Note that a whole chunk
+    /// is kept in memory until it's full, so `usize::MAX` keeps everything.
+    pub fn with_chunk_size(inner: W, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "Chunk size must be at least 1");
+
+        let mut writer = BitWriter::new();
+        //Neither byte aligned nor reused chunks nor one table, and no chunk count, since the number of chunks isn't known up front
+        writer.write_bits_u16(0, 3);
+        writer.write_bits_u32(HUFFMAN_UNKNOWN_CHUNK_NUM, HUFFMAN_CHUNK_SIZE_BITS);
+
+        HuffmanWriter { encoder: HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS), writer, inner, chunk_size, chunk: Vec::new(), chunks_written: 0 }
+    }
+
+    fn write_chunk(&mut self) -> io::Result<()> {
+        self.encoder.encode_chunk(&self.chunk, &mut self.writer);
+        self.chunk.clear();
+        self.chunks_written += 1;
+
+        self.inner.write_all(&self.writer.take_bytes())
+    }
+
+    /// Writes whatever's left of the last chunk and the padding, and gives
+    /// back the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        //An empty input is still written as a single empty chunk, same as encode_all
+        if !self.chunk.is_empty() || self.chunks_written == 0 {
+            self.write_chunk()?;
+        }
+        self.inner.write_all(&self.writer.get_bytes())?;
+        self.inner.flush()?;
+
+        Ok(self.inner)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> Write for HuffmanWriter<W>{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let byte_num = min(buf.len(), self.chunk_size - self.chunk.len());
+        self.chunk.extend(buf[..byte_num].iter().map(|byte| *byte as HuffmanSymbol));
+
+        if self.chunk.len() == self.chunk_size {
+            self.write_chunk()?;
+        }
+
+        Ok(byte_num)
+    }
+
+    /// Only flushes the inner writer, since the chunk being filled can't be
+    /// written until it's full or the writer is finished.
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl AdaptiveHuffmanTree {
+    fn new(max_symbols: usize) -> Self {
+        AdaptiveHuffmanTree {
+            nodes: vec![AdaptiveNode { weight: 0, parent: None, kind: AdaptiveNodeKind::Nyt }],
+            leaves: vec![None; max_symbols],
+            nyt: 0,
+            symbol_bits: symbol_bits(max_symbols)
+        }
+    }
+
+    /// Writes the path from the root to a node, `0` for left and `1` for right.
+    fn write_path(&self, mut node: usize, writer: &mut BitWriter) {
+        let mut path = Vec::new();
+        while let Some(parent) = self.nodes[node].parent {
+            path.push(matches!(self.nodes[parent].kind, AdaptiveNodeKind::Node(_, right) if right == node));
+            node = parent;
+        }
+        for bit in path.into_iter().rev() {
+            writer.write_bits_u16(bit as u16, 1);
+        }
+    }
+
+    /// Follows bits from the root until a leaf or the NYT node is reached.
+    fn read_path(&self, reader: &mut BitReader) -> Result<usize, HuffmanError> {
+        let mut node = 0;
+        while let AdaptiveNodeKind::Node(left, right) = self.nodes[node].kind {
+            node = if reader.read_bit().ok_or(HuffmanError::UnexpectedEnd)? {right} else {left};
+        }
+        Ok(node)
+    }
+
+    /// Splits the NYT node into a new NYT node and a leaf for the symbol,
+    /// returning the leaf.
+    fn add_symbol(&mut self, symbol: HuffmanSymbol) -> usize {
+        let parent = self.nyt;
+        let leaf = self.nodes.len();
+        self.nyt = leaf + 1;
+
+        self.nodes.push(AdaptiveNode { weight: 0, parent: Some(parent), kind: AdaptiveNodeKind::Leaf(symbol) });
+        self.nodes.push(AdaptiveNode { weight: 0, parent: Some(parent), kind: AdaptiveNodeKind::Nyt });
+        self.nodes[parent].kind = AdaptiveNodeKind::Node(leaf, self.nyt);
+        self.leaves[symbol as usize] = Some(leaf);
+
+        leaf
+    }
+
+    /// Swaps the subtrees at two nodes. The nodes stay where they are in the
+    /// tree, it's what's under them that moves.
+    fn swap(&mut self, a: usize, b: usize) {
+        let (weight, kind) = (self.nodes[a].weight, self.nodes[a].kind);
+        self.nodes[a].weight = self.nodes[b].weight;
+        self.nodes[a].kind = self.nodes[b].kind;
+        self.nodes[b].weight = weight;
+        self.nodes[b].kind = kind;
+
+        for node in [a, b] {
+            match self.nodes[node].kind {
+                AdaptiveNodeKind::Nyt => self.nyt = node,
+                AdaptiveNodeKind::Leaf(symbol) => self.leaves[symbol as usize] = Some(node),
+                AdaptiveNodeKind::Node(left, right) => {
+                    self.nodes[left].parent = Some(node);
+                    self.nodes[right].parent = Some(node);
+                }
+            }
+        }
+    }
+
+    /// Adds one to the weight of a leaf and all its ancestors. Before each
+    /// node is incremented, it's swapped with the first node with the same
+    /// weight (unless that's its parent), which keeps the sibling property.
+    fn increment(&mut self, leaf: usize) {
+        let mut node = leaf;
+        loop {
+            let weight = self.nodes[node].weight;
+            let mut leader = node;
+            while leader > 0 && self.nodes[leader - 1].weight == weight {
+                leader -= 1;
+            }
+            if leader != node && Some(leader) != self.nodes[node].parent {
+                self.swap(leader, node);
+                node = leader;
+            }
+
+            self.nodes[node].weight += 1;
+            match self.nodes[node].parent {
+                Some(parent) => node = parent,
+                None => break
+            }
+        }
+    }
+
+    fn encode(&mut self, symbol: HuffmanSymbol, writer: &mut BitWriter) {
+        let leaf = match self.leaves[symbol as usize] {
+            Some(leaf) => {
+                self.write_path(leaf, writer);
+                leaf
+            },
+            None => {
+                self.write_path(self.nyt, writer);
+                writer.write_bits_u16(symbol, self.symbol_bits);
+                self.add_symbol(symbol)
+            }
+        };
+        self.increment(leaf);
+    }
+
+    /// Decodes a symbol, returning `HuffmanError::BadSymbolMap` if a new one
+    /// is outside the alphabet or already has a leaf, since adding it again
+    /// would break the tree.
+    fn decode(&mut self, reader: &mut BitReader) -> Result<HuffmanSymbol, HuffmanError> {
+        let node = self.read_path(reader)?;
+        let (leaf, symbol) = match self.nodes[node].kind {
+            AdaptiveNodeKind::Leaf(symbol) => (node, symbol),
+            _ => {
+                let symbol = read_bits(reader, self.symbol_bits)? as usize;
+                if self.leaves.get(symbol) != Some(&None) {
+                    return Err(HuffmanError::BadSymbolMap);
+                }
+                (self.add_symbol(symbol as HuffmanSymbol), symbol as HuffmanSymbol)
+            }
+        };
+        self.increment(leaf);
+        Ok(symbol)
+    }
+}
+
+// Encodes a slice of bytes using Huffman encoding.
+// 
+// This encoding uses chunking, which can result in better compression.
+// `chunk_size` denotes the size of each chunk. If you don't want any
+// chunking, set `chunk_size` to `usize::MAX`. Otherwise, I've found
+// that `1 << 18`, or roughly 256 KB, is a good size for chunks.
+// 
+// `max_path_size` denotes the maximum length of the Huffman paths of each
+// symbol. This is necessary for making decompression fast. Note that using
+// larger `max_path_size`s results in decompression taking up much more space.
+// Therefore, it's advised to make `max_path_size` as small as possible.
+// If you're unsure what to set this to, I've found that `11` is a good length.
 
 #[cfg(test)]
 mod tests{
@@ -625,7 +2113,6 @@ mod tests{
         use crate::huffman::{HuffmanEncoder, HuffmanDecoder, HUFFMAN_MAX_SYMBOLS};
         let contents = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
 
-        let start_time = time::Instant::now();
         let mut writer = BitWriter::new();
         let mut encoder = HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS);
 
@@ -642,7 +2129,7 @@ mod tests{
 
         let start_time = time::Instant::now();
 
-        let decoded_bytes = decoder.decode_all_bytes(&mut reader);
+        let decoded_bytes = decoder.decode_all_bytes(&mut reader).unwrap();
 
         let elapsed_time = start_time.elapsed().as_millis();
         println!("Decompression time:[{}]ms Speed:[{}]MB/s", elapsed_time, ((encoded_bytes.len() as f32) / 1000f32) / (elapsed_time as f32));
@@ -664,4 +2151,1338 @@ mod tests{
         huffman_test(HUFFMAN_DEFAULT_CHUNK_SIZE);
     }
 
-}
\ No newline at end of file
+    fn huffman_roundtrip(bytes: &[u8], chunk_size: usize) -> Vec<u8> {
+        use crate::huffman::{HuffmanEncoder, HuffmanDecoder, HUFFMAN_MAX_SYMBOLS};
+
+        let mut writer = BitWriter::new();
+        let mut encoder = HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS);
+        encoder.encode_all_bytes(bytes, chunk_size, &mut writer);
+        let encoded_bytes = writer.get_bytes();
+
+        let mut reader = BitReader::new(&encoded_bytes);
+        let mut decoder = HuffmanDecoder::new();
+        decoder.decode_all_bytes(&mut reader).unwrap()
+    }
+
+    #[test]
+    pub fn huffman_test_single_symbol(){
+        let bytes = vec![0x41u8; 4096];
+        assert!(huffman_roundtrip(&bytes, usize::MAX) == bytes, "Single symbol input changed after encoding and decoding");
+        assert!(huffman_roundtrip(&bytes, 1000) == bytes, "Single symbol input changed after encoding and decoding with chunking");
+        assert!(huffman_roundtrip(&bytes[..1], usize::MAX) == bytes[..1], "Single byte input changed after encoding and decoding");
+    }
+
+    #[test]
+    pub fn huffman_test_empty(){
+        assert!(huffman_roundtrip(&[], usize::MAX).is_empty(), "Empty input wasn't empty after encoding and decoding");
+    }
+
+    #[test]
+    pub fn huffman_test_table_size(){
+        use std::fs;
+        use crate::huffman::{HuffmanEncoder, HuffmanDecoder, HuffmanError, HUFFMAN_MAX_SYMBOLS, HUFFMAN_DEFAULT_CHUNK_SIZE, decode_table_size, max_code_len_for_table_size};
+
+        assert!(max_code_len_for_table_size(decode_table_size(10)) == Some(10), "Exact table size for a length of 10 should allow a length of 10");
+        assert!(max_code_len_for_table_size(decode_table_size(10) - 1) == Some(9), "Table size just under a length of 10 should allow a length of 9");
+        assert!(max_code_len_for_table_size(0).is_none(), "A table size of 0 shouldn't allow any length");
+
+        let contents = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let table_size = decode_table_size(10) + 100;
+
+        let mut encoder = HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS);
+        assert!(encoder.fit_max_code_len_to_table_size(table_size) == Ok(10), "Wrong max code length chosen for table size {table_size}");
+
+        let mut writer = BitWriter::new();
+        encoder.encode_all_bytes(&contents, HUFFMAN_DEFAULT_CHUNK_SIZE, &mut writer);
+        let encoded_bytes = writer.get_bytes();
+
+        let mut reader = BitReader::new(&encoded_bytes);
+        let mut decoder = HuffmanDecoder::new();
+        let decoded_bytes = decoder.decode_all_bytes(&mut reader).unwrap();
+        assert!(decoder.table_size() <= table_size, "Decoder table size [{}] larger than limit [{table_size}]", decoder.table_size());
+        assert!(decoded_bytes == contents, "Bytes different after encoding and decoding");
+
+        let mut encoder = HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS);
+        assert!(encoder.fit_max_code_len_to_table_size(decode_table_size(8)) == Err(HuffmanError::TableSizeTooSmall { table_size: decode_table_size(8), required: decode_table_size(9) }), "Table size too small for the alphabet should error");
+    }
+
+
+    #[test]
+    pub fn huffman_test_long_codes(){
+        use crate::huffman::{HuffmanEncoder, HuffmanDecoder, HuffmanError, HuffmanSymbol, HUFFMAN_MAX_SYMBOLS, decode_table_size};
+
+        // Fibonacci frequencies give the deepest possible tree, so this needs
+        // codes way longer than the default max to encode without limiting
+        let mut symbols:Vec<HuffmanSymbol> = Vec::new();
+        let (mut a, mut b) = (1usize, 1usize);
+        for symbol in 0..26 {
+            symbols.extend(std::iter::repeat_n(symbol as HuffmanSymbol, a));
+            (a, b) = (b, a + b);
+        }
+
+        let mut writer = BitWriter::new();
+        let mut encoder = HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS);
+        encoder.set_max_code_len(20);
+        encoder.encode_all(&symbols, usize::MAX, &mut writer);
+        let encoded_bytes = writer.get_bytes();
+
+        //The decoder has to be told codes can be that long
+        let decoded = HuffmanDecoder::new().decode_all(&mut BitReader::new(&encoded_bytes));
+        assert!(decoded == Err(HuffmanError::BadCodeLengths), "Codes longer than the decoder's max weren't caught [{:?}]", decoded.map(|symbols| symbols.len()));
+
+        let mut reader = BitReader::new(&encoded_bytes);
+        let mut decoder = HuffmanDecoder::new();
+        decoder.set_max_code_len(20);
+        let decoded_symbols = decoder.decode_all(&mut reader).unwrap();
+        assert!(decoder.table_size() == decode_table_size(20), "Decoder didn't use 20 bit codes, table size [{}]", decoder.table_size());
+        assert!(decoded_symbols == symbols, "Symbols different after encoding and decoding with a max code length of 20");
+    }
+
+    #[test]
+    pub fn huffman_test_byte_aligned_chunks(){
+        use std::fs;
+        use crate::huffman::{HuffmanEncoder, HuffmanDecoder, HUFFMAN_MAX_SYMBOLS, HUFFMAN_CHUNK_SIZE_BITS};
+
+        let contents = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let chunk_size = 10000;
+
+        let mut writer = BitWriter::new();
+        let mut encoder = HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS);
+        encoder.set_byte_align_chunks(true);
+        encoder.encode_all_bytes(&contents, chunk_size, &mut writer);
+        let encoded_bytes = writer.get_bytes();
+
+        let mut reader = BitReader::new(&encoded_bytes);
+        let mut decoder = HuffmanDecoder::new();
+        assert!(decoder.decode_all_bytes(&mut reader).unwrap() == contents, "Bytes different after encoding and decoding with byte aligned chunks");
+
+        //Every chunk should decode on its own, starting from a fresh reader at its byte offset
+        let mut reader = BitReader::new(&encoded_bytes);
+        assert!(reader.read_bit() == Some(true), "Byte aligned flag wasn't set");
+        assert!(reader.read_bit() == Some(false), "Reused chunks flag was set");
+        assert!(reader.read_bit() == Some(false), "Global table flag was set");
+        let chunk_num = reader.read_bits_into_u32(HUFFMAN_CHUNK_SIZE_BITS).unwrap() as usize;
+        assert!(chunk_num == contents.len().div_ceil(chunk_size), "Wrong chunk count [{chunk_num}]");
+        for chunk in contents.chunks(chunk_size) {
+            reader.align_to_byte();
+            let offset = encoded_bytes.len() - (reader.remaining_bits() >> 3);
+
+            let mut chunk_reader = BitReader::new(&encoded_bytes[offset..]);
+            let mut chunk_decoder = HuffmanDecoder::new();
+            chunk_decoder.read_huffman_table(&mut chunk_reader).unwrap();
+            let decoded_chunk = HuffmanDecoder::symbols_to_bytes(&chunk_decoder.decode_chunk(&mut chunk_reader).unwrap()).unwrap();
+            assert!(decoded_chunk == chunk, "Chunk at byte offset [{offset}] decoded incorrectly");
+
+            decoder.read_huffman_table(&mut reader).unwrap();
+            decoder.decode_chunk(&mut reader).unwrap();
+        }
+    }
+
+    #[test]
+    pub fn huffman_test_back_to_back(){
+        use std::fs;
+        use crate::huffman::{HuffmanEncoder, HuffmanDecoder, HuffmanError, HuffmanSymbol, HUFFMAN_MAX_SYMBOLS};
+        use crate::api::{compress, decompress, Codec};
+        use crate::error::DecodeError;
+
+        let contents = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let (first, second) = contents.split_at(40000);
+
+        //The chunk count says where the first stream ends, even with more bits right after it
+        let mut writer = BitWriter::new();
+        HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS).encode_all_bytes(first, 10000, &mut writer);
+        HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS).encode_all_bytes(second, 30000, &mut writer);
+        HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS).encode_all_bytes(&[], 30000, &mut writer);
+        let encoded_bytes = writer.get_bytes();
+
+        let mut reader = BitReader::new(&encoded_bytes);
+        assert!(HuffmanDecoder::new().decode_all_bytes(&mut reader).unwrap() == first, "First stream different after decoding");
+        assert!(HuffmanDecoder::new().decode_all_bytes(&mut reader).unwrap() == second, "Second stream different after decoding");
+        assert!(HuffmanDecoder::new().decode_all_bytes(&mut reader).unwrap().is_empty(), "Empty stream wasn't empty after decoding");
+        assert!(reader.remaining_bits() < 8, "[{}] bits left after the last stream", reader.remaining_bits());
+
+        //One byte past a multiple of the chunk size, then more than 32 bits of zeros that look like another chunk
+        let symbols:Vec<HuffmanSymbol> = first[..10001].iter().map(|&byte| byte as HuffmanSymbol).collect();
+        let mut writer = BitWriter::new();
+        HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS).encode_all(&symbols, 1000, &mut writer);
+        writer.write_bits_u32(0, 32);
+        writer.write_bits_u32(0, 8);
+        let encoded_bytes = writer.get_bytes();
+
+        let mut reader = BitReader::new(&encoded_bytes);
+        let decoded = HuffmanDecoder::new().decode_all(&mut reader).unwrap();
+        assert!(decoded == symbols, "Symbols different after decoding with [{}] symbols instead of [{}]", decoded.len(), symbols.len());
+        assert!(reader.remaining_bits() >= 40, "Zeros after the stream were read, only [{}] bits left", reader.remaining_bits());
+
+        //A header that's cut off isn't a stream without a chunk count, it's just cut off
+        let compressed = compress(Codec::Huffman, first);
+        for len in 0..=4 {
+            let decompressed = decompress(Codec::Huffman, &compressed[..len]);
+            assert!(decompressed == Err(DecodeError::Huffman(HuffmanError::UnexpectedEnd)), "Header cut off at [{len}] bytes wasn't caught [{:?}]", decompressed.map(|bytes| bytes.len()));
+        }
+    }
+
+    #[test]
+    pub fn huffman_test_remapped(){
+        use std::fs;
+        use crate::huffman::{HuffmanEncoder, HuffmanDecoder, HuffmanError, HuffmanSymbol, HUFFMAN_MAX_SYMBOLS, HUFFMAN_TABLE_VERSION};
+
+        //Only uppercase letters, so 26 symbols out of 256
+        let contents = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let letters:Vec<HuffmanSymbol> = contents.iter().filter(|byte| byte.is_ascii_alphabetic()).map(|byte| byte.to_ascii_uppercase() as HuffmanSymbol).collect();
+
+        let mut encoder = HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS);
+        let mut plain = BitWriter::new();
+        encoder.encode_chunk(&letters, &mut plain);
+        let mut plain_table = BitWriter::new();
+        encoder.write_huffman_table(&mut plain_table);
+
+        let mut remapped = BitWriter::new();
+        encoder.encode_chunk_remapped(&letters, &mut remapped);
+        let mut remapped_table = BitWriter::new();
+        encoder.write_remapped_huffman_table(&mut remapped_table);
+
+        println!("Table bits: [{}] Remapped table bits: [{}]", plain_table.total_bits_written(), remapped_table.total_bits_written());
+        assert!(remapped_table.total_bits_written() * 2 < plain_table.total_bits_written(), "Remapped table took [{}] bits, the plain one [{}]", remapped_table.total_bits_written(), plain_table.total_bits_written());
+        assert!(remapped.total_bits_written() < plain.total_bits_written(), "Remapped chunk took [{}] bits, the plain one [{}]", remapped.total_bits_written(), plain.total_bits_written());
+
+        let encoded_bytes = remapped.get_bytes();
+        let decoded = HuffmanDecoder::new().decode_chunk_remapped(&mut BitReader::new(&encoded_bytes));
+        assert!(decoded.as_ref() == Ok(&letters), "Letters different after remapped encoding and decoding");
+
+        //Every symbol, the first and last ones alone, and nothing
+        let every:Vec<HuffmanSymbol> = (0..HUFFMAN_MAX_SYMBOLS as HuffmanSymbol).chain(0..10).collect();
+        for symbols in [every, vec![0; 5], vec![255], Vec::new()] {
+            let mut writer = BitWriter::new();
+            encoder.encode_chunk_remapped(&symbols, &mut writer);
+            let encoded_bytes = writer.get_bytes();
+            let decoded = HuffmanDecoder::new().decode_chunk_remapped(&mut BitReader::new(&encoded_bytes));
+            assert!(decoded.as_ref() == Ok(&symbols), "Symbols different after remapped encoding and decoding [{decoded:?}]");
+        }
+
+        //Gaps that run past what symbols can be written as, to symbol 599
+        let mut writer = BitWriter::new();
+        writer.write_bits_u16(HUFFMAN_TABLE_VERSION as u16, 8);
+        writer.write_bits_u16(2, 9);
+        writer.write_bits_u16(1, 5);
+        writer.write_elias_gamma(300);
+        writer.write_elias_gamma(300);
+        let encoded_bytes = writer.get_bytes();
+        assert!(HuffmanDecoder::new().decode_chunk_remapped(&mut BitReader::new(&encoded_bytes)) == Err(HuffmanError::BadSymbolMap), "Symbols past the alphabet weren't caught");
+
+        //A table cut off anywhere is an error, not a smaller table
+        let table_bytes = remapped_table.get_bytes();
+        for len in 0..table_bytes.len() {
+            let read = HuffmanDecoder::new().read_remapped_huffman_table(&mut BitReader::new(&table_bytes[..len]));
+            assert!(read == Err(HuffmanError::UnexpectedEnd), "Remapped table cut off at [{len}] bytes wasn't caught [{read:?}]");
+        }
+    }
+
+    #[test]
+    pub fn huffman_test_decode_chunk_into(){
+        use std::fs;
+        use crate::error::DecodeError;
+        use crate::huffman::{HuffmanEncoder, HuffmanDecoder, HuffmanSymbol, HUFFMAN_MAX_SYMBOLS};
+
+        let contents = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let symbols:Vec<HuffmanSymbol> = contents.iter().map(|&byte| byte as HuffmanSymbol).collect();
+        let chunk_size = 10000;
+
+        let mut encoder = HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS);
+        let mut writer = BitWriter::new();
+        for chunk in symbols.chunks(chunk_size) {
+            encoder.encode_chunk(chunk, &mut writer);
+        }
+        let encoded_bytes = writer.get_bytes();
+
+        //The same slice gets reused for every chunk, the last one is shorter
+        let mut reader = BitReader::new(&encoded_bytes);
+        let mut expected_reader = BitReader::new(&encoded_bytes);
+        let (mut decoder, mut expected_decoder) = (HuffmanDecoder::new(), HuffmanDecoder::new());
+        let mut out = vec![0; chunk_size];
+        for chunk in symbols.chunks(chunk_size) {
+            decoder.read_huffman_table(&mut reader).unwrap();
+            let symbol_num = decoder.decode_chunk_into(&mut reader, &mut out).unwrap();
+            expected_decoder.read_huffman_table(&mut expected_reader).unwrap();
+            let expected = expected_decoder.decode_chunk(&mut expected_reader).unwrap();
+
+            assert!(out[..symbol_num] == expected[..] && expected == chunk, "Chunk decoded into a slice different from decode_chunk");
+            assert!(reader.bits_consumed() == expected_reader.bits_consumed(), "Decoding into a slice read [{}] bits instead of [{}]", reader.bits_consumed(), expected_reader.bits_consumed());
+        }
+
+        //Too small a slice, then a chunk missing its end
+        let mut reader = BitReader::new(&encoded_bytes);
+        decoder.read_huffman_table(&mut reader).unwrap();
+        assert!(decoder.decode_chunk_into(&mut reader, &mut out[..(chunk_size - 1)]) == Err(DecodeError::OutputTooLarge), "Slice that's too small wasn't caught");
+
+        let mut writer = BitWriter::new();
+        encoder.encode_chunk(&symbols[..100], &mut writer);
+        let encoded_bytes = writer.get_bytes();
+        let mut reader = BitReader::new(&encoded_bytes[..(encoded_bytes.len() - 10)]);
+        decoder.read_huffman_table(&mut reader).unwrap();
+        assert!(decoder.decode_chunk_into(&mut reader, &mut out) == Err(DecodeError::UnexpectedEnd), "Truncated chunk wasn't caught");
+
+        //Cut off in the middle of the chunk size, which would otherwise read as a smaller one
+        let mut reader = BitReader::new(&encoded_bytes);
+        decoder.read_huffman_table(&mut reader).unwrap();
+        let cut_len = (reader.bits_consumed() + 16) / 8;
+        let mut reader = BitReader::new(&encoded_bytes[..cut_len]);
+        decoder.read_huffman_table(&mut reader).unwrap();
+        let decoded = decoder.decode_chunk_into(&mut reader, &mut out);
+        assert!(decoded == Err(DecodeError::UnexpectedEnd), "Chunk size cut off wasn't caught [{decoded:?}]");
+    }
+
+    #[test]
+    pub fn huffman_test_reuse_identical_chunks(){
+        use std::fs;
+        use crate::huffman::{HuffmanEncoder, HuffmanDecoder, HUFFMAN_MAX_SYMBOLS};
+
+        let contents = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let chunk_size = 4096;
+
+        //Repeated blocks, a zero-filled region, then some normal text
+        let mut bytes = Vec::new();
+        for _ in 0..8 {
+            bytes.extend_from_slice(&contents[..chunk_size]);
+        }
+        bytes.extend(vec![0u8; chunk_size * 8]);
+        bytes.extend_from_slice(&contents[..10000]);
+
+        for byte_align_chunks in [false, true] {
+            let mut sizes = Vec::new();
+            for reuse_identical_chunks in [false, true] {
+                let mut writer = BitWriter::new();
+                let mut encoder = HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS);
+                encoder.set_byte_align_chunks(byte_align_chunks);
+                encoder.set_reuse_identical_chunks(reuse_identical_chunks);
+                encoder.encode_all_bytes(&bytes, chunk_size, &mut writer);
+                let encoded_bytes = writer.get_bytes();
+                sizes.push(encoded_bytes.len());
+
+                let mut reader = BitReader::new(&encoded_bytes);
+                let mut decoder = HuffmanDecoder::new();
+                assert!(decoder.decode_all_bytes(&mut reader).unwrap() == bytes, "Bytes different after encoding and decoding, reuse [{reuse_identical_chunks}] align [{byte_align_chunks}]");
+            }
+            println!("Encoded size without reuse: [{}] with reuse: [{}]", sizes[0], sizes[1]);
+            assert!(sizes[1] * 2 < sizes[0], "Reusing identical chunks didn't shrink the output enough [{}] [{}]", sizes[1], sizes[0]);
+        }
+
+        //The last chunk being a repeat is the tricky one, since it's just a bit
+        let bytes = vec![0x41u8; chunk_size * 3];
+        let mut writer = BitWriter::new();
+        let mut encoder = HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS);
+        encoder.set_reuse_identical_chunks(true);
+        encoder.encode_all_bytes(&bytes, chunk_size, &mut writer);
+        let encoded_bytes = writer.get_bytes();
+        assert!(HuffmanDecoder::new().decode_all_bytes(&mut BitReader::new(&encoded_bytes)).unwrap() == bytes, "Repeated last chunk decoded incorrectly");
+
+        let mut writer = BitWriter::new();
+        encoder.encode_all_bytes(&[], chunk_size, &mut writer);
+        let encoded_bytes = writer.get_bytes();
+        assert!(HuffmanDecoder::new().decode_all_bytes(&mut BitReader::new(&encoded_bytes)).unwrap().is_empty(), "Nothing wasn't empty after encoding and decoding with reused chunks");
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    pub fn huffman_test_decode_to_writer(){
+        use std::{fs, io::{self, Write}};
+        use crate::huffman::{HuffmanEncoder, HuffmanDecoder, HUFFMAN_MAX_SYMBOLS};
+
+        //Remembers the biggest write, to check the output comes a chunk at a time
+        struct ChunkSink {
+            bytes: Vec<u8>,
+            largest_write: usize
+        }
+        impl Write for ChunkSink {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.largest_write = self.largest_write.max(buf.len());
+                self.bytes.extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let contents = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let chunk_size = 10000;
+
+        for reuse_identical_chunks in [false, true] {
+            let mut bytes = contents[..20000].to_vec();
+            bytes.extend_from_slice(&[0u8; 30000]);
+            bytes.extend_from_slice(&contents[20000..]);
+
+            let mut writer = BitWriter::new();
+            let mut encoder = HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS);
+            encoder.set_reuse_identical_chunks(reuse_identical_chunks);
+            encoder.encode_all_bytes(&bytes, chunk_size, &mut writer);
+            let encoded_bytes = writer.get_bytes();
+
+            let decoded_bytes = HuffmanDecoder::new().decode_all_bytes(&mut BitReader::new(&encoded_bytes)).unwrap();
+            let mut sink = ChunkSink { bytes: Vec::new(), largest_write: 0 };
+            HuffmanDecoder::new().decode_all_to(&mut BitReader::new(&encoded_bytes), &mut sink).unwrap();
+
+            assert!(sink.bytes == decoded_bytes && sink.bytes == bytes, "Bytes different decoding to a writer, reusing chunks [{reuse_identical_chunks}]");
+            assert!(sink.largest_write <= chunk_size, "Wrote [{}] bytes at once, more than a chunk", sink.largest_write);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    pub fn huffman_test_reader(){
+        use std::{fs, io::{self, Read}};
+        use crate::huffman::{HuffmanEncoder, HuffmanReader, HUFFMAN_MAX_SYMBOLS};
+
+        let contents = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let mut bytes = contents.clone();
+        bytes.extend_from_slice(&[0u8; 30000]);
+
+        for (chunk_size, byte_align_chunks, reuse_identical_chunks) in [(usize::MAX, false, false), (10000, true, false), (10000, false, true)] {
+            let mut writer = BitWriter::new();
+            let mut encoder = HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS);
+            encoder.set_byte_align_chunks(byte_align_chunks);
+            encoder.set_reuse_identical_chunks(reuse_identical_chunks);
+            encoder.encode_all_bytes(&bytes, chunk_size, &mut writer);
+            let encoded_bytes = writer.get_bytes();
+
+            let mut decoded = Vec::new();
+            io::copy(&mut HuffmanReader::new(BitReader::new(&encoded_bytes)), &mut decoded).unwrap();
+            assert!(decoded == bytes, "Bytes different after copying from a HuffmanReader with chunks of [{chunk_size}]");
+
+            //Small reads that don't line up with the chunks
+            let mut reader = HuffmanReader::new(BitReader::new(&encoded_bytes));
+            let mut decoded = Vec::new();
+            let mut buf = [0u8; 777];
+            loop {
+                let byte_num = reader.read(&mut buf).unwrap();
+                if byte_num == 0 {break;}
+                decoded.extend_from_slice(&buf[..byte_num]);
+            }
+            assert!(decoded == bytes, "Bytes different after small reads from a HuffmanReader with chunks of [{chunk_size}]");
+        }
+
+        let mut writer = BitWriter::new();
+        HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS).encode_all_bytes(&[], usize::MAX, &mut writer);
+        let encoded_bytes = writer.get_bytes();
+        let mut decoded = Vec::new();
+        HuffmanReader::new(BitReader::new(&encoded_bytes)).read_to_end(&mut decoded).unwrap();
+        assert!(decoded.is_empty(), "Nothing wasn't empty after reading from a HuffmanReader");
+
+        //A header that's cut off is an error on the first read
+        for len in 0..=4 {
+            let read = HuffmanReader::new(BitReader::new(&encoded_bytes[..len])).read_to_end(&mut decoded);
+            assert!(read.is_err(), "Header cut off at [{len}] bytes wasn't caught by HuffmanReader");
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    pub fn huffman_test_writer(){
+        use std::{cmp::min, fs, io::{self, Write}};
+        use rand::prelude::*;
+        use crate::huffman::{HuffmanEncoder, HuffmanDecoder, HuffmanWriter, HUFFMAN_MAX_SYMBOLS};
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(2718);
+
+        for chunk_size in [usize::MAX, 10000, 1 << 16] {
+            let mut huffman_writer = HuffmanWriter::with_chunk_size(Vec::new(), chunk_size);
+            let mut pos = 0;
+            while pos < bytes.len() {
+                let write_size = min(rng.gen_range(1..=300), bytes.len() - pos);
+                huffman_writer.write_all(&bytes[pos..(pos + write_size)]).unwrap();
+                pos += write_size;
+            }
+            let encoded_bytes = huffman_writer.finish().unwrap();
+
+            let decoded_bytes = HuffmanDecoder::new().decode_all_bytes(&mut BitReader::new(&encoded_bytes)).unwrap();
+            assert!(decoded_bytes == bytes, "Bytes different after writing to a HuffmanWriter with chunks of [{chunk_size}]");
+
+            //Only the chunk count in the first 34 bits is different, since HuffmanWriter doesn't know it
+            let mut writer = BitWriter::new();
+            HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS).encode_all_bytes(&bytes, chunk_size, &mut writer);
+            let expected_bytes = writer.get_bytes();
+            assert!(encoded_bytes.len() == expected_bytes.len() && encoded_bytes[5..] == expected_bytes[5..], "HuffmanWriter output different from encode_all_bytes with chunks of [{chunk_size}]");
+        }
+
+        //io::copy, with a chunk size that fits the input exactly
+        let mut huffman_writer = HuffmanWriter::with_chunk_size(Vec::new(), bytes.len() / 7);
+        io::copy(&mut &bytes[..(bytes.len() / 7) * 7], &mut huffman_writer).unwrap();
+        let encoded_bytes = huffman_writer.finish().unwrap();
+        assert!(HuffmanDecoder::new().decode_all_bytes(&mut BitReader::new(&encoded_bytes)).unwrap() == bytes[..(bytes.len() / 7) * 7], "Bytes different after copying into a HuffmanWriter");
+
+        let encoded_bytes = HuffmanWriter::new(Vec::new()).finish().unwrap();
+        assert!(HuffmanDecoder::new().decode_all_bytes(&mut BitReader::new(&encoded_bytes)).unwrap().is_empty(), "Nothing wasn't empty after writing to a HuffmanWriter");
+    }
+
+    #[test]
+    pub fn huffman_test_chunk_size(){
+        use std::fs;
+        use crate::huffman::{compress_huffman, decompress_huffman};
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let bytes = &bytes[..2000];
+
+        //Every byte is its own chunk, with its own table
+        let compressed = compress_huffman(bytes, 1);
+        println!("Chunk size 1: [{}] -> [{}]", bytes.len(), compressed.len());
+        assert!(decompress_huffman(&compressed).as_deref() == Ok(bytes), "Bytes different after compressing with a chunk size of 1");
+
+        //Just one chunk, same as a chunk size of exactly the input
+        let compressed = compress_huffman(bytes, bytes.len() * 10);
+        assert!(compressed == compress_huffman(bytes, bytes.len()), "Chunk size bigger than the input wasn't a single chunk");
+        assert!(decompress_huffman(&compressed).as_deref() == Ok(bytes), "Bytes different after compressing with a chunk size bigger than the input");
+        assert!(decompress_huffman(&compress_huffman(&[], 1)) == Ok(Vec::new()), "Nothing wasn't empty after compressing and decompressing");
+    }
+
+    #[test]
+    pub fn huffman_test_fixed_table(){
+        use std::fs;
+        use crate::huffman::{HuffmanEncoder, HuffmanDecoder, HuffmanError, HUFFMAN_MAX_SYMBOLS};
+        use crate::error::DecodeError;
+
+        //How often each letter shows up in English, per 10000 letters
+        let english = [817, 149, 278, 425, 1270, 223, 202, 609, 697, 15, 77, 403, 241, 675, 751, 193, 10, 599, 633, 906, 276, 98, 236, 15, 197, 7];
+        //Every byte can show up, but letters (and spaces) a lot more
+        let mut freqs = vec![1u64; 256];
+        for (letter, freq) in english.iter().enumerate() {
+            freqs[b'a' as usize + letter] = *freq;
+            freqs[b'A' as usize + letter] = freq / 20 + 1;
+        }
+        freqs[b' ' as usize] = 1800;
+        freqs[b'.' as usize] = 100;
+        freqs[b',' as usize] = 100;
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let symbols = HuffmanEncoder::bytes_to_symbols(&bytes);
+        let mut encoder = HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS);
+
+        for chunk_size in [256, 4096, symbols.len()] {
+            encoder.set_frequencies(&freqs);
+            let mut writer = BitWriter::new();
+            encoder.encode_all_with_fixed_table(&symbols, chunk_size, &mut writer);
+            let fixed_bytes = writer.get_bytes();
+
+            let mut writer = BitWriter::new();
+            encoder.encode_all(&symbols, chunk_size, &mut writer);
+            let per_chunk_bytes = writer.get_bytes();
+            println!("Chunk size: [{chunk_size}] Fixed table: [{}] Table per chunk: [{}]", fixed_bytes.len(), per_chunk_bytes.len());
+            if chunk_size == 256 {
+                assert!(fixed_bytes.len() < per_chunk_bytes.len(), "Fixed table [{}] wasn't smaller than small chunks with their own tables [{}]", fixed_bytes.len(), per_chunk_bytes.len());
+            }
+
+            let decoded = HuffmanDecoder::new().decode_all_fixed_table(&mut BitReader::new(&fixed_bytes));
+            assert!(decoded.as_ref() == Ok(&symbols), "lorem_ipsum different after encoding and decoding with a fixed table of chunk size [{chunk_size}]");
+            //Cut off in the table, the chunk count, a chunk size or the codes
+            for len in (0..600).chain((600..fixed_bytes.len()).step_by(4099)) {
+                let truncated = HuffmanDecoder::new().decode_all_fixed_table(&mut BitReader::new(&fixed_bytes[..len]));
+                assert!(truncated == Err(DecodeError::Huffman(HuffmanError::UnexpectedEnd)), "Fixed table stream cut off at [{len}] bytes wasn't caught [{:?}]", truncated.map(|decoded| decoded.len()));
+            }
+        }
+
+        //No symbols is no chunks, with just the table
+        let mut writer = BitWriter::new();
+        encoder.encode_all_with_fixed_table(&[], 4096, &mut writer);
+        let encoded_bytes = writer.get_bytes();
+        assert!(HuffmanDecoder::new().decode_all_fixed_table(&mut BitReader::new(&encoded_bytes)) == Ok(Vec::new()), "Nothing wasn't empty after encoding with a fixed table");
+    }
+
+    #[test]
+    pub fn huffman_test_dot_graph(){
+        use std::fs;
+        use std::collections::HashSet;
+        use crate::huffman::{HuffmanEncoder, HUFFMAN_MAX_SYMBOLS};
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        for symbols in [HuffmanEncoder::bytes_to_symbols(&bytes), vec![b'a' as u16; 10], Vec::new()] {
+            let mut encoder = HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS);
+            encoder.build_frequency_table(&symbols);
+            encoder.build_huffman_table();
+            let dot = encoder.dot_graph();
+
+            let mut lines:Vec<&str> = dot.lines().collect();
+            assert!(lines.first() == Some(&"digraph huffman {") && lines.last() == Some(&"}"), "Graph isn't wrapped in a digraph [{dot}]");
+            lines = lines[1..(lines.len() - 1)].to_vec();
+
+            let mut nodes = HashSet::new();
+            let mut children = HashSet::new();
+            let mut leaves = 0;
+            for line in lines {
+                assert!(line.ends_with("];"), "Line [{line}] isn't a statement");
+                let (names, _) = line.trim().split_once(" [").expect("Statement has no attributes");
+                if let Some((parent, child)) = names.split_once(" -> ") {
+                    assert!(nodes.contains(parent) && nodes.contains(child), "Edge [{line}] has a node that wasn't declared before it");
+                    assert!(children.insert(child), "Node [{child}] has more than one parent");
+                } else {
+                    assert!(nodes.insert(names), "Node [{names}] declared twice");
+                    if line.contains("shape=box") {leaves += 1;}
+                }
+            }
+
+            let table_len = encoder.iter().count();
+            println!("Symbols: [{}] Nodes: [{}] Leaves: [{leaves}]", table_len, nodes.len());
+            assert!(leaves == table_len, "Graph has [{leaves}] leaves instead of [{table_len}]");
+            assert!(children.len() + 1 == nodes.len() || nodes.is_empty(), "Graph with [{}] nodes and [{}] edges isn't a tree", nodes.len(), children.len());
+            if table_len > 1 {
+                assert!(nodes.len() == 2 * table_len - 1, "Tree with [{table_len}] leaves has [{}] nodes instead of [{}]", nodes.len(), 2 * table_len - 1);
+            }
+        }
+    }
+
+    #[test]
+    pub fn huffman_test_auto_global_table(){
+        use crate::huffman::{HuffmanEncoder, HuffmanDecoder, HUFFMAN_MAX_SYMBOLS};
+        use rand::prelude::*;
+
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(2087);
+        //The alphabet over and over, so every chunk would get the same table
+        let stationary:Vec<u8> = b"abcdefghijklmnopqrstuvwxyz".iter().copied().cycle().take(100000).collect();
+        //Each chunk uses its own 16 bytes, so one table would need codes for all of them
+        let shifting:Vec<u8> = (0..100000).map(|i| (i / 4096 * 16) as u8 + rng.gen_range(0..16)).collect();
+
+        for (bytes, name, global_expected) in [(&stationary, "stationary", true), (&shifting, "shifting", false)] {
+            let mut encoder = HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS);
+            let mut writer = BitWriter::new();
+            encoder.encode_all_bytes(bytes, 4096, &mut writer);
+            let per_chunk_bytes = writer.get_bytes();
+
+            encoder.set_auto_global_table(true);
+            let mut writer = BitWriter::new();
+            encoder.encode_all_bytes(bytes, 4096, &mut writer);
+            let auto_bytes = writer.get_bytes();
+            println!("{name}: [{}] Table per chunk: [{}] Picked automatically: [{}]", bytes.len(), per_chunk_bytes.len(), auto_bytes.len());
+
+            //The global table flag is the third bit
+            let global = auto_bytes[0] & 0b00100000 != 0;
+            assert!(global == global_expected, "Global table flag was [{global}] for the {name} input");
+            assert!(auto_bytes.len() <= per_chunk_bytes.len(), "Picking the table automatically made the {name} input bigger [{}] > [{}]", auto_bytes.len(), per_chunk_bytes.len());
+            assert!(HuffmanDecoder::new().decode_all_bytes(&mut BitReader::new(&auto_bytes)).as_ref() == Ok(bytes), "{name} input different after encoding and decoding");
+
+            encoder.set_byte_align_chunks(true);
+            encoder.set_reuse_identical_chunks(true);
+            let mut writer = BitWriter::new();
+            encoder.encode_all_bytes(bytes, 4096, &mut writer);
+            let auto_bytes = writer.get_bytes();
+            assert!(HuffmanDecoder::new().decode_all_bytes(&mut BitReader::new(&auto_bytes)).as_ref() == Ok(bytes), "{name} input different after encoding and decoding with byte aligned and reused chunks");
+        }
+    }
+
+    #[test]
+    pub fn huffman_test_deterministic_ties(){
+        use std::fs;
+        use rand::prelude::*;
+        use crate::huffman::{HuffmanEncoder, HuffmanNode, HuffmanSymbol, HUFFMAN_MAX_SYMBOLS, compress_huffman};
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        //Every byte exactly as often, which is nothing but ties
+        let flat:Vec<u8> = (0..=255).cycle().take(256 * 40).collect();
+        for input in [&bytes, &flat] {
+            assert!(compress_huffman(input, 4096) == compress_huffman(input, 4096), "Same input compressed to different bytes");
+        }
+
+        //Lots of frequencies the same, so which leaf the heap gets first matters without a tie-break
+        let freqs:Vec<u64> = (0..200).map(|symbol| 1 + symbol % 7).collect();
+        let mut encoder = HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS);
+        encoder.set_frequencies(&freqs);
+        let expected:Vec<(HuffmanSymbol, usize)> = encoder.iter().collect();
+
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(2091);
+        let mut symbols:Vec<usize> = (0..freqs.len()).collect();
+        for _ in 0..20 {
+            symbols.shuffle(&mut rng);
+            let root = HuffmanNode::tree(symbols.iter().map(|&symbol| HuffmanNode::leaf(symbol as HuffmanSymbol, freqs[symbol]))).unwrap();
+            encoder.table.clear();
+            root.leaves(&mut encoder.table);
+            encoder.table.sort();
+            encoder.limit_huffman_table_code_sizes();
+            let table:Vec<(HuffmanSymbol, usize)> = encoder.iter().collect();
+            assert!(table == expected, "Table different when the leaves were shuffled");
+        }
+    }
+
+    #[test]
+    pub fn huffman_test_decode_chunk_cut_off(){
+        use std::fs;
+        use crate::huffman::{HuffmanEncoder, HuffmanDecoder, HuffmanError, HuffmanSymbol, HUFFMAN_MAX_SYMBOLS, compress_huffman, decompress_huffman};
+        use crate::api::{compress, decompress, Codec};
+        use crate::error::DecodeError;
+
+        //Each letter half as often as the one before, ending with the rarest one, which gets an 11 bit code
+        let mut symbols:Vec<HuffmanSymbol> = (0..12).flat_map(|i| vec![(b'a' + i) as HuffmanSymbol; 2048 >> i]).collect();
+        symbols.push(b'm' as HuffmanSymbol);
+        let mut writer = BitWriter::new();
+        HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS).encode_chunk(&symbols, &mut writer);
+        let encoded_bytes = writer.get_bytes();
+
+        //Without the last byte, only the start of the last code is left, which padded with zeros is the code of some other letter
+        let mut reader = BitReader::new(&encoded_bytes[..(encoded_bytes.len() - 1)]);
+        let mut decoder = HuffmanDecoder::new();
+        decoder.read_huffman_table(&mut reader).unwrap();
+        assert!(decoder.decode_chunk(&mut reader) == Err(HuffmanError::UnexpectedEnd), "Chunk cut off in its last code wasn't caught");
+
+        //Cut off anywhere, a whole stream is an error too
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let compressed = compress_huffman(&bytes[..2000], 512);
+        for len in 0..compressed.len() {
+            let decompressed = decompress_huffman(&compressed[..len]);
+            assert!(decompressed.is_err(), "Stream cut off at [{len}] of [{}] bytes decoded to [{:?}] bytes", compressed.len(), decompressed.map(|bytes| bytes.len()));
+        }
+        let compressed = compress(Codec::Huffman, &bytes);
+        let decompressed = decompress(Codec::Huffman, &compressed[..(compressed.len() - 3)]);
+        assert!(decompressed == Err(DecodeError::Huffman(HuffmanError::UnexpectedEnd)), "Cut off stream wasn't caught by decompress [{:?}]", decompressed.map(|bytes| bytes.len()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Chunk size must be at least 1")]
+    pub fn huffman_test_chunk_size_zero(){
+        use crate::huffman::compress_huffman;
+
+        compress_huffman(b"Some bytes", 0);
+    }
+
+    #[test]
+    pub fn huffman_test_max_chunk_size(){
+        use std::fs;
+        use crate::huffman::{HuffmanEncoder, HuffmanDecoder, HuffmanError, HUFFMAN_MAX_SYMBOLS, HUFFMAN_CHUNK_SIZE_BITS, HUFFMAN_DEFAULT_MAX_CHUNK_SIZE};
+        use crate::error::DecodeError;
+
+        //A table, then a chunk that says it has u32::MAX symbols
+        let mut encoder = HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS);
+        encoder.build_frequency_table(&HuffmanEncoder::bytes_to_symbols(b"abcabcaab"));
+        encoder.build_huffman_table();
+        let mut writer = BitWriter::new();
+        writer.write_bits_u16(0, 3);
+        writer.write_bits_u32(1, HUFFMAN_CHUNK_SIZE_BITS);
+        encoder.write_huffman_table(&mut writer);
+        writer.write_bits_u32(u32::MAX, HUFFMAN_CHUNK_SIZE_BITS);
+        writer.write_bits_u16(0, 16);
+        let encoded_bytes = writer.get_bytes();
+
+        let mut reader = BitReader::new(&encoded_bytes);
+        //Past the flags and the chunk count
+        reader.read_bits_into_u32(3);
+        reader.read_bits_into_u32(HUFFMAN_CHUNK_SIZE_BITS);
+        let mut decoder = HuffmanDecoder::new();
+        decoder.read_huffman_table(&mut reader).unwrap();
+        let decoded = decoder.decode_chunk(&mut reader);
+        assert!(decoded == Err(HuffmanError::ChunkTooLarge { chunk_size: u32::MAX as usize, max_chunk_size: HUFFMAN_DEFAULT_MAX_CHUNK_SIZE }), "Chunk size of u32::MAX wasn't caught");
+        let decoded_all = HuffmanDecoder::new().decode_all_bytes(&mut BitReader::new(&encoded_bytes));
+        assert!(decoded_all == Err(DecodeError::Huffman(decoded.unwrap_err())), "Chunk size of u32::MAX wasn't caught by decode_all_bytes [{decoded_all:?}]");
+
+        //A real chunk that's just bigger than the decoder allows
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let mut writer = BitWriter::new();
+        HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS).encode_all_bytes(&bytes[..10000], 4096, &mut writer);
+        let encoded_bytes = writer.get_bytes();
+        let mut decoder = HuffmanDecoder::new();
+        decoder.set_max_chunk_size(4095);
+        let decoded = decoder.decode_all_bytes(&mut BitReader::new(&encoded_bytes));
+        assert!(decoded == Err(DecodeError::Huffman(HuffmanError::ChunkTooLarge { chunk_size: 4096, max_chunk_size: 4095 })), "Chunk bigger than the maximum wasn't caught [{decoded:?}]");
+        decoder.set_max_chunk_size(4096);
+        assert!(decoder.decode_all_bytes(&mut BitReader::new(&encoded_bytes)) == Ok(bytes[..10000].to_vec()), "Chunks right at the maximum weren't decoded");
+    }
+
+    #[test]
+    #[should_panic(expected = "Encoder needs at least one symbol")]
+    pub fn huffman_test_no_symbols(){
+        use crate::huffman::HuffmanEncoder;
+
+        HuffmanEncoder::new(0);
+    }
+
+    #[test]
+    pub fn huffman_test_one_symbol_alphabet(){
+        use crate::huffman::{HuffmanEncoder, HuffmanDecoder};
+
+        let symbols = vec![0; 100];
+        let mut writer = BitWriter::new();
+        HuffmanEncoder::new(1).encode_all(&symbols, usize::MAX, &mut writer);
+        let encoded_bytes = writer.get_bytes();
+        assert!(HuffmanDecoder::new().decode_all(&mut BitReader::new(&encoded_bytes)).unwrap() == symbols, "Symbols different after encoding and decoding with a single symbol alphabet");
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    pub fn huffman_test_parallel(){
+        use std::{fs, time};
+        use crate::huffman::{HuffmanEncoder, HuffmanDecoder, HUFFMAN_MAX_SYMBOLS};
+
+        let contents = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let mut bytes = contents.clone();
+        bytes.extend_from_slice(&[0u8; 30000]);
+        let symbols = HuffmanEncoder::bytes_to_symbols(&bytes);
+
+        for (chunk_size, threads, reuse_identical_chunks) in [(10000, 4, false), (10000, 3, true), (1 << 16, 0, false), (usize::MAX, 2, false), (7, 1, true)] {
+            let mut encoder = HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS);
+            encoder.set_reuse_identical_chunks(reuse_identical_chunks);
+
+            let start_time = time::Instant::now();
+            let parallel_bytes = encoder.encode_all_parallel(&symbols, chunk_size, threads);
+            let parallel_time = start_time.elapsed();
+
+            let start_time = time::Instant::now();
+            let mut writer = BitWriter::new();
+            encoder.set_byte_align_chunks(true);
+            encoder.encode_all(&symbols, chunk_size, &mut writer);
+            let sequential_time = start_time.elapsed();
+            println!("Chunk size: [{chunk_size}] Threads: [{threads}] Parallel time: [{parallel_time:?}] Sequential time: [{sequential_time:?}]");
+
+            assert!(parallel_bytes == writer.get_bytes(), "Parallel encoding different from sequential with chunks of [{chunk_size}] on [{threads}] threads");
+            let decoded = HuffmanDecoder::new().decode_all(&mut BitReader::new(&parallel_bytes)).unwrap();
+            assert!(decoded == symbols, "Symbols different after parallel encoding and decoding with chunks of [{chunk_size}] on [{threads}] threads");
+        }
+
+        let encoded_bytes = HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS).encode_all_parallel(&[], 1000, 4);
+        assert!(HuffmanDecoder::new().decode_all(&mut BitReader::new(&encoded_bytes)).unwrap().is_empty(), "Nothing wasn't empty after parallel encoding and decoding");
+    }
+
+    #[test]
+    pub fn huffman_test_stats(){
+        use std::fs;
+        use crate::huffman::{HuffmanEncoder, HUFFMAN_MAX_SYMBOLS, HUFFMAN_MAX_SYMBOLS_SIZE, HUFFMAN_MAX_LEVEL_SIZE, HUFFMAN_CHUNK_SIZE_BITS, HUFFMAN_RAW_BITS_SIZE};
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let symbols = HuffmanEncoder::bytes_to_symbols(&bytes);
+        let mut encoder = HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS);
+
+        for chunk_size in [100, 10000, symbols.len()] {
+            let chunk = &symbols[..chunk_size];
+            let mut writer = BitWriter::new();
+            //Something already in the writer shouldn't count
+            writer.write_bits_u16(0b101, 3);
+            let stats = encoder.encode_chunk_with_stats(chunk, &mut writer);
+            println!("{stats:?}");
+
+            let mut freq_table = vec![0u64; HUFFMAN_MAX_SYMBOLS];
+            for symbol in chunk {
+                freq_table[*symbol as usize] += 1;
+            }
+            let code_bits:u64 = encoder.iter().map(|(symbol, level)| freq_table[symbol as usize] * level as u64).sum();
+            let bits_per_level = ((stats.max_level as f32).log2().ceil() as usize).max(1);
+            let table_bits = 8 + 1 + HUFFMAN_MAX_SYMBOLS_SIZE + HUFFMAN_MAX_LEVEL_SIZE + stats.num_symbols * (HUFFMAN_MAX_SYMBOLS_SIZE + bits_per_level);
+
+            if stats.raw {
+                //Everything in lorem_ipsum fits in 7 bits
+                let raw_bits = 8 + 1 + HUFFMAN_RAW_BITS_SIZE + HUFFMAN_CHUNK_SIZE_BITS + chunk_size * 7;
+                assert!(stats.output_bits == raw_bits && raw_bits < code_bits as usize + table_bits + HUFFMAN_CHUNK_SIZE_BITS, "Raw chunk took [{}] bits instead of [{raw_bits}]", stats.output_bits);
+            } else {
+                assert!(stats.output_bits == code_bits as usize + table_bits + HUFFMAN_CHUNK_SIZE_BITS, "Output bits [{}] don't match the code bits [{code_bits}] plus the table [{table_bits}]", stats.output_bits);
+            }
+            assert!(stats.output_bits == writer.total_bits_written() - 3, "Output bits [{}] don't match what was written", stats.output_bits);
+            assert!(stats.input_bytes == chunk_size && stats.num_symbols == freq_table.iter().filter(|freq| **freq > 0).count(), "Wrong input size or number of symbols [{stats:?}]");
+            assert!(stats.min_level <= stats.max_level && stats.min_level as f64 <= stats.avg_code_len && stats.avg_code_len <= stats.max_level as f64, "Average code length outside the levels [{stats:?}]");
+        }
+
+        let stats = encoder.encode_chunk_with_stats(&[], &mut BitWriter::new());
+        assert!(stats.num_symbols == 0 && stats.input_bytes == 0 && stats.avg_code_len == 0.0, "Empty chunk had stats [{stats:?}]");
+    }
+
+    #[test]
+    pub fn huffman_test_order1(){
+        use std::fs;
+        use crate::huffman::{HuffmanEncoder, HuffmanDecoder, HuffmanError, HUFFMAN_MAX_SYMBOLS, HUFFMAN_DEFAULT_CHUNK_SIZE};
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+
+        let mut writer = BitWriter::new();
+        HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS).encode_all_bytes(&bytes, HUFFMAN_DEFAULT_CHUNK_SIZE, &mut writer);
+        let order0_len = writer.get_bytes().len();
+
+        let mut writer = BitWriter::new();
+        HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS).encode_order1(&bytes, &mut writer);
+        let encoded = writer.get_bytes();
+        println!("Order-0 bytes: [{order0_len}] Order-1 bytes: [{}]", encoded.len());
+
+        assert!(encoded.len() < order0_len, "Order-1 compressed worse than order-0, {} >= {order0_len}", encoded.len());
+        assert!(HuffmanDecoder::new().decode_order1(&mut BitReader::new(&encoded)) == Ok(bytes.clone()), "Bytes different after order-1 encoding and decoding");
+        assert!(HuffmanDecoder::new().decode_order1(&mut BitReader::new(&encoded[..encoded.len() - 10])) == Err(HuffmanError::UnexpectedEnd), "Truncated order-1 bytes decoded");
+        for cut in 0..64 {
+            assert!(HuffmanDecoder::new().decode_order1(&mut BitReader::new(&encoded[..cut])) == Err(HuffmanError::UnexpectedEnd), "Order-1 bytes cut off at [{cut}] decoded");
+        }
+
+        //Short inputs only have the shared table
+        for short in [&b""[..], b"a", b"abababababab"] {
+            let mut writer = BitWriter::new();
+            HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS).encode_order1(short, &mut writer);
+            let decoded = HuffmanDecoder::new().decode_order1(&mut BitReader::new(&writer.get_bytes()));
+            assert!(decoded.as_deref() == Ok(short), "Short bytes different after order-1 encoding and decoding [{decoded:?}]");
+        }
+    }
+
+    #[test]
+    pub fn huffman_test_adaptive(){
+        use std::fs;
+        use crate::huffman::{HuffmanEncoder, HuffmanDecoder, HuffmanError, HUFFMAN_MAX_SYMBOLS};
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let symbols = HuffmanEncoder::bytes_to_symbols(&bytes);
+
+        for len in [0, 1, 2, 100, 4096, symbols.len()] {
+            let mut writer = BitWriter::new();
+            HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS).encode_adaptive(&symbols[..len], &mut writer);
+            let encoded = writer.get_bytes();
+
+            let decoded = HuffmanDecoder::new().decode_adaptive(&mut BitReader::new(&encoded), len).unwrap();
+            assert!(decoded == symbols[..len], "Symbols different after adaptive encoding and decoding [{len}]");
+        }
+
+        //Every symbol, each showing up a different number of times
+        let all_symbols:Vec<u16> = (0..HUFFMAN_MAX_SYMBOLS as u16).flat_map(|symbol| std::iter::repeat_n(symbol, symbol as usize % 7 + 1)).collect();
+        let mut writer = BitWriter::new();
+        HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS).encode_adaptive(&all_symbols, &mut writer);
+        let decoded = HuffmanDecoder::new().decode_adaptive(&mut BitReader::new(&writer.get_bytes()), all_symbols.len()).unwrap();
+        assert!(decoded == all_symbols, "Every symbol different after adaptive encoding and decoding");
+
+        //Cut off anywhere is an error, not a panic
+        let mut writer = BitWriter::new();
+        HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS).encode_adaptive(&symbols[..100], &mut writer);
+        let encoded = writer.get_bytes();
+        for len in 0..encoded.len() {
+            let decoded = HuffmanDecoder::new().decode_adaptive(&mut BitReader::new(&encoded[..len]), 100);
+            assert!(decoded == Err(HuffmanError::UnexpectedEnd), "Adaptive symbols cut off at [{len}] bytes weren't caught [{decoded:?}]");
+        }
+
+        //A new symbol past the alphabet
+        let decoded = HuffmanDecoder::with_max_symbols(300).decode_adaptive(&mut BitReader::new(&[0xFF; 3]), 1);
+        assert!(decoded == Err(HuffmanError::BadSymbolMap), "Adaptive symbol past the alphabet wasn't caught [{decoded:?}]");
+
+        //The same new symbol twice, the second time through the NYT node (on the right after the first symbol)
+        let mut writer = BitWriter::new();
+        writer.write_bits_u16(5, 9);
+        writer.write_bits_u16(1, 1);
+        writer.write_bits_u16(5, 9);
+        let decoded = HuffmanDecoder::new().decode_adaptive(&mut BitReader::new(&writer.get_bytes()), 2);
+        assert!(decoded == Err(HuffmanError::BadSymbolMap), "Adaptive symbol added twice wasn't caught [{decoded:?}]");
+    }
+
+    #[test]
+    pub fn huffman_test_adaptive_size(){
+        use std::fs;
+        use crate::huffman::{HuffmanEncoder, HUFFMAN_MAX_SYMBOLS};
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let symbols = HuffmanEncoder::bytes_to_symbols(&bytes[..4096]);
+        let mut encoder = HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS);
+
+        let mut writer = BitWriter::new();
+        encoder.encode_chunk(&symbols, &mut writer);
+        let static_bits = writer.total_bits_written();
+
+        let mut writer = BitWriter::new();
+        encoder.encode_adaptive(&symbols, &mut writer);
+        let adaptive_bits = writer.total_bits_written();
+
+        println!("4KB static: [{static_bits}] bits Adaptive: [{adaptive_bits}] bits");
+        assert!(adaptive_bits < static_bits, "Adaptive [{adaptive_bits}] bits not smaller than static [{static_bits}] bits for 4KB");
+    }
+
+    #[test]
+    pub fn huffman_test_large_alphabet(){
+        use rand::prelude::*;
+        use crate::huffman::{HuffmanEncoder, HuffmanDecoder, HuffmanSymbol, HUFFMAN_MAX_ALPHABET_SIZE};
+
+        //Skewed towards small symbols, but every one of the 1000 shows up
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(2123);
+        let mut symbols:Vec<HuffmanSymbol> = (0..1000).collect();
+        symbols.extend((0..100000).map(|_| (rng.gen_range(0.0f64..1.0).powi(3) * 1000.0) as HuffmanSymbol));
+
+        for chunk_size in [1000, 1 << 14, usize::MAX] {
+            let mut writer = BitWriter::new();
+            HuffmanEncoder::new(1000).encode_all(&symbols, chunk_size, &mut writer);
+            let encoded_bytes = writer.get_bytes();
+            println!("1000 symbols Chunk size: [{chunk_size}] Encoded size: [{}]", encoded_bytes.len());
+
+            let decoded = HuffmanDecoder::with_max_symbols(1000).decode_all(&mut BitReader::new(&encoded_bytes)).unwrap();
+            assert!(decoded == symbols, "Symbols different after encoding and decoding with a 1000 symbol alphabet [{chunk_size}]");
+        }
+
+        let mut writer = BitWriter::new();
+        HuffmanEncoder::new(1000).encode_adaptive(&symbols, &mut writer);
+        let decoded = HuffmanDecoder::with_max_symbols(1000).decode_adaptive(&mut BitReader::new(&writer.get_bytes()), symbols.len()).unwrap();
+        assert!(decoded == symbols, "Symbols different after adaptive encoding and decoding with a 1000 symbol alphabet");
+
+        //Every symbol of the biggest alphabets, which have as many symbols as the count can hold
+        for max_symbols in [512, HUFFMAN_MAX_ALPHABET_SIZE] {
+            let symbols:Vec<HuffmanSymbol> = (0..max_symbols as HuffmanSymbol).chain(0..10).collect();
+            let mut writer = BitWriter::new();
+            HuffmanEncoder::new(max_symbols).encode_all(&symbols, usize::MAX, &mut writer);
+            let decoded = HuffmanDecoder::with_max_symbols(max_symbols).decode_all(&mut BitReader::new(&writer.get_bytes())).unwrap();
+            assert!(decoded == symbols, "Symbols different after encoding and decoding every symbol of a [{max_symbols}] symbol alphabet");
+        }
+    }
+
+    #[test]
+    pub fn huffman_test_wide_symbols(){
+        use rand::prelude::*;
+        use crate::huffman::{HuffmanEncoder, HuffmanDecoder, HuffmanError, HuffmanSymbol, HUFFMAN_MAX_SYMBOLS};
+        use crate::error::DecodeError;
+
+        //Every 9 bit symbol can show up, once evenly and once skewed towards small ones
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(2089);
+        let uniform:Vec<HuffmanSymbol> = (0..100000).map(|_| rng.gen_range(0..HUFFMAN_MAX_SYMBOLS as HuffmanSymbol)).collect();
+        let skewed:Vec<HuffmanSymbol> = (0..100000).map(|_| (rng.gen_range(0.0f64..1.0).powi(4) * HUFFMAN_MAX_SYMBOLS as f64) as HuffmanSymbol).collect();
+
+        for (symbols, name) in [(&uniform, "uniform"), (&skewed, "skewed")] {
+            for chunk_size in [4096, usize::MAX] {
+                let mut encoder = HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS);
+                let mut writer = BitWriter::new();
+                encoder.encode_all(symbols, chunk_size, &mut writer);
+                let encoded_bytes = writer.get_bytes();
+                println!("{name} 9 bit symbols: [{}] Chunk size: [{chunk_size}] Encoded size: [{}]", symbols.len(), encoded_bytes.len());
+                assert!(encoded_bytes.len() * 8 <= symbols.len() * 9 + 2000, "{name} 9 bit symbols took [{}] bytes", encoded_bytes.len());
+
+                let decoded = HuffmanDecoder::new().decode_all(&mut BitReader::new(&encoded_bytes)).unwrap();
+                assert!(decoded == *symbols, "{name} 9 bit symbols different after encoding and decoding [{chunk_size}]");
+
+                encoder.set_byte_align_chunks(true);
+                encoder.set_auto_global_table(true);
+                let mut writer = BitWriter::new();
+                encoder.encode_all(symbols, chunk_size, &mut writer);
+                let decoded = HuffmanDecoder::new().decode_all(&mut BitReader::new(&writer.get_bytes())).unwrap();
+                assert!(decoded == *symbols, "{name} 9 bit symbols different after encoding and decoding with a global table [{chunk_size}]");
+            }
+        }
+
+        //They just can't be turned into bytes
+        let mut writer = BitWriter::new();
+        HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS).encode_all(&skewed, 4096, &mut writer);
+        let decoded = HuffmanDecoder::new().decode_all_bytes(&mut BitReader::new(&writer.get_bytes()));
+        assert!(matches!(decoded, Err(DecodeError::Huffman(HuffmanError::SymbolNotByte(symbol))) if symbol > 255), "9 bit symbols decoded as bytes [{:?}]", decoded.map(|bytes| bytes.len()));
+
+        //Cut off or corrupt symbols are an error, not a panic
+        let encoded_bytes = writer.get_bytes();
+        for len in (0..encoded_bytes.len()).step_by(997) {
+            let decoded = HuffmanDecoder::new().decode_all(&mut BitReader::new(&encoded_bytes[..len]));
+            assert!(decoded == Err(HuffmanError::UnexpectedEnd), "9 bit symbols cut off at [{len}] bytes weren't caught [{:?}]", decoded.map(|symbols| symbols.len()));
+        }
+        let mut corrupted = encoded_bytes.clone();
+        corrupted[5] ^= 0xFF;
+        assert!(HuffmanDecoder::new().decode_all(&mut BitReader::new(&corrupted)).is_err(), "Corrupt table of 9 bit symbols wasn't caught");
+    }
+
+    #[test]
+    #[should_panic]
+    pub fn huffman_test_alphabet_too_large(){
+        use crate::huffman::{HuffmanEncoder, HUFFMAN_MAX_ALPHABET_SIZE};
+
+        HuffmanEncoder::new(HUFFMAN_MAX_ALPHABET_SIZE + 1);
+    }
+
+    #[test]
+    pub fn huffman_test_decode_one_truncated(){
+        use std::fs;
+        use crate::huffman::{HuffmanEncoder, HuffmanDecoder, HUFFMAN_MAX_SYMBOLS};
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let symbols = HuffmanEncoder::bytes_to_symbols(&bytes[..4096]);
+        let mut encoder = HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS);
+        encoder.build_frequency_table(&symbols);
+        encoder.build_huffman_table();
+
+        let mut writer = BitWriter::new();
+        encoder.write_huffman_table(&mut writer);
+        let mut symbol_ends = Vec::new();
+        for symbol in &symbols {
+            encoder.encode_symbol(*symbol, &mut writer);
+            symbol_ends.push(writer.total_bits_written());
+        }
+        let encoded_bytes = writer.get_bytes();
+
+        //Every symbol decodes from the whole stream
+        let mut decoder = HuffmanDecoder::new();
+        let mut reader = BitReader::new(&encoded_bytes);
+        decoder.read_huffman_table(&mut reader).unwrap();
+        let decoded:Vec<_> = symbols.iter().map(|_| decoder.decode_one(&mut reader).unwrap()).collect();
+        assert!(decoded == symbols, "Symbols different after encoding and decoding one at a time");
+
+        //Cut the stream at every byte boundary that lands in the middle of a symbol
+        let mut truncations = 0;
+        for (i, pair) in symbol_ends.windows(2).enumerate() {
+            let (start, end) = (pair[0], pair[1]);
+            let cut = end / 8 * 8;
+            if cut <= start || cut == end {
+                continue;
+            }
+
+            let mut reader = BitReader::new(&encoded_bytes[..(cut / 8)]);
+            decoder.read_huffman_table(&mut reader).unwrap();
+            for symbol in &symbols[..=i] {
+                assert!(decoder.decode_one(&mut reader) == Some(*symbol), "Symbol before the cut at bit [{cut}] decoded wrong");
+            }
+            let remaining_bits = reader.remaining_bits();
+            assert!(decoder.decode_one(&mut reader).is_none(), "Symbol cut off at bit [{cut}] was decoded");
+            assert!(reader.remaining_bits() == remaining_bits, "Failed decode at bit [{cut}] still read bits");
+            truncations += 1;
+        }
+        assert!(truncations > 0, "No symbols were cut in the middle");
+
+        assert!(decoder.decode_one(&mut BitReader::new(&[])).is_none(), "Symbol decoded from nothing");
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    pub fn huffman_test_symbol_not_byte(){
+        use std::io::Read;
+        use crate::huffman::{HuffmanDecoder, HuffmanReader, HuffmanError, HUFFMAN_CHUNK_SIZE_BITS, HUFFMAN_TABLE_VERSION};
+        use crate::api::{decompress, Codec};
+        use crate::error::DecodeError;
+
+        //Not byte aligned, no reused chunks, no global table, one chunk, then a table with 'a' and 300, both with a level of 1
+        let mut writer = BitWriter::new();
+        writer.write_bits_u16(0, 3);
+        writer.write_bits_u32(1, HUFFMAN_CHUNK_SIZE_BITS);
+        writer.write_bits_u16(HUFFMAN_TABLE_VERSION as u16, 8);
+        writer.write_bits_u16(0, 1);
+        writer.write_bits_u16(2, 9);
+        writer.write_bits_u16(1, 5);
+        writer.write_bits_u16(b'a' as u16, 9);
+        writer.write_bits_u16(0, 1);
+        writer.write_bits_u16(300, 9);
+        writer.write_bits_u16(0, 1);
+        //Three symbols, which have to include both
+        writer.write_bits_u32(3, HUFFMAN_CHUNK_SIZE_BITS);
+        writer.write_bits_u16(0b010, 3);
+        let encoded_bytes = writer.get_bytes();
+
+        let symbols = HuffmanDecoder::new().decode_all(&mut BitReader::new(&encoded_bytes)).unwrap();
+        assert!(symbols.len() == 3 && symbols.contains(&300), "Hand built stream decoded wrong [{symbols:?}]");
+
+        let expected = Err(DecodeError::Huffman(HuffmanError::SymbolNotByte(300)));
+        assert!(HuffmanDecoder::new().decode_all_bytes(&mut BitReader::new(&encoded_bytes)) == expected, "Symbol that isn't a byte wasn't caught");
+        assert!(decompress(Codec::Huffman, &encoded_bytes) == expected, "Symbol that isn't a byte wasn't caught by decompress");
+        assert!(HuffmanDecoder::symbols_to_bytes(&[1, 2, 255]) == Ok(vec![1, 2, 255]), "Byte symbols weren't turned into bytes");
+
+        let mut decoded = Vec::new();
+        let err = HuffmanReader::new(BitReader::new(&encoded_bytes)).read_to_end(&mut decoded).unwrap_err();
+        assert!(err.kind() == std::io::ErrorKind::InvalidData, "Symbol that isn't a byte gave the wrong error from a HuffmanReader [{err}]");
+        let err = HuffmanDecoder::new().decode_all_to(&mut BitReader::new(&encoded_bytes), &mut decoded).unwrap_err();
+        assert!(err.kind() == std::io::ErrorKind::InvalidData, "Symbol that isn't a byte gave the wrong error from decode_all_to [{err}]");
+    }
+
+    #[test]
+    pub fn huffman_test_scan_symbols(){
+        use std::fs;
+        use crate::huffman::{HuffmanEncoder, HuffmanSymbol, HUFFMAN_MAX_SYMBOLS};
+        use crate::mtf::mtf_encode;
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let symbols = HuffmanEncoder::bytes_to_symbols(&mtf_encode(&bytes));
+
+        let mut slice_encoder = HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS);
+        slice_encoder.build_frequency_table(&symbols);
+        slice_encoder.build_huffman_table();
+
+        //Straight from the MTF output, without collecting the symbols
+        let mut iter_encoder = HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS);
+        iter_encoder.scan_symbols(mtf_encode(&bytes).into_iter().map(|byte| byte as HuffmanSymbol));
+        iter_encoder.build_huffman_table();
+        assert!(iter_encoder.iter().eq(slice_encoder.iter()), "Table from an iterator different from the one from a slice");
+
+        //Scanning adds to what's there, in as many pieces as you like
+        iter_encoder.build_frequency_table(&[]);
+        let (first, second) = symbols.split_at(symbols.len() / 3);
+        iter_encoder.scan_symbols(first.iter().copied());
+        iter_encoder.scan_symbols(second.iter().copied());
+        iter_encoder.build_huffman_table();
+        assert!(iter_encoder.iter().eq(slice_encoder.iter()), "Table from scanning in pieces different from the one from a slice");
+    }
+
+    #[test]
+    pub fn huffman_test_table_version(){
+        use std::fs;
+        use crate::huffman::{HuffmanEncoder, HuffmanDecoder, HuffmanError, HUFFMAN_MAX_SYMBOLS, HUFFMAN_TABLE_VERSION};
+        use crate::error::DecodeError;
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let mut writer = BitWriter::new();
+        HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS).encode_all_bytes(&bytes[..10000], 4096, &mut writer);
+        let mut encoded_bytes = writer.get_bytes();
+        assert!(HuffmanDecoder::new().decode_all_bytes(&mut BitReader::new(&encoded_bytes)) == Ok(bytes[..10000].to_vec()), "Bytes different after encoding and decoding with the current table version");
+
+        //The first table's version comes right after the 3 flag bits and the chunk count, so this flips its last bit
+        encoded_bytes[5] ^= 0b00100000;
+        let expected = Err(DecodeError::Huffman(HuffmanError::UnsupportedTableVersion(HUFFMAN_TABLE_VERSION ^ 1)));
+        let decoded = HuffmanDecoder::new().decode_all_bytes(&mut BitReader::new(&encoded_bytes));
+        assert!(decoded == expected, "Wrong table version wasn't caught [{decoded:?}]");
+        let decoded = HuffmanDecoder::new().decode_all(&mut BitReader::new(&encoded_bytes));
+        assert!(decoded == Err(HuffmanError::UnsupportedTableVersion(HUFFMAN_TABLE_VERSION ^ 1)), "Wrong table version wasn't caught by decode_all [{decoded:?}]");
+
+        //Same thing with just a table
+        let mut encoder = HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS);
+        encoder.build_frequency_table(&HuffmanEncoder::bytes_to_symbols(&bytes[..1000]));
+        encoder.build_huffman_table();
+        let mut writer = BitWriter::new();
+        encoder.write_huffman_table(&mut writer);
+        let mut table_bytes = writer.get_bytes();
+        assert!(table_bytes[0] == HUFFMAN_TABLE_VERSION, "Table didn't start with the version [{}]", table_bytes[0]);
+        table_bytes[0] = 0xFF;
+        let read = HuffmanDecoder::new().read_huffman_table(&mut BitReader::new(&table_bytes));
+        assert!(read == Err(HuffmanError::UnsupportedTableVersion(0xFF)), "Wrong table version wasn't caught [{read:?}]");
+
+        //A table that's cut off anywhere is an error, not a panic
+        table_bytes[0] = HUFFMAN_TABLE_VERSION;
+        for len in 0..table_bytes.len() {
+            let read = HuffmanDecoder::new().read_huffman_table(&mut BitReader::new(&table_bytes[..len]));
+            assert!(read == Err(HuffmanError::UnexpectedEnd), "Table cut off at [{len}] bytes wasn't caught [{read:?}]");
+        }
+    }
+
+    #[test]
+    pub fn huffman_test_bad_code_lengths(){
+        use crate::huffman::{HuffmanDecoder, HuffmanError, HUFFMAN_TABLE_VERSION};
+
+        //A table of (symbol, level) pairs with the given max level, like write_huffman_table writes
+        fn write_table(writer: &mut BitWriter, max_level: u16, bits_per_level: usize, levels: &[u16]) {
+            writer.write_bits_u16(HUFFMAN_TABLE_VERSION as u16, 8);
+            writer.write_bits_u16(0, 1);
+            writer.write_bits_u16(levels.len() as u16, 9);
+            writer.write_bits_u16(max_level, 5);
+            for (symbol, level) in levels.iter().enumerate() {
+                writer.write_bits_u16(symbol as u16, 9);
+                writer.write_bits_u16(level - 1, bits_per_level);
+            }
+        }
+        fn table_bytes(max_level: u16, bits_per_level: usize, levels: &[u16]) -> Vec<u8> {
+            let mut writer = BitWriter::new();
+            write_table(&mut writer, max_level, bits_per_level, levels);
+            writer.get_bytes()
+        }
+
+        let cases = [
+            //A max level of 31 would need maps with 1 << 31 entries
+            (table_bytes(31, 5, &[1, 1]), "max level of 31"),
+            //24 is allowed, but only if the decoder's told so
+            (table_bytes(24, 5, &[1, 24]), "max level past the decoder's max"),
+            //Only two codes have a length of 1
+            (table_bytes(1, 1, &[1, 1, 1]), "three codes of length 1"),
+            (table_bytes(3, 2, &[1, 2, 2, 3, 3]), "too many short codes"),
+            //3 bits per level can say 8, which is longer than the max level
+            (table_bytes(5, 3, &[1, 8]), "level longer than the max level"),
+            (table_bytes(2, 1, &[2, 1]), "levels out of order")
+        ];
+        for (bytes, name) in cases {
+            let read = HuffmanDecoder::new().read_huffman_table(&mut BitReader::new(&bytes));
+            assert!(read == Err(HuffmanError::BadCodeLengths), "Table with {name} wasn't caught [{read:?}]");
+        }
+
+        //Codes that don't use up every path are fine, like a table of one symbol
+        let read = HuffmanDecoder::new().read_huffman_table(&mut BitReader::new(&table_bytes(2, 1, &[1, 2])));
+        assert!(read == Ok(()), "Table with an unused path wasn't read [{read:?}]");
+
+        //But a chunk can't use the unused path, 11, even right after a table that had a code for it
+        let mut writer = BitWriter::new();
+        write_table(&mut writer, 2, 1, &[1, 2, 2]);
+        write_table(&mut writer, 2, 1, &[1, 2]);
+        writer.write_bits_u32(5, 32);
+        writer.write_bits_u16(0xFF, 8);
+        let bytes = writer.get_bytes();
+        let mut decoder = HuffmanDecoder::new();
+        let mut reader = BitReader::new(&bytes);
+        decoder.read_huffman_table(&mut reader).unwrap();
+        decoder.read_huffman_table(&mut reader).unwrap();
+        let decoded = decoder.decode_chunk(&mut reader);
+        assert!(decoded == Err(HuffmanError::UnknownCode), "Code on an unused path wasn't caught [{decoded:?}]");
+    }
+
+    #[test]
+    pub fn huffman_test_raw_chunk(){
+        use crate::huffman::{HuffmanEncoder, HuffmanDecoder, HuffmanError, HUFFMAN_MAX_SYMBOLS, HUFFMAN_CHUNK_SIZE_BITS, HUFFMAN_RAW_BITS_SIZE, HUFFMAN_TABLE_VERSION};
+        use crate::api::{compress, decompress, Codec};
+        use crate::lz::{LZEncoder, LZDecoder};
+        use rand::prelude::*;
+
+        //Random bytes don't compress, so the chunk should come out barely bigger than it went in
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(2080);
+        let mut bytes = vec![0u8; 50000];
+        rng.fill_bytes(&mut bytes);
+        let symbols = HuffmanEncoder::bytes_to_symbols(&bytes);
+
+        let mut encoder = HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS);
+        let mut writer = BitWriter::new();
+        let stats = encoder.encode_chunk_with_stats(&symbols, &mut writer);
+        let overhead = 8 + 1 + HUFFMAN_RAW_BITS_SIZE + HUFFMAN_CHUNK_SIZE_BITS;
+        println!("Random bytes: [{}] bits -> [{}] bits", bytes.len() * 8, stats.output_bits);
+        assert!(stats.raw && stats.output_bits <= bytes.len() * 8 + overhead, "Random bytes took [{}] bits [{stats:?}]", stats.output_bits);
+
+        let encoded_bytes = writer.get_bytes();
+        let mut decoder = HuffmanDecoder::new();
+        let mut reader = BitReader::new(&encoded_bytes);
+        decoder.read_huffman_table(&mut reader).unwrap();
+        assert!(decoder.decode_chunk(&mut reader).as_ref() == Ok(&symbols), "Random bytes different after a raw chunk");
+
+        let mut reader = BitReader::new(&encoded_bytes);
+        let mut out = vec![0; symbols.len()];
+        decoder.read_huffman_table(&mut reader).unwrap();
+        assert!(decoder.decode_chunk_into(&mut reader, &mut out) == Ok(symbols.len()) && out == symbols, "Random bytes different after a raw chunk decoded into a slice");
+
+        //A raw chunk that's cut off is an error, and so is a width of 0, which the encoder never writes
+        let mut reader = BitReader::new(&encoded_bytes[..(encoded_bytes.len() - 1)]);
+        decoder.read_huffman_table(&mut reader).unwrap();
+        assert!(decoder.decode_chunk(&mut reader) == Err(HuffmanError::UnexpectedEnd), "Cut off raw chunk wasn't caught");
+        let mut writer = BitWriter::new();
+        writer.write_bits_u16(HUFFMAN_TABLE_VERSION as u16, 8);
+        writer.write_bits_u16(1, 1);
+        writer.write_bits_u16(0, HUFFMAN_RAW_BITS_SIZE);
+        writer.write_bits_u32(1 << 20, HUFFMAN_CHUNK_SIZE_BITS);
+        let read = decoder.read_huffman_table(&mut BitReader::new(&writer.get_bytes()));
+        assert!(read == Err(HuffmanError::BadSymbolMap), "Raw chunk with 0 bit symbols wasn't caught [{read:?}]");
+
+        //A raw chunk, then one that isn't
+        let mut mixed = bytes[..20000].to_vec();
+        mixed.extend(std::iter::repeat_n(b'a', 20000));
+        let mut writer = BitWriter::new();
+        encoder.encode_all_bytes(&mixed, 20000, &mut writer);
+        let encoded_bytes = writer.get_bytes();
+        //Each 'a' still takes a bit, a table of one symbol has nothing shorter
+        assert!(encoded_bytes.len() < 20000 + 20000 / 8 + 100, "Raw and Huffman chunks took [{}] bytes", encoded_bytes.len());
+        assert!(HuffmanDecoder::new().decode_all_bytes(&mut BitReader::new(&encoded_bytes)) == Ok(mixed), "Bytes different after raw and Huffman chunks");
+
+        //LZ's literals go through encode_chunk too
+        let mut writer = BitWriter::new();
+        LZEncoder::new(1 << 15, false).huffman_encode_all(&bytes, 1 << 16, &mut writer);
+        let encoded_bytes = writer.get_bytes();
+        println!("Random bytes with LZ: [{}] -> [{}]", bytes.len(), encoded_bytes.len());
+        assert!(LZDecoder::new().huffman_decode_all(&mut BitReader::new(&encoded_bytes)) == Some(bytes.clone()), "Random bytes different after LZ with raw literals");
+
+        assert!(decompress(Codec::Huffman, &compress(Codec::Huffman, &bytes)) == Ok(bytes), "Random bytes different after compressing and decompressing");
+    }
+
+    #[test]
+    pub fn huffman_test_symbols_iter(){
+        use std::fs;
+        use crate::huffman::{HuffmanEncoder, HuffmanDecoder, HuffmanSymbol, HUFFMAN_MAX_SYMBOLS, HUFFMAN_CHUNK_SIZE_BITS};
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let symbols = HuffmanEncoder::bytes_to_symbols(&bytes[..4096]);
+        let mut encoder = HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS);
+        let mut writer = BitWriter::new();
+        encoder.encode_chunk(&symbols, &mut writer);
+        let encoded_bytes = writer.get_bytes();
+
+        let mut decoder = HuffmanDecoder::new();
+        let mut reader = BitReader::new(&encoded_bytes);
+        decoder.read_huffman_table(&mut reader).unwrap();
+        let chunk = decoder.decode_chunk(&mut reader).unwrap();
+
+        //Skip past the chunk size, since the iterator doesn't read it
+        let mut reader = BitReader::new(&encoded_bytes);
+        decoder.read_huffman_table(&mut reader).unwrap();
+        let count = reader.read_bits_into_u32(HUFFMAN_CHUNK_SIZE_BITS).unwrap() as usize;
+        let iterated:Vec<_> = decoder.symbols(&mut reader, count).collect();
+        assert!(iterated == chunk, "Symbols from the iterator different from decode_chunk");
+
+        let mut reader = BitReader::new(&encoded_bytes);
+        decoder.read_huffman_table(&mut reader).unwrap();
+        reader.read_bits_into_u32(HUFFMAN_CHUNK_SIZE_BITS).unwrap();
+        let spaces = decoder.symbols(&mut reader, count).take(1000).filter(|symbol| *symbol == b' ' as HuffmanSymbol).count();
+        let expected = chunk[..1000].iter().filter(|symbol| **symbol == b' ' as HuffmanSymbol).count();
+        assert!(spaces == expected, "Spaces in the first 1000 symbols [{spaces}] different from decode_chunk [{expected}]");
+
+        //Asking for more symbols than there are stops once the reader runs out
+        let mut reader = BitReader::new(&encoded_bytes);
+        decoder.read_huffman_table(&mut reader).unwrap();
+        reader.read_bits_into_u32(HUFFMAN_CHUNK_SIZE_BITS).unwrap();
+        let iterated = decoder.symbols(&mut reader, usize::MAX).count();
+        assert!(iterated >= count && iterated < count + 8, "Iterator over too many symbols gave [{iterated}] symbols for [{count}]");
+    }
+}
@@ -0,0 +1,55 @@
+//! LZ77, LZW, Huffman, DEFLATE, gzip and zlib compression, all written from scratch.
+//! 
+//! The simplest way in is `compress` and `decompress` with a `Codec`. The
+//! `Compressor` and `Decompressor` traits do the same for generic code, and
+//! the modules have everything else if you want more control.
+//! 
+//! Everything that needs threads, files or `std::io` is behind the `std`
+//! feature, which is on by default. Without it, the codecs only need `alloc`.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+pub mod huffman;
+pub mod bitstream;
+pub mod lzw;
+pub mod lz77;
+pub mod lz;
+pub mod error;
+pub mod api;
+pub mod deflate;
+pub mod stats;
+pub mod checksum;
+pub mod container;
+#[cfg(feature = "std")]
+pub mod batch;
+#[cfg(feature = "std")]
+pub mod frame;
+pub mod rle;
+pub mod mtf;
+pub mod bwt;
+pub mod gzip;
+pub mod zlib;
+pub mod filter;
+#[cfg(feature = "std")]
+pub mod bench;
+pub mod verify;
+pub mod entropy;
+pub mod range;
+pub mod pipeline;
+mod map;
+
+pub use api::{compress, decompress, Codec, Compressor, Decompressor, CompressDecompress};
+pub use bitstream::{BitWriter, BitReader};
+pub use error::DecodeError;
+pub use huffman::{compress_huffman, decompress_huffman, HuffmanEncoder, HuffmanDecoder, HuffmanStats};
+#[cfg(feature = "std")]
+pub use huffman::{HuffmanReader, HuffmanWriter};
+pub use lzw::{compress_lzw, decompress_lzw, LzwEncoder, LzwDecoder};
+pub use lz77::{lz77_compress_simple, lz77_compress_simple_with_stats, LZ77Stats, lz77_compress_with_key_len, lz77_compress_lazy, lz77_compress_optimal, lz77_decompress, LZ77Encoded, LZ77StreamEncoder};
+pub use deflate::{deflate_compress, deflate_decompress, BlockType};
+pub use gzip::{gzip_compress, gzip_decompress};
+pub use zlib::{zlib_compress, zlib_decompress};
+pub use lz::{LZEncoder, LZDecoder};
+pub use pipeline::{Pipeline, Stage};
@@ -1,623 +1,606 @@
-// use crate::bitstream::{BitReader, BitWriter};
-// use crate::huffman::{HuffmanSymbol, HuffmanPath, HuffmanEncoder, HuffmanDecoder, HUFFMAN_CHUNK_SIZE_BITS, HUFFMAN_MAX_SYMBOLS};
-// use std::collections::HashMap;
-// use std::fmt::{self};
-// use std::cmp::{min, max};
-
-// const LZ_CHUNK_SIZE:usize = 1 << 18;
-// const MAX_MATCH_NUM:usize = 16;
-
-// type LZLength = u32;
-// type LZOffset = u32;
-
-// fn fast_log2_floor_u32(n: u32) -> u32 {
-//     31 - n.leading_zeros()
-// }
-
-// fn huffman_symbol_from_length(length: usize) -> HuffmanSymbol {
-//     if length < 16{
-//         return length as HuffmanSymbol;
-//     }
-
-//     (12 + fast_log2_floor_u32(length as u32)) as HuffmanSymbol
-// }
-
-// fn huffman_symbol_from_offset(offset: usize) -> HuffmanSymbol {
-//     if offset < 2{
-//         return offset as HuffmanSymbol;
-//     }
-
-//     (1 + fast_log2_floor_u32(offset as u32)) as HuffmanSymbol
-// }
-
-// fn extra_huffman_symbol(v: usize) -> HuffmanSymbol {
-//     (v - (1 << fast_log2_floor_u32(v as u32))) as HuffmanSymbol
-// }
-
-// fn key_from_bytes(buffer: &[u8], pos: usize) -> u32{
-//     let mut hash:u32 = 0;
-//     let byte_num = if pos + 3 >= buffer.len() {buffer.len() - pos} else {3};
-//     for i in 0..byte_num{
-//         hash <<= 8;
-//         hash |= buffer[pos + i] as u32;
-//     }
-
-//     hash
-// }
-
-// struct MatchFinder {
-//     window_size:usize,
-//     head_map:HashMap<u32, usize>,
-//     next_map:HashMap<usize, usize>
-// }
-
-// pub struct LZEncoder<'a>{
-//     writer: &'a mut BitWriter,
-//     matcher: MatchFinder,
-//     do_optimal_parsing: bool,
-//     literals: Vec<u8>,
-//     match_lengths: Vec<usize>,
-//     match_offsets: Vec<usize>,
-//     match_literal_lengths: Vec<usize>
-// }
-
-// pub struct LZDecoder<'a, 'b: 'a> {
-//     decoder: HuffmanDecoder<'a, 'b>,
-//     literals: Vec<u8>,
-//     match_lengths:Vec<usize>,
-//     match_offsets: Vec<usize>,
-//     match_literal_lengths: Vec<usize>,
-//     decoded: Vec<u8>
-// }
-
-// impl MatchFinder {
-//     fn new(window_size:usize) -> Self {
-//         MatchFinder {
-//             window_size,
-//             head_map: HashMap::with_capacity(window_size),
-//             next_map: HashMap::with_capacity(window_size)
-//         }
-//     }
-
-//     fn insert(&mut self, buffer:&[u8], pos: usize) {
-//         let key = key_from_bytes(buffer, pos);
-
-//         if let Some(head) = self.head_map.get(&key){
-//             self.next_map.insert(pos, *head);
-//         }
-//         self.head_map.insert(key, pos);
-//     }
-
-//     fn find_match(&mut self, buffer:&[u8], pos: usize) -> (usize, usize) {
-//         let mut best_match_len:usize = 0;
-//         let mut best_match_pos:usize = 0;
-
-//         let key = key_from_bytes(buffer, pos);
-//         let min_pos_option:Option<usize> = if self.window_size > pos {None} else {Some(pos - self.window_size)};
-
-//         let mut next_option = self.head_map.get(&key);
-//         let mut hits = 0;
-//         let max_hits = 16;
-        
-//         while let Some(next) = next_option {
-//             let next = *next;
-//             if let Some(min_pos) = min_pos_option {
-//                 if next <= min_pos {break;}
-//             }
-//             hits += 1;
-//             if hits >= max_hits {break;}
-
-//             let match_len = self.max_match_len(buffer, pos, next);
-//             if match_len > best_match_len {
-//                 best_match_len = match_len;
-//                 best_match_pos = next;
-//             }
-
-//             next_option = self.next_map.get(&next);
-//         }
-
-//         if let Some(head) = self.head_map.get(&key){
-//             self.next_map.insert(pos, *head);
-//         }
-//         self.head_map.insert(key, pos);
-
-//         //println!("Pos: {pos} Best match: {best_match_pos} Best match length; {best_match_len}");
-
-//         (best_match_len, best_match_pos)
-//     }
-
-//     fn find_matches(&mut self, buffer:&[u8], pos: usize) -> (Vec<usize>, Vec<usize>) {
-//         let mut match_lens = Vec::with_capacity(MAX_MATCH_NUM);
-//         let mut match_dists = Vec::with_capacity(MAX_MATCH_NUM);
-
-//         let key = key_from_bytes(buffer, pos);
-//         let min_pos_option:Option<usize> = if self.window_size > pos {None} else {Some(pos - self.window_size)};
-
-//         let mut next_option = self.head_map.get(&key);
-//         let mut hits = 0;
-        
-//         while let Some(next) = next_option {
-//             let next = *next;
-//             if let Some(min_pos) = min_pos_option {
-//                 if next <= min_pos {break;}
-//             }
-//             hits += 1;
-//             if hits >= MAX_MATCH_NUM {break;}
-
-//             let match_len = self.max_match_len(buffer, pos, next);
-//             if match_len > 0 {
-//                 match_lens.push(match_len);
-//                 match_dists.push(if next > pos {next - pos} else {pos - next});
-//             }
-
-//             next_option = self.next_map.get(&next);
-//         }
-
-//         if let Some(head) = self.head_map.get(&key){
-//             self.next_map.insert(pos, *head);
-//         }
-//         self.head_map.insert(key, pos);
-
-//         //println!("Pos: {pos} Best match: {best_match_pos} Best match length; {best_match_len}");
-
-//         (match_lens, match_dists)
-//     }
-
-
-//     fn max_match_len(&self, buffer: &[u8], source_pos: usize, match_pos: usize) -> usize {
-        
-//         if key_from_bytes(buffer, source_pos) != key_from_bytes(buffer, match_pos) {
-//             return 0;
-//         }
-
-//         let mut len = 4;
-//         while source_pos + len < buffer.len() && buffer[source_pos + len] == buffer[match_pos + len] {
-//             len += 1;
-//         }
-
-//         len
-//     }
-// }
-
-// impl<'a> fmt::Display for LZEncoder<'a>{
-//     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-
-//         let mut repr:String = String::new();
-//         repr.push_str("Literals: ");
-//         for byte in &self.literals{
-//             repr.push_str(format!("{} ",*byte).as_str());
-//         }
-//         repr.push_str("\nMatches:\n");
-//         for i in 0..self.match_lengths.len(){
-//             repr.push_str(format!("Match: [length: {} offset: {} literal_length: {}]\n", self.match_lengths[i], self.match_offsets[i], self.match_literal_lengths[i]).as_str());
-//         }
-
-//         write!(f,"{}",repr)
-        
-//     }
-// }
-
-
-// impl<'a> LZEncoder<'a>{
-//     pub fn new(writer: &'a mut BitWriter, window_size: usize, do_optimal_parsing: bool) -> Self {
-//         LZEncoder { 
-//             writer,
-//             matcher: MatchFinder::new(window_size), 
-//             do_optimal_parsing, 
-//             literals: Vec::with_capacity(LZ_CHUNK_SIZE), 
-//             match_lengths: Vec::with_capacity(LZ_CHUNK_SIZE >> 2), 
-//             match_offsets: Vec::with_capacity(LZ_CHUNK_SIZE >> 2), 
-//             match_literal_lengths: Vec::with_capacity(LZ_CHUNK_SIZE >> 2)
-//         }
-//     }
-
-//     fn simple_parse(&mut self, buffer: &[u8]){
-//         let min_match_len:usize = 5;
-//         let mut literal_num = 0;
-//         let mut pos = 0;
-    
-//         while pos < buffer.len() {
-//             let (mut match_len, match_pos) = self.matcher.find_match(buffer, pos);
-//             if match_len >= min_match_len && pos < buffer.len() - 4 {
-    
-//                 //println!("Pos: {pos} Match: [length: {match_len} offset: {} literal_count: {literal_num}]", pos - match_pos);
-                
-//                 self.match_lengths.push(match_len);
-//                 self.match_offsets.push(pos - match_pos);
-//                 self.match_literal_lengths.push(literal_num);
-    
-//                 literal_num = 0;
-    
-//                 match_len -= 1;
-//                 while match_len > 0{
-//                     pos += 1;
-//                     self.matcher.insert(buffer, pos);
-//                     match_len -= 1;
-//                 }
-    
-//             } else {
-//                 //println!("Pos: {pos} Literal: {}", buffer[pos]);
-//                 self.literals.push(buffer[pos]);
-//                 literal_num += 1;
-//             }
-//             pos += 1;
-//         }
-    
-//         if literal_num > 0 {
-//             //println!("Match: [length: 0 offset: 0 literal_count: {literal_num}]");
-//             self.match_lengths.push(0);
-//             self.match_offsets.push(0);
-//             self.match_literal_lengths.push(literal_num);
-//         }
-    
-//         //println!("Match lengths: {match_lengths:?}\nMatch offsets: {match_offsets:?}\nLiteral lengths: {literal_lengths:?}\nLiterals: {literals:?}");
-//     }
-
-//     fn optimal_parse_literal_price(byte: u8) -> u32 {6}
-
-//     ///
-//     /// 
-//     /// These costs were found using Glin Scott's tutorial. There might be better ones though
-//     fn optimal_parse_match_price(length: usize, offset: usize) -> u32{
-//         let length_cost = 6 + fast_log2_floor_u32(length as u32);
-//         let log2_dist = fast_log2_floor_u32(offset as u32);
-//         let offset_cost = if log2_dist >= 3 {log2_dist - 3} else {0};
-        
-//         length_cost + offset_cost
-//     }
-
-//     fn optimal_parse(&mut self, buffer: &[u8]) {
-//         let mut matcher = MatchFinder::new(64);
-
-//         let mut prices:Vec<u32> = vec![u32::MAX; buffer.len() + 1];
-//         let mut lengths:Vec<usize> = vec![0; buffer.len() + 1];
-//         let mut offsets:Vec<usize> = vec![0; buffer.len() + 1];
-
-//         prices[0] = 0;
-
-//         for i in 0..buffer.len() {
-//             let literal_cost = prices[i] + LZEncoder::optimal_parse_literal_price(buffer[i]);
-//             if literal_cost < prices[i + 1] {
-//                 prices[i + 1] = literal_cost;
-//                 lengths[i + 1] = 1;
-//                 offsets[i + 1] = 0;
-//             }
-
-//             if i + 4 >= buffer.len() {continue;}
-
-//             let (match_lengths, match_dists) = matcher.find_matches(buffer, i);
-//             for j in 0..match_lengths.len() {
-//                 let match_price = prices[i] + LZEncoder::optimal_parse_match_price(match_lengths[j],match_dists[j]);
-//                 if match_price < prices[i + match_lengths[j]] {
-//                     prices[i + match_lengths[j]] = match_price;
-//                     lengths[i + match_lengths[j]] = match_lengths[j];
-//                     offsets[i + match_lengths[j]] = match_dists[j];
-//                 }
-//             }
-//         }
-
-//         if lengths[buffer.len()] <= 1{
-//             let match_num = self.match_lengths.len();
-//             self.match_offsets.push(0);
-//             self.match_lengths.push(0);
-//             self.match_literal_lengths.push(0);
-//         }
-
-//         let mut i = buffer.len();
-//         while i > 0 {
-//             if lengths[i] > 1 {
-//                 self.match_lengths.push(lengths[i]);
-//                 self.match_offsets.push(offsets[i]);
-//                 self.match_literal_lengths.push(0);
-//                 i -= lengths[i];
-//             } else {
-//                 self.literals.push(buffer[i - 1]);
-//                 self.match_literal_lengths[self.match_lengths.len() - 1] += 1;
-//                 i -= 1;
-//             }
-//         }
-
-//         self.match_lengths = self.match_lengths.iter().copied().rev().collect();
-//         self.match_offsets = self.match_offsets.iter().copied().rev().collect();
-//         self.match_literal_lengths = self.match_literal_lengths.iter().copied().rev().collect();
-//         self.literals = self.literals.iter().copied().rev().collect();
-//     }
-
-//     pub fn parse(&mut self, buffer: &[u8]) {
-//         self.literals.clear();
-//         self.match_lengths.clear();
-//         self.match_offsets.clear();
-//         self.match_literal_lengths.clear();
-//         match self.do_optimal_parsing{
-//             true => self.optimal_parse(buffer),
-//             false => self.simple_parse(buffer)
-//         }
-//     }
-
-//     fn huffman_encode_lengths(&mut self) {
-//         let mut encoder:HuffmanEncoder = HuffmanEncoder::new(self.writer, 32);
-
-//         for i in 0..self.match_lengths.len() {
-//             encoder.scan_symbol(huffman_symbol_from_length(self.match_lengths[i]));
-//         }
-
-//         encoder.build_huffman_table();
-//         encoder.writer.write_bits_u32(self.match_lengths.len() as u32, HUFFMAN_CHUNK_SIZE_BITS);
-        
-//         for i in 0..self.match_lengths.len(){
-//             let length = self.match_lengths[i];
-//             encoder.encode_symbol(huffman_symbol_from_length(length));
-//             if length >= 16 {
-//                 encoder.writer.write_bits_u32(extra_huffman_symbol(length) as u32, fast_log2_floor_u32(length as u32) as usize);
-//             }
-//         }
-//     }
-
-//     fn huffman_encode_offsets(&mut self) {
-//         let mut encoder:HuffmanEncoder = HuffmanEncoder::new(self.writer, 32);
-
-//         for i in 0..self.match_offsets.len() {
-//             encoder.scan_symbol(huffman_symbol_from_offset(self.match_offsets[i]));
-//         }
-
-//         encoder.build_huffman_table();
-
-//         encoder.writer.write_bits_u32(self.match_offsets.len() as u32, HUFFMAN_CHUNK_SIZE_BITS);
-//         for i in 0..self.match_offsets.len(){
-//             let offset = self.match_offsets[i];
-//             encoder.encode_symbol(huffman_symbol_from_offset(offset));
-//             if offset >= 2 {
-//                 encoder.writer.write_bits_u32(extra_huffman_symbol(offset) as u32, fast_log2_floor_u32(offset as u32) as usize);
-//             }
-//         }
-
-//     }
-
-//     fn huffman_encode_literal_lengths(&mut self){
-//         let mut encoder:HuffmanEncoder = HuffmanEncoder::new(self.writer, 32);
-
-//         for i in 0..self.match_literal_lengths.len() {
-//             encoder.scan_symbol(huffman_symbol_from_length(self.match_literal_lengths[i]));
-//         }
-
-//         encoder.build_huffman_table();
-
-//         encoder.writer.write_bits_u32(self.match_literal_lengths.len() as u32, HUFFMAN_CHUNK_SIZE_BITS);
-//         for i in 0..self.match_literal_lengths.len(){
-//             let literal_length = self.match_literal_lengths[i];
-//             encoder.encode_symbol(huffman_symbol_from_length(literal_length));
-//             if literal_length >= 16 {
-//                 encoder.writer.write_bits_u32(extra_huffman_symbol(literal_length) as u32, fast_log2_floor_u32(literal_length as u32) as usize);
-//             }
-//         }
-
-
-//     }
-
-//     fn huffman_encode_literals(&mut self){
-//         let mut encoder:HuffmanEncoder = HuffmanEncoder::new(self.writer, HUFFMAN_MAX_SYMBOLS);
-
-//         encoder.encode_all_bytes(&self.literals, usize::MAX);
-//     }
-
-//     pub fn huffman_encode_chunk(&mut self, buffer: &[u8]){
-//         self.parse(buffer);
-
-//         self.huffman_encode_literals();
-//         self.huffman_encode_lengths();
-//         self.huffman_encode_offsets();
-//         self.huffman_encode_literal_lengths();
-//     }
-
-//     pub fn huffman_encode_all(&mut self, buffer: &[u8], chunk_size: usize) {
-//         let chunk_size = min(chunk_size, buffer.len());
-
-//         for start_pos in (0..buffer.len()).step_by(chunk_size){
-//             let end_pos = min(start_pos + chunk_size, buffer.len());
-//             let chunk = &buffer[start_pos..end_pos];
-//             self.huffman_encode_chunk(chunk);
-//         }
-//     }
-
-//     pub fn writer(&self) -> &BitWriter {
-//         &self.writer
-//     }
-
-//     pub fn writer_mut(&mut self) -> &mut BitWriter {
-//         &mut self.writer
-//     }
-
-// }
-
-// impl<'a, 'b:'a> LZDecoder<'a, 'b> {
-//     pub fn new(reader: &'a mut BitReader<'b>) -> Self {
-//         LZDecoder { 
-//             decoder: HuffmanDecoder::new(reader), 
-//             literals: Vec::with_capacity(LZ_CHUNK_SIZE), 
-//             match_lengths: Vec::with_capacity(LZ_CHUNK_SIZE >> 2),
-//             match_offsets: Vec::with_capacity(LZ_CHUNK_SIZE >> 2), 
-//             match_literal_lengths: Vec::with_capacity(LZ_CHUNK_SIZE >> 2),
-//             decoded: Vec::new()
-//         }
-//     }
-
-//     fn huffman_decode_literals(&mut self) {
-//         self.literals.clear();
-
-//         self.decoder.read_huffman_table();
-//         self.literals.append(&mut HuffmanDecoder::symbols_to_bytes(&self.decoder.decode_chunk()));
-//         //println!("Literals: {:?}", self.literals);
-//     }
-
-//     fn huffman_decode_lengths(&mut self) {
-//         self.match_lengths.clear();
-
-//         self.decoder.read_huffman_table();
-//         let match_num = self.decoder.reader.read_bits_into_u32(HUFFMAN_CHUNK_SIZE_BITS).unwrap();
-//         for _ in 0..match_num{
-//             let mut val = self.decoder.decode_one() as u32;
-//             if val >= 16 {
-//                 let extra_bits = val - 12;
-//                 val = (1 << extra_bits) | self.decoder.reader.read_bits_into_u32(extra_bits as usize).unwrap();
-//             }
-//             self.match_lengths.push(val as usize);
-//         }
-
-//         //println!("Match lengths: {:?}", self.match_lengths);
-//     }
-
-//     fn huffman_decode_offsets(&mut self) {
-//         self.match_offsets.clear();
-
-//         self.decoder.read_huffman_table();
-//         let match_num = self.decoder.reader.read_bits_into_u32(HUFFMAN_CHUNK_SIZE_BITS).unwrap();
-//         for _ in 0..match_num{
-//             let mut val = self.decoder.decode_one() as u32;
-//             if val >= 2 {
-//                 let extra_bits = val - 1;
-//                 val = (1 << extra_bits) | self.decoder.reader.read_bits_into_u32(extra_bits as usize).unwrap();
-//             }
-//             self.match_offsets.push(val as usize);
-//         }
-
-//         //println!("Match offsets: {:?}", self.match_offsets);
-//     }
-
-//     fn huffman_decode_literal_lengths(&mut self) {
-//         self.match_literal_lengths.clear();
-
-//         self.decoder.read_huffman_table();
-//         let match_num = self.decoder.reader.read_bits_into_u32(HUFFMAN_CHUNK_SIZE_BITS).unwrap();
-//         for _ in 0..match_num{
-//             let mut val = self.decoder.decode_one() as u32;
-//             if val >= 16 {
-//                 let extra_bits = val - 12;
-//                 val = (1 << extra_bits) | self.decoder.reader.read_bits_into_u32(extra_bits as usize).unwrap();
-//             }
-//             self.match_literal_lengths.push(val as usize);
-//         }
-
-//         //println!("Match literal lengths: {:?}", self.match_literal_lengths);
-//     }
-
-//     pub fn huffman_decode_chunk(&mut self) -> Vec<u8>{
-//         let mut decoded = Vec::new();
-//         self.huffman_decode_literals();
-//         self.huffman_decode_lengths();
-//         self.huffman_decode_offsets();
-//         self.huffman_decode_literal_lengths();
-
-//         let mut curr_literal:usize = 0;
-//         for i in 0..self.match_lengths.len(){
-//             for _ in 0..self.match_literal_lengths[i]{
-//                 decoded.push(self.literals[curr_literal]);
-//                 curr_literal += 1;
-//             }
-//             let match_start = decoded.len() - self.match_offsets[i];
-
-//             for j in 0..self.match_lengths[i] {
-//                 decoded.push(decoded[match_start + j]);
-//             }
-//         }
-
-//         decoded
-//     }
-
-//     pub fn huffman_decode_all(&mut self) -> Vec<u8> {
-//         let mut decoded = Vec::new();
-//         while self.decoder.reader.remaining_bits() > HUFFMAN_CHUNK_SIZE_BITS {
-//             decoded.append(&mut self.huffman_decode_chunk());
-//         }
-
-//         decoded
-//     }
-
-// }
-
-// #[cfg(test)]
-// mod tests {
-//     use crate::{bitstream::{BitWriter, BitReader}, lz::{LZDecoder, LZ_CHUNK_SIZE}};
-
-//     #[test]
-//     fn fast_log2_floor_u32_test() {
-//         use rand::prelude::*;
-//         use crate::lz::fast_log2_floor_u32;
-
-//         let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(2123);
-//         let mut vals = Vec::with_capacity(8192);
-//         for _ in 0..8192 {vals.push(rng.gen::<u32>());}
-
-//         for val in &vals {
-//             let val = *val;
-//             assert!(fast_log2_floor_u32(val) == (val as f32).log2().floor() as u32, "Fast log2 failed with value {val}");
-//         }
-//     }
-
-//     #[test]
-//     fn lz_simple_parse_test() {
-//         use crate::lz::LZEncoder;
-//         use std::{fs, time};
-        
-//         let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
-//         let mut writer = BitWriter::new();
-//         let mut encoder:LZEncoder = LZEncoder::new(&mut writer, 64, false);
-
-//         let start_time = time::Instant::now();
-
-//         encoder.parse(&bytes);
-
-//         let elapsed_time = start_time.elapsed().as_millis();
-//         println!("Simple parse took {elapsed_time}ms at a speed of {}MB/s", ((bytes.len() as f32) / 1000000f32) / ((elapsed_time as f32) / 1000f32));
-
-//         //println!("{encoder}");
-//     }
-
-//     #[test]
-//     fn lz_optimal_parse_test() {
-//         use crate::lz::LZEncoder;
-//         use std::{fs, time};
-        
-//         let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
-//         let mut writer = BitWriter::new();
-//         let mut encoder:LZEncoder = LZEncoder::new(&mut writer, 64, true);
-
-//         let start_time = time::Instant::now();
-
-//         encoder.parse(&bytes);
-
-//         let elapsed_time = start_time.elapsed().as_millis();
-//         println!("Simple parse took {elapsed_time}ms at a speed of {}MB/s", ((bytes.len() as f32) / 1000000f32) / ((elapsed_time as f32) / 1000f32));
-
-//     }
-//     #[test]
-//     fn lz_compression_decompression_test() {
-//         use crate::lz::{LZEncoder};
-//         use std::{fs, time};
-
-//         //let contents = "ABCABCABCDEDEGGZ".as_bytes().to_vec();
-//         let contents = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
-//         let mut writer = BitWriter::new();
-//         let mut encoder:LZEncoder = LZEncoder::new(&mut writer, 64, true);
-        
-//         let start_time = time::Instant::now();
-//         encoder.huffman_encode_all(&contents, LZ_CHUNK_SIZE);
-//         let encoded_bytes = encoder.writer().get_bytes();
-
-//         let elapsed_time = start_time.elapsed().as_millis();
-//         println!("Bytes unencoded: [{}] Bytes encoded:[{}] Compression ratio:[{}]\nTime:[{}]ms Speed:[{}]MB/s",contents.len(), encoded_bytes.len(), (encoded_bytes.len() as f32) / (contents.len() as f32), elapsed_time, ((contents.len() as f32) / 1000f32) / (elapsed_time as f32));
-        
-        
-//         let mut reader = BitReader::new(&encoded_bytes);
-//         let mut decoder = LZDecoder::new(&mut reader);
-//         let start_time = time::Instant::now();
-//         let decoded_bytes = decoder.huffman_decode_all();
-//         let elapsed_time = start_time.elapsed().as_millis();
-//         println!("Decompression time:[{}]ms Speed:[{}]MB/s", elapsed_time, ((encoded_bytes.len() as f32) / 1000f32) / (elapsed_time as f32));
-
-//         assert!(contents.len() == decoded_bytes.len(), "Number of bytes different after encoding and decoding");
-//         for i in 0..contents.len(){
-//             assert!(contents[i] == decoded_bytes[i], "Byte at position {i} different after encoding and decoding [{}] -> [{}]", contents[i], decoded_bytes[i]);
-//         }
-        
-//     }
-// }
\ No newline at end of file
+use crate::bitstream::{BitReader, BitWriter};
+use crate::huffman::{HuffmanSymbol, HuffmanEncoder, HuffmanDecoder, HUFFMAN_CHUNK_SIZE_BITS};
+use crate::map::{Map, map_with_capacity};
+use core::fmt::{self};
+use core::cmp::min;
+use alloc::{vec::Vec, vec, string::String, format};
+
+pub const LZ_CHUNK_SIZE:usize = 1 << 18;
+const MAX_MATCH_NUM:usize = 16;
+/// Lengths (and literal lengths) below this are their own symbol, longer ones
+/// get a symbol for their top bit and the rest as extra bits.
+const LZ_LENGTH_DIRECT_SYMBOLS:usize = 16;
+/// Enough symbols for the lengths and offsets of a whole `LZ_CHUNK_SIZE`
+/// chunk, see `huffman_symbol_from_length`.
+const LZ_MAX_SYMBOLS:usize = 32;
+/// The bytes it takes to make a key for the match finder.
+const LZ_KEY_LEN:usize = 3;
+
+fn fast_log2_floor_u32(n: u32) -> u32 {
+    31 - n.leading_zeros()
+}
+
+fn huffman_symbol_from_length(length: usize) -> HuffmanSymbol {
+    if length < LZ_LENGTH_DIRECT_SYMBOLS {
+        return length as HuffmanSymbol;
+    }
+
+    (12 + fast_log2_floor_u32(length as u32)) as HuffmanSymbol
+}
+
+fn huffman_symbol_from_offset(offset: usize) -> HuffmanSymbol {
+    if offset < 2{
+        return offset as HuffmanSymbol;
+    }
+
+    (1 + fast_log2_floor_u32(offset as u32)) as HuffmanSymbol
+}
+
+/// Everything below the top bit of `v`, which gets written as extra bits after
+/// its symbol.
+fn extra_huffman_symbol(v: usize) -> u32 {
+    (v - (1 << fast_log2_floor_u32(v as u32))) as u32
+}
+
+fn key_from_bytes(buffer: &[u8], pos: usize) -> u32{
+    let mut hash:u32 = 0;
+    let byte_num = min(buffer.len() - pos, LZ_KEY_LEN);
+    for i in 0..byte_num{
+        hash <<= 8;
+        hash |= buffer[pos + i] as u32;
+    }
+
+    hash
+}
+
+struct MatchFinder {
+    window_size:usize,
+    head_map:Map<u32, usize>,
+    next_map:Map<usize, usize>
+}
+
+/// Compresses bytes by parsing them into literals and matches, then Huffman
+/// coding the literals, match lengths, match offsets and literal lengths (the
+/// number of literals before each match) as four separate streams, each with
+/// its own table.
+///
+/// Lengths and offsets are too big for a symbol each, so they're coded as
+/// their top bit, with the rest written out as extra bits after the code.
+pub struct LZEncoder {
+    window_size: usize,
+    do_optimal_parsing: bool,
+    literals: Vec<u8>,
+    match_lengths: Vec<usize>,
+    match_offsets: Vec<usize>,
+    match_literal_lengths: Vec<usize>
+}
+
+/// Decodes what `LZEncoder` encoded.
+pub struct LZDecoder {
+    literals: Vec<u8>,
+    match_lengths:Vec<usize>,
+    match_offsets: Vec<usize>,
+    match_literal_lengths: Vec<usize>
+}
+
+impl MatchFinder {
+    fn new(window_size:usize) -> Self {
+        MatchFinder {
+            window_size,
+            head_map: map_with_capacity(window_size),
+            next_map: map_with_capacity(window_size)
+        }
+    }
+
+    fn insert(&mut self, buffer:&[u8], pos: usize) {
+        let key = key_from_bytes(buffer, pos);
+
+        if let Some(head) = self.head_map.get(&key){
+            self.next_map.insert(pos, *head);
+        }
+        self.head_map.insert(key, pos);
+    }
+
+    fn find_match(&mut self, buffer:&[u8], pos: usize) -> (usize, usize) {
+        let mut best_match_len:usize = 0;
+        let mut best_match_pos:usize = 0;
+
+        let key = key_from_bytes(buffer, pos);
+        let min_pos_option:Option<usize> = if self.window_size > pos {None} else {Some(pos - self.window_size)};
+
+        let mut next_option = self.head_map.get(&key);
+        let mut hits = 0;
+        let max_hits = 16;
+
+        while let Some(next) = next_option {
+            let next = *next;
+            if let Some(min_pos) = min_pos_option {
+                if next <= min_pos {break;}
+            }
+            hits += 1;
+            if hits >= max_hits {break;}
+
+            let match_len = self.max_match_len(buffer, pos, next);
+            if match_len > best_match_len {
+                best_match_len = match_len;
+                best_match_pos = next;
+            }
+
+            next_option = self.next_map.get(&next);
+        }
+
+        self.insert(buffer, pos);
+
+        (best_match_len, best_match_pos)
+    }
+
+    fn find_matches(&mut self, buffer:&[u8], pos: usize) -> (Vec<usize>, Vec<usize>) {
+        let mut match_lens = Vec::with_capacity(MAX_MATCH_NUM);
+        let mut match_dists = Vec::with_capacity(MAX_MATCH_NUM);
+
+        let key = key_from_bytes(buffer, pos);
+        let min_pos_option:Option<usize> = if self.window_size > pos {None} else {Some(pos - self.window_size)};
+
+        let mut next_option = self.head_map.get(&key);
+        let mut hits = 0;
+
+        while let Some(next) = next_option {
+            let next = *next;
+            if let Some(min_pos) = min_pos_option {
+                if next <= min_pos {break;}
+            }
+            hits += 1;
+            if hits >= MAX_MATCH_NUM {break;}
+
+            let match_len = self.max_match_len(buffer, pos, next);
+            if match_len > 0 {
+                match_lens.push(match_len);
+                match_dists.push(pos - next);
+            }
+
+            next_option = self.next_map.get(&next);
+        }
+
+        self.insert(buffer, pos);
+
+        (match_lens, match_dists)
+    }
+
+    /// How many bytes match starting at `source_pos` and `match_pos`. Keys
+    /// near the end of the buffer are made from fewer bytes, so two matching
+    /// keys doesn't mean anything matches, and every byte gets checked.
+    fn max_match_len(&self, buffer: &[u8], source_pos: usize, match_pos: usize) -> usize {
+        let mut len = 0;
+        while source_pos + len < buffer.len() && buffer[source_pos + len] == buffer[match_pos + len] {
+            len += 1;
+        }
+
+        len
+    }
+}
+
+impl fmt::Display for LZEncoder{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+
+        let mut repr:String = String::new();
+        repr.push_str("Literals: ");
+        for byte in &self.literals{
+            repr.push_str(format!("{} ",*byte).as_str());
+        }
+        repr.push_str("\nMatches:\n");
+        for i in 0..self.match_lengths.len(){
+            repr.push_str(format!("Match: [length: {} offset: {} literal_length: {}]\n", self.match_lengths[i], self.match_offsets[i], self.match_literal_lengths[i]).as_str());
+        }
+
+        write!(f,"{}",repr)
+
+    }
+}
+
+
+impl LZEncoder{
+    /// Matches are looked for at most `window_size` bytes back. With
+    /// `do_optimal_parsing`, every chunk gets parsed with the cheapest mix of
+    /// literals and matches (going by a rough estimate of their size) instead
+    /// of just taking the longest match every time, which is slower but
+    /// smaller.
+    pub fn new(window_size: usize, do_optimal_parsing: bool) -> Self {
+        LZEncoder {
+            window_size,
+            do_optimal_parsing,
+            literals: Vec::with_capacity(LZ_CHUNK_SIZE),
+            match_lengths: Vec::with_capacity(LZ_CHUNK_SIZE >> 2),
+            match_offsets: Vec::with_capacity(LZ_CHUNK_SIZE >> 2),
+            match_literal_lengths: Vec::with_capacity(LZ_CHUNK_SIZE >> 2)
+        }
+    }
+
+    fn simple_parse(&mut self, buffer: &[u8]){
+        let min_match_len:usize = 5;
+        let mut matcher = MatchFinder::new(self.window_size);
+        let mut literal_num = 0;
+        let mut pos = 0;
+
+        while pos < buffer.len() {
+            let (mut match_len, match_pos) = matcher.find_match(buffer, pos);
+            if match_len >= min_match_len && pos + 4 < buffer.len() {
+                self.match_lengths.push(match_len);
+                self.match_offsets.push(pos - match_pos);
+                self.match_literal_lengths.push(literal_num);
+
+                literal_num = 0;
+
+                match_len -= 1;
+                while match_len > 0{
+                    pos += 1;
+                    matcher.insert(buffer, pos);
+                    match_len -= 1;
+                }
+
+            } else {
+                self.literals.push(buffer[pos]);
+                literal_num += 1;
+            }
+            pos += 1;
+        }
+
+        if literal_num > 0 {
+            self.match_lengths.push(0);
+            self.match_offsets.push(0);
+            self.match_literal_lengths.push(literal_num);
+        }
+    }
+
+    fn optimal_parse_literal_price(_byte: u8) -> u32 {6}
+
+    /// These costs were found using Glin Scott's tutorial. There might be better ones though
+    fn optimal_parse_match_price(length: usize, offset: usize) -> u32{
+        let length_cost = 6 + fast_log2_floor_u32(length as u32);
+        let log2_dist = fast_log2_floor_u32(offset as u32);
+        let offset_cost = log2_dist.saturating_sub(3);
+
+        length_cost + offset_cost
+    }
+
+    fn optimal_parse(&mut self, buffer: &[u8]) {
+        let mut matcher = MatchFinder::new(self.window_size);
+
+        let mut prices:Vec<u32> = vec![u32::MAX; buffer.len() + 1];
+        let mut lengths:Vec<usize> = vec![0; buffer.len() + 1];
+        let mut offsets:Vec<usize> = vec![0; buffer.len() + 1];
+
+        prices[0] = 0;
+
+        for i in 0..buffer.len() {
+            let literal_cost = prices[i] + LZEncoder::optimal_parse_literal_price(buffer[i]);
+            if literal_cost < prices[i + 1] {
+                prices[i + 1] = literal_cost;
+                lengths[i + 1] = 1;
+                offsets[i + 1] = 0;
+            }
+
+            if i + 4 >= buffer.len() {continue;}
+
+            let (match_lengths, match_dists) = matcher.find_matches(buffer, i);
+            for j in 0..match_lengths.len() {
+                let match_price = prices[i] + LZEncoder::optimal_parse_match_price(match_lengths[j],match_dists[j]);
+                if match_price < prices[i + match_lengths[j]] {
+                    prices[i + match_lengths[j]] = match_price;
+                    lengths[i + match_lengths[j]] = match_lengths[j];
+                    offsets[i + match_lengths[j]] = match_dists[j];
+                }
+            }
+        }
+
+        //Literals at the end go with an empty match
+        if lengths[buffer.len()] <= 1{
+            self.match_offsets.push(0);
+            self.match_lengths.push(0);
+            self.match_literal_lengths.push(0);
+        }
+
+        let mut i = buffer.len();
+        while i > 0 {
+            if lengths[i] > 1 {
+                self.match_lengths.push(lengths[i]);
+                self.match_offsets.push(offsets[i]);
+                self.match_literal_lengths.push(0);
+                i -= lengths[i];
+            } else {
+                self.literals.push(buffer[i - 1]);
+                self.match_literal_lengths[self.match_lengths.len() - 1] += 1;
+                i -= 1;
+            }
+        }
+
+        self.match_lengths.reverse();
+        self.match_offsets.reverse();
+        self.match_literal_lengths.reverse();
+        self.literals.reverse();
+    }
+
+    pub fn parse(&mut self, buffer: &[u8]) {
+        self.literals.clear();
+        self.match_lengths.clear();
+        self.match_offsets.clear();
+        self.match_literal_lengths.clear();
+        match self.do_optimal_parsing{
+            true => self.optimal_parse(buffer),
+            false => self.simple_parse(buffer)
+        }
+    }
+
+    /// Writes a table, the number of values, then each value's symbol and its
+    /// extra bits, if it has any.
+    fn huffman_encode_values(values: &[usize], to_symbol: fn(usize) -> HuffmanSymbol, has_extra_bits: fn(usize) -> bool, writer: &mut BitWriter) {
+        let mut encoder = HuffmanEncoder::new(LZ_MAX_SYMBOLS);
+        encoder.scan_symbols(values.iter().map(|value| to_symbol(*value)));
+        encoder.build_huffman_table();
+        encoder.write_huffman_table(writer);
+
+        writer.write_bits_u32(values.len() as u32, HUFFMAN_CHUNK_SIZE_BITS);
+        for value in values {
+            encoder.encode_symbol(to_symbol(*value), writer);
+            if has_extra_bits(*value) {
+                writer.write_bits_u32(extra_huffman_symbol(*value), fast_log2_floor_u32(*value as u32) as usize);
+            }
+        }
+    }
+
+    fn huffman_encode_literals(&mut self, writer: &mut BitWriter){
+        HuffmanEncoder::new(256).encode_chunk(&HuffmanEncoder::bytes_to_symbols(&self.literals), writer);
+    }
+
+    pub fn huffman_encode_chunk(&mut self, buffer: &[u8], writer: &mut BitWriter){
+        self.parse(buffer);
+
+        self.huffman_encode_literals(writer);
+        LZEncoder::huffman_encode_values(&self.match_lengths, huffman_symbol_from_length, |length| length >= LZ_LENGTH_DIRECT_SYMBOLS, writer);
+        LZEncoder::huffman_encode_values(&self.match_offsets, huffman_symbol_from_offset, |offset| offset >= 2, writer);
+        LZEncoder::huffman_encode_values(&self.match_literal_lengths, huffman_symbol_from_length, |length| length >= LZ_LENGTH_DIRECT_SYMBOLS, writer);
+    }
+
+    /// Encodes `buffer` in chunks of `chunk_size`, which can be at most
+    /// `LZ_CHUNK_SIZE` since the lengths would need more symbols otherwise.
+    /// Matches don't go across chunks. The number of chunks goes first, so
+    /// the decoder knows where they end.
+    pub fn huffman_encode_all(&mut self, buffer: &[u8], chunk_size: usize, writer: &mut BitWriter) {
+        assert!(chunk_size > 0 && chunk_size <= LZ_CHUNK_SIZE, "Chunk size must be between [1] and [{LZ_CHUNK_SIZE}], given [{chunk_size}]");
+
+        writer.write_bits_u32(buffer.len().div_ceil(chunk_size) as u32, HUFFMAN_CHUNK_SIZE_BITS);
+        for chunk in buffer.chunks(chunk_size){
+            self.huffman_encode_chunk(chunk, writer);
+        }
+    }
+
+}
+
+impl Default for LZDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LZDecoder {
+    pub fn new() -> Self {
+        LZDecoder {
+            literals: Vec::with_capacity(LZ_CHUNK_SIZE),
+            match_lengths: Vec::with_capacity(LZ_CHUNK_SIZE >> 2),
+            match_offsets: Vec::with_capacity(LZ_CHUNK_SIZE >> 2),
+            match_literal_lengths: Vec::with_capacity(LZ_CHUNK_SIZE >> 2)
+        }
+    }
+
+    /// Reads what `huffman_encode_values` wrote into `values`, where
+    /// `extra_bits` gives the number of extra bits for each symbol that has
+    /// them.
+    fn huffman_decode_values(reader: &mut BitReader, values: &mut Vec<usize>, extra_bits: fn(HuffmanSymbol) -> Option<usize>) -> Option<()> {
+        values.clear();
+
+        let mut decoder = HuffmanDecoder::with_max_symbols(LZ_MAX_SYMBOLS);
+        decoder.read_huffman_table(reader).ok()?;
+        let value_num = reader.read_bits_into_u32_exact(HUFFMAN_CHUNK_SIZE_BITS)?;
+        for _ in 0..value_num{
+            let symbol = decoder.decode_one(reader)?;
+            let value = match extra_bits(symbol) {
+                Some(extra_bits) if extra_bits < 32 => (1 << extra_bits) | reader.read_bits_into_u32_exact(extra_bits)? as usize,
+                //Only a corrupt table has symbols this big
+                Some(_) => return None,
+                None => symbol as usize
+            };
+            values.push(value);
+        }
+
+        Some(())
+    }
+
+    fn huffman_decode_literals(&mut self, reader: &mut BitReader) -> Option<()> {
+        self.literals.clear();
+
+        let mut decoder = HuffmanDecoder::with_max_symbols(256);
+        decoder.read_huffman_table(reader).ok()?;
+        let literal_num = reader.read_bits_into_u32_exact(HUFFMAN_CHUNK_SIZE_BITS)?;
+        for _ in 0..literal_num {
+            self.literals.push(u8::try_from(decoder.decode_one(reader)?).ok()?);
+        }
+
+        Some(())
+    }
+
+    /// Decodes one chunk, or returns `None` if it's corrupt (a bad table, a
+    /// match reaching back before the start of the chunk, or not enough
+    /// literals) or the reader runs out anywhere in it.
+    pub fn huffman_decode_chunk(&mut self, reader: &mut BitReader) -> Option<Vec<u8>>{
+        let length_extra_bits = |symbol| if symbol as usize >= LZ_LENGTH_DIRECT_SYMBOLS {Some(symbol as usize - 12)} else {None};
+        let offset_extra_bits = |symbol| if symbol >= 2 {Some(symbol as usize - 1)} else {None};
+
+        self.huffman_decode_literals(reader)?;
+        LZDecoder::huffman_decode_values(reader, &mut self.match_lengths, length_extra_bits)?;
+        LZDecoder::huffman_decode_values(reader, &mut self.match_offsets, offset_extra_bits)?;
+        LZDecoder::huffman_decode_values(reader, &mut self.match_literal_lengths, length_extra_bits)?;
+        if self.match_lengths.len() != self.match_offsets.len() || self.match_lengths.len() != self.match_literal_lengths.len() {
+            return None;
+        }
+
+        let mut decoded = Vec::new();
+        let mut curr_literal:usize = 0;
+        for i in 0..self.match_lengths.len(){
+            let literals = self.literals.get(curr_literal..(curr_literal + self.match_literal_lengths[i]))?;
+            decoded.extend_from_slice(literals);
+            curr_literal += self.match_literal_lengths[i];
+
+            if self.match_lengths[i] > 0 {
+                let match_start = decoded.len().checked_sub(self.match_offsets[i]).filter(|_| self.match_offsets[i] > 0)?;
+                for j in 0..self.match_lengths[i] {
+                    decoded.push(decoded[match_start + j]);
+                }
+            }
+        }
+
+        Some(decoded)
+    }
+
+    /// Decodes the chunks `huffman_encode_all` wrote, or returns `None` if
+    /// any of them is corrupt or cut off, including the count.
+    pub fn huffman_decode_all(&mut self, reader: &mut BitReader) -> Option<Vec<u8>> {
+        let chunk_num = reader.read_bits_into_u32_exact(HUFFMAN_CHUNK_SIZE_BITS)?;
+        let mut decoded = Vec::new();
+        for _ in 0..chunk_num {
+            decoded.append(&mut self.huffman_decode_chunk(reader)?);
+        }
+
+        Some(decoded)
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{bitstream::{BitWriter, BitReader}, lz::{LZDecoder, LZ_CHUNK_SIZE}};
+
+    #[test]
+    fn fast_log2_floor_u32_test() {
+        use rand::prelude::*;
+        use crate::lz::fast_log2_floor_u32;
+
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(2123);
+        let mut vals = Vec::with_capacity(8192);
+        for _ in 0..8192 {vals.push(rng.gen_range(1..=u32::MAX));}
+
+        for val in &vals {
+            let val = *val;
+            assert!(fast_log2_floor_u32(val) == (val as f64).log2().floor() as u32, "Fast log2 failed with value {val}");
+        }
+    }
+
+    #[test]
+    fn lz_simple_parse_test() {
+        use crate::lz::LZEncoder;
+        use std::{fs, time};
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let mut encoder:LZEncoder = LZEncoder::new(1 << 15, false);
+
+        let start_time = time::Instant::now();
+
+        encoder.parse(&bytes);
+
+        let elapsed_time = start_time.elapsed().as_millis();
+        println!("Simple parse took {elapsed_time}ms at a speed of {}MB/s", ((bytes.len() as f32) / 1000000f32) / ((elapsed_time as f32) / 1000f32));
+
+        let literal_num:usize = encoder.match_literal_lengths.iter().sum();
+        let match_len:usize = encoder.match_lengths.iter().sum();
+        assert!(literal_num == encoder.literals.len() && literal_num + match_len == bytes.len(), "Literals [{literal_num}] and matches [{match_len}] don't add up to [{}] bytes", bytes.len());
+    }
+
+    #[test]
+    fn lz_optimal_parse_test() {
+        use crate::lz::LZEncoder;
+        use std::{fs, time};
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let mut encoder:LZEncoder = LZEncoder::new(1 << 15, true);
+
+        let start_time = time::Instant::now();
+
+        encoder.parse(&bytes);
+
+        let elapsed_time = start_time.elapsed().as_millis();
+        println!("Optimal parse took {elapsed_time}ms at a speed of {}MB/s", ((bytes.len() as f32) / 1000000f32) / ((elapsed_time as f32) / 1000f32));
+
+        let literal_num:usize = encoder.match_literal_lengths.iter().sum();
+        let match_len:usize = encoder.match_lengths.iter().sum();
+        assert!(literal_num == encoder.literals.len() && literal_num + match_len == bytes.len(), "Literals [{literal_num}] and matches [{match_len}] don't add up to [{}] bytes", bytes.len());
+    }
+
+    #[test]
+    fn lz_compression_decompression_test() {
+        use crate::lz::{LZEncoder};
+        use std::{fs, time};
+
+        let contents = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        for do_optimal_parsing in [false, true] {
+            let mut writer = BitWriter::new();
+            let mut encoder:LZEncoder = LZEncoder::new(1 << 15, do_optimal_parsing);
+
+            let start_time = time::Instant::now();
+            encoder.huffman_encode_all(&contents, LZ_CHUNK_SIZE, &mut writer);
+            let encoded_bytes = writer.get_bytes();
+
+            let elapsed_time = start_time.elapsed().as_millis();
+            println!("Optimal parsing: [{do_optimal_parsing}] Bytes unencoded: [{}] Bytes encoded:[{}] Compression ratio:[{}]\nTime:[{}]ms Speed:[{}]MB/s",contents.len(), encoded_bytes.len(), (encoded_bytes.len() as f32) / (contents.len() as f32), elapsed_time, ((contents.len() as f32) / 1000f32) / (elapsed_time as f32));
+            assert!(encoded_bytes.len() < contents.len() / 2, "lorem_ipsum compressed to [{}] bytes", encoded_bytes.len());
+
+            let mut reader = BitReader::new(&encoded_bytes);
+            let mut decoder = LZDecoder::new();
+            let start_time = time::Instant::now();
+            let decoded_bytes = decoder.huffman_decode_all(&mut reader).expect("lorem_ipsum couldn't be decoded");
+            let elapsed_time = start_time.elapsed().as_millis();
+            println!("Decompression time:[{}]ms Speed:[{}]MB/s", elapsed_time, ((encoded_bytes.len() as f32) / 1000f32) / (elapsed_time as f32));
+
+            assert!(contents.len() == decoded_bytes.len(), "Number of bytes different after encoding and decoding");
+            for i in 0..contents.len(){
+                assert!(contents[i] == decoded_bytes[i], "Byte at position {i} different after encoding and decoding [{}] -> [{}]", contents[i], decoded_bytes[i]);
+            }
+        }
+    }
+
+    #[test]
+    fn lz_small_test() {
+        use crate::lz::LZEncoder;
+
+        //Small chunks, runs and inputs shorter than a key
+        let inputs:[&[u8]; 6] = [b"", b"a", b"ab", b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa", b"ABCABCABCDEDEGGZ", b"abcdeabcdeabcdeabcde12345abcde"];
+        for input in inputs {
+            for (do_optimal_parsing, chunk_size) in [(false, 1), (false, 7), (true, 3), (true, LZ_CHUNK_SIZE)] {
+                let mut writer = BitWriter::new();
+                LZEncoder::new(64, do_optimal_parsing).huffman_encode_all(input, chunk_size, &mut writer);
+                let encoded_bytes = writer.get_bytes();
+                let decoded = LZDecoder::new().huffman_decode_all(&mut BitReader::new(&encoded_bytes));
+                assert!(decoded.as_deref() == Some(input), "[{input:?}] different after encoding and decoding with chunks of [{chunk_size}] [{decoded:?}]");
+            }
+        }
+    }
+
+    #[test]
+    fn lz_truncated_test() {
+        use crate::lz::LZEncoder;
+        use std::fs;
+
+        let contents = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let mut writer = BitWriter::new();
+        LZEncoder::new(1 << 15, false).huffman_encode_all(&contents[..2000], 300, &mut writer);
+        let encoded_bytes = writer.get_bytes();
+
+        //Cut off anywhere, even between chunks, it shouldn't come back as fewer bytes
+        for cut in 0..encoded_bytes.len() {
+            let decoded = LZDecoder::new().huffman_decode_all(&mut BitReader::new(&encoded_bytes[..cut]));
+            assert!(decoded.is_none(), "Bytes cut off at [{cut}] decoded to [{:?}] bytes", decoded.map(|bytes| bytes.len()));
+        }
+
+        //Whatever comes after the last chunk is left alone
+        let mut padded = encoded_bytes.clone();
+        padded.extend_from_slice(&[0xFF; 16]);
+        assert!(LZDecoder::new().huffman_decode_all(&mut BitReader::new(&padded)).as_deref() == Some(&contents[..2000]), "Bytes different with more bytes after the stream");
+    }
+}
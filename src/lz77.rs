@@ -1,18 +1,98 @@
-use std::collections::HashMap;
-use std::fmt::{self};
+use alloc::collections::VecDeque;
+use core::cmp::min;
+use core::fmt::{self};
+use crate::bitstream::{BitWriter, BitReader};
+use crate::error::DecodeError;
+use crate::map::{Map, map_with_capacity};
+use alloc::{vec::Vec, string::String, vec, format};
 
-type LZ77MapKey = [u8; 3];
+/// The window size to use when nothing else needs a particular one.
+pub const LZ77_DEFAULT_WINDOW_SIZE:usize = 0xFFFF;
+/// The number of bits used to write the size of the decompressed bytes.
+pub const LZ77_DECOMPRESSED_SIZE_BITS:usize = 32;
+/// The number of bits used to write the number of literals and matches.
+pub const LZ77_DATA_NUM_BITS:usize = 32;
+/// The number of bits used to write how many bits each match length takes up.
+const LZ77_LENGTH_BITS_SIZE:usize = 6;
+/// The number of bytes hashed to find match candidates when nothing else
+/// needs a particular one.
+pub const LZ77_DEFAULT_KEY_LEN:usize = 3;
+/// The longest key that fits in an `LZ77MapKey`.
+pub const LZ77_MAX_KEY_LEN:usize = 4;
+/// About how many bits a literal takes once it's entropy coded, for
+/// `lz77_match_worth_it`.
+pub const LZ77_LITERAL_COST_BITS:usize = 6;
+/// About how many bits a match takes once it's entropy coded (a length code
+/// and an offset code, like DEFLATE), not counting the offset's extra bits.
+pub const LZ77_MATCH_BASE_COST_BITS:usize = 10;
+/// How long the substrings `train_dictionary` counts are. Shorter ones show
+/// up everywhere whether they're worth a match or not.
+const LZ77_TRAIN_KEY_LEN:usize = 8;
+/// How long each piece `train_dictionary` picks for the dictionary is.
+const LZ77_TRAIN_SEGMENT_LEN:usize = 64;
+
+/// What `next_map` holds for positions with no earlier position in their chain.
+const LZ77_NO_POS:usize = usize::MAX;
+
+/// The first `key_len` bytes at a position, packed into a `u32`.
+type LZ77MapKey = u32;
 struct LZ77MatchFinder <'a>{
     buffer: &'a [u8],
     window_size:usize,
+    key_len:usize,
     min_match_len:usize,
     max_match_len:usize,
-    max_match_num:usize,
-    head_map:HashMap<LZ77MapKey, usize>,
-    next_map:HashMap<usize, usize>
+    max_chain_len:usize,
+    /// Once a match at least this long is found, the rest of the chain isn't
+    /// checked. It's `max_match_len` unless `lz77_compress_simple` says otherwise.
+    good_match_len:usize,
+    /// Whether `find_match` skips matches that cost more than the literals
+    /// they replace, see `lz77_match_worth_it`.
+    check_match_cost:bool,
+    /// What the searches have done so far, if anyone asked.
+    stats:Option<LZ77Stats>,
+    head_map:Map<LZ77MapKey, usize>,
+    /// The position before each position with the same key, or `LZ77_NO_POS`.
+    /// Positions index it directly instead of being hashed, modulo its length.
+    /// It only needs to be one longer than the window, since chain walks stop
+    /// at the first position out of the window, before anything newer could
+    /// have taken its slot.
+    next_map:Vec<usize>
+}
+
+/// How hard the match finder worked, from `lz77_compress_simple_with_stats`.
+/// Chains that get walked all the way to `max_chain_len` a lot mean a longer
+/// chain could still find better matches.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LZ77Stats {
+    /// The number of positions a match was looked for at.
+    pub searches: usize,
+    /// The number of earlier positions compared against, over every search.
+    pub chain_nodes: usize,
+    pub matches: usize,
+    pub literals: usize,
+    /// All the match lengths added up.
+    pub total_match_len: usize
+}
+
+impl LZ77Stats {
+    /// The average number of earlier positions compared per search.
+    pub fn avg_chain_depth(&self) -> f64 {
+        if self.searches == 0 {0.0} else {self.chain_nodes as f64 / self.searches as f64}
+    }
+
+    pub fn avg_match_len(&self) -> f64 {
+        if self.matches == 0 {0.0} else {self.total_match_len as f64 / self.matches as f64}
+    }
+}
+
+impl fmt::Display for LZ77Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Searches: [{}]\nChain nodes: [{}] (average depth [{:.2}])\nMatches: [{}] (average length [{:.2}])\nLiterals: [{}]", self.searches, self.chain_nodes, self.avg_chain_depth(), self.matches, self.avg_match_len(), self.literals)
+    }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LZ77Data {
     Literal(u8),
     Match(usize, usize)
@@ -23,20 +103,173 @@ pub struct LZ77Encoded {
     data: Vec<LZ77Data>
 }
 
+impl LZ77Encoded {
+    /// Creates encoded data out of already-parsed literals and matches.
+    pub fn new(data: Vec<LZ77Data>) -> Self {
+        LZ77Encoded { data }
+    }
+
+    pub fn data(&self) -> &[LZ77Data] {
+        &self.data
+    }
+
+    /// The number of bytes the data decompresses to.
+    pub fn decompressed_len(&self) -> usize {
+        self.data.iter().map(|data| match *data {
+            LZ77Data::Literal(_) => 1,
+            LZ77Data::Match(length, _) => length
+        }).sum()
+    }
+
+    /// The number of bits `write` uses for each match length, which is just
+    /// enough for the longest one.
+    fn length_bits(&self) -> usize {
+        let max_length = self.data.iter().map(|data| match *data {
+            LZ77Data::Literal(_) => 0,
+            LZ77Data::Match(length, _) => length
+        }).max().unwrap_or(0);
+
+        (usize::BITS - max_length.leading_zeros()) as usize
+    }
+
+    /// Renders the parse as a Graphviz DOT graph, for seeing what the parser
+    /// actually chose. Each node is a position in the decompressed bytes, and
+    /// each edge is a literal or match going from one position to the next,
+    /// labeled with the number of bits `write` would use for it.
+    /// 
+    /// Run it through `dot -Tpng` to get a picture.
+    pub fn to_dot(&self, window_size: usize) -> String {
+        let offset_bits = offset_bits(window_size);
+        let length_bits = self.length_bits();
+
+        let mut dot = String::from("digraph lz77 {\n    rankdir=LR;\n    node [shape=circle];\n    p0 [label=\"0\"];\n");
+        let mut pos = 0;
+        for data in &self.data {
+            let (next_pos, label) = match *data {
+                LZ77Data::Literal(byte) => (pos + 1, format!("literal 0x{byte:02x} (9 bits)")),
+                LZ77Data::Match(length, offset) => (pos + length, format!("match length {length} offset {offset} ({} bits)", 1 + length_bits + offset_bits))
+            };
+            dot.push_str(&format!("    p{next_pos} [label=\"{next_pos}\"];\n    p{pos} -> p{next_pos} [label=\"{label}\"];\n"));
+            pos = next_pos;
+        }
+        dot.push_str("}\n");
+
+        dot
+    }
+
+    /// Writes the literals and matches to a `BitWriter`.
+    /// 
+    /// First comes the size of the decompressed bytes (so
+    /// `decompressed_size_hint` can read it without decoding everything),
+    /// then the number of literals and matches, then the number of
+    /// bits each match length takes up (just enough for the longest one).
+    /// After that, each literal is a `0` bit followed by the byte, and each
+    /// match is a `1` bit followed by the length and `offset - 1`. Offsets use
+    /// `ceil(log2(window_size))` bits, so use the same window size that was
+    /// used to compress when reading it back.
+    pub fn write(&self, writer: &mut BitWriter, window_size: usize) {
+        assert!(self.data.len() < 1 << LZ77_DATA_NUM_BITS, "Too many literals and matches to write [{}]", self.data.len());
+
+        let offset_bits = offset_bits(window_size);
+        let length_bits = self.length_bits();
+        assert!(length_bits <= 32, "Match lengths are too long to write, they need [{length_bits}] bits");
+
+        let decompressed_len = self.decompressed_len();
+        assert!(decompressed_len < 1 << LZ77_DECOMPRESSED_SIZE_BITS, "Decompressed size is too large to write [{decompressed_len}]");
+
+        writer.write_bits_u32(decompressed_len as u32, LZ77_DECOMPRESSED_SIZE_BITS);
+        writer.write_bits_u32(self.data.len() as u32, LZ77_DATA_NUM_BITS);
+        writer.write_bits_u32(length_bits as u32, LZ77_LENGTH_BITS_SIZE);
+
+        for data in &self.data {
+            match *data {
+                LZ77Data::Literal(byte) => {
+                    //The flag bit is the 0 at the top
+                    writer.write_bits_u16(byte as u16, 9);
+                },
+                LZ77Data::Match(length, offset) => {
+                    assert!(offset > 0 && offset <= window_size, "Match offset [{offset}] is outside the window [{window_size}]");
+                    writer.write_bits_u16(1, 1);
+                    writer.write_bits_u32(length as u32, length_bits);
+                    writer.write_bits_u32((offset - 1) as u32, offset_bits);
+                }
+            }
+        }
+    }
+
+    /// Reads literals and matches written by `write`. `window_size` has to be
+    /// the same one they were written with. Returns
+    /// `DecodeError::UnexpectedEnd` if the reader runs out before all of them
+    /// are read, and `DecodeError::BadDecompressedSize` if they don't add up
+    /// to the decompressed size in the header. That way the lengths, which
+    /// decompressing trusts, can't ask for more than the header says.
+    pub fn read(reader: &mut BitReader, window_size: usize) -> Result<LZ77Encoded, DecodeError> {
+        let offset_bits = offset_bits(window_size);
+        let decompressed_size = reader.read_bits_into_u32_exact(LZ77_DECOMPRESSED_SIZE_BITS).ok_or(DecodeError::UnexpectedEnd)? as usize;
+        let data_num = reader.read_bits_into_u32_exact(LZ77_DATA_NUM_BITS).ok_or(DecodeError::UnexpectedEnd)? as usize;
+        let length_bits = reader.read_bits_into_u32_exact(LZ77_LENGTH_BITS_SIZE).ok_or(DecodeError::UnexpectedEnd)? as usize;
+        if length_bits > 32 {
+            return Err(DecodeError::BadMatchLengthBits(length_bits));
+        }
+
+        //A corrupt count can't make it allocate more than a literal for every 9 bits left
+        let mut data = Vec::with_capacity(min(data_num, reader.remaining_bits() / 9));
+        let mut total_len:usize = 0;
+        for _ in 0..data_num {
+            let next = if reader.read_bit().ok_or(DecodeError::UnexpectedEnd)? {
+                let length = reader.read_bits_into_u32_exact(length_bits).ok_or(DecodeError::UnexpectedEnd)? as usize;
+                let offset = reader.read_bits_into_u32_exact(offset_bits).ok_or(DecodeError::UnexpectedEnd)? as usize + 1;
+                LZ77Data::Match(length, offset)
+            } else {
+                LZ77Data::Literal(reader.read_bits_into_u32_exact(8).ok_or(DecodeError::UnexpectedEnd)? as u8)
+            };
+            total_len = match next {
+                LZ77Data::Literal(_) => total_len + 1,
+                LZ77Data::Match(length, _) => total_len.saturating_add(length)
+            };
+            if total_len > decompressed_size {
+                return Err(DecodeError::BadDecompressedSize { expected: decompressed_size, actual: total_len });
+            }
+            data.push(next);
+        }
+        if total_len != decompressed_size {
+            return Err(DecodeError::BadDecompressedSize { expected: decompressed_size, actual: total_len });
+        }
+
+        Ok(LZ77Encoded { data })
+    }
+}
+
 impl<'a> LZ77MatchFinder <'a> {
-    fn new(buffer: &'a [u8], window_size:usize, min_match_len:usize, max_match_len:usize, max_match_num:usize) -> Self {
+    /// `max_chain_len` is the number of earlier positions with the same key
+    /// that are checked when looking for a match. Longer chains find better
+    /// matches, but take longer.
+    /// 
+    /// `key_len` is the number of bytes that have to be the same for a
+    /// position to end up in the same chain. Longer keys mean fewer positions
+    /// in each chain that don't actually match, but matches shorter than the
+    /// key can't be found at all, and neither can matches in the last
+    /// `key_len` bytes.
+    fn new(buffer: &'a [u8], window_size:usize, min_match_len:usize, max_match_len:usize, max_chain_len:usize, key_len:usize) -> Self {
 
         assert!(min_match_len > 0, "Minimum match length cannot be 0!");
         assert!(window_size > 0, "Window size must be greater than 1!");
+        assert!(max_chain_len > 0, "Maximum chain length cannot be 0!");
+        assert!((1..=LZ77_MAX_KEY_LEN).contains(&key_len), "Key length must be between 1 and [{LZ77_MAX_KEY_LEN}], given [{key_len}]");
+        assert!(max_match_len >= key_len, "Maximum match length of [{max_match_len}] is shorter than the key length of [{key_len}]");
 
         LZ77MatchFinder {
             buffer,
             window_size,
+            key_len,
             min_match_len,
             max_match_len,
-            max_match_num,
-            head_map: HashMap::with_capacity(window_size),
-            next_map: HashMap::with_capacity(window_size)
+            max_chain_len,
+            good_match_len: max_match_len,
+            check_match_cost: false,
+            stats: None,
+            head_map: map_with_capacity(window_size),
+            next_map: vec![LZ77_NO_POS; buffer.len().clamp(1, window_size + 1)]
         }
     }
 
@@ -54,73 +287,79 @@ impl<'a> LZ77MatchFinder <'a> {
     
     #[inline]
     fn key_from_bytes(&self, pos: usize) -> LZ77MapKey {
-        let buf: &[u8] = &self.buffer[pos..(pos+3)];
-        [buf[0], buf[1], buf[2]]
+        self.buffer[pos..(pos + self.key_len)].iter().fold(0, |key, byte| (key << 8) | *byte as LZ77MapKey)
     }
 
     #[inline]
     fn insert(&mut self, pos: usize){
         let key = self.key_from_bytes(pos);
 
-        if let Some(head) = self.head_map.get(&key){
-            self.next_map.insert(pos, *head);
-        }
-        self.head_map.insert(key, pos);
+        let head = self.head_map.insert(key, pos);
+        let slot = pos % self.next_map.len();
+        self.next_map[slot] = head.unwrap_or(LZ77_NO_POS);
+    }
+
+    /// The position before `pos` in its chain, if there is one.
+    #[inline]
+    fn next(&self, pos: usize) -> Option<usize> {
+        Some(self.next_map[pos % self.next_map.len()]).filter(|next| *next != LZ77_NO_POS)
     }
 
     fn find_match(&mut self, pos: usize) -> LZ77Data {
         let mut length:usize = 0;
         let mut offset:usize = 0;
 
-        let min_pos:usize = if self.window_size > pos {0} else {pos - self.window_size};
-        let mut next_option = self.head_map.get(&self.key_from_bytes(pos));
-        let mut match_num = 0;
+        let min_pos:usize = pos.saturating_sub(self.window_size);
+        let mut next_option = self.head_map.get(&self.key_from_bytes(pos)).copied();
+        let mut chain_len = 0;
         
         while let Some(next) = next_option {
-            let next = *next;
             if next < min_pos {break;}
-            match_num += 1;
-            if match_num > self.max_match_num {break;}
+            chain_len += 1;
+            if chain_len > self.max_chain_len {break;}
 
-            let match_len = self.match_len(pos + 3, next + 3) + 3;
-            if match_len > length {
+            let match_len = self.match_len(pos + self.key_len, next + self.key_len) + self.key_len;
+            if match_len > length && (!self.check_match_cost || lz77_match_worth_it(match_len, pos - next)) {
                 length = match_len;
                 offset = pos - next;
+                if length >= self.good_match_len {break;}
             }
 
-            next_option = self.next_map.get(&next);
+            next_option = self.next(next);
         }
 
+        self.record_search(chain_len);
         self.insert(pos);
 
         //println!("Pos: {pos} Best match: {best_match_pos} Best match length; {best_match_len}");
 
-        if length >= self.min_match_len && offset >= self.min_match_len {LZ77Data::Match(length, offset)} else {LZ77Data::Literal(self.buffer[pos])}
+        //Matches can overlap the bytes they encode, so small offsets (like an offset of 1 for a run of one byte) are fine
+        if length >= self.min_match_len {LZ77Data::Match(length, offset)} else {LZ77Data::Literal(self.buffer[pos])}
     }
 
     fn find_matches(&mut self, pos: usize) -> Vec<LZ77Data> {
-        let mut data = Vec::with_capacity(self.max_match_num);
+        let mut data = Vec::with_capacity(self.max_chain_len);
 
-        let min_pos:usize = if self.window_size > pos {0} else {pos - self.window_size};
-        let mut next_option = self.head_map.get(&self.key_from_bytes(pos));
-        let mut match_num = 0;
+        let min_pos:usize = pos.saturating_sub(self.window_size);
+        let mut next_option = self.head_map.get(&self.key_from_bytes(pos)).copied();
+        let mut chain_len = 0;
         
         while let Some(next) = next_option {
-            let next = *next;
             if next < min_pos {break;}
 
-            match_num += 1;
-            if match_num >= self.max_match_num {break;}
+            chain_len += 1;
+            if chain_len > self.max_chain_len {break;}
 
-            let length = self.match_len(pos + 3, next + 3) + 3;
+            let length = self.match_len(pos + self.key_len, next + self.key_len) + self.key_len;
 
             if length >= self.min_match_len {
                 data.push(LZ77Data::Match(length, pos - next));
             }
 
-            next_option = self.next_map.get(&next);
+            next_option = self.next(next);
         }
 
+        self.record_search(chain_len);
         self.insert(pos);
 
         //println!("Pos: {pos} Best match: {best_match_pos} Best match length; {best_match_len}");
@@ -128,32 +367,249 @@ impl<'a> LZ77MatchFinder <'a> {
         data
     }
 
+    /// Adds a search to the stats, if they're being kept. `chain_len` can be
+    /// one past `max_chain_len` when the walk was cut short, which doesn't count.
+    #[inline]
+    fn record_search(&mut self, chain_len: usize) {
+        if let Some(stats) = &mut self.stats {
+            stats.searches += 1;
+            stats.chain_nodes += min(chain_len, self.max_chain_len);
+        }
+    }
+
+    /// The number of bytes at `source_pos` that match the bytes at `match_pos`,
+    /// up to `max_match_len - key_len` (the bytes of the key already match).
+    /// 
+    /// The match is allowed to run past `source_pos`. If the distance is
+    /// smaller than the length, the bytes just repeat from the start of the
+    /// match, which is exactly what `lz77_decompress` does with overlapping
+    /// matches. So a run of one byte becomes a single match with an offset of 1.
     #[inline]
     fn match_len(&self, source_pos: usize, match_pos: usize) -> usize {
+        let max_len = min(self.max_match_len - self.key_len, self.buffer.len() - source_pos);
+        common_prefix_len(&self.buffer[source_pos..(source_pos + max_len)], &self.buffer[match_pos..(match_pos + max_len)])
+    }
+}
 
-        // let mut len:usize = 3;
-        // let dist = source_pos - match_pos;
+/// How many bytes at the start of `a` and `b` are the same. It compares `8`
+/// bytes at a time as `u64`s, and the first different byte is wherever the
+/// lowest set bit of the XOR is (they're read little endian, so the first
+/// byte is the lowest one). Whatever's left over is compared a byte at a
+/// time.
+#[inline]
+fn common_prefix_len(a: &[u8], b: &[u8]) -> usize {
+    let len = min(a.len(), b.len());
+    let (a, b) = (&a[..len], &b[..len]);
+    let mut prefix_len:usize = 0;
 
-        // while len < self.max_match_len as usize && source_pos + len < self.buffer.len() - 3 && self.buffer[source_pos + len] == self.buffer[match_pos + (len % dist)] {
-        //     len += 1;
-        // }
+    for (a_word, b_word) in a.chunks_exact(8).zip(b.chunks_exact(8)) {
+        let diff = u64::from_le_bytes(a_word.try_into().unwrap()) ^ u64::from_le_bytes(b_word.try_into().unwrap());
+        if diff != 0 {
+            return prefix_len + (diff.trailing_zeros() / 8) as usize;
+        }
+        prefix_len += 8;
+    }
 
-        // len
-        self.buffer[source_pos..]
-            .iter()
-            .take(self.max_match_len - 3)
-            .zip(&self.buffer[match_pos..])
-            .take_while(|&(a, b)| a == b)
-            .count()
+    while prefix_len < len && a[prefix_len] == b[prefix_len] {
+        prefix_len += 1;
     }
+
+    prefix_len
 }
 
-pub fn lz77_compress_simple(buffer: &[u8], window_size: usize, min_match_len: usize, max_match_len: usize, max_match_num: usize) -> LZ77Encoded{
-    let mut matcher: LZ77MatchFinder = LZ77MatchFinder::new(buffer, window_size, min_match_len, max_match_len, max_match_num);
-    let mut data = Vec::with_capacity(buffer.len());
-    let mut pos = 0;
+/// Greedy LZ77 compression, where the longest match found at each position is
+/// always used.
+/// 
+/// At most `max_chain_len` earlier positions are checked for each match, and
+/// the search stops early once a match of at least `good_match_len` is found
+/// (like zlib's `nice_match`). Passing `max_match_len` never stops early,
+/// while something small like `16` makes very repetitive data compress a
+/// lot faster, for a slightly worse ratio.
+pub fn lz77_compress_simple(buffer: &[u8], window_size: usize, min_match_len: usize, max_match_len: usize, max_chain_len: usize, good_match_len: usize) -> LZ77Encoded{
+    assert!(good_match_len > 0, "Good match length cannot be 0!");
+
+    let mut matcher: LZ77MatchFinder = LZ77MatchFinder::new(buffer, window_size, min_match_len, max_match_len, max_chain_len, LZ77_DEFAULT_KEY_LEN);
+    matcher.good_match_len = good_match_len;
+    greedy_parse(&mut matcher, 0)
+}
+
+/// Same as `lz77_compress_simple`, but also returns `LZ77Stats` on how the
+/// match finder did, for tuning `window_size` and `max_chain_len`.
+pub fn lz77_compress_simple_with_stats(buffer: &[u8], window_size: usize, min_match_len: usize, max_match_len: usize, max_chain_len: usize, good_match_len: usize) -> (LZ77Encoded, LZ77Stats) {
+    assert!(good_match_len > 0, "Good match length cannot be 0!");
+
+    let mut matcher: LZ77MatchFinder = LZ77MatchFinder::new(buffer, window_size, min_match_len, max_match_len, max_chain_len, LZ77_DEFAULT_KEY_LEN);
+    matcher.good_match_len = good_match_len;
+    matcher.stats = Some(LZ77Stats::default());
+    let encoded = greedy_parse(&mut matcher, 0);
+
+    let mut stats = matcher.stats.take().unwrap_or_default();
+    for data in &encoded.data {
+        match *data {
+            LZ77Data::Literal(_) => stats.literals += 1,
+            LZ77Data::Match(length, _) => {
+                stats.matches += 1;
+                stats.total_match_len += length;
+            }
+        }
+    }
+
+    (encoded, stats)
+}
+
+/// About how many bits a match takes once it's entropy coded: a length code
+/// and an offset code (`LZ77_MATCH_BASE_COST_BITS` together), then the
+/// offset's extra bits, which grow with `log2(offset)`.
+pub fn lz77_match_cost_bits(offset: usize) -> usize {
+    LZ77_MATCH_BASE_COST_BITS + offset.max(1).ilog2() as usize
+}
+
+/// Whether a match is cheaper than the literals it replaces. Long matches
+/// always are, but a short match far back can take more bits than just
+/// writing its bytes out, like a match of `3` more than `255` back.
+pub fn lz77_match_worth_it(length: usize, offset: usize) -> bool {
+    lz77_match_cost_bits(offset) < length * LZ77_LITERAL_COST_BITS
+}
+
+/// Same as `lz77_compress_simple` (never stopping early), but a match is
+/// only used if it's also `lz77_match_worth_it`. The fixed size matches
+/// `LZ77Encoded::write` writes don't get any cheaper, so this only helps if
+/// the literals and matches get entropy coded after, like DEFLATE does.
+pub fn lz77_compress_cost_aware(buffer: &[u8], window_size: usize, min_match_len: usize, max_match_len: usize, max_chain_len: usize) -> LZ77Encoded{
+    let mut matcher: LZ77MatchFinder = LZ77MatchFinder::new(buffer, window_size, min_match_len, max_match_len, max_chain_len, LZ77_DEFAULT_KEY_LEN);
+    matcher.check_match_cost = true;
+    greedy_parse(&mut matcher, 0)
+}
+
+/// Same as `lz77_compress_simple`, but hashes `key_len` bytes (up to
+/// `LZ77_MAX_KEY_LEN`) to find match candidates instead of
+/// `LZ77_DEFAULT_KEY_LEN`.
+/// 
+/// A key of 4 bytes puts a lot fewer positions that only share 3 bytes in the
+/// same chain, so on text the chain walks find more real matches. The catch
+/// is that matches are never shorter than the key, so `min_match_len` is
+/// effectively at least `key_len`.
+pub fn lz77_compress_with_key_len(buffer: &[u8], window_size: usize, min_match_len: usize, max_match_len: usize, max_chain_len: usize, key_len: usize) -> LZ77Encoded{
+    let mut matcher: LZ77MatchFinder = LZ77MatchFinder::new(buffer, window_size, min_match_len, max_match_len, max_chain_len, key_len);
+    greedy_parse(&mut matcher, 0)
+}
+
+/// Greedy LZ77 compression with a preset dictionary, which is handy for lots
+/// of small files that share the same boilerplate (like JSON records), since
+/// they don't have much to match within themselves.
+/// 
+/// The dictionary acts like it came right before `buffer`, so matches can
+/// reach back into it (as long as it's within the window), but none of it
+/// gets encoded. Decompress with `lz77_decompress_with_dict` using the same
+/// dictionary.
+pub fn lz77_compress_with_dict(buffer: &[u8], dict: &[u8], window_size: usize, min_match_len: usize, max_match_len: usize, max_chain_len: usize) -> LZ77Encoded{
+    let mut dict_and_buffer = Vec::with_capacity(dict.len() + buffer.len());
+    dict_and_buffer.extend_from_slice(dict);
+    dict_and_buffer.extend_from_slice(buffer);
+
+    let mut matcher: LZ77MatchFinder = LZ77MatchFinder::new(&dict_and_buffer, window_size, min_match_len, max_match_len, max_chain_len, LZ77_DEFAULT_KEY_LEN);
+    //Only the last window's worth of the dictionary can ever be matched
+    for pos in dict.len().saturating_sub(window_size)..dict.len() {
+        if pos + matcher.key_len > dict_and_buffer.len() {break;}
+        matcher.insert(pos);
+    }
+
+    greedy_parse(&mut matcher, dict.len())
+}
+
+/// Builds a dictionary of up to `dict_size` bytes for `lz77_compress_with_dict`
+/// out of some samples of the kind of data it'll be used on.
+/// 
+/// Every `LZ77_TRAIN_KEY_LEN` byte substring gets counted once for each
+/// sample it shows up in, and each `LZ77_TRAIN_SEGMENT_LEN` byte piece of a
+/// sample is worth the counts of the substrings in it. The best piece is
+/// picked over and over, with the substrings it has no longer counting for
+/// the others, so the same boilerplate doesn't get picked twice. It stops once
+/// the dictionary is full or nothing's left that shows up anywhere.
+/// 
+/// The best pieces go last, so they're the closest to the data and get the
+/// smallest offsets. If the last piece doesn't fit, only its end goes in.
+pub fn train_dictionary(samples: &[&[u8]], dict_size: usize) -> Vec<u8> {
+    //Each position of each sample gets the index of its substring's count
+    let mut key_indices:Map<&[u8], usize> = map_with_capacity(samples.iter().map(|sample| sample.len()).sum());
+    let mut counts:Vec<u64> = Vec::new();
+    let mut last_sample:Vec<usize> = Vec::new();
+    let sample_keys:Vec<Vec<usize>> = samples.iter().enumerate().map(|(sample_num, sample)| {
+        sample.windows(LZ77_TRAIN_KEY_LEN).map(|key| {
+            let index = *key_indices.entry(key).or_insert_with(|| {
+                counts.push(0);
+                last_sample.push(usize::MAX);
+                counts.len() - 1
+            });
+            if last_sample[index] != sample_num {
+                last_sample[index] = sample_num;
+                counts[index] += 1;
+            }
+            index
+        }).collect()
+    }).collect();
+
+    let mut segments:Vec<&[u8]> = Vec::new();
+    let mut dict_len = 0;
+    let mut prefix_sums = Vec::new();
+    while dict_len < dict_size {
+        //The sample, position and score of the best segment so far
+        let mut best = (0, 0, 0);
+        for (sample_num, keys) in sample_keys.iter().enumerate() {
+            prefix_sums.clear();
+            prefix_sums.push(0);
+            for &index in keys {
+                prefix_sums.push(prefix_sums.last().unwrap() + counts[index]);
+            }
+
+            for pos in 0..keys.len() {
+                let end = min(pos + LZ77_TRAIN_SEGMENT_LEN - LZ77_TRAIN_KEY_LEN + 1, keys.len());
+                let score = prefix_sums[end] - prefix_sums[pos];
+                if score > best.2 {
+                    best = (sample_num, pos, score);
+                }
+            }
+        }
+
+        let (sample_num, pos, score) = best;
+        if score == 0 {
+            break;
+        }
+        let end = min(pos + LZ77_TRAIN_SEGMENT_LEN, samples[sample_num].len());
+        for &index in &sample_keys[sample_num][pos..(end + 1 - LZ77_TRAIN_KEY_LEN)] {
+            counts[index] = 0;
+        }
+        let segment = &samples[sample_num][pos..end];
+        let segment = &segment[segment.len().saturating_sub(dict_size - dict_len)..];
+        dict_len += segment.len();
+        segments.push(segment);
+    }
 
-    while pos + 3 < buffer.len() {
+    segments.iter().rev().flat_map(|segment| segment.iter().copied()).collect()
+}
+
+/// Greedily parses the matcher's buffer from `start` on.
+fn greedy_parse(matcher: &mut LZ77MatchFinder, start: usize) -> LZ77Encoded {
+    let buffer = matcher.buffer;
+    let mut data = Vec::with_capacity(buffer.len() - start);
+    let pos = greedy_parse_until(matcher, start, buffer.len(), &mut data);
+
+    for byte in &buffer[pos..] {
+        data.push(LZ77Data::Literal(*byte));
+    }
+
+    LZ77Encoded { data }
+}
+
+/// Greedily parses the matcher's buffer from `start` until a literal or match
+/// starts at or past `end` (or there's not enough left to hash), returning
+/// where it stopped. The last match can run past `end`.
+fn greedy_parse_until(matcher: &mut LZ77MatchFinder, start: usize, end: usize, data: &mut Vec<LZ77Data>) -> usize {
+    let buffer = matcher.buffer;
+    let key_len = matcher.key_len;
+    let mut pos = start;
+
+    while pos < end && pos + key_len < buffer.len() {
         //println!("{pos} {} {}", buffer.len(), (pos as f32) / (buffer.len() as f32));
 
         let d = matcher.find_match(pos);
@@ -163,7 +619,7 @@ pub fn lz77_compress_simple(buffer: &[u8], window_size: usize, min_match_len: us
             LZ77Data::Match(length, _) => {
                 //println!("Found match of length {length} at distance {dist}, moving up to {}", pos + length);
                 for pos_to_add in (pos..).take(length).skip(1) {
-                    if pos_to_add + 3 <= buffer.len() {break;}
+                    if pos_to_add + key_len > buffer.len() {break;}
                     matcher.insert(pos_to_add);
                 }
                 pos += length;
@@ -172,36 +628,351 @@ pub fn lz77_compress_simple(buffer: &[u8], window_size: usize, min_match_len: us
         }
     }
 
+    pos
+
+    //println!("Match lengths: {match_lengths:?}\nMatch offsets: {match_offsets:?}\nLiteral lengths: {literal_lengths:?}\nLiterals: {literals:?}");
+}
+
+/// Greedy LZ77 compression that takes its input a chunk at a time, keeping
+/// only the last window of it (plus whatever's given but not encoded yet)
+/// around. The chains are the same as `lz77_compress_simple`'s with
+/// positions older than the window thrown out, so memory stays bounded no
+/// matter how big the input gets, and the literals and matches come out the
+/// same as `lz77_compress_simple` with a `good_match_len` of `max_match_len`.
+pub struct LZ77StreamEncoder {
+    window_size: usize,
+    min_match_len: usize,
+    max_match_len: usize,
+    max_chain_len: usize,
+    /// The last window of bytes that were encoded, then the ones that weren't.
+    buffer: Vec<u8>,
+    /// The next position in `buffer` to encode.
+    pos: usize,
+    head_map: Map<LZ77MapKey, usize>,
+    next_map: Vec<usize>
+}
+
+impl LZ77StreamEncoder {
+    /// Makes an encoder with the same settings as `lz77_compress_simple`,
+    /// `panic`ing on the same ones.
+    pub fn new(window_size: usize, min_match_len: usize, max_match_len: usize, max_chain_len: usize) -> Self {
+        //Checks the settings
+        LZ77MatchFinder::new(&[], window_size, min_match_len, max_match_len, max_chain_len, LZ77_DEFAULT_KEY_LEN);
+
+        LZ77StreamEncoder {
+            window_size,
+            min_match_len,
+            max_match_len,
+            max_chain_len,
+            buffer: Vec::new(),
+            pos: 0,
+            head_map: Map::new(),
+            next_map: vec![LZ77_NO_POS; window_size + 1]
+        }
+    }
+
+    /// Encodes another chunk, returning the literals and matches for every
+    /// byte far enough from the end that the longest match (and the keys of
+    /// every position in it) could fit. The rest wait for the next chunk (or
+    /// `finish`).
+    pub fn update(&mut self, chunk: &[u8]) -> Vec<LZ77Data> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut data = Vec::new();
+        let end = self.buffer.len().saturating_sub(self.max_match_len + LZ77_DEFAULT_KEY_LEN);
+        self.parse_until(end, &mut data);
+        self.slide();
+
+        data
+    }
+
+    /// Encodes everything that's left.
+    pub fn finish(mut self) -> Vec<LZ77Data> {
+        let mut data = Vec::new();
+        self.parse_until(self.buffer.len(), &mut data);
+
+        for byte in &self.buffer[self.pos..] {
+            data.push(LZ77Data::Literal(*byte));
+        }
+
+        data
+    }
+
+    fn parse_until(&mut self, end: usize, data: &mut Vec<LZ77Data>) {
+        let mut matcher = LZ77MatchFinder {
+            buffer: &self.buffer,
+            window_size: self.window_size,
+            key_len: LZ77_DEFAULT_KEY_LEN,
+            min_match_len: self.min_match_len,
+            max_match_len: self.max_match_len,
+            max_chain_len: self.max_chain_len,
+            good_match_len: self.max_match_len,
+            check_match_cost: false,
+            stats: None,
+            head_map: core::mem::take(&mut self.head_map),
+            next_map: core::mem::take(&mut self.next_map)
+        };
+        self.pos = greedy_parse_until(&mut matcher, self.pos, end, data);
+
+        self.head_map = matcher.head_map;
+        self.next_map = matcher.next_map;
+    }
+
+    /// Throws out the bytes (and positions in the chains) that are out of the
+    /// window. It only ever shifts by a multiple of `next_map`'s length, so
+    /// every position stays in the same slot.
+    fn slide(&mut self) {
+        let slots = self.next_map.len();
+        let shift = self.pos.saturating_sub(self.window_size) / slots * slots;
+        if shift == 0 {
+            return;
+        }
+
+        self.buffer.drain(..shift);
+        self.pos -= shift;
+        self.head_map.retain(|_, pos| {
+            if *pos < shift {
+                return false;
+            }
+            *pos -= shift;
+            true
+        });
+        for next in &mut self.next_map {
+            if *next != LZ77_NO_POS {
+                *next = if *next < shift {LZ77_NO_POS} else {*next - shift};
+            }
+        }
+    }
+}
+
+/// The number of bits `LZ77Encoded::write` uses for a literal.
+const LZ77_LITERAL_BITS:u64 = 9;
+
+/// LZ77 compression that picks the literals and matches that take up the
+/// fewest bits overall, instead of just taking the longest match it sees.
+/// 
+/// Every position gets its candidate matches from `find_matches` first. Then
+/// a forward pass works out the cheapest way to reach each position, trying a
+/// literal and every length (down to `min_match_len`) of the longest match
+/// starting at each position it can already reach. Walking back from the end
+/// gives the parse.
+/// 
+/// The costs are what `write` actually uses: 9 bits for a literal, and the
+/// flag bit, length bits and offset bits for a match. Lengths and offsets are
+/// written with fixed widths, so every match costs the same no matter how
+/// long or far back it is, and the cheapest parse is the one with the
+/// fewest matches and literals.
+pub fn lz77_compress_optimal(buffer: &[u8], window_size: usize, min_match_len: usize, max_match_len: usize, max_chain_len: usize) -> LZ77Encoded{
+    let mut matcher: LZ77MatchFinder = LZ77MatchFinder::new(buffer, window_size, min_match_len, max_match_len, max_chain_len, LZ77_DEFAULT_KEY_LEN);
+    let match_cost = 1 + (usize::BITS - max_match_len.leading_zeros()) as u64 + offset_bits(window_size) as u64;
+
+    //The cheapest cost to reach each position, and the literal or match used to get there
+    let mut costs = vec![u64::MAX; buffer.len() + 1];
+    let mut steps = vec![LZ77Data::Literal(0); buffer.len() + 1];
+    costs[0] = 0;
+
+    for pos in 0..buffer.len() {
+        let cost = costs[pos];
+        if cost + LZ77_LITERAL_BITS < costs[pos + 1] {
+            costs[pos + 1] = cost + LZ77_LITERAL_BITS;
+            steps[pos + 1] = LZ77Data::Literal(buffer[pos]);
+        }
+
+        if pos + matcher.key_len > buffer.len() {
+            continue;
+        }
+        //Every match costs the same, so only the longest one matters, and any shorter part of it works too
+        let longest = matcher.find_matches(pos).into_iter().max_by_key(|data| match *data {
+            LZ77Data::Match(length, offset) => (length, usize::MAX - offset),
+            LZ77Data::Literal(_) => (0, 0)
+        });
+        if let Some(LZ77Data::Match(longest_length, offset)) = longest {
+            for length in min_match_len..=longest_length {
+                if cost + match_cost < costs[pos + length] {
+                    costs[pos + length] = cost + match_cost;
+                    steps[pos + length] = LZ77Data::Match(length, offset);
+                }
+            }
+        }
+    }
+
+    let mut data = Vec::new();
+    let mut pos = buffer.len();
+    while pos > 0 {
+        let step = steps[pos];
+        data.push(step);
+        pos -= match step {
+            LZ77Data::Literal(_) => 1,
+            LZ77Data::Match(length, _) => length
+        };
+    }
+    data.reverse();
+
+    LZ77Encoded { data }
+}
+
+/// LZ77 compression with lazy matching, like DEFLATE does it.
+/// 
+/// Before using the match found at a position, the matches at the next
+/// `lazy_depth` positions are checked too. If one of those is worth more
+/// (it's longer by more than the extra literals it needs), literals are used
+/// up to that position instead and the better match gets used (or deferred
+/// again). A `lazy_depth` of `0` is just greedy, and `1` is what DEFLATE does.
+/// Every position gets added to the hash chains, even the ones inside
+/// matches, so later matches aren't missed.
+pub fn lz77_compress_lazy(buffer: &[u8], window_size: usize, min_match_len: usize, max_match_len: usize, max_chain_len: usize, lazy_depth: usize) -> LZ77Encoded{
+    let mut matcher: LZ77MatchFinder = LZ77MatchFinder::new(buffer, window_size, min_match_len, max_match_len, max_chain_len, LZ77_DEFAULT_KEY_LEN);
+    let key_len = matcher.key_len;
+    let mut data = Vec::with_capacity(buffer.len());
+    let mut pos = 0;
+    //What was found at pos, pos + 1, ... so far. These positions are already in the hash chains, so they can't be looked up again
+    let mut found: VecDeque<LZ77Data> = VecDeque::with_capacity(lazy_depth + 1);
+
+    while pos + key_len < buffer.len() {
+        if found.is_empty() {
+            found.push_back(matcher.find_match(pos));
+        }
+
+        let d = found[0];
+        match d {
+            LZ77Data::Match(length, _) => {
+                if length < max_match_len {
+                    while found.len() <= lazy_depth && pos + found.len() + key_len < buffer.len() {
+                        found.push_back(matcher.find_match(pos + found.len()));
+                    }
+                }
+
+                //Deferring by k positions costs k literals, so a later match has to make up for them
+                let mut best_defer = 0;
+                let mut best_reach = length;
+                for (defer, later) in found.iter().enumerate().skip(1) {
+                    if let LZ77Data::Match(later_length, _) = *later {
+                        if later_length + 1 > best_reach + defer {
+                            best_defer = defer;
+                            best_reach = later_length + 1 - defer;
+                        }
+                    }
+                }
+
+                if best_defer > 0 {
+                    for byte in &buffer[pos..(pos + best_defer)] {
+                        data.push(LZ77Data::Literal(*byte));
+                    }
+                    found.drain(..best_defer);
+                    pos += best_defer;
+                    continue;
+                }
+
+                for pos_to_add in (pos + found.len())..(pos + length) {
+                    if pos_to_add + key_len > buffer.len() {break;}
+                    matcher.insert(pos_to_add);
+                }
+                found.drain(..min(length, found.len()));
+                data.push(d);
+                pos += length;
+            },
+            _ => {
+                data.push(d);
+                found.pop_front();
+                pos += 1;
+            }
+        }
+    }
+
     for byte in &buffer[pos..] {
         data.push(LZ77Data::Literal(*byte));
     }
 
     LZ77Encoded { data }
+}
 
-    //println!("Match lengths: {match_lengths:?}\nMatch offsets: {match_offsets:?}\nLiteral lengths: {literal_lengths:?}\nLiterals: {literals:?}");
+/// Reads the size of the decompressed bytes from the header of data written
+/// by `LZ77Encoded::write`, without reading any of the literals and matches.
+/// Returns `None` if the bytes are too short to have a header.
+pub fn decompressed_size_hint(encoded: &[u8]) -> Option<usize> {
+    if encoded.len() << 3 < LZ77_DECOMPRESSED_SIZE_BITS {
+        return None;
+    }
+
+    BitReader::new(encoded).read_bits_into_u32(LZ77_DECOMPRESSED_SIZE_BITS).map(|size| size as usize)
 }
 
-pub fn lz77_decompress(encoded: LZ77Encoded) -> Vec<u8> {
+/// The number of bits needed to write every offset in a window, which is
+/// `ceil(log2(window_size))` since offsets are written minus one.
+fn offset_bits(window_size: usize) -> usize {
+    (usize::BITS - window_size.saturating_sub(1).leading_zeros()) as usize
+}
+
+/// Decompresses LZ77 literals and matches, returning
+/// `DecodeError::BadMatchOffset` if a match has an offset of `0` or reaches
+/// back before the first byte, which only happens if they're corrupt.
+/// 
+/// The output grows as it's decoded instead of being sized up front from the
+/// match lengths, since those can be anything if the data's corrupt.
+pub fn lz77_decompress(encoded: LZ77Encoded) -> Result<Vec<u8>, DecodeError> {
     let mut decompressed = Vec::new();
+    decompress_onto(encoded, &mut decompressed)?;
+
+    Ok(decompressed)
+}
+
+/// Decompresses data compressed with `lz77_compress_with_dict`. The dictionary
+/// has to be the same one it was compressed with. Matches can reach back into
+/// the dictionary, but not before it.
+pub fn lz77_decompress_with_dict(encoded: LZ77Encoded, dict: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    let mut decompressed = dict.to_vec();
+    decompress_onto(encoded, &mut decompressed)?;
+
+    Ok(decompressed.split_off(dict.len()))
+}
+
+/// Decompresses onto the end of `decompressed`, so matches can reach back into
+/// whatever's already there.
+/// Runs of literals get added all at once, and so do matches that don't
+/// overlap the bytes they're copying into (`offset >= length`). Matches that
+/// do overlap, like a run of the same byte, copy bytes they wrote themselves,
+/// so they get copied in pieces that double each time: first `offset` bytes,
+/// then `2 * offset` and so on.
+fn decompress_onto(encoded: LZ77Encoded, decompressed: &mut Vec<u8>) -> Result<(), DecodeError> {
+    let data = encoded.data;
+    let mut i = 0;
 
-    for data in encoded.data {
-        match data {
-            LZ77Data::Literal(val) => {
-                decompressed.push(val);
+    while i < data.len() {
+        match data[i] {
+            LZ77Data::Literal(_) => {
+                let run_len = data[i..].iter().take_while(|data| matches!(data, LZ77Data::Literal(_))).count();
+                decompressed.extend(data[i..(i + run_len)].iter().map(|data| match data {
+                    LZ77Data::Literal(val) => *val,
+                    LZ77Data::Match(..) => unreachable!()
+                }));
+                i += run_len;
             },
             LZ77Data::Match(length, offset) => {
+                if offset == 0 || offset > decompressed.len() {
+                    return Err(DecodeError::BadMatchOffset(offset));
+                }
                 let start_pos = decompressed.len() - offset;
-                for i in 0..length {
-                    decompressed.push(decompressed[start_pos + i]);
+                if offset >= length {
+                    decompressed.extend_from_within(start_pos..(start_pos + length));
+                } else {
+                    //Everything from start_pos on repeats every offset bytes, so each copy can be as long as all of it
+                    let end_len = decompressed.len() + length;
+                    while decompressed.len() < end_len {
+                        let copy_len = min(end_len - decompressed.len(), decompressed.len() - start_pos);
+                        decompressed.extend_from_within(start_pos..(start_pos + copy_len));
+                    }
                 }
+                i += 1;
             }
         }
     }
 
-    decompressed
+    Ok(())
 }
 
-fn encoded_byte_num(encoded: &LZ77Encoded, match_size_bytes: usize) -> usize {
+/// Estimates the number of bytes the encoded data would take up, if every
+/// literal took up a byte and every match took up `match_size_bytes` bytes.
+pub fn encoded_byte_num(encoded: &LZ77Encoded, match_size_bytes: usize) -> usize {
     let mut encoded_bytes = 0;
 
     for data in &encoded.data{
@@ -242,13 +1013,13 @@ mod tests {
         let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
         //let bytes = "Blah blah blah blah blah!".as_bytes().to_vec();
         let start_time = time::Instant::now();
-        let lz77_encoded = lz77_compress_simple(&bytes, 0xFFFF, 3, 256, 6);
+        let lz77_encoded = lz77_compress_simple(&bytes, 0xFFFF, 3, 256, 6, 256);
         let encoded_num = encoded_byte_num(&lz77_encoded, 3);
         let elapsed_time = start_time.elapsed().as_millis();
         println!("Bytes unencoded:[{}] Bytes encoded:[{encoded_num}] Compression Ratio:[{}]\nTime:[{elapsed_time}]ms Speed:[{}]MB/s", bytes.len(), (encoded_num as f32) / (bytes.len() as f32), ((bytes.len() as f32) / 1000000f32) / ((elapsed_time as f32) / 1000f32));
 
         let start_time = time::Instant::now();
-        let lz77_decoded = lz77_decompress(lz77_encoded);
+        let lz77_decoded = lz77_decompress(lz77_encoded).unwrap();
         let elapsed_time = start_time.elapsed().as_millis();
         println!("Decompression time:[{elapsed_time}]ms Speed:[{}]MB/s", ((lz77_decoded.len() as f32) / 1000000f32) / ((elapsed_time as f32) / 1000f32));
 
@@ -258,4 +1029,604 @@ mod tests {
         }
 
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn lz77_compress_decompress_run() {
+        use crate::lz77::{lz77_compress_simple, encoded_byte_num, LZ77Data};
+
+        let bytes = vec![0x41u8; 1 << 20];
+
+        //With no cap on the match length, the whole run is one literal and one match
+        let lz77_encoded = lz77_compress_simple(&bytes, 0xFFFF, 3, usize::MAX, 6, usize::MAX);
+        assert!(lz77_encoded.data.len() == 2, "Run compressed into [{}] pieces of data instead of 2", lz77_encoded.data.len());
+        assert!(matches!(lz77_encoded.data[0], LZ77Data::Literal(0x41)), "Run didn't start with a literal");
+        assert!(matches!(lz77_encoded.data[1], LZ77Data::Match(length, 1) if length == bytes.len() - 1), "Run wasn't encoded as one match with an offset of 1");
+        assert!(lz77_decompress(lz77_encoded).unwrap() == bytes, "Run compressed and decompressed incorrectly");
+
+        //With a capped match length, the run is split up into the fewest matches possible
+        let max_match_len = 258;
+        let lz77_encoded = lz77_compress_simple(&bytes, 0xFFFF, 3, max_match_len, 6, max_match_len);
+        let encoded_num = encoded_byte_num(&lz77_encoded, 3);
+        assert!(lz77_encoded.data.len() <= bytes.len() / max_match_len + 2, "Run compressed into [{}] pieces of data", lz77_encoded.data.len());
+        assert!(encoded_num < bytes.len() / 64, "Run compressed into [{encoded_num}] bytes");
+        assert!(lz77_decompress(lz77_encoded).unwrap() == bytes, "Run compressed and decompressed incorrectly with a capped match length");
+    }
+
+    #[test]
+    fn lz77_cost_aware_test() {
+        use crate::lz77::{lz77_compress_simple, lz77_compress_cost_aware, lz77_match_worth_it, LZ77Data};
+
+        assert!(lz77_match_worth_it(3, 1), "Short match close by wasn't worth it");
+        assert!(!lz77_match_worth_it(3, 1000), "Short match far away was worth it");
+        assert!(lz77_match_worth_it(258, 32768), "Long match far away wasn't worth it");
+
+        //A short match far back, then a run where the offset is smaller than the minimum match length
+        let mut bytes = b"xyz".to_vec();
+        bytes.extend((0..1000u32).map(|i| (i.wrapping_mul(2654435761) >> 24) as u8 | 0x80));
+        bytes.extend(b"xyz");
+        bytes.extend(vec![0x41u8; 100]);
+
+        let simple = lz77_compress_simple(&bytes, 0xFFFF, 3, 258, 16, 258);
+        let cost_aware = lz77_compress_cost_aware(&bytes, 0xFFFF, 3, 258, 16);
+        let far_match = |data: &LZ77Data| matches!(data, LZ77Data::Match(3, offset) if *offset > 1000);
+        assert!(simple.data.iter().any(far_match), "Simple parse didn't use the far match");
+        assert!(!cost_aware.data.iter().any(far_match), "Cost aware parse used the far match");
+        assert!(matches!(cost_aware.data.last(), Some(LZ77Data::Match(99, 1))), "Cost aware parse didn't use the run [{:?}]", cost_aware.data.last());
+        assert!(lz77_decompress(cost_aware).unwrap() == bytes, "Bytes different after cost aware compression and decompression");
+    }
+
+    #[test]
+    fn lz77_common_prefix_len_test() {
+        use crate::lz77::{common_prefix_len, LZ77MatchFinder, LZ77_DEFAULT_KEY_LEN};
+        use rand::prelude::*;
+        use std::{cmp::min, fs, time};
+
+        fn byte_prefix_len(a: &[u8], b: &[u8]) -> usize {
+            a.iter().zip(b).take_while(|(a, b)| a == b).count()
+        }
+
+        //Random bytes with a lot of copies in them, so there are long matches at every alignment
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(2070);
+        let mut bytes = vec![0u8; 1 << 12];
+        rng.fill_bytes(&mut bytes);
+        for _ in 0..200 {
+            let start = rng.gen_range(0..bytes.len() - 64);
+            let len = rng.gen_range(1..64);
+            bytes.extend_from_within(start..(start + len));
+            bytes.push(rng.gen());
+        }
+
+        let max_match_len = 258;
+        let matcher = LZ77MatchFinder::new(&bytes, 0xFFFF, 3, max_match_len, 16, LZ77_DEFAULT_KEY_LEN);
+        for _ in 0..100000 {
+            let source_pos = rng.gen_range(1..bytes.len());
+            let match_pos = rng.gen_range(0..source_pos);
+            let max_len = min(max_match_len - LZ77_DEFAULT_KEY_LEN, bytes.len() - source_pos);
+            let expected = byte_prefix_len(&bytes[source_pos..(source_pos + max_len)], &bytes[match_pos..]);
+            let actual = matcher.match_len(source_pos, match_pos);
+            assert!(actual == expected, "Match length at [{source_pos}] and [{match_pos}] was [{actual}] instead of [{expected}]");
+        }
+        for (a, b) in [(&b""[..], &b""[..]), (b"abcdefghij", b"abcdefghij"), (b"abcdefghij", b"abcdefghiz"), (b"abcdefgh", b"abcdefgh_"), (b"abcdefgz", b"abcdefgh")] {
+            assert!(common_prefix_len(a, b) == byte_prefix_len(a, b), "Prefix length of [{a:?}] and [{b:?}] was [{}]", common_prefix_len(a, b));
+        }
+
+        //Lorem ipsum against a copy of itself, which is one long match from every position
+        let text = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let copy = text.clone();
+        let start_time = time::Instant::now();
+        let mut byte_total = 0;
+        for start in (0..text.len()).step_by(1000) {
+            byte_total += byte_prefix_len(&text[start..], &copy[start..]);
+        }
+        let byte_time = start_time.elapsed();
+        let start_time = time::Instant::now();
+        let mut word_total = 0;
+        for start in (0..text.len()).step_by(1000) {
+            word_total += common_prefix_len(&text[start..], &copy[start..]);
+        }
+        let word_time = start_time.elapsed();
+        println!("Bytes compared: [{word_total}] Byte at a time: [{:.1}]MB/s Word at a time: [{:.1}]MB/s", word_total as f64 / byte_time.as_secs_f64() / 1000000.0, word_total as f64 / word_time.as_secs_f64() / 1000000.0);
+        assert!(word_total == byte_total, "Word at a time compared [{word_total}] bytes, byte at a time [{byte_total}]");
+    }
+
+    #[test]
+    fn lz77_stats_test() {
+        use crate::lz77::{lz77_compress_simple, lz77_compress_simple_with_stats, LZ77Stats};
+        use std::fs;
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+
+        let mut last_stats = LZ77Stats::default();
+        for max_chain_len in [1, 4, 16, 64] {
+            let (encoded, stats) = lz77_compress_simple_with_stats(&bytes, 0xFFFF, 3, 256, max_chain_len, 256);
+            println!("Max chain length: [{max_chain_len}]\n{stats}");
+
+            assert!(encoded.data() == lz77_compress_simple(&bytes, 0xFFFF, 3, 256, max_chain_len, 256).data(), "Keeping stats changed the literals and matches with a max chain length of [{max_chain_len}]");
+            assert!(stats.matches + stats.literals == encoded.data().len(), "Stats counted [{}] literals and matches instead of [{}]", stats.matches + stats.literals, encoded.data().len());
+            assert!(stats.total_match_len + stats.literals == bytes.len(), "Stats cover [{}] bytes instead of [{}]", stats.total_match_len + stats.literals, bytes.len());
+            assert!(stats.avg_chain_depth() <= max_chain_len as f64, "Average chain depth [{}] is longer than the max chain length [{max_chain_len}]", stats.avg_chain_depth());
+            assert!(stats.chain_nodes > last_stats.chain_nodes, "Max chain length of [{max_chain_len}] walked [{}] chain nodes, fewer than before [{}]", stats.chain_nodes, last_stats.chain_nodes);
+            assert!(stats.avg_match_len() >= last_stats.avg_match_len(), "Max chain length of [{max_chain_len}] found shorter matches on average [{}] than before [{}]", stats.avg_match_len(), last_stats.avg_match_len());
+            last_stats = stats;
+        }
+
+        let (_, stats) = lz77_compress_simple_with_stats(&[], 0xFFFF, 3, 256, 16, 256);
+        assert!(stats == LZ77Stats::default() && stats.avg_chain_depth() == 0.0 && stats.avg_match_len() == 0.0, "Nothing had stats [{stats:?}]");
+    }
+
+    #[test]
+    fn lz77_overlapping_match() {
+        use crate::lz77::{lz77_compress_simple, LZ77Data};
+
+        let bytes = b"aaaaaaaaaaaa".to_vec();
+        let lz77_encoded = lz77_compress_simple(&bytes, 0xFFFF, 3, 258, 6, 258);
+        assert!(lz77_encoded.data.len() == 2, "Short run compressed into [{}] pieces of data instead of 2", lz77_encoded.data.len());
+        assert!(matches!(lz77_encoded.data[1], LZ77Data::Match(11, 1)), "Short run wasn't encoded as one overlapping match [{:?}]", lz77_encoded.data[1]);
+        assert!(lz77_decompress(lz77_encoded).unwrap() == bytes, "Short run compressed and decompressed incorrectly");
+
+        // Longer period, the match still overlaps the bytes it's encoding
+        let bytes = b"abcabcabcabcabcabcab".to_vec();
+        let lz77_encoded = lz77_compress_simple(&bytes, 0xFFFF, 3, 258, 6, 258);
+        assert!(lz77_encoded.data.len() == 4, "Repeating pattern compressed into [{}] pieces of data instead of 4", lz77_encoded.data.len());
+        assert!(matches!(lz77_encoded.data[3], LZ77Data::Match(17, 3)), "Repeating pattern wasn't encoded as one overlapping match [{:?}]", lz77_encoded.data[3]);
+        assert!(lz77_decompress(lz77_encoded).unwrap() == bytes, "Repeating pattern compressed and decompressed incorrectly");
+    }
+
+    #[test]
+    fn lz77_write_read() {
+        use crate::lz77::{lz77_compress_simple, decompressed_size_hint, LZ77Data, LZ77Encoded};
+        use crate::bitstream::{BitWriter, BitReader};
+        use crate::api::{decompress, Codec};
+        use crate::error::DecodeError;
+        use std::fs;
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+
+        for window_size in [1 << 12, 0xFFFF, 1 << 16] {
+            let lz77_encoded = lz77_compress_simple(&bytes, window_size, 3, 256, 6, 256);
+
+            let mut writer = BitWriter::new();
+            lz77_encoded.write(&mut writer, window_size);
+            let written_bytes = writer.get_bytes();
+            println!("Window size: {window_size} Written size: {}", written_bytes.len());
+            assert!(decompressed_size_hint(&written_bytes) == Some(bytes.len()), "Size hint [{:?}] different from the actual size [{}]", decompressed_size_hint(&written_bytes), bytes.len());
+
+            let mut reader = BitReader::new(&written_bytes);
+            let read_encoded = LZ77Encoded::read(&mut reader, window_size).unwrap();
+            assert!(lz77_decompress(read_encoded).unwrap() == bytes, "Bytes different after writing, reading and decompressing with window size [{window_size}]");
+
+            for len in [0, 5, 11, written_bytes.len() / 2, written_bytes.len() - 1] {
+                let read = LZ77Encoded::read(&mut BitReader::new(&written_bytes[..len]), window_size);
+                assert!(matches!(read, Err(DecodeError::UnexpectedEnd)), "Cut off bytes of length [{len}] read as [{:?}]", read.map(|encoded| encoded.data.len()));
+            }
+        }
+
+        //A huge count with nothing after it
+        let mut writer = BitWriter::new();
+        writer.write_bits_u32(0, 32);
+        writer.write_bits_u32(u32::MAX, 32);
+        writer.write_bits_u32(8, 6);
+        let read = LZ77Encoded::read(&mut BitReader::new(&writer.get_bytes()), 0xFFFF);
+        assert!(matches!(read, Err(DecodeError::UnexpectedEnd)), "Huge count with no data read as [{:?}]", read.map(|encoded| encoded.data.len()));
+
+        //Huge matches that add up to way more than the header says get turned down before anything's allocated for them
+        let mut data = vec![LZ77Data::Literal(b'a')];
+        data.extend((0..4000).map(|_| LZ77Data::Match(u32::MAX as usize, 1)));
+        let mut writer = BitWriter::new();
+        writer.write_bits_u32(1, 32);
+        writer.write_bits_u32(data.len() as u32, 32);
+        writer.write_bits_u32(32, 6);
+        for data in &data {
+            match *data {
+                LZ77Data::Literal(byte) => writer.write_bits_u16(byte as u16, 9),
+                LZ77Data::Match(length, offset) => {
+                    writer.write_bits_u16(1, 1);
+                    writer.write_bits_u32(length as u32, 32);
+                    writer.write_bits_u32(offset as u32 - 1, 16);
+                }
+            }
+        }
+        let decompressed = decompress(Codec::Lz77, &writer.get_bytes());
+        assert!(decompressed == Err(DecodeError::BadDecompressedSize { expected: 1, actual: 1 + u32::MAX as usize }), "Matches past the header's size weren't caught [{:?}]", decompressed.map(|bytes| bytes.len()));
+
+        //Adding up to less than the header says is just as wrong
+        let mut writer = BitWriter::new();
+        LZ77Encoded::new(vec![LZ77Data::Literal(b'a'), LZ77Data::Match(3, 1)]).write(&mut writer, 0xFFFF);
+        let mut encoded_bytes = writer.get_bytes();
+        encoded_bytes[3] = 5;
+        let read = LZ77Encoded::read(&mut BitReader::new(&encoded_bytes), 0xFFFF);
+        assert!(matches!(read, Err(DecodeError::BadDecompressedSize { expected: 5, actual: 4 })), "Literals and matches short of the header's size weren't caught [{:?}]", read.map(|encoded| encoded.data.len()));
+
+        assert!(decompressed_size_hint(&[0, 0, 0]).is_none(), "Size hint found in bytes too short to have a header");
+    }
+
+    #[test]
+    fn lz77_to_dot() {
+        use crate::lz77::{lz77_compress_simple, LZ77Data};
+
+        let bytes = b"abcabcabcabcxyz";
+        let lz77_encoded = lz77_compress_simple(bytes, 0xFFFF, 3, 256, 6, 256);
+        assert!(lz77_encoded.data().len() < bytes.len(), "Nothing was matched in [{}]", String::from_utf8_lossy(bytes));
+        let dot = lz77_encoded.to_dot(0xFFFF);
+        println!("{dot}");
+
+        assert!(dot.starts_with("digraph lz77 {") && dot.ends_with("}\n"), "DOT output isn't a digraph");
+        assert!(dot.matches('{').count() == 1 && dot.matches('}').count() == 1, "DOT output has unbalanced braces");
+        assert!(dot.matches('"').count().is_multiple_of(2), "DOT output has an unclosed quote");
+
+        let edge_num = dot.matches(" -> ").count();
+        let match_num = lz77_encoded.data().iter().filter(|data| matches!(data, LZ77Data::Match(_, _))).count();
+        assert!(edge_num == lz77_encoded.data().len(), "[{edge_num}] edges for [{}] literals and matches", lz77_encoded.data().len());
+        assert!(match_num > 0 && dot.matches("match length").count() == match_num, "Wrong number of match edges");
+        assert!(dot.contains(&format!("-> p{} ", bytes.len())), "The last edge doesn't end at the end of the bytes");
+    }
+
+    #[test]
+    fn lz77_dict() {
+        use crate::lz77::{lz77_compress_simple, lz77_compress_with_dict, lz77_decompress_with_dict, encoded_byte_num};
+
+        let dict = br#"{"id": 0, "name": "", "email": "@example.com", "active": true, "roles": ["user"], "created_at": "2023-01-01T00:00:00Z"}"#;
+        let records:[&[u8]; 3] = [
+            br#"{"id": 1742, "name": "Ada", "email": "ada@example.com", "active": true, "roles": ["user"], "created_at": "2023-04-12T09:30:00Z"}"#,
+            br#"{"id": 1743, "name": "Grace", "email": "grace@example.com", "active": false, "roles": ["admin"], "created_at": "2023-04-12T10:00:00Z"}"#,
+            b"nothing like the dictionary at all"
+        ];
+
+        for record in records {
+            let without_dict = lz77_compress_simple(record, 0xFFFF, 3, 256, 16, 256);
+            let with_dict = lz77_compress_with_dict(record, dict, 0xFFFF, 3, 256, 16);
+            let (without_size, with_size) = (encoded_byte_num(&without_dict, 3), encoded_byte_num(&with_dict, 3));
+            println!("Record size: [{}] Without dictionary: [{without_size}] With dictionary: [{with_size}]", record.len());
+
+            assert!(with_dict.decompressed_len() == record.len(), "Dictionary was encoded along with the record");
+            assert!(lz77_decompress_with_dict(with_dict, dict).unwrap() == record, "Record different after compressing and decompressing with a dictionary");
+            if record.starts_with(b"{") {
+                assert!(with_size * 2 < without_size, "Dictionary didn't help much with a record like it, [{with_size}] vs [{without_size}]");
+            }
+        }
+
+        //A dictionary bigger than the window only matches in the last window's worth
+        let big_dict:Vec<u8> = (0..5000).map(|i| (i * 7 % 251) as u8).collect();
+        let record = &big_dict[4900..4990];
+        let with_dict = lz77_compress_with_dict(record, &big_dict, 256, 3, 256, 16);
+        assert!(lz77_decompress_with_dict(with_dict, &big_dict).unwrap() == record, "Record different after compressing and decompressing with a dictionary bigger than the window");
+        let with_dict = lz77_compress_with_dict(&big_dict[..100], &big_dict, 256, 3, 256, 16);
+        assert!(lz77_decompress_with_dict(with_dict, &big_dict).unwrap() == big_dict[..100], "Record different after compressing and decompressing with a dictionary bigger than the window");
+        assert!(lz77_decompress_with_dict(lz77_compress_with_dict(&[], dict, 0xFFFF, 3, 256, 16), dict).unwrap().is_empty(), "Nothing wasn't empty after compressing and decompressing with a dictionary");
+    }
+
+    #[test]
+    fn lz77_train_dictionary() {
+        use crate::lz77::{lz77_compress_simple, lz77_compress_with_dict, lz77_decompress_with_dict, train_dictionary, encoded_byte_num};
+
+        let names = ["Ada", "Grace", "Alan", "Edsger", "Barbara", "Donald", "Margaret", "Ken"];
+        let records:Vec<Vec<u8>> = names.iter().enumerate().map(|(i, name)| {
+            format!(r#"{{"id": {}, "name": "{name}", "email": "{}@example.com", "active": {}, "roles": ["user"], "created_at": "2023-04-{:02}T09:30:00Z"}}"#, 1700 + i * 13, name.to_lowercase(), i % 3 != 0, i + 1).into_bytes()
+        }).collect();
+        let samples:Vec<&[u8]> = records.iter().map(|record| &record[..]).collect();
+
+        let dict = train_dictionary(&samples, 256);
+        println!("Dictionary: [{}]", String::from_utf8_lossy(&dict));
+        assert!(!dict.is_empty() && dict.len() <= 256, "Dictionary has [{}] bytes", dict.len());
+
+        let (mut total_without, mut total_with) = (0, 0);
+        for record in &samples {
+            let without_dict = encoded_byte_num(&lz77_compress_simple(record, 0xFFFF, 3, 256, 16, 256), 3);
+            let with_dict = lz77_compress_with_dict(record, &dict, 0xFFFF, 3, 256, 16);
+            let with_size = encoded_byte_num(&with_dict, 3);
+            assert!(with_size < without_dict, "Trained dictionary didn't help with [{}], [{with_size}] vs [{without_dict}]", String::from_utf8_lossy(record));
+            assert!(lz77_decompress_with_dict(with_dict, &dict).unwrap() == *record, "Record different after compressing and decompressing with a trained dictionary");
+            total_without += without_dict;
+            total_with += with_size;
+        }
+        println!("Without dictionary: [{total_without}] With trained dictionary: [{total_with}]");
+        assert!(total_with * 2 < total_without, "Trained dictionary didn't help much, [{total_with}] vs [{total_without}]");
+
+        //Capped by the size, and nothing to learn from nothing
+        assert!(train_dictionary(&samples, 10).len() == 10, "Dictionary wasn't cut off at the size");
+        assert!(train_dictionary(&[], 256).is_empty() && train_dictionary(&[b"short"], 256).is_empty(), "Dictionary made from nothing");
+    }
+
+    #[test]
+    fn lz77_lazy() {
+        use crate::lz77::{lz77_compress_simple, lz77_compress_lazy, encoded_byte_num};
+        use std::{fs, time};
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+
+        let greedy_num = encoded_byte_num(&lz77_compress_simple(&bytes, 0xFFFF, 3, 256, 16, 256), 3);
+        let start_time = time::Instant::now();
+        let lazy_encoded = lz77_compress_lazy(&bytes, 0xFFFF, 3, 256, 16, 1);
+        println!("Lazy compression time: {:?}", start_time.elapsed());
+        let lazy_num = encoded_byte_num(&lazy_encoded, 3);
+        println!("Bytes encoded greedily:[{greedy_num}] Bytes encoded lazily:[{lazy_num}]");
+
+        assert!(lazy_num < greedy_num, "Lazy matching didn't compress better, {lazy_num} >= {greedy_num}");
+        assert!(lz77_decompress(lazy_encoded).unwrap() == bytes, "LZ77 lazy compression and decompression resulted in different bytes");
+
+        let run = vec![0x41u8; 10000];
+        assert!(lz77_decompress(lz77_compress_lazy(&run, 0xFFFF, 3, 258, 16, 1)).unwrap() == run, "LZ77 lazy compression and decompression of a run resulted in different bytes");
+        assert!(lz77_decompress(lz77_compress_lazy(&[], 0xFFFF, 3, 258, 16, 1)).unwrap().is_empty(), "LZ77 lazy compression and decompression of nothing wasn't empty");
+    }
+
+    #[test]
+    fn lz77_lazy_depth() {
+        use crate::lz77::{lz77_compress_lazy, encoded_byte_num};
+        use std::fs;
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+
+        let mut last_num = usize::MAX;
+        for lazy_depth in 0..=3 {
+            let lazy_encoded = lz77_compress_lazy(&bytes, 0xFFFF, 3, 256, 16, lazy_depth);
+            let lazy_num = encoded_byte_num(&lazy_encoded, 3);
+            println!("Lazy depth: [{lazy_depth}] Bytes encoded: [{lazy_num}]");
+
+            assert!(lazy_num <= last_num, "Lazy depth [{lazy_depth}] compressed worse than the one before it, {lazy_num} > {last_num}");
+            assert!(lz77_decompress(lazy_encoded).unwrap() == bytes, "LZ77 lazy compression with depth [{lazy_depth}] and decompression resulted in different bytes");
+            last_num = lazy_num;
+        }
+    }
+
+    #[test]
+    fn lz77_chain_len() {
+        use crate::lz77::{lz77_compress_simple, encoded_byte_num};
+        use std::fs;
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+
+        let short_chain_num = encoded_byte_num(&lz77_compress_simple(&bytes, 0xFFFF, 3, 256, 1, 256), 3);
+        let long_chain_encoded = lz77_compress_simple(&bytes, 0xFFFF, 3, 256, 64, 256);
+        let long_chain_num = encoded_byte_num(&long_chain_encoded, 3);
+        println!("Bytes encoded with chain length 1:[{short_chain_num}] Bytes encoded with chain length 64:[{long_chain_num}]");
+
+        assert!(long_chain_num <= short_chain_num, "Longer chain compressed worse, {long_chain_num} > {short_chain_num}");
+        assert!(lz77_decompress(long_chain_encoded).unwrap() == bytes, "LZ77 compression and decompression with a long chain resulted in different bytes");
+    }
+
+    #[test]
+    fn lz77_good_match_len() {
+        use crate::lz77::{lz77_compress_simple_with_stats, encoded_byte_num};
+        use rand::prelude::*;
+
+        //A short pattern over and over, with the odd byte changed, so every chain is full of long matches
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(52);
+        let mut bytes:Vec<u8> = b"the same old words again and again, ".iter().copied().cycle().take(1 << 20).collect();
+        for _ in 0..bytes.len() / 64 {
+            let pos = rng.gen_range(0..bytes.len());
+            bytes[pos] = rng.gen();
+        }
+
+        let (full_encoded, full_stats) = lz77_compress_simple_with_stats(&bytes, 0xFFFF, 3, 256, 256, 256);
+        let (good_encoded, good_stats) = lz77_compress_simple_with_stats(&bytes, 0xFFFF, 3, 256, 256, 8);
+
+        let full_num = encoded_byte_num(&full_encoded, 3);
+        let good_num = encoded_byte_num(&good_encoded, 3);
+        println!("Full chain: [{full_num}] bytes, [{}] chain nodes Good match length 8: [{good_num}] bytes, [{}] chain nodes", full_stats.chain_nodes, good_stats.chain_nodes);
+
+        assert!(good_stats.chain_nodes < full_stats.chain_nodes, "Stopping at good matches didn't walk less of the chains, {} >= {}", good_stats.chain_nodes, full_stats.chain_nodes);
+        assert!(full_num <= good_num, "Checking the whole chain compressed worse, {full_num} > {good_num}");
+        assert!(lz77_decompress(good_encoded).unwrap() == bytes, "LZ77 compression and decompression with a low good match length resulted in different bytes");
+    }
+
+    #[test]
+    fn lz77_match_positions_inserted() {
+        use crate::lz77::{lz77_compress_simple, LZ77Data, LZ77_DEFAULT_WINDOW_SIZE};
+        use crate::bitstream::BitWriter;
+        use std::fs;
+
+        //"efZZ" only shows up earlier inside the "abcdefZZ" match, so it can only be found if that match's positions made it into the chains
+        let bytes = b"abcdef12abcdefZZ34efZZ";
+        let encoded = lz77_compress_simple(bytes, 0xFFFF, 3, 256, 16, 256);
+        assert!(matches!(encoded.data().last(), Some(LZ77Data::Match(4, 6))), "Match starting inside an earlier match not found [{encoded}]");
+        assert!(lz77_decompress(encoded).unwrap() == bytes, "LZ77 compression and decompression resulted in different bytes");
+
+        //What lorem_ipsum took back when only the first position of each match was added
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let encoded = lz77_compress_simple(&bytes, LZ77_DEFAULT_WINDOW_SIZE, 3, 256, 16, 256);
+        let mut writer = BitWriter::new();
+        encoded.write(&mut writer, LZ77_DEFAULT_WINDOW_SIZE);
+        let size = writer.get_bytes().len();
+        assert!(size < 30812, "Greedy compression didn't get any better [{size}]");
+        assert!(lz77_decompress(encoded).unwrap() == bytes, "LZ77 compression and decompression of lorem_ipsum resulted in different bytes");
+    }
+
+    #[test]
+    fn lz77_stream() {
+        use crate::lz77::{lz77_compress_simple, LZ77StreamEncoder, LZ77Data, LZ77Encoded};
+        use rand::prelude::*;
+        use std::fs;
+
+        //Words picked at random, so there's lots to match but it doesn't just repeat
+        let lorem = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let words:Vec<&[u8]> = lorem.split(|byte| *byte == b' ').take(2000).collect();
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(2061);
+        let mut bytes = Vec::new();
+        while bytes.len() < 4_000_000 {
+            bytes.extend_from_slice(words[rng.gen_range(0..words.len())]);
+            bytes.push(b' ');
+        }
+
+        let window_size = 4096;
+        let mut encoder = LZ77StreamEncoder::new(window_size, 3, 256, 16);
+        let mut data:Vec<LZ77Data> = Vec::new();
+        let mut max_buffered = 0;
+        for chunk in bytes.chunks(10000) {
+            data.extend(encoder.update(chunk));
+            max_buffered = max_buffered.max(encoder.buffer.len());
+            assert!(encoder.head_map.len() <= encoder.buffer.len(), "Chains have [{}] heads, more than the [{}] bytes kept around", encoder.head_map.len(), encoder.buffer.len());
+        }
+        data.extend(encoder.finish());
+        println!("Most bytes kept around: [{max_buffered}]");
+
+        assert!(max_buffered < 3 * window_size + 10000, "Kept [{max_buffered}] bytes around for a window of [{window_size}]");
+        assert!(data.iter().all(|data| match data {LZ77Data::Match(_, offset) => *offset <= window_size, _ => true}), "Match reached past the window");
+
+        //A prefix, so the one-shot version doesn't take forever
+        let prefix = &bytes[..300000];
+        let mut encoder = LZ77StreamEncoder::new(window_size, 3, 256, 16);
+        let mut prefix_data = Vec::new();
+        for chunk in prefix.chunks(777) {
+            prefix_data.extend(encoder.update(chunk));
+        }
+        prefix_data.extend(encoder.finish());
+        let simple = lz77_compress_simple(prefix, window_size, 3, 256, 16, 256);
+        assert!(format!("{}", LZ77Encoded::new(prefix_data)) == format!("{simple}"), "Streamed LZ77 different from compressing all at once");
+
+        assert!(lz77_decompress(LZ77Encoded::new(data)).unwrap() == bytes, "Streamed LZ77 compression and decompression resulted in different bytes");
+        assert!(LZ77StreamEncoder::new(window_size, 3, 256, 16).finish().is_empty(), "Nothing wasn't empty after streaming");
+    }
+
+    #[test]
+    fn lz77_decompress_bulk() {
+        use crate::lz77::{lz77_compress_simple, LZ77Data, LZ77Encoded};
+        use std::{fs, time};
+
+        //How decompression worked before literal runs and matches were copied all at once
+        fn decompress_byte_by_byte(encoded: &LZ77Encoded) -> Vec<u8> {
+            let mut decompressed = Vec::new();
+            for data in encoded.data() {
+                match *data {
+                    LZ77Data::Literal(val) => decompressed.push(val),
+                    LZ77Data::Match(length, offset) => {
+                        let start_pos = decompressed.len() - offset;
+                        for i in 0..length {
+                            decompressed.push(decompressed[start_pos + i]);
+                        }
+                    }
+                }
+            }
+            decompressed
+        }
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let encoded = lz77_compress_simple(&bytes, 0xFFFF, 3, 256, 16, 256);
+
+        let start_time = time::Instant::now();
+        let byte_by_byte = decompress_byte_by_byte(&encoded);
+        let byte_by_byte_time = start_time.elapsed();
+
+        let start_time = time::Instant::now();
+        let decoded = lz77_decompress(LZ77Encoded::new(encoded.data().to_vec())).unwrap();
+        let bulk_time = start_time.elapsed();
+
+        println!("Byte by byte: [{byte_by_byte_time:?}] [{:.1}]MB/s Bulk: [{bulk_time:?}] [{:.1}]MB/s", bytes.len() as f64 / 1e6 / byte_by_byte_time.as_secs_f64(), bytes.len() as f64 / 1e6 / bulk_time.as_secs_f64());
+        assert!(decoded == byte_by_byte && decoded == bytes, "Bulk decompression different from byte by byte decompression");
+
+        //Overlapping matches, non-overlapping ones and literal runs all mixed together
+        let data = vec![LZ77Data::Literal(b'a'), LZ77Data::Literal(b'b'), LZ77Data::Match(6, 2), LZ77Data::Match(4, 8), LZ77Data::Literal(b'c'), LZ77Data::Match(3, 1), LZ77Data::Match(5, 5)];
+        let encoded = LZ77Encoded::new(data);
+        let decoded = decompress_byte_by_byte(&encoded);
+        assert!(decoded == b"ababababababccccbcccc", "Byte by byte decompression wrong [{}]", String::from_utf8_lossy(&decoded));
+        assert!(lz77_decompress(encoded).unwrap() == decoded, "Bulk decompression different from byte by byte decompression on mixed matches");
+    }
+
+    #[test]
+    fn lz77_decompress_bad_offset() {
+        use crate::lz77::{lz77_decompress_with_dict, LZ77Data, LZ77Encoded};
+        use crate::error::DecodeError;
+
+        //Only 3 bytes to reach back into, then an offset of 0
+        let data = vec![LZ77Data::Literal(b'a'), LZ77Data::Literal(b'b'), LZ77Data::Literal(b'c'), LZ77Data::Match(3, 4)];
+        assert!(lz77_decompress(LZ77Encoded::new(data)) == Err(DecodeError::BadMatchOffset(4)), "Offset past the start wasn't caught");
+        let data = vec![LZ77Data::Literal(b'a'), LZ77Data::Match(3, 0)];
+        assert!(lz77_decompress(LZ77Encoded::new(data)) == Err(DecodeError::BadMatchOffset(0)), "Offset of 0 wasn't caught");
+        assert!(lz77_decompress(LZ77Encoded::new(vec![LZ77Data::Match(1, 1)])) == Err(DecodeError::BadMatchOffset(1)), "Match before any bytes wasn't caught");
+
+        //Right at the start is fine, and so is reaching into a dictionary, but not past it
+        let data = vec![LZ77Data::Literal(b'a'), LZ77Data::Literal(b'b'), LZ77Data::Match(5, 2)];
+        assert!(lz77_decompress(LZ77Encoded::new(data)).as_deref() == Ok(&b"abababa"[..]), "Match reaching back to the first byte wasn't decompressed");
+        let data = vec![LZ77Data::Match(4, 3)];
+        assert!(lz77_decompress_with_dict(LZ77Encoded::new(data.clone()), b"xyz").as_deref() == Ok(&b"xyzx"[..]), "Match into the dictionary wasn't decompressed");
+        assert!(lz77_decompress_with_dict(LZ77Encoded::new(data), b"yz") == Err(DecodeError::BadMatchOffset(3)), "Offset past the dictionary wasn't caught");
+    }
+
+    #[test]
+    fn lz77_key_len() {
+        use crate::lz77::{lz77_compress_with_key_len, LZ77Data, LZ77_DEFAULT_WINDOW_SIZE};
+        use crate::bitstream::BitWriter;
+        use std::{fs, time};
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let mut sizes = Vec::new();
+
+        for key_len in [3, 4] {
+            let start_time = time::Instant::now();
+            let encoded = lz77_compress_with_key_len(&bytes, LZ77_DEFAULT_WINDOW_SIZE, 3, 256, 16, key_len);
+            let time = start_time.elapsed();
+
+            let mut writer = BitWriter::new();
+            encoded.write(&mut writer, LZ77_DEFAULT_WINDOW_SIZE);
+            let size = writer.get_bytes().len();
+            println!("Key length: [{key_len}] Compressed size: [{size}] Time: [{time:?}]");
+            sizes.push(size);
+
+            assert!(encoded.data().iter().all(|data| !matches!(data, LZ77Data::Match(length, _) if *length < key_len)), "Match shorter than the key length of [{key_len}]");
+            assert!(lz77_decompress(encoded).unwrap() == bytes, "Bytes different after compressing and decompressing with a key length of [{key_len}]");
+        }
+        //Same chain length, but the chains aren't full of positions that only share 3 bytes
+        assert!(sizes[1] < sizes[0], "Key length of 4 [{}] didn't compress better than 3 [{}]", sizes[1], sizes[0]);
+
+        //Buffers too short to have a single key in them
+        for len in 0..6 {
+            let short = &bytes[..len];
+            assert!(lz77_decompress(lz77_compress_with_key_len(short, LZ77_DEFAULT_WINDOW_SIZE, 3, 256, 16, 4)).unwrap() == short, "Short buffer of [{len}] bytes different after compressing and decompressing");
+        }
+    }
+
+    #[test]
+    fn lz77_same_output() {
+        use crate::lz77::{lz77_compress_simple, lz77_compress_lazy, LZ77_DEFAULT_WINDOW_SIZE};
+        use crate::bitstream::BitWriter;
+        use crate::checksum::crc32;
+        use std::{fs, time};
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+
+        //The sizes and CRC-32s of what these wrote back when the chains were kept in a HashMap
+        //(greedy's changed since it started adding the positions inside matches to the chains)
+        for (lazy_depth, size, crc) in [(None, 28206, 0xbdf1a885), (Some(1), 24229, 0xd80d36e3)] {
+            let start_time = time::Instant::now();
+            let encoded = match lazy_depth {
+                None => lz77_compress_simple(&bytes, LZ77_DEFAULT_WINDOW_SIZE, 3, 256, 16, 256),
+                Some(lazy_depth) => lz77_compress_lazy(&bytes, LZ77_DEFAULT_WINDOW_SIZE, 3, 256, 16, lazy_depth)
+            };
+            println!("Lazy depth: [{lazy_depth:?}] Time: [{:?}]", start_time.elapsed());
+
+            let mut writer = BitWriter::new();
+            encoded.write(&mut writer, LZ77_DEFAULT_WINDOW_SIZE);
+            let encoded_bytes = writer.get_bytes();
+            assert!(encoded_bytes.len() == size && crc32(&encoded_bytes) == crc, "Output with lazy depth [{lazy_depth:?}] changed, size [{}] CRC-32 [{:08x}]", encoded_bytes.len(), crc32(&encoded_bytes));
+        }
+    }
+
+    #[test]
+    fn lz77_optimal() {
+        use crate::lz77::{lz77_compress_simple, lz77_compress_optimal, LZ77Encoded, LZ77_DEFAULT_WINDOW_SIZE};
+        use crate::bitstream::BitWriter;
+        use std::{fs, time};
+
+        fn written_size(encoded: &LZ77Encoded) -> usize {
+            let mut writer = BitWriter::new();
+            encoded.write(&mut writer, LZ77_DEFAULT_WINDOW_SIZE);
+            writer.get_bytes().len()
+        }
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+
+        let greedy = lz77_compress_simple(&bytes, LZ77_DEFAULT_WINDOW_SIZE, 3, 256, 16, 256);
+        let start_time = time::Instant::now();
+        let optimal = lz77_compress_optimal(&bytes, LZ77_DEFAULT_WINDOW_SIZE, 3, 256, 16);
+        let optimal_time = start_time.elapsed();
+
+        let (greedy_size, optimal_size) = (written_size(&greedy), written_size(&optimal));
+        println!("Greedy size: [{greedy_size}] Optimal size: [{optimal_size}] Optimal time: [{optimal_time:?}]");
+        assert!(optimal_size <= greedy_size, "Optimal parse [{optimal_size}] bigger than greedy parse [{greedy_size}]");
+        assert!(lz77_decompress(greedy).unwrap() == bytes, "Bytes different after greedy compressing and decompressing");
+        assert!(lz77_decompress(optimal).unwrap() == bytes, "Bytes different after optimal compressing and decompressing");
+
+        for short in [&b""[..], b"a", b"aaaa", b"abcabcabc"] {
+            assert!(lz77_decompress(lz77_compress_optimal(short, LZ77_DEFAULT_WINDOW_SIZE, 3, 256, 16)).unwrap() == short, "Short buffer different after optimal compressing and decompressing");
+        }
+    }
+}
@@ -1,19 +1,44 @@
 use crate::bitstream::{BitReader, BitWriter};
-use std::char::MAX;
-use std::collections::HashMap;
+use crate::error::DecodeError;
+use crate::map::{Map, map_with_capacity};
+use core::fmt;
+use alloc::{vec::Vec, vec};
+
+pub(crate) const MIN_CODE_LEN:usize = 9;
+pub(crate) const MAX_CODE_LEN:usize = 12;
+/// The largest maximum code length that can be used. The CLEAR_CODE is
+/// written using one more bit than the maximum code length.
+pub const LZW_MAX_CODE_LEN_LIMIT:usize = 16;
+/// A `max_dict_entries` that never clears the table early, so it's only
+/// cleared once the codes can't get any longer.
+pub const LZW_UNLIMITED_DICT_ENTRIES:usize = usize::MAX;
+const CLEAR_CODE:LzwCode = 256;
+const EOD_CODE:LzwCode = 257;
+const START_CODE:LzwCode = 258;
+
+type LzwCode = u32;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum LzwError {
+    /// A code was read that isn't in the table yet.
+    InvalidCode(LzwCode),
+    /// The encoded bytes ended before the EOD code was read.
+    UnexpectedEnd
+}
 
-const MIN_CODE_LEN:usize = 9;
-const MAX_CODE_LEN:usize = 12;
-const MAX_CODE:u16 = 1 << MAX_CODE_LEN;
-const START_MAX_CODE:u16 = 1 << MIN_CODE_LEN;
-const CLEAR_CODE:u16 = 256;
-const EOD_CODE:u16 = 257;
-const START_CODE:u16 = 258;
+impl fmt::Display for LzwError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LzwError::InvalidCode(code) => write!(f, "Bad compression with symbol {code}"),
+            LzwError::UnexpectedEnd => write!(f, "Encoded bytes ended before the EOD code")
+        }
+    }
+}
 
 #[derive(Clone, Copy)]
 struct LZWEDecompressionTableData {
-    prev: u16,
-    next: u16,
+    prev: LzwCode,
+    next: LzwCode,
     byte: u8
 }
 
@@ -23,8 +48,8 @@ impl LZWEDecompressionTableData {
     }
 }
 
-fn new_lzw_decompression_table()-> Vec<LZWEDecompressionTableData>{
-    let mut table = vec![LZWEDecompressionTableData::new(); MAX_CODE as usize];
+fn new_lzw_decompression_table(max_code: LzwCode)-> Vec<LZWEDecompressionTableData>{
+    let mut table = vec![LZWEDecompressionTableData::new(); max_code as usize];
     for i in 0..=255{
         table[i as usize].byte = i;
     }
@@ -32,6 +57,55 @@ fn new_lzw_decompression_table()-> Vec<LZWEDecompressionTableData>{
     table
 }
 
+/// Which spec's rules decide when the code width grows and when the table
+/// gets cleared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LzwVariant {
+    /// GIF's rules, where the code width grows once a code that needs the 
+    /// extra bit can actually be written, and the table is cleared once every 
+    /// code of the maximum length is used. Note that GIF images pack their 
+    /// codes least significant bit first, while the codes here are always 
+    /// written most significant bit first.
+    Gif,
+    /// TIFF's rules, which use "early change": the code width grows one code 
+    /// sooner than GIF, and the table is cleared two codes before it's full.
+    /// Every stream also starts with a CLEAR_CODE. The output matches TIFF's 
+    /// LZW compression byte for byte.
+    Tiff
+}
+
+impl LzwVariant {
+    /// The value of `next_code` at which the table is full and gets cleared.
+    fn table_limit(&self, max_code_len: usize) -> LzwCode {
+        match self {
+            LzwVariant::Gif => 1 << max_code_len,
+            LzwVariant::Tiff => (1 << max_code_len) - 2
+        }
+    }
+
+    /// Updates the code width after `next_code` was incremented. Returns 
+    /// `true` if the table is full (or has `max_dict_entries` codes) and
+    /// needs to be cleared instead.
+    /// 
+    /// Both the compressor and decompressor count a table entry for every
+    /// code, so calling this after every code keeps them in sync.
+    fn advance(&self, next_code: LzwCode, code_len: &mut usize, max_code_len: usize, max_dict_entries: usize) -> bool {
+        if next_code == self.table_limit(max_code_len) || next_code as usize == max_dict_entries {
+            return true;
+        }
+
+        let grow = match self {
+            LzwVariant::Gif => next_code > 1 << *code_len,
+            LzwVariant::Tiff => next_code >= 1 << *code_len
+        };
+        if grow && *code_len < max_code_len {
+            *code_len += 1;
+        }
+
+        false
+    }
+}
+
 /// LZW compression.
 /// 
 /// In the event of a table overflow, the GIF approach of remaking the table is
@@ -40,52 +114,165 @@ fn new_lzw_decompression_table()-> Vec<LZWEDecompressionTableData>{
 /// This implementation is based on the C implementation found at
 /// https://rosettacode.org/wiki/LZW_compression#C. I think this implementation
 /// is what GIF uses, but I'm not sure.
+/// 
+/// An empty input compresses to an empty output.
 pub fn compress_lzw(bytes: &[u8]) -> Vec<u8> {
-    let mut writer = BitWriter::new();
-    let mut code_len:usize = MIN_CODE_LEN;
-    let mut curr_max_code:u16 = START_MAX_CODE;
-    let mut table:HashMap<(u16, u16), u16> = HashMap::with_capacity(MAX_CODE as usize);
-
-    let mut code = bytes[0] as u16;
-    let mut next_code = START_CODE;
-    
-    for byte in &bytes[1..] {
-        let byte = *byte as u16;
-        
-        //let next_option = table[code as usize].next[byte as usize];
-
-        if let Some(next) = table.get(&(code, byte)){
-            code = *next;
-        } else {
-            //println!("{code}");
-            writer.write_bits_u16(code, code_len);
-            table.insert((code, byte), next_code);
-            code = byte;
-
-            next_code += 1;
-
-            if next_code == curr_max_code {
-                
-                code_len += 1;
-                curr_max_code <<= 1;
-
-                if code_len > MAX_CODE_LEN {
-                    writer.write_bits_u16(CLEAR_CODE, code_len);
-                    
-                    code_len = MIN_CODE_LEN;
-                    curr_max_code = START_MAX_CODE;
-                    next_code = START_CODE;
-
-                    table.clear();
+    compress_lzw_with_params(bytes, MIN_CODE_LEN, MAX_CODE_LEN, LzwVariant::Gif, LZW_UNLIMITED_DICT_ENTRIES)
+}
+
+/// Checks that the given code lengths and dictionary size can actually be
+/// used.
+fn assert_lzw_params(min_code_len: usize, max_code_len: usize, max_dict_entries: usize) {
+    assert!(min_code_len >= MIN_CODE_LEN, "Minimum code length must be at least [{MIN_CODE_LEN}], given [{min_code_len}]");
+    assert!(max_code_len <= LZW_MAX_CODE_LEN_LIMIT, "Maximum code length must be at most [{LZW_MAX_CODE_LEN_LIMIT}], given [{max_code_len}]");
+    assert!(min_code_len <= max_code_len, "Minimum code length [{min_code_len}] larger than maximum code length [{max_code_len}]");
+    assert!(max_dict_entries > START_CODE as usize, "Dictionary needs room for more than the [{START_CODE}] starting codes, given [{max_dict_entries}] entries");
+}
+
+/// LZW compression with configurable code lengths.
+/// 
+/// Codes start out `min_code_len` bits long and grow up to `max_code_len`
+/// bits, after which the table is cleared. Larger maximum code lengths
+/// allow a larger table, which usually compresses large files better.
+/// `variant` decides exactly when the code length grows and the table is
+/// cleared.
+/// 
+/// `max_dict_entries` clears the table as soon as it has that many codes
+/// (counting the bytes, `CLEAR_CODE` and `EOD_CODE`), even if the codes could
+/// still get longer. A small table forgets old strings sooner, which helps
+/// when the data changes as it goes, without making the codes any shorter.
+/// Use `LZW_UNLIMITED_DICT_ENTRIES` to only clear it once it's full.
+/// 
+/// `panic`s if `min_code_len` is less than `9`, `max_code_len` is more than
+/// `LZW_MAX_CODE_LEN_LIMIT`, `min_code_len > max_code_len`, or
+/// `max_dict_entries` doesn't leave room for a single new code.
+pub fn compress_lzw_with_params(bytes: &[u8], min_code_len: usize, max_code_len: usize, variant: LzwVariant, max_dict_entries: usize) -> Vec<u8> {
+    compress_lzw_with_resets(bytes, min_code_len, max_code_len, variant, max_dict_entries).0
+}
+
+/// The same as `compress_lzw_with_params`, but also returns the number of
+/// times the table got full and was cleared (not counting the `CLEAR_CODE`
+/// every stream starts with).
+pub fn compress_lzw_with_resets(bytes: &[u8], min_code_len: usize, max_code_len: usize, variant: LzwVariant, max_dict_entries: usize) -> (Vec<u8>, usize) {
+    let mut encoder = LzwEncoder::with_params(min_code_len, max_code_len, variant, max_dict_entries);
+    let mut encoded = encoder.update(bytes);
+    encoded.extend(encoder.finish_codes());
+
+    (encoded, encoder.resets)
+}
+
+/// LZW compression that takes its input a chunk at a time, so it doesn't all
+/// have to be in memory at once. Feeding the chunks to `update` and then
+/// calling `finish` gives the same bytes as `compress_lzw_with_params` on all
+/// of them together, no matter how they're split up.
+pub struct LzwEncoder {
+    min_code_len: usize,
+    max_code_len: usize,
+    variant: LzwVariant,
+    max_dict_entries: usize,
+    code_len: usize,
+    /// The code for the bytes matched so far, or `None` if nothing's been
+    /// given yet.
+    code: Option<LzwCode>,
+    next_code: LzwCode,
+    table: Map<(LzwCode, LzwCode), LzwCode>,
+    resets: usize,
+    writer: BitWriter
+}
+
+impl Default for LzwEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LzwEncoder {
+    /// Makes an encoder with the same settings as `compress_lzw`.
+    pub fn new() -> Self {
+        Self::with_params(MIN_CODE_LEN, MAX_CODE_LEN, LzwVariant::Gif, LZW_UNLIMITED_DICT_ENTRIES)
+    }
+
+    /// Makes an encoder with the same settings as `compress_lzw_with_params`,
+    /// `panic`ing on the same ones.
+    pub fn with_params(min_code_len: usize, max_code_len: usize, variant: LzwVariant, max_dict_entries: usize) -> Self {
+        assert_lzw_params(min_code_len, max_code_len, max_dict_entries);
+
+        LzwEncoder {
+            min_code_len,
+            max_code_len,
+            variant,
+            max_dict_entries,
+            code_len: min_code_len,
+            code: None,
+            next_code: START_CODE,
+            table: map_with_capacity(1 << max_code_len),
+            resets: 0,
+            writer: BitWriter::new()
+        }
+    }
+
+    /// Compresses another chunk, returning whatever whole bytes of output are
+    /// ready. The last code is held back until the next chunk (or `finish`),
+    /// since it might still get longer.
+    pub fn update(&mut self, chunk: &[u8]) -> Vec<u8> {
+        for byte in chunk {
+            let byte = *byte as LzwCode;
+
+            let Some(code) = self.code else {
+                self.writer.write_bits_u32(CLEAR_CODE, self.code_len);
+                self.code = Some(byte);
+                continue;
+            };
+
+            if let Some(next) = self.table.get(&(code, byte)){
+                self.code = Some(*next);
+            } else {
+                self.writer.write_bits_u32(code, self.code_len);
+                self.table.insert((code, byte), self.next_code);
+                self.code = Some(byte);
+
+                self.next_code += 1;
+
+                if self.variant.advance(self.next_code, &mut self.code_len, self.max_code_len, self.max_dict_entries) {
+                    self.writer.write_bits_u32(CLEAR_CODE, self.code_len);
+                    self.resets += 1;
+
+                    self.code_len = self.min_code_len;
+                    self.next_code = START_CODE;
+
+                    self.table.clear();
                 }
             }
         }
+
+        self.writer.take_bytes()
     }
 
-    writer.write_bits_u16(code,code_len);
-    writer.write_bits_u16(EOD_CODE, code_len);
+    /// Writes the last code and the EOD_CODE, returning the rest of the
+    /// output. If nothing was ever given, there's no output at all.
+    pub fn finish(mut self) -> Vec<u8> {
+        self.finish_codes()
+    }
+
+    fn finish_codes(&mut self) -> Vec<u8> {
+        let Some(code) = self.code.take() else {
+            return Vec::new();
+        };
+
+        self.writer.write_bits_u32(code, self.code_len);
 
-    writer.get_bytes()
+        //The decompressor counts the last code as a table entry too, so the code length has to be updated before the EOD_CODE
+        self.next_code += 1;
+        if self.variant.advance(self.next_code, &mut self.code_len, self.max_code_len, self.max_dict_entries) {
+            self.writer.write_bits_u32(CLEAR_CODE, self.code_len);
+            self.resets += 1;
+            self.code_len = self.min_code_len;
+        }
+        self.writer.write_bits_u32(EOD_CODE, self.code_len);
+
+        self.writer.align_to_byte();
+        self.writer.take_bytes()
+    }
 }
 
 /// LZW decompression.
@@ -96,68 +283,211 @@ pub fn compress_lzw(bytes: &[u8]) -> Vec<u8> {
 /// This implementation is based on the C implementation found at
 /// https://rosettacode.org/wiki/LZW_compression#C. I think this implementation
 /// is what GIF uses, but I'm not sure.
-pub fn decompress_lzw(encoded_bytes: &[u8]) -> Vec<u8> {
-    let mut reader = BitReader::new(encoded_bytes);
+/// 
+/// Returns an error if the encoded bytes are malformed, rather than panicking.
+/// An empty input decompresses to an empty output.
+pub fn decompress_lzw(encoded_bytes: &[u8]) -> Result<Vec<u8>, LzwError> {
+    decompress_lzw_with_params(encoded_bytes, MIN_CODE_LEN, MAX_CODE_LEN, LzwVariant::Gif, LZW_UNLIMITED_DICT_ENTRIES)
+}
+
+/// LZW decompression with configurable code lengths. The code lengths,
+/// variant and dictionary size must be the same ones used in
+/// `compress_lzw_with_params`.
+/// 
+/// `panic`s on the same settings `compress_lzw_with_params` does.
+pub fn decompress_lzw_with_params(encoded_bytes: &[u8], min_code_len: usize, max_code_len: usize, variant: LzwVariant, max_dict_entries: usize) -> Result<Vec<u8>, LzwError> {
+    assert_lzw_params(min_code_len, max_code_len, max_dict_entries);
+
     let mut decoded_bytes = Vec::new();
 
-    let mut code_len = MIN_CODE_LEN;
-    let mut curr_max_code:u16 = START_MAX_CODE;
+    decompress_lzw_with::<LzwError, _>(encoded_bytes, LzwDecoder::with_params(min_code_len, max_code_len, variant, max_dict_entries), |byte| {
+        decoded_bytes.push(byte);
+        Ok(())
+    })?;
+
+    Ok(decoded_bytes)
+}
 
-    let mut table = new_lzw_decompression_table();
+/// LZW decompression into a caller-provided slice, returning the number of
+/// bytes written.
+/// 
+/// Nothing is allocated for the output, so this works with a fixed output
+/// buffer. Returns `DecodeError::OutputTooLarge` if the decompressed bytes 
+/// don't fit in `out`.
+pub fn decompress_lzw_into_slice(encoded_bytes: &[u8], out: &mut [u8]) -> Result<usize, DecodeError> {
+    let mut byte_num = 0;
+
+    decompress_lzw_with::<DecodeError, _>(encoded_bytes, LzwDecoder::new(), |byte| {
+        let slot = out.get_mut(byte_num).ok_or(DecodeError::OutputTooLarge)?;
+        *slot = byte;
+        byte_num += 1;
+        Ok(())
+    })?;
+
+    Ok(byte_num)
+}
 
-    let mut next_code = START_CODE;
+/// Does the actual LZW decompression with `decoder`, handing each decoded
+/// byte to `emit`.
+fn decompress_lzw_with<E, F>(encoded_bytes: &[u8], mut decoder: LzwDecoder, mut emit: F) -> Result<(), E>
+where
+E: From<LzwError>,
+F: FnMut(u8) -> Result<(), E> {
+    if encoded_bytes.is_empty() {
+        return Ok(());
+    }
+
+    let mut reader = BitReader::new(encoded_bytes);
+
+    while !decoder.done {
+        //Read a code from the bit reader. If there's nothing left, the EOD code was never found
+        let code = reader.read_bits_into_u32(decoder.code_len).ok_or(LzwError::UnexpectedEnd)?;
+        decoder.decode_code(code, &mut emit)?;
+    }
+
+    Ok(())
+}
+
+/// LZW decompression that takes its input a chunk at a time, the other half
+/// of `LzwEncoder`. Codes can be split between chunks any which way, the
+/// bits that don't make a whole code yet are kept for the next `update`.
+pub struct LzwDecoder {
+    min_code_len: usize,
+    max_code_len: usize,
+    variant: LzwVariant,
+    max_dict_entries: usize,
+    code_len: usize,
+    next_code: LzwCode,
+    table: Vec<LZWEDecompressionTableData>,
+    /// Bits that have been given but not read yet, at the bottom.
+    bits: u64,
+    bit_num: usize,
+    /// Whether anything was given at all, since no input at all is fine.
+    started: bool,
+    /// Whether the EOD_CODE was read, after which everything is ignored.
+    done: bool
+}
+
+impl Default for LzwDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LzwDecoder {
+    /// Makes a decoder for `compress_lzw` and `LzwEncoder::new`.
+    pub fn new() -> Self {
+        Self::with_params(MIN_CODE_LEN, MAX_CODE_LEN, LzwVariant::Gif, LZW_UNLIMITED_DICT_ENTRIES)
+    }
+
+    /// Makes a decoder for the same code lengths, variant and dictionary size
+    /// something was compressed with, `panic`ing on the same settings
+    /// `compress_lzw_with_params` does.
+    pub fn with_params(min_code_len: usize, max_code_len: usize, variant: LzwVariant, max_dict_entries: usize) -> Self {
+        assert_lzw_params(min_code_len, max_code_len, max_dict_entries);
+
+        LzwDecoder {
+            min_code_len,
+            max_code_len,
+            variant,
+            max_dict_entries,
+            code_len: min_code_len,
+            next_code: START_CODE,
+            table: new_lzw_decompression_table(1 << max_code_len),
+            bits: 0,
+            bit_num: 0,
+            started: false,
+            done: false
+        }
+    }
+
+    /// Decompresses another chunk, returning the bytes decoded from every
+    /// whole code in it so far.
+    pub fn update(&mut self, chunk: &[u8]) -> Result<Vec<u8>, LzwError> {
+        let mut decoded = Vec::new();
+        self.started |= !chunk.is_empty();
+
+        for byte in chunk {
+            if self.done {
+                break;
+            }
+            self.bits = (self.bits << 8) | *byte as u64;
+            self.bit_num += 8;
+
+            while !self.done && self.bit_num >= self.code_len {
+                self.bit_num -= self.code_len;
+                let code = ((self.bits >> self.bit_num) & ((1 << self.code_len) - 1)) as LzwCode;
+                self.decode_code(code, &mut |byte| {
+                    decoded.push(byte);
+                    Ok::<(), LzwError>(())
+                })?;
+            }
+        }
+
+        Ok(decoded)
+    }
+
+    /// Checks that the EOD_CODE was read, returning `LzwError::UnexpectedEnd`
+    /// if it wasn't (unless nothing was ever given).
+    pub fn finish(self) -> Result<(), LzwError> {
+        if self.started && !self.done {
+            return Err(LzwError::UnexpectedEnd);
+        }
+        Ok(())
+    }
+
+    /// Handles a single code, handing the bytes it stands for to `emit`.
+    fn decode_code<E, F>(&mut self, code: LzwCode, emit: &mut F) -> Result<(), E>
+    where
+    E: From<LzwError>,
+    F: FnMut(u8) -> Result<(), E> {
+        let max_code:LzwCode = 1 << self.max_code_len;
+        let table = &mut self.table;
 
-    loop {
-        //Read a code from the bit reader. This should never panic.
-        let code = reader.read_bits_into_u16(code_len).unwrap();
-        
         //If the EOD code is read, you reached the end of the encoded data, so exit
-        if code == EOD_CODE { 
-            break; 
+        if code == EOD_CODE {
+            self.done = true;
+            return Ok(());
         }
         //If the CLEAR_CODE code is read, restart the table
         if code == CLEAR_CODE {
-            table = new_lzw_decompression_table();
-            code_len = MIN_CODE_LEN;
-            curr_max_code = START_MAX_CODE;
-            next_code = START_CODE;
-            continue;
+            *table = new_lzw_decompression_table(max_code);
+            self.code_len = self.min_code_len;
+            self.next_code = START_CODE;
+            return Ok(());
         }
 
-        //The read code should never be larger than the next code
-        if code >= next_code {
-            panic!("Bad compression with symbol {code}");
+        //The read code should never be larger than the next code, and the table should never overflow (or pass the cap) without a CLEAR_CODE
+        if code >= self.next_code || self.next_code >= max_code || self.next_code as usize >= self.max_dict_entries {
+            return Err(LzwError::InvalidCode(code).into());
         }
 
         let mut curr = code;
-        table[next_code as usize].prev = code;
+        table[self.next_code as usize].prev = code;
 
         //While the current code isn't a byte
-        while curr > u8::MAX as u16 {
+        while curr > u8::MAX as LzwCode {
             let tmp = table[curr as usize].prev;
             table[tmp as usize].next = curr;
             curr = tmp;
         }
 
-        table[(next_code as usize) - 1].byte = curr as u8;
+        table[(self.next_code as usize) - 1].byte = curr as u8;
 
         while table[curr as usize].next > 0{
-            decoded_bytes.push(table[curr as usize].byte);
+            emit(table[curr as usize].byte)?;
             let tmp = table[curr as usize].next;
             table[curr as usize].next = 0;
             curr = tmp;
         }
-        decoded_bytes.push(table[curr as usize].byte);
+        emit(table[curr as usize].byte)?;
 
-        next_code += 1;
-        if next_code >= curr_max_code {
-            code_len += 1;
-            curr_max_code <<= 1;
-        }
+        //If the table is full, the next code read will be a CLEAR_CODE
+        self.next_code += 1;
+        self.variant.advance(self.next_code, &mut self.code_len, self.max_code_len, self.max_dict_entries);
 
+        Ok(())
     }
-
-    decoded_bytes
 }
 
 #[cfg(test)]
@@ -206,7 +536,7 @@ mod tests{
         println!("Bytes unencoded: [{}] Bytes encoded:[{}] Compression ratio:[{}]\nTime:[{}]ms Speed:[{}]MB/s",bytes.len(), encoded_bytes.len(), (encoded_bytes.len() as f32) / (bytes.len() as f32), elapsed_time, ((bytes.len() as f32) / 1000f32) / (elapsed_time as f32));
         //println!("{encoded_bytes:?}");
         let start_time = time::Instant::now();
-        let decoded_bytes = decompress_lzw(&encoded_bytes);
+        let decoded_bytes = decompress_lzw(&encoded_bytes).unwrap();
         let elapsed_time = start_time.elapsed().as_millis();
 
         println!("Decompression time:[{}]ms Speed:[{}]MB/s", elapsed_time, ((encoded_bytes.len() as f32) / 1000f32) / (elapsed_time as f32));
@@ -214,4 +544,205 @@ mod tests{
         assert!(decoded_bytes.len() == bytes.len(), "Number of bytes changed during compression and decompression.");
         assert!(bytes.iter().zip(&decoded_bytes).all(|(a,b)| *a == *b), "Bytes compressed and decompressed incorrectly");
     }
-}
\ No newline at end of file
+
+    #[test]
+    pub fn lzw_empty_test() {
+        use crate::lzw::{compress_lzw, decompress_lzw};
+
+        let encoded_bytes = compress_lzw(&[]);
+        assert!(encoded_bytes.is_empty(), "Empty input should compress to nothing");
+        assert!(decompress_lzw(&encoded_bytes).unwrap().is_empty(), "Empty input should decompress to nothing");
+    }
+
+    #[test]
+    pub fn lzw_corrupt_test() {
+        use crate::lzw::decompress_lzw;
+        use rand::prelude::*;
+
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(2123);
+        for _ in 0..64 {
+            let byte_num = rng.gen_range(1..=4096);
+            let bytes: Vec<u8> = (0..byte_num).map(|_| rng.gen::<u8>()).collect();
+            assert!(decompress_lzw(&bytes).is_err(), "Random bytes decompressed without an error");
+        }
+    }
+
+    #[test]
+    pub fn lzw_into_slice_test() {
+        use crate::lzw::{compress_lzw, decompress_lzw_into_slice};
+        use crate::error::DecodeError;
+        use std::fs;
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let encoded_bytes = compress_lzw(&bytes);
+
+        let mut out = vec![0; bytes.len()];
+        let byte_num = decompress_lzw_into_slice(&encoded_bytes, &mut out).unwrap();
+        assert!(byte_num == bytes.len(), "Wrong number of bytes decompressed into slice");
+        assert!(out == bytes, "Bytes compressed and decompressed into slice incorrectly");
+
+        let mut out = vec![0; bytes.len() - 1];
+        assert!(decompress_lzw_into_slice(&encoded_bytes, &mut out) == Err(DecodeError::OutputTooLarge), "Undersized slice should error");
+    }
+
+    #[test]
+    pub fn lzw_params_test() {
+        use crate::lzw::{compress_lzw_with_params, decompress_lzw_with_params, LzwVariant, LZW_UNLIMITED_DICT_ENTRIES};
+        use std::fs;
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+
+        for max_code_len in [12, 14, 16] {
+            let encoded_bytes = compress_lzw_with_params(&bytes, 9, max_code_len, LzwVariant::Gif, LZW_UNLIMITED_DICT_ENTRIES);
+            println!("Max code length:[{max_code_len}] Bytes unencoded:[{}] Bytes encoded:[{}] Compression ratio:[{}]", bytes.len(), encoded_bytes.len(), (encoded_bytes.len() as f32) / (bytes.len() as f32));
+
+            let decoded_bytes = decompress_lzw_with_params(&encoded_bytes, 9, max_code_len, LzwVariant::Gif, LZW_UNLIMITED_DICT_ENTRIES).unwrap();
+            assert!(decoded_bytes == bytes, "Bytes compressed and decompressed incorrectly with max code length {max_code_len}");
+        }
+    }
+
+    #[test]
+    pub fn lzw_variant_test() {
+        use crate::lzw::{compress_lzw_with_params, decompress_lzw_with_params, LzwVariant, LZW_UNLIMITED_DICT_ENTRIES};
+        use std::fs;
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+
+        for variant in [LzwVariant::Gif, LzwVariant::Tiff] {
+            //Every length up to 1024 ends the stream at a different point relative to the code length changes
+            for len in (1..1024).chain([bytes.len()]) {
+                let encoded_bytes = compress_lzw_with_params(&bytes[..len], 9, 12, variant, LZW_UNLIMITED_DICT_ENTRIES);
+                let decoded_bytes = decompress_lzw_with_params(&encoded_bytes, 9, 12, variant, LZW_UNLIMITED_DICT_ENTRIES).unwrap();
+                assert!(decoded_bytes == bytes[..len], "Bytes compressed and decompressed incorrectly with variant {variant:?} and length {len}");
+            }
+        }
+    }
+
+    #[test]
+    pub fn lzw_tiff_snippet_test() {
+        use crate::lzw::{compress_lzw_with_params, decompress_lzw_with_params, LzwVariant, LZW_UNLIMITED_DICT_ENTRIES};
+
+        //"TOBEORNOTTOBEORTOBEORNOT" compressed following libtiff's LZW encoder
+        let tiff_bytes = [0x80, 0x15, 0x09, 0xE4, 0x22, 0x29, 0x3C, 0xA4, 0x4E, 0x27, 0x95, 0x20, 0x50, 0x48, 0x34, 0x2E, 0x0B, 0x07, 0x84, 0xC0, 0x40];
+        let bytes = "TOBEORNOTTOBEORTOBEORNOT".as_bytes();
+
+        assert!(decompress_lzw_with_params(&tiff_bytes, 9, 12, LzwVariant::Tiff, LZW_UNLIMITED_DICT_ENTRIES).unwrap() == bytes, "TIFF snippet decompressed incorrectly");
+        assert!(compress_lzw_with_params(bytes, 9, 12, LzwVariant::Tiff, LZW_UNLIMITED_DICT_ENTRIES) == tiff_bytes, "TIFF snippet compressed differently than TIFF");
+    }
+
+    #[test]
+    pub fn lzw_code_len_boundary_test() {
+        use crate::lzw::{compress_lzw_with_resets, decompress_lzw_with_params, LzwVariant, START_CODE, LZW_UNLIMITED_DICT_ENTRIES};
+        use rand::prelude::*;
+
+        //Random bytes almost never repeat a pair, so nearly every byte adds a
+        //table entry, which makes it easy to land right on each code length change
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(3141);
+        let bytes:Vec<u8> = (0..20000).map(|_| rng.gen::<u8>()).collect();
+
+        for variant in [LzwVariant::Gif, LzwVariant::Tiff] {
+            for (max_code_len, max_resets) in [(12, 4), (14, 1)] {
+                for boundary in (10..=max_code_len).map(|code_len| 1usize << code_len) {
+                    let entries = boundary - START_CODE as usize;
+                    for len in (entries - 8)..(entries + 8) {
+                        let (encoded_bytes, _) = compress_lzw_with_resets(&bytes[..len], 9, max_code_len, variant, LZW_UNLIMITED_DICT_ENTRIES);
+                        let decoded_bytes = decompress_lzw_with_params(&encoded_bytes, 9, max_code_len, variant, LZW_UNLIMITED_DICT_ENTRIES).unwrap();
+                        assert!(decoded_bytes == bytes[..len], "Bytes compressed and decompressed incorrectly with variant {variant:?}, max code length {max_code_len} and length {len}");
+                    }
+                }
+
+                //Crossing every code length change several times over
+                let (encoded_bytes, resets) = compress_lzw_with_resets(&bytes, 9, max_code_len, variant, LZW_UNLIMITED_DICT_ENTRIES);
+                assert!(resets >= max_resets, "Table only reset [{resets}] times with variant {variant:?} and max code length {max_code_len}");
+                let decoded_bytes = decompress_lzw_with_params(&encoded_bytes, 9, max_code_len, variant, LZW_UNLIMITED_DICT_ENTRIES).unwrap();
+                assert!(decoded_bytes == bytes, "Bytes compressed and decompressed incorrectly with variant {variant:?} and max code length {max_code_len}");
+            }
+        }
+    }
+
+    #[test]
+    pub fn lzw_dict_cap_test() {
+        use crate::lzw::{compress_lzw_with_resets, decompress_lzw_with_params, LzwEncoder, LzwDecoder, LzwError, LzwVariant, LZW_UNLIMITED_DICT_ENTRIES};
+        use std::fs;
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+
+        for variant in [LzwVariant::Gif, LzwVariant::Tiff] {
+            let (_, full_resets) = compress_lzw_with_resets(&bytes, 9, 12, variant, LZW_UNLIMITED_DICT_ENTRIES);
+            let mut last_resets = full_resets;
+            //Smaller and smaller tables, with codes that still grow to 12 bits (or would, for the biggest one).
+            //The powers of two fill up right as the codes would need another bit.
+            for max_dict_entries in [3000, 1024, 1000, 512, 300] {
+                let (encoded_bytes, resets) = compress_lzw_with_resets(&bytes, 9, 12, variant, max_dict_entries);
+                println!("Variant:[{variant:?}] Max entries:[{max_dict_entries}] Resets:[{resets}] Bytes encoded:[{}]", encoded_bytes.len());
+                assert!(resets > last_resets, "Table with [{max_dict_entries}] entries only reset [{resets}] times, not more than [{last_resets}]");
+                last_resets = resets;
+
+                let decoded_bytes = decompress_lzw_with_params(&encoded_bytes, 9, 12, variant, max_dict_entries).unwrap();
+                assert!(decoded_bytes == bytes, "Bytes compressed and decompressed incorrectly with variant {variant:?} and [{max_dict_entries}] entries");
+
+                let mut encoder = LzwEncoder::with_params(9, 12, variant, max_dict_entries);
+                let mut streamed = encoder.update(&bytes);
+                streamed.extend(encoder.finish());
+                assert!(streamed == encoded_bytes, "Streamed LZW with [{max_dict_entries}] entries different from compressing all at once");
+            }
+        }
+
+        //A table bigger than the codes allow is the same as no cap at all
+        let (encoded_bytes, _) = compress_lzw_with_resets(&bytes, 9, 12, LzwVariant::Gif, 1 << 12);
+        assert!(encoded_bytes == compress_lzw_with_resets(&bytes, 9, 12, LzwVariant::Gif, LZW_UNLIMITED_DICT_ENTRIES).0, "Cap of a full table changed the output");
+
+        //Going past a smaller cap than the one the stream was made with is caught
+        let (encoded_bytes, _) = compress_lzw_with_resets(&bytes, 9, 12, LzwVariant::Gif, 1000);
+        let mut decoder = LzwDecoder::with_params(9, 12, LzwVariant::Gif, 500);
+        assert!(matches!(decoder.update(&encoded_bytes), Err(LzwError::InvalidCode(_))), "Codes past the cap weren't caught");
+    }
+
+    #[test]
+    pub fn lzw_streaming_test() {
+        use crate::lzw::{compress_lzw, compress_lzw_with_params, LzwEncoder, LzwDecoder, LzwError, LzwVariant, LZW_UNLIMITED_DICT_ENTRIES};
+        use std::fs;
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let encoded_bytes = compress_lzw(&bytes);
+
+        let mut encoder = LzwEncoder::new();
+        let mut streamed = Vec::new();
+        for chunk in bytes.chunks(1024) {
+            streamed.extend(encoder.update(chunk));
+        }
+        streamed.extend(encoder.finish());
+        assert!(streamed == encoded_bytes, "Streamed LZW compression different from compressing all at once");
+
+        //Codes split between chunks, and more than one table reset
+        let mut decoder = LzwDecoder::new();
+        let mut decoded_bytes = Vec::new();
+        for chunk in encoded_bytes.chunks(1000) {
+            decoded_bytes.extend(decoder.update(chunk).unwrap());
+        }
+        assert!(decoder.finish().is_ok(), "Streamed LZW decompression didn't find the end");
+        assert!(decoded_bytes == bytes, "Streamed LZW decompression resulted in different bytes");
+
+        //TIFF's rules, a byte at a time
+        let encoded_bytes = compress_lzw_with_params(&bytes, 9, 10, LzwVariant::Tiff, LZW_UNLIMITED_DICT_ENTRIES);
+        let mut encoder = LzwEncoder::with_params(9, 10, LzwVariant::Tiff, LZW_UNLIMITED_DICT_ENTRIES);
+        let mut decoder = LzwDecoder::with_params(9, 10, LzwVariant::Tiff, LZW_UNLIMITED_DICT_ENTRIES);
+        let mut streamed = Vec::new();
+        let mut decoded_bytes = Vec::new();
+        for byte in &bytes {
+            let encoded = encoder.update(&[*byte]);
+            decoded_bytes.extend(decoder.update(&encoded).unwrap());
+            streamed.extend(encoded);
+        }
+        let encoded = encoder.finish();
+        decoded_bytes.extend(decoder.update(&encoded).unwrap());
+        streamed.extend(encoded);
+        assert!(streamed == encoded_bytes && decoded_bytes == bytes, "Streamed TIFF LZW different from doing it all at once");
+
+        assert!(LzwEncoder::new().finish().is_empty() && LzwDecoder::new().finish().is_ok(), "Nothing wasn't empty after streaming");
+        let mut decoder = LzwDecoder::new();
+        let encoded_bytes = compress_lzw(b"Not all of it");
+        decoder.update(&encoded_bytes[..encoded_bytes.len() - 1]).unwrap();
+        assert!(decoder.finish() == Err(LzwError::UnexpectedEnd), "Truncated stream finished fine");
+    }
+}
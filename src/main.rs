@@ -1,53 +1,203 @@
-mod huffman;
-mod bitstream;
-mod lzw;
-mod lz;
-mod lz77;
-
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Instant;
 use clap::{Parser, Subcommand};
+use lz_compression::api::{compress as compress_bytes, decompress as decompress_bytes, Codec};
+use lz_compression::batch::{collect_files, compress_files, compressed_path};
+use lz_compression::bench::{self, BenchResult};
+use lz_compression::checksum::Checksum;
+use lz_compression::container::{compress_and_pack_with_checksum, unpack};
+use lz_compression::stats::{codec_stats, CompressionStats};
+use lz_compression::verify::check_with;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
-    #[arg(long)]
-    two: String,
-    #[arg(long)]
-    one: String,
+    #[command(subcommand)]
+    command: Commands,
 }
 
 #[derive(Subcommand)]
 enum Commands {
-    /// does testing things
-    Test {
-        /// lists test values
+    /// Compresses files into checksummed containers
+    Compress {
+        /// The codec to use: huffman, lzw, lz77 or deflate
+        #[arg(short, long, default_value_t = Codec::Lz77)]
+        codec: Codec,
+        /// The checksum the container uses to catch corruption: none, crc32
+        /// or adler32
+        #[arg(long, default_value_t = Checksum::Crc32)]
+        checksum: Checksum,
+        /// The files to compress, or directories if --recursive is set
+        #[arg(short, long, num_args = 1.., required = true)]
+        input: Vec<PathBuf>,
+        /// Where to write the compressed file, which only works with a single
+        /// input. Defaults to the input path with .lz on the end
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Compresses every file under any directories given
+        #[arg(short, long)]
+        recursive: bool,
+        /// The number of threads to compress multiple files with, 0 uses as
+        /// many as there are cores
+        #[arg(short = 'j', long, default_value_t = 0)]
+        threads: usize,
+        /// Prints a detailed breakdown of the compression, for a single input
+        #[arg(long)]
+        stats: bool,
+    },
+    /// Decompresses a container made by compress
+    Decompress {
+        /// The codec the input should be using. Containers already say which
+        /// one they use, so this is just a check
+        #[arg(short, long)]
+        codec: Option<Codec>,
+        /// The compressed file
+        #[arg(short, long)]
+        input: PathBuf,
+        /// Where to write the decompressed file
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Compresses and decompresses a file with every codec, printing the
+    /// ratio and speed of each
+    Bench {
+        /// The file to benchmark with
+        #[arg(short, long)]
+        input: PathBuf,
+    },
+    /// Checks that a codec decompresses a file back to what it was, without
+    /// writing anything
+    Verify {
+        /// The codec to check: huffman, lzw, lz77 or deflate
+        #[arg(short, long)]
+        codec: Codec,
+        /// The file to check with
         #[arg(short, long)]
-        list: bool,
+        input: PathBuf,
     },
 }
 
-fn main() {
-    
-    //use std::{env, fs, time};
-    //use crate::huffman::{encode_bytes_huffman, decode_bytes_huffman};
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let result = match cli.command {
+        Commands::Compress { codec, checksum, input, output, recursive, threads, stats } => compress(codec, checksum, &input, output, recursive, threads, stats),
+        Commands::Decompress { codec, input, output } => decompress(codec, &input, &output),
+        Commands::Bench { input } => run_bench(&input),
+        Commands::Verify { codec, input } => verify(codec, &input),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn ratio(input_size: usize, output_size: usize) -> f64 {
+    if output_size == 0 {0.0} else {input_size as f64 / output_size as f64}
+}
+
+fn compress(codec: Codec, checksum: Checksum, inputs: &[PathBuf], output: Option<PathBuf>, recursive: bool, threads: usize, stats: bool) -> Result<(), String> {
+    //A single file gets compressed right here, so there's one place to time it
+    if let [input] = inputs {
+        if !input.is_dir() {
+            return compress_single(codec, checksum, input, output.unwrap_or_else(|| compressed_path(input)), stats);
+        }
+    }
+
+    if output.is_some() {
+        return Err("--output only works with a single input file".to_string());
+    }
+    if stats {
+        return Err("--stats only works with a single input file".to_string());
+    }
+
+    let files = collect_files(inputs, recursive).map_err(|err| err.to_string())?;
+    let start_time = Instant::now();
+    let results = compress_files(&files, codec, checksum, threads);
+    let time = start_time.elapsed();
+
+    let mut failed = 0;
+    for (file, result) in files.iter().zip(results) {
+        match result.and_then(|output| Ok((fs::metadata(file)?.len(), fs::metadata(&output)?.len(), output))) {
+            Ok((input_size, output_size, output)) => eprintln!("{} -> {} Ratio: [{:.3}]", file.display(), output.display(), ratio(input_size as usize, output_size as usize)),
+            Err(err) => {
+                eprintln!("{}: {err}", file.display());
+                failed += 1;
+            }
+        }
+    }
+    eprintln!("Compressed [{}] files Time: [{time:?}]", files.len() - failed);
+
+    if failed > 0 {
+        return Err(format!("[{failed}] files couldn't be compressed"));
+    }
+    Ok(())
+}
+
+fn compress_single(codec: Codec, checksum: Checksum, input: &Path, output: PathBuf, stats: bool) -> Result<(), String> {
+    let bytes = fs::read(input).map_err(|err| format!("{}: {err}", input.display()))?;
+
+    let start_time = Instant::now();
+    let packed = compress_and_pack_with_checksum(codec, checksum, &bytes);
+    let time = start_time.elapsed();
+
+    fs::write(&output, &packed).map_err(|err| format!("{}: {err}", output.display()))?;
+    eprintln!("{} -> {} Ratio: [{:.3}] Time: [{time:?}]", input.display(), output.display(), ratio(bytes.len(), packed.len()));
+
+    if stats {
+        let stats = CompressionStats { input_size: bytes.len(), output_size: packed.len(), time, codec: codec_stats(codec, &bytes) };
+        println!("{stats}");
+    }
+
+    Ok(())
+}
+
+fn decompress(codec: Option<Codec>, input: &Path, output: &Path) -> Result<(), String> {
+    let bytes = fs::read(input).map_err(|err| format!("{}: {err}", input.display()))?;
+
+    let start_time = Instant::now();
+    let (used_codec, decompressed) = unpack(&bytes).map_err(|err| format!("{}: {err}", input.display()))?;
+    let time = start_time.elapsed();
 
+    if let Some(codec) = codec {
+        if codec != used_codec {
+            return Err(format!("{} was compressed with {used_codec}, not {codec}", input.display()));
+        }
+    }
 
-    // let contents = fs::read("enwik8").expect("File could not be opened and/or read");
+    fs::write(output, &decompressed).map_err(|err| format!("{}: {err}", output.display()))?;
+    eprintln!("{} -> {} Ratio: [{:.3}] Time: [{time:?}]", input.display(), output.display(), ratio(decompressed.len(), bytes.len()));
+
+    Ok(())
+}
+
+fn run_bench(input: &Path) -> Result<(), String> {
+    let results = bench::run(input).map_err(|err| format!("{}: {err}", input.display()))?;
+
+    println!("{}", BenchResult::table_header());
+    for result in &results {
+        println!("{result}");
+    }
+
+    let failed:Vec<&str> = results.iter().filter(|result| !result.roundtrip).map(|result| result.codec.name()).collect();
+    if !failed.is_empty() {
+        return Err(format!("{} didn't decompress back to the input", failed.join(", ")));
+    }
+    Ok(())
+}
 
-    // let start_time = time::Instant::now();
-    // let encoded_bytes = encode_bytes_huffman(&contents, 1<<18, 11);
-    // let elapsed_time = start_time.elapsed().as_millis();
+fn verify(codec: Codec, input: &Path) -> Result<(), String> {
+    let bytes = fs::read(input).map_err(|err| format!("{}: {err}", input.display()))?;
 
-    // println!("Bytes unencoded: [{}] Bytes encoded:[{}] Compression ratio:[{}]\nTime:[{}]ms Speed:[{}]MB/s",contents.len(), encoded_bytes.len(), (encoded_bytes.len() as f32) / (contents.len() as f32), elapsed_time, ((contents.len() as f32) / 1000f32) / (elapsed_time as f32));
-    
-    // let start_time = time::Instant::now();
-    // let decoded_bytes = decode_bytes_huffman(&encoded_bytes);
-    // let elapsed_time = start_time.elapsed().as_millis();
+    let compressed_size = check_with(&bytes, |bytes| compress_bytes(codec, bytes), |compressed| decompress_bytes(codec, compressed))
+        .map_err(|mismatch| format!("{} didn't roundtrip with {codec}: {mismatch}", input.display()))?;
+    eprintln!("{} roundtrips with {codec} Ratio: [{:.3}]", input.display(), ratio(bytes.len(), compressed_size));
 
-    // println!("Decompression time:[{}]ms Speed:[{}]MB/s", elapsed_time, ((encoded_bytes.len() as f32) / 1000f32) / (elapsed_time as f32));
-    
-    // assert!(contents.len() == decoded_bytes.len(), "Number of bytes different after encoding and decoding");
-    // for i in 0..contents.len(){
-    //     assert!(contents[i] == decoded_bytes[i], "Bytes different after encoding and decoding");
-    // }
+    Ok(())
 }
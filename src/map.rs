@@ -0,0 +1,21 @@
+//! The map LZW and LZ77 keep their tables in. With `std` it's a `HashMap`,
+//! without it there's no hasher to seed one with, so it's a `BTreeMap` from
+//! `alloc` instead. That's slower, but it has the same API for everything
+//! they need.
+
+#[cfg(feature = "std")]
+pub(crate) type Map<K, V> = std::collections::HashMap<K, V>;
+#[cfg(not(feature = "std"))]
+pub(crate) type Map<K, V> = alloc::collections::BTreeMap<K, V>;
+
+/// An empty map with room for `capacity` entries, if the map can reserve
+/// room ahead of time (a `BTreeMap` can't).
+#[cfg(feature = "std")]
+pub(crate) fn map_with_capacity<K, V>(capacity: usize) -> Map<K, V> {
+    Map::with_capacity(capacity)
+}
+
+#[cfg(not(feature = "std"))]
+pub(crate) fn map_with_capacity<K: Ord, V>(_capacity: usize) -> Map<K, V> {
+    Map::new()
+}
@@ -0,0 +1,75 @@
+use alloc::vec::Vec;
+/// The symbol list both sides start with, which is just every byte in order.
+fn initial_symbols() -> [u8; 256] {
+    let mut symbols = [0u8; 256];
+    for (i, symbol) in symbols.iter_mut().enumerate() {
+        *symbol = i as u8;
+    }
+    symbols
+}
+
+/// Move-to-front transform. Every byte is replaced by where it currently is in
+/// a list of all 256 bytes, and then gets moved to the front of the list.
+///
+/// Bytes that showed up recently get small indices, so anything with a lot of
+/// local repetition (like the output of a BWT) turns into mostly small values,
+/// which `HuffmanEncoder` can then give short codes. The output is the same
+/// length as the input.
+pub fn mtf_encode(bytes: &[u8]) -> Vec<u8> {
+    let mut symbols = initial_symbols();
+
+    bytes.iter().map(|&byte| {
+        let index = symbols.iter().position(|&symbol| symbol == byte).expect("Every byte is in the symbol list");
+        //Shift everything before it back one, and put it at the front
+        symbols.copy_within(0..index, 1);
+        symbols[0] = byte;
+        index as u8
+    }).collect()
+}
+
+/// The inverse of `mtf_encode`. Every index is valid, so this can't fail.
+pub fn mtf_decode(indices: &[u8]) -> Vec<u8> {
+    let mut symbols = initial_symbols();
+
+    indices.iter().map(|&index| {
+        let index = index as usize;
+        let byte = symbols[index];
+        symbols.copy_within(0..index, 1);
+        symbols[0] = byte;
+        byte
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mtf::{mtf_encode, mtf_decode};
+
+    #[test]
+    fn mtf_test() {
+        use std::fs;
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let encoded = mtf_encode(&bytes);
+        assert!(encoded.len() == bytes.len(), "Encoded length [{}] different from input length [{}]", encoded.len(), bytes.len());
+        assert!(mtf_decode(&encoded) == bytes, "Bytes different after encoding and decoding");
+
+        let every_byte:Vec<u8> = (0..=255).rev().chain(0..=255).collect();
+        assert!(mtf_decode(&mtf_encode(&every_byte)) == every_byte, "Every byte different after encoding and decoding");
+        assert!(mtf_encode(&[]).is_empty() && mtf_decode(&[]).is_empty(), "Nothing wasn't empty after encoding and decoding");
+    }
+
+    #[test]
+    fn mtf_repetitive_test() {
+        //Runs of the same byte, like what a BWT tends to spit out
+        let mut bytes = Vec::new();
+        for i in 0..1000 {
+            bytes.extend(std::iter::repeat_n(b"abcde"[i % 5], 20));
+        }
+
+        let encoded = mtf_encode(&bytes);
+        let zeros = encoded.iter().filter(|&&index| index == 0).count();
+        println!("Zeros: [{zeros}] out of [{}]", encoded.len());
+        assert!(zeros * 10 >= encoded.len() * 9, "Repetitive bytes gave only [{zeros}] zeros out of [{}]", encoded.len());
+        assert!(mtf_decode(&encoded) == bytes, "Repetitive bytes different after encoding and decoding");
+    }
+}
@@ -0,0 +1,215 @@
+use crate::api::{Compressor, Decompressor};
+use crate::bwt::{bwt_transform, bwt_inverse, BWT_MAX_BLOCK_SIZE};
+use crate::error::DecodeError;
+use crate::filter::{delta_encode_stride, delta_decode_stride};
+use crate::mtf::{mtf_encode, mtf_decode};
+use crate::rle::{rle_compress, rle_decompress};
+use alloc::{vec::Vec, boxed::Box};
+
+/// One step of a `Pipeline`. `forward` transforms (or compresses) the bytes,
+/// and `inverse` undoes it.
+///
+/// Every `Compressor` that's also a `Decompressor` is already a stage, so
+/// the codecs in `api` can go at the end of a pipeline as they are.
+pub trait Stage {
+    fn forward(&self, input: &[u8]) -> Vec<u8>;
+    fn inverse(&self, input: &[u8]) -> Result<Vec<u8>, DecodeError>;
+}
+
+impl<T: Compressor + Decompressor> Stage for T {
+    fn forward(&self, input: &[u8]) -> Vec<u8> {
+        self.compress(input)
+    }
+
+    fn inverse(&self, input: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        self.decompress(input)
+    }
+}
+
+/// Stages run one after the other. Something like bzip2 (BWT, then MTF,
+/// then Huffman) is
+/// `Pipeline::new().then(Bwt::default()).then(Mtf).then(Huffman::default())`.
+///
+/// Compressing runs the stages in the order they were added, and
+/// decompressing runs their inverses backwards. A pipeline is a
+/// `Compressor` and `Decompressor` itself, so it can be a stage of another
+/// one too.
+#[derive(Default)]
+pub struct Pipeline {
+    stages: Vec<Box<dyn Stage>>
+}
+
+impl Pipeline {
+    /// A pipeline with no stages, which leaves bytes as they are.
+    pub fn new() -> Self {
+        Pipeline { stages: Vec::new() }
+    }
+
+    /// Adds a stage to the end.
+    pub fn then(mut self, stage: impl Stage + 'static) -> Self {
+        self.stages.push(Box::new(stage));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.stages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+}
+
+impl Compressor for Pipeline {
+    fn compress(&self, input: &[u8]) -> Vec<u8> {
+        self.stages.iter().fold(input.to_vec(), |bytes, stage| stage.forward(&bytes))
+    }
+}
+
+impl Decompressor for Pipeline {
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        self.stages.iter().rev().try_fold(input.to_vec(), |bytes, stage| stage.inverse(&bytes))
+    }
+}
+
+/// `bwt_transform` on every `block_size` bytes. Each block is written as its
+/// primary index (a little endian `u32`), then the transformed bytes.
+/// Decompressing needs the same `block_size`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bwt {
+    pub block_size: usize
+}
+
+impl Default for Bwt {
+    fn default() -> Self {
+        Bwt { block_size: BWT_MAX_BLOCK_SIZE }
+    }
+}
+
+impl Stage for Bwt {
+    fn forward(&self, input: &[u8]) -> Vec<u8> {
+        assert!(self.block_size > 0 && self.block_size <= BWT_MAX_BLOCK_SIZE, "Block size has to be between 1 and [{BWT_MAX_BLOCK_SIZE}], given [{}]", self.block_size);
+
+        let mut output = Vec::with_capacity(input.len() + input.len().div_ceil(self.block_size) * 4);
+        for block in input.chunks(self.block_size) {
+            let (transformed, primary_index) = bwt_transform(block);
+            output.extend_from_slice(&(primary_index as u32).to_le_bytes());
+            output.extend_from_slice(&transformed);
+        }
+        output
+    }
+
+    fn inverse(&self, input: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        let mut output = Vec::with_capacity(input.len());
+        let mut pos = 0;
+        while pos < input.len() {
+            let index_bytes = input.get(pos..(pos + 4)).ok_or(DecodeError::UnexpectedEnd)?;
+            let primary_index = u32::from_le_bytes(index_bytes.try_into().unwrap()) as usize;
+            let block = &input[(pos + 4)..(pos + 4 + self.block_size).min(input.len())];
+            if primary_index >= block.len().max(1) {
+                return Err(DecodeError::BadBwtIndex(primary_index));
+            }
+
+            output.extend(bwt_inverse(block, primary_index));
+            pos += 4 + block.len();
+        }
+        Ok(output)
+    }
+}
+
+/// `mtf_encode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Mtf;
+
+impl Stage for Mtf {
+    fn forward(&self, input: &[u8]) -> Vec<u8> {
+        mtf_encode(input)
+    }
+
+    fn inverse(&self, input: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        Ok(mtf_decode(input))
+    }
+}
+
+/// `rle_compress`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rle;
+
+impl Stage for Rle {
+    fn forward(&self, input: &[u8]) -> Vec<u8> {
+        rle_compress(input)
+    }
+
+    fn inverse(&self, input: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        Ok(rle_decompress(input)?)
+    }
+}
+
+/// `delta_encode_stride` with `stride`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Delta {
+    pub stride: usize
+}
+
+impl Default for Delta {
+    fn default() -> Self {
+        Delta { stride: 1 }
+    }
+}
+
+impl Stage for Delta {
+    fn forward(&self, input: &[u8]) -> Vec<u8> {
+        let mut output = input.to_vec();
+        delta_encode_stride(&mut output, self.stride);
+        output
+    }
+
+    fn inverse(&self, input: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        let mut output = input.to_vec();
+        delta_decode_stride(&mut output, self.stride);
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pipeline::{Pipeline, Bwt, Mtf, Rle, Delta};
+
+    #[test]
+    fn pipeline_test() {
+        use crate::api::{Compressor, Decompressor, Huffman};
+        use std::fs;
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+
+        let pipeline = Pipeline::new().then(Bwt::default()).then(Mtf).then(Huffman::default());
+        let compressed = pipeline.compress(&bytes);
+        let huffman_only = Huffman::default().compress(&bytes);
+        println!("BWT + MTF + Huffman: [{}] -> [{}] Huffman only: [{}]", bytes.len(), compressed.len(), huffman_only.len());
+        assert!(compressed.len() < huffman_only.len(), "BWT + MTF + Huffman compressed to [{}] bytes, Huffman alone to [{}]", compressed.len(), huffman_only.len());
+        assert!(pipeline.decompress(&compressed) == Ok(bytes.clone()), "Bytes different after going through BWT + MTF + Huffman and back");
+
+        //Small blocks, every other stage, a nested pipeline, and nothing at all
+        let pipeline = Pipeline::new().then(Delta { stride: 2 }).then(Bwt { block_size: 1000 }).then(Mtf).then(Rle).then(Pipeline::new().then(Huffman::default()));
+        assert!(pipeline.len() == 5, "Pipeline has [{}] stages instead of 5", pipeline.len());
+        assert!(pipeline.decompress(&pipeline.compress(&bytes)) == Ok(bytes.clone()), "Bytes different after going through every stage and back");
+        assert!(pipeline.decompress(&pipeline.compress(&[])) == Ok(Vec::new()), "Nothing wasn't empty after going through every stage and back");
+        assert!(Pipeline::new().compress(&bytes) == bytes, "Empty pipeline changed the bytes");
+    }
+
+    #[test]
+    fn pipeline_corrupt_test() {
+        use crate::api::Decompressor;
+        use crate::error::DecodeError;
+        use crate::pipeline::Stage;
+
+        let bwt = Bwt { block_size: 4 };
+        let transformed = bwt.forward(b"bananas");
+        assert!(bwt.inverse(&transformed) == Ok(b"bananas".to_vec()), "Bytes different after BWT blocks and back");
+        assert!(bwt.inverse(&transformed[..2]) == Err(DecodeError::UnexpectedEnd), "Truncated primary index wasn't caught");
+        assert!(bwt.inverse(&[9, 0, 0, 0, b'a']) == Err(DecodeError::BadBwtIndex(9)), "Primary index outside the block wasn't caught");
+
+        let pipeline = Pipeline::new().then(Mtf).then(Rle);
+        assert!(matches!(pipeline.decompress(&[3, b'a']), Err(DecodeError::Rle(_))), "Truncated RLE wasn't caught");
+    }
+}
@@ -0,0 +1,137 @@
+use crate::bitstream::{BitWriter, BitReader};
+use crate::entropy::{EntropyCoder, Model};
+use crate::huffman::HuffmanSymbol;
+use alloc::vec::Vec;
+
+/// Once the top byte of `low` and `low + range` are the same, it can't change
+/// anymore, so it gets written out.
+const RANGE_TOP:u32 = 1 << 24;
+/// If `range` gets smaller than this, it's forced back up even if the top
+/// byte isn't settled yet. It's also the biggest total a model can have.
+const RANGE_BOTTOM:u32 = 1 << 16;
+
+/// A range coder (Subbotin's carryless one), which is arithmetic coding done
+/// with integers. Unlike Huffman, symbols don't need a whole number of bits
+/// each, so skewed frequencies compress better.
+///
+/// Everything it writes is whole bytes, and it flushes `4` of them at the end
+/// of `encode`, so the decoder reads exactly what was written.
+#[derive(Debug, Default)]
+pub struct RangeCoder {
+    low: u32,
+    range: u32,
+    code: u32
+}
+
+impl RangeCoder {
+    pub fn new() -> Self {
+        RangeCoder { low: 0, range: u32::MAX, code: 0 }
+    }
+
+    /// Narrows the range down to the symbol's part of it.
+    fn narrow(&mut self, cum_freq: u32, freq: u32, total: u32) {
+        self.range /= total;
+        self.low = self.low.wrapping_add(cum_freq * self.range);
+        self.range *= freq;
+    }
+
+    /// Whether the top byte is ready to go. If `range` got too small before
+    /// that happened, it gets cut down so that it is.
+    fn needs_shift(&mut self) -> bool {
+        if (self.low ^ self.low.wrapping_add(self.range)) < RANGE_TOP {
+            return true;
+        }
+        if self.range < RANGE_BOTTOM {
+            self.range = self.low.wrapping_neg() & (RANGE_BOTTOM - 1);
+            return true;
+        }
+        false
+    }
+
+    fn shift(&mut self) {
+        self.low <<= 8;
+        self.range <<= 8;
+    }
+}
+
+impl EntropyCoder for RangeCoder {
+    fn encode<M: Model>(&mut self, model: &M, symbols: &[HuffmanSymbol], writer: &mut BitWriter) {
+        assert!(model.total() <= RANGE_BOTTOM, "Model total can be at most [{RANGE_BOTTOM}], given [{}]", model.total());
+        *self = RangeCoder::new();
+
+        for &symbol in symbols {
+            let freq = model.freq(symbol);
+            assert!(freq > 0, "Symbol [{symbol}] has a frequency of 0, so it can't be encoded");
+            self.narrow(model.cum_freq(symbol), freq, model.total());
+
+            while self.needs_shift() {
+                writer.write_bits_u32(self.low >> 24, 8);
+                self.shift();
+            }
+        }
+
+        for _ in 0..4 {
+            writer.write_bits_u32(self.low >> 24, 8);
+            self.low <<= 8;
+        }
+    }
+
+    fn decode<M: Model>(&mut self, model: &M, reader: &mut BitReader, count: usize) -> Option<Vec<HuffmanSymbol>> {
+        *self = RangeCoder::new();
+        for _ in 0..4 {
+            self.code = (self.code << 8) | reader.read_bits_into_u32(8)?;
+        }
+
+        //The count comes from the input, so don't trust it with more than a symbol for every bit left
+        let mut decoded = Vec::with_capacity(count.min(reader.remaining_bits()));
+        for _ in 0..count {
+            let total = model.total();
+            //Corrupt input can point past the total, so clamp it to the last symbol
+            let target = (self.code.wrapping_sub(self.low) / (self.range / total)).min(total - 1);
+            let symbol = model.symbol_at(target);
+            decoded.push(symbol);
+            self.narrow(model.cum_freq(symbol), model.freq(symbol), total);
+
+            while self.needs_shift() {
+                self.code = (self.code << 8) | reader.read_bits_into_u32(8)?;
+                self.shift();
+            }
+        }
+
+        Some(decoded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::range::RangeCoder;
+
+    #[test]
+    fn range_coder_test() {
+        use crate::bitstream::{BitWriter, BitReader};
+        use crate::entropy::{EntropyCoder, FrequencyModel};
+        use crate::huffman::HuffmanSymbol;
+        use rand::prelude::*;
+
+        //Very skewed symbols, which Huffman can't do better than 1 bit each for
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(54);
+        let symbols:Vec<HuffmanSymbol> = (0..100000).map(|_| if rng.gen_bool(0.98) {0} else {rng.gen_range(1..4)}).collect();
+        let model = FrequencyModel::from_symbols(&symbols, 4);
+
+        let mut writer = BitWriter::new();
+        RangeCoder::new().encode(&model, &symbols, &mut writer);
+        let bytes = writer.get_bytes();
+        println!("Symbols: [{}] Range coded bytes: [{}]", symbols.len(), bytes.len());
+        assert!(bytes.len() * 8 < symbols.len() / 4, "Skewed symbols took [{}] bytes", bytes.len());
+
+        let decoded = RangeCoder::new().decode(&model, &mut BitReader::new(&bytes), symbols.len());
+        assert!(decoded.as_ref() == Some(&symbols), "Symbols different after range coding");
+        assert!(RangeCoder::new().decode(&model, &mut BitReader::new(&bytes[..bytes.len() - 1]), symbols.len()).is_none(), "Truncated range coded symbols decoded");
+        assert!(RangeCoder::new().decode(&model, &mut BitReader::new(&bytes), u32::MAX as usize).is_none(), "More symbols decoded than were range coded");
+
+        let mut writer = BitWriter::new();
+        RangeCoder::new().encode(&model, &[], &mut writer);
+        let bytes = writer.get_bytes();
+        assert!(RangeCoder::new().decode(&model, &mut BitReader::new(&bytes), 0) == Some(Vec::new()), "Nothing different after range coding");
+    }
+}
@@ -0,0 +1,150 @@
+use core::fmt;
+use alloc::vec::Vec;
+
+/// The longest run (of either kind) a single header byte can describe.
+pub const RLE_MAX_RUN_LEN:usize = 128;
+/// Repeats shorter than this are just left in a literal run, since a repeat
+/// of 2 takes up as much space as 2 literals but breaks up the literal run.
+const RLE_MIN_REPEAT_LEN:usize = 3;
+/// The header byte PackBits never uses for anything, which is skipped.
+const RLE_NO_OP:u8 = 128;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RleError {
+    /// The encoded bytes ended in the middle of a run.
+    UnexpectedEnd
+}
+
+impl fmt::Display for RleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RleError::UnexpectedEnd => write!(f, "Encoded bytes ended in the middle of a run")
+        }
+    }
+}
+
+/// The number of times the byte at `pos` repeats, up to `RLE_MAX_RUN_LEN`.
+fn repeat_len(bytes: &[u8], pos: usize) -> usize {
+    bytes[pos..].iter().take(RLE_MAX_RUN_LEN).take_while(|byte| **byte == bytes[pos]).count()
+}
+
+/// Run-length encoding, the same way PackBits does it.
+///
+/// Every run starts with a header byte `n`. If `n` is `0` to `127`, the next
+/// `n + 1` bytes are literals. If `n` is `129` to `255`, the next byte is
+/// repeated `257 - n` times (so `2` to `128` times). Runs longer than 128
+/// bytes are split up.
+///
+/// Bytes without many repeats grow by a byte for every 128, so this works
+/// best as a pre-pass on data with long runs, like bitmaps or sparse buffers.
+pub fn rle_compress(bytes: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(bytes.len() + bytes.len().div_ceil(RLE_MAX_RUN_LEN));
+    let mut literal_start = 0;
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        let repeat = repeat_len(bytes, pos);
+        //Literals are written once a repeat worth using starts, or there are too many of them
+        let end_literals = repeat >= RLE_MIN_REPEAT_LEN || pos - literal_start == RLE_MAX_RUN_LEN;
+        if end_literals && pos > literal_start {
+            encoded.push((pos - literal_start - 1) as u8);
+            encoded.extend_from_slice(&bytes[literal_start..pos]);
+            literal_start = pos;
+        }
+
+        if repeat >= RLE_MIN_REPEAT_LEN {
+            encoded.push((257 - repeat) as u8);
+            encoded.push(bytes[pos]);
+            pos += repeat;
+            literal_start = pos;
+        } else {
+            pos += 1;
+        }
+    }
+
+    if pos > literal_start {
+        encoded.push((pos - literal_start - 1) as u8);
+        encoded.extend_from_slice(&bytes[literal_start..pos]);
+    }
+
+    encoded
+}
+
+/// Run-length decoding of bytes encoded with `rle_compress`, or anything else
+/// using PackBits.
+///
+/// Returns an error if the encoded bytes end in the middle of a run.
+pub fn rle_decompress(bytes: &[u8]) -> Result<Vec<u8>, RleError> {
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        let header = bytes[pos];
+        pos += 1;
+
+        if header < RLE_NO_OP {
+            let literals = bytes.get(pos..(pos + header as usize + 1)).ok_or(RleError::UnexpectedEnd)?;
+            decoded.extend_from_slice(literals);
+            pos += literals.len();
+        } else if header > RLE_NO_OP {
+            let byte = *bytes.get(pos).ok_or(RleError::UnexpectedEnd)?;
+            decoded.resize(decoded.len() + 257 - header as usize, byte);
+            pos += 1;
+        }
+    }
+
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rle::{rle_compress, rle_decompress};
+
+    #[test]
+    fn rle_test() {
+        use std::fs;
+
+        let zeros = vec![0u8; 10000];
+        let encoded = rle_compress(&zeros);
+        println!("Zeros: [{}] -> [{}]", zeros.len(), encoded.len());
+        assert!(encoded.len() == zeros.len().div_ceil(128) * 2, "Zeros weren't split into runs of 128 [{}]", encoded.len());
+        assert!(rle_decompress(&encoded) == Ok(zeros), "Zeros different after compressing and decompressing");
+
+        let no_repeats:Vec<u8> = (0..10000).map(|i| (i % 256) as u8).collect();
+        let encoded = rle_compress(&no_repeats);
+        println!("No repeats: [{}] -> [{}]", no_repeats.len(), encoded.len());
+        assert!(encoded.len() == no_repeats.len() + no_repeats.len().div_ceil(128), "Bytes without repeats grew by more than a byte every 128 [{}]", encoded.len());
+        assert!(rle_decompress(&encoded) == Ok(no_repeats), "Bytes without repeats different after compressing and decompressing");
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let encoded = rle_compress(&bytes);
+        println!("lorem_ipsum: [{}] -> [{}]", bytes.len(), encoded.len());
+        assert!(rle_decompress(&encoded) == Ok(bytes), "lorem_ipsum different after compressing and decompressing");
+
+        //Runs of every length around the limits, with literals in between
+        let mut mixed = Vec::new();
+        for len in 1..300 {
+            mixed.extend(std::iter::repeat_n((len % 7) as u8, len));
+            mixed.extend_from_slice(b"xy");
+        }
+        assert!(rle_decompress(&rle_compress(&mixed)) == Ok(mixed), "Mixed runs different after compressing and decompressing");
+
+        assert!(rle_compress(&[]).is_empty() && rle_decompress(&[]) == Ok(Vec::new()), "Nothing wasn't empty after compressing and decompressing");
+    }
+
+    #[test]
+    fn rle_corrupt_test() {
+        use crate::rle::RleError;
+
+        //A literal run that's missing its last byte
+        assert!(rle_decompress(&[3, b'a', b'b', b'c']) == Err(RleError::UnexpectedEnd), "Truncated literal run wasn't caught");
+        //A repeat without the byte to repeat
+        assert!(rle_decompress(&[0xFE]) == Err(RleError::UnexpectedEnd), "Truncated repeat wasn't caught");
+
+        let encoded = rle_compress(b"aaaaaaaabcdefg");
+        assert!(rle_decompress(&encoded[..(encoded.len() - 1)]) == Err(RleError::UnexpectedEnd), "Truncated encoded bytes weren't caught");
+
+        //The no-op header is skipped, same as PackBits
+        assert!(rle_decompress(&[128, 0xFD, b'z', 128]) == Ok(b"zzzz".to_vec()), "No-op header wasn't skipped");
+    }
+}
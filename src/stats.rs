@@ -0,0 +1,232 @@
+use core::fmt;
+use core::time::Duration;
+use crate::api::{Codec, LZ77_DEFAULT_MIN_MATCH_LEN, LZ77_DEFAULT_MAX_MATCH_LEN, LZ77_DEFAULT_MAX_CHAIN_LEN};
+use crate::deflate::{DEFLATE_WINDOW_SIZE, DEFLATE_MIN_MATCH_LEN, DEFLATE_MAX_MATCH_LEN, DEFLATE_MAX_CHAIN_LEN};
+use crate::huffman::{HuffmanEncoder, HuffmanSymbol, HUFFMAN_MAX_SYMBOLS, HUFFMAN_DEFAULT_CHUNK_SIZE};
+use crate::lz77::{LZ77Encoded, LZ77Data, lz77_compress_simple, LZ77_DEFAULT_WINDOW_SIZE};
+use crate::lzw::{compress_lzw_with_resets, LzwVariant, MIN_CODE_LEN, MAX_CODE_LEN, LZW_UNLIMITED_DICT_ENTRIES};
+use alloc::vec;
+
+/// The details that only make sense for one codec.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CodecStats {
+    Huffman { chunks: usize, avg_code_len: f64 },
+    Lz77 { literals: usize, matches: usize },
+    Lzw { resets: usize }
+}
+
+/// Everything worth knowing about one compression run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompressionStats {
+    pub input_size: usize,
+    pub output_size: usize,
+    pub time: Duration,
+    pub codec: CodecStats
+}
+
+impl CompressionStats {
+    /// Input size over output size, so bigger is better.
+    pub fn ratio(&self) -> f64 {
+        if self.output_size == 0 {
+            return 0.0;
+        }
+        self.input_size as f64 / self.output_size as f64
+    }
+}
+
+impl fmt::Display for CompressionStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Input size: [{}] bytes", self.input_size)?;
+        writeln!(f, "Output size: [{}] bytes", self.output_size)?;
+        writeln!(f, "Ratio: [{:.3}]", self.ratio())?;
+        writeln!(f, "Time: [{:?}]", self.time)?;
+
+        match self.codec {
+            CodecStats::Huffman { chunks, avg_code_len } => write!(f, "Chunks: [{chunks}]\nAverage code length: [{avg_code_len:.3}] bits"),
+            CodecStats::Lz77 { literals, matches } => write!(f, "Literals: [{literals}]\nMatches: [{matches}]"),
+            CodecStats::Lzw { resets } => write!(f, "Resets: [{resets}]")
+        }
+    }
+}
+
+/// How many times each byte shows up in `bytes`.
+pub fn byte_histogram(bytes: &[u8]) -> [u64; 256] {
+    let mut histogram = [0u64; 256];
+    for byte in bytes {
+        histogram[*byte as usize] += 1;
+    }
+    histogram
+}
+
+#[cfg(feature = "std")]
+/// The Shannon entropy of `bytes` in bits per byte, going by how often each
+/// byte shows up. Times the length and over 8, it's about the smallest any
+/// order-0 coder (like Huffman) could get them, so it's a quick way to tell
+/// whether compressing is worth it. It doesn't know about repeats though, so
+/// LZ77 or LZW can still do better.
+/// 
+/// Goes from `0.0` (only one byte value, or nothing at all) to `8.0` (every
+/// byte value equally often).
+pub fn shannon_entropy(bytes: &[u8]) -> f64 {
+    if bytes.is_empty() {
+        return 0.0;
+    }
+
+    let total = bytes.len() as f64;
+    -byte_histogram(bytes).iter().filter(|&&count| count > 0).map(|&count| {
+        let p = count as f64 / total;
+        p * p.log2()
+    }).sum::<f64>()
+}
+
+/// The number of chunks `bytes` gets split into and the average code length
+/// across all of them, if it's Huffman encoded with chunks of `chunk_size`.
+///
+/// This builds every chunk's table again, so it's about as slow as encoding.
+pub fn huffman_stats(bytes: &[u8], chunk_size: usize) -> CodecStats {
+    if bytes.is_empty() {
+        return CodecStats::Huffman { chunks: 1, avg_code_len: 0.0 };
+    }
+
+    let mut encoder = HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS);
+    let symbols = HuffmanEncoder::bytes_to_symbols(bytes);
+    let mut chunks = 0;
+    let mut total_bits:u64 = 0;
+
+    for chunk in symbols.chunks(chunk_size) {
+        let mut freq_table = vec![0u64; HUFFMAN_MAX_SYMBOLS];
+        for symbol in chunk {
+            freq_table[*symbol as usize] += 1;
+        }
+
+        encoder.build_frequency_table(chunk);
+        encoder.build_huffman_table();
+        total_bits += encoder.iter().map(|(symbol, level): (HuffmanSymbol, usize)| freq_table[symbol as usize] * level as u64).sum::<u64>();
+        chunks += 1;
+    }
+
+    CodecStats::Huffman { chunks, avg_code_len: total_bits as f64 / bytes.len() as f64 }
+}
+
+/// The number of literals and matches in LZ77 encoded data.
+pub fn lz77_stats(encoded: &LZ77Encoded) -> CodecStats {
+    let matches = encoded.data().iter().filter(|data| matches!(data, LZ77Data::Match(_, _))).count();
+
+    CodecStats::Lz77 { literals: encoded.data().len() - matches, matches }
+}
+
+/// The codec specific stats for compressing `bytes` with `api::compress`.
+/// DEFLATE doesn't have its own, so it gets the literal/match breakdown of
+/// its LZ77 pass.
+///
+/// This redoes whatever part of the compression the stats come from, so it's
+/// about as slow as compressing again.
+pub fn codec_stats(codec: Codec, bytes: &[u8]) -> CodecStats {
+    match codec {
+        Codec::Huffman => huffman_stats(bytes, HUFFMAN_DEFAULT_CHUNK_SIZE),
+        Codec::Lzw => CodecStats::Lzw { resets: compress_lzw_with_resets(bytes, MIN_CODE_LEN, MAX_CODE_LEN, LzwVariant::Gif, LZW_UNLIMITED_DICT_ENTRIES).1 },
+        Codec::Lz77 => lz77_stats(&lz77_compress_simple(bytes, LZ77_DEFAULT_WINDOW_SIZE, LZ77_DEFAULT_MIN_MATCH_LEN, LZ77_DEFAULT_MAX_MATCH_LEN, LZ77_DEFAULT_MAX_CHAIN_LEN, LZ77_DEFAULT_MAX_MATCH_LEN)),
+        Codec::Deflate => lz77_stats(&lz77_compress_simple(bytes, DEFLATE_WINDOW_SIZE, DEFLATE_MIN_MATCH_LEN, DEFLATE_MAX_MATCH_LEN, DEFLATE_MAX_CHAIN_LEN, DEFLATE_MAX_MATCH_LEN))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::time::Instant;
+    use crate::stats::{CompressionStats, CodecStats};
+
+    #[test]
+    fn stats_test() {
+        use crate::stats::{huffman_stats, lz77_stats};
+        use crate::huffman::{HuffmanEncoder, HUFFMAN_MAX_SYMBOLS, HUFFMAN_DEFAULT_CHUNK_SIZE};
+        use crate::bitstream::BitWriter;
+        use crate::lz77::{lz77_compress_simple, encoded_byte_num, LZ77Encoded};
+        use crate::lzw::{compress_lzw_with_resets, LzwVariant, LZW_UNLIMITED_DICT_ENTRIES};
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+
+        let start_time = Instant::now();
+        let mut writer = BitWriter::new();
+        HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS).encode_all_bytes(&bytes, HUFFMAN_DEFAULT_CHUNK_SIZE / 4, &mut writer);
+        let huffman = CompressionStats { input_size: bytes.len(), output_size: writer.get_bytes().len(), time: start_time.elapsed(), codec: huffman_stats(&bytes, HUFFMAN_DEFAULT_CHUNK_SIZE / 4) };
+        println!("{huffman}");
+        let printed = huffman.to_string();
+        for field in ["Input size: [", "Output size: [", "Ratio: [", "Time: [", "Chunks: [", "Average code length: ["] {
+            assert!(printed.contains(field), "Huffman stats missing field [{field}]");
+        }
+        let CodecStats::Huffman { chunks, avg_code_len } = huffman.codec else { panic!("Wrong codec stats") };
+        assert!(chunks == bytes.len().div_ceil(HUFFMAN_DEFAULT_CHUNK_SIZE / 4), "Wrong number of chunks [{chunks}]");
+        //The header and padding are the only difference between this and the actual size
+        let code_bytes = (avg_code_len * bytes.len() as f64 / 8.0) as usize;
+        assert!(code_bytes <= huffman.output_size && huffman.output_size - code_bytes < 4096, "Average code length [{avg_code_len}] doesn't match the output size [{}]", huffman.output_size);
+
+        let start_time = Instant::now();
+        let lz77_encoded = lz77_compress_simple(&bytes, 0xFFFF, 3, 256, 6, 256);
+        let time = start_time.elapsed();
+        let lz77 = CompressionStats { input_size: bytes.len(), output_size: encoded_byte_num(&lz77_encoded, 3), time, codec: lz77_stats(&lz77_encoded) };
+        println!("{lz77}");
+        let printed = lz77.to_string();
+        assert!(printed.contains("Literals: [") && printed.contains("Matches: ["), "LZ77 stats missing literal/match breakdown");
+        let CodecStats::Lz77 { literals, matches } = lz77.codec else { panic!("Wrong codec stats") };
+        assert!(literals + matches == lz77_encoded.data().len() && matches > 0, "Wrong literal/match breakdown [{literals}] [{matches}]");
+        assert!(lz77_stats(&LZ77Encoded::new(Vec::new())) == CodecStats::Lz77 { literals: 0, matches: 0 }, "Empty LZ77 data had literals or matches");
+
+        let start_time = Instant::now();
+        let (lzw_encoded, resets) = compress_lzw_with_resets(&bytes, 9, 12, LzwVariant::Gif, LZW_UNLIMITED_DICT_ENTRIES);
+        let lzw = CompressionStats { input_size: bytes.len(), output_size: lzw_encoded.len(), time: start_time.elapsed(), codec: CodecStats::Lzw { resets } };
+        println!("{lzw}");
+        assert!(lzw.to_string().contains("Resets: ["), "LZW stats missing reset count");
+        assert!(resets > 0, "Table never got reset compressing lorem_ipsum with 12 bit codes");
+        let (_, bigger_resets) = compress_lzw_with_resets(&bytes, 9, 16, LzwVariant::Gif, LZW_UNLIMITED_DICT_ENTRIES);
+        assert!(bigger_resets < resets, "Bigger table reset as often [{bigger_resets}] as a smaller one [{resets}]");
+    }
+
+    #[test]
+    fn codec_stats_test() {
+        use crate::stats::codec_stats;
+        use crate::api::Codec;
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+
+        for codec in Codec::ALL {
+            let stats = codec_stats(codec, &bytes);
+            let right_kind = match codec {
+                Codec::Huffman => matches!(stats, CodecStats::Huffman { .. }),
+                Codec::Lzw => matches!(stats, CodecStats::Lzw { .. }),
+                Codec::Lz77 | Codec::Deflate => matches!(stats, CodecStats::Lz77 { .. })
+            };
+            assert!(right_kind, "Wrong kind of stats for {codec:?} [{stats:?}]");
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn entropy_test() {
+        use crate::stats::{byte_histogram, shannon_entropy};
+        use crate::huffman::{HuffmanEncoder, HUFFMAN_MAX_SYMBOLS};
+        use rand::prelude::*;
+
+        assert!(shannon_entropy(&[0x41; 10000]) == 0.0, "One byte value didn't have an entropy of 0 [{}]", shannon_entropy(&[0x41; 10000]));
+        assert!(shannon_entropy(&[]) == 0.0, "Nothing didn't have an entropy of 0");
+        assert!((shannon_entropy(b"abababab") - 1.0).abs() < 1e-9, "Two byte values didn't have an entropy of 1 [{}]", shannon_entropy(b"abababab"));
+
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(2065);
+        let mut random = vec![0u8; 1 << 20];
+        rng.fill_bytes(&mut random);
+        let entropy = shannon_entropy(&random);
+        assert!(entropy > 7.99 && entropy <= 8.0, "Random bytes had an entropy of [{entropy}]");
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let entropy = shannon_entropy(&bytes);
+        println!("lorem_ipsum entropy: [{entropy:.3}] bits per byte, at least [{}] bytes", (entropy * bytes.len() as f64 / 8.0) as usize);
+        assert!(entropy > 3.0 && entropy < 5.0, "Text had an entropy of [{entropy}]");
+
+        //Same counts as the encoder's frequency table
+        let histogram = byte_histogram(&bytes);
+        let mut encoder = HuffmanEncoder::new(HUFFMAN_MAX_SYMBOLS);
+        encoder.build_frequency_table(&HuffmanEncoder::bytes_to_symbols(&bytes));
+        assert!(encoder.frequency_table()[..256] == histogram, "Histogram different from the frequency table");
+        assert!(histogram.iter().sum::<u64>() == bytes.len() as u64, "Histogram doesn't add up to the number of bytes");
+    }
+}
@@ -0,0 +1,111 @@
+use core::fmt;
+use crate::api::{compress, decompress, Codec};
+use crate::error::DecodeError;
+use alloc::{vec::Vec, string::ToString, format};
+
+/// Where decompressed bytes first stopped matching the original ones.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Mismatch {
+    /// The first offset where the bytes differ.
+    pub offset: usize,
+    /// The original byte at `offset`, or `None` if the decompressed bytes are
+    /// longer than the original.
+    pub expected: Option<u8>,
+    /// The decompressed byte at `offset`, or `None` if they ended early (or
+    /// decompressing failed).
+    pub actual: Option<u8>,
+    /// The error decompressing returned, if it did.
+    pub error: Option<DecodeError>
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(err) = &self.error {
+            return write!(f, "Decompressing failed: {err}");
+        }
+
+        let byte = |byte: Option<u8>| byte.map_or("nothing".to_string(), |byte| format!("{byte:#04x}"));
+        write!(f, "Bytes differ at offset {}: expected {}, got {}", self.offset, byte(self.expected), byte(self.actual))
+    }
+}
+
+/// The first place two byte slices differ, or `None` if they're the same.
+fn first_difference(expected: &[u8], actual: &[u8]) -> Option<Mismatch> {
+    let offset = expected.iter().zip(actual).position(|(expected, actual)| expected != actual).unwrap_or(expected.len().min(actual.len()));
+    if offset == expected.len() && offset == actual.len() {
+        return None;
+    }
+
+    Some(Mismatch { offset, expected: expected.get(offset).copied(), actual: actual.get(offset).copied(), error: None })
+}
+
+/// Compresses then decompresses `bytes` with the given functions, returning
+/// the compressed size if the bytes came back the same, or where they first
+/// didn't.
+///
+/// `check` uses this with `api::compress` and `api::decompress`, but any pair
+/// works, which is handy for checking a codec that isn't in `Codec` yet.
+pub fn check_with<C, D>(bytes: &[u8], compress: C, decompress: D) -> Result<usize, Mismatch>
+where
+C: FnOnce(&[u8]) -> Vec<u8>,
+D: FnOnce(&[u8]) -> Result<Vec<u8>, DecodeError> {
+    let compressed = compress(bytes);
+    let decompressed = decompress(&compressed).map_err(|err| Mismatch { offset: 0, expected: bytes.first().copied(), actual: None, error: Some(err) })?;
+
+    match first_difference(bytes, &decompressed) {
+        Some(mismatch) => Err(mismatch),
+        None => Ok(compressed.len())
+    }
+}
+
+/// Checks that a codec gives back `bytes` after compressing and
+/// decompressing them.
+pub fn check(codec: Codec, bytes: &[u8]) -> Result<(), Mismatch> {
+    check_with(bytes, |bytes| compress(codec, bytes), |compressed| decompress(codec, compressed)).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::verify::{check, check_with, Mismatch};
+
+    #[test]
+    fn verify_test() {
+        use std::fs;
+        use crate::api::Codec;
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        for codec in Codec::ALL {
+            assert!(check(codec, &bytes) == Ok(()), "{codec} didn't roundtrip [{:?}]", check(codec, &bytes));
+            assert!(check(codec, &[]) == Ok(()), "{codec} didn't roundtrip nothing");
+        }
+
+        let size = check_with(&bytes, |bytes| bytes.to_vec(), |compressed| Ok(compressed.to_vec()));
+        assert!(size == Ok(bytes.len()), "Wrong compressed size for copying [{size:?}]");
+    }
+
+    #[test]
+    fn verify_mismatch_test() {
+        use crate::api::{compress, decompress, Codec};
+        use crate::error::DecodeError;
+
+        let bytes = b"Some bytes to check, some bytes to check".to_vec();
+
+        //A decoder that flips a bit in the middle
+        let flipped = check_with(&bytes, |bytes| compress(Codec::Lz77, bytes), |compressed| {
+            let mut decompressed = decompress(Codec::Lz77, compressed)?;
+            decompressed[25] ^= 1;
+            Ok(decompressed)
+        });
+        assert!(flipped == Err(Mismatch { offset: 25, expected: Some(bytes[25]), actual: Some(bytes[25] ^ 1), error: None }), "Flipped bit reported wrong [{flipped:?}]");
+
+        //Decoders that lose the end, or add to it
+        let short = check_with(&bytes, |bytes| bytes.to_vec(), |compressed| Ok(compressed[..30].to_vec()));
+        assert!(short == Err(Mismatch { offset: 30, expected: Some(bytes[30]), actual: None, error: None }), "Missing end reported wrong [{short:?}]");
+        let long = check_with(&bytes, |bytes| bytes.to_vec(), |compressed| Ok([compressed, b"!"].concat()));
+        assert!(long == Err(Mismatch { offset: bytes.len(), expected: None, actual: Some(b'!'), error: None }), "Extra end reported wrong [{long:?}]");
+
+        let failed = check_with(&bytes, |bytes| bytes.to_vec(), |_| Err(DecodeError::OutputTooLarge));
+        assert!(failed.as_ref().is_err_and(|mismatch| mismatch.error == Some(DecodeError::OutputTooLarge)), "Decode error reported wrong [{failed:?}]");
+        println!("{}", failed.unwrap_err());
+    }
+}
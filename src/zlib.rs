@@ -0,0 +1,153 @@
+use core::fmt;
+use crate::checksum::adler32;
+use crate::deflate::{DeflateError, deflate_compress, deflate_decompress_with_size};
+use alloc::vec::Vec;
+
+/// The only compression method zlib has, which is DEFLATE.
+const ZLIB_METHOD_DEFLATE:u8 = 8;
+/// `log2` of the window size, minus 8. `7` is DEFLATE's whole 32K window.
+const ZLIB_WINDOW_BITS:u8 = 7;
+/// The compression level in the header, which is only a hint. `2` is zlib's
+/// "default".
+const ZLIB_LEVEL_DEFAULT:u8 = 2;
+const ZLIB_FLAG_DICT:u8 = 1 << 5;
+const ZLIB_HEADER_LEN:usize = 2;
+const ZLIB_TRAILER_LEN:usize = 4;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ZlibError {
+    /// The bytes ended in the middle of the header or trailer.
+    UnexpectedEnd,
+    /// The compression method isn't DEFLATE, or the window is bigger than
+    /// DEFLATE allows.
+    UnsupportedMethod(u8),
+    /// The header's check bits are wrong, so it probably isn't zlib at all.
+    BadHeader,
+    /// The stream needs a preset dictionary.
+    PresetDictionary,
+    Deflate(DeflateError),
+    /// The Adler-32 of the decompressed bytes isn't the one in the trailer.
+    ChecksumMismatch { expected: u32, actual: u32 }
+}
+
+impl fmt::Display for ZlibError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ZlibError::UnexpectedEnd => write!(f, "Bytes ended in the middle of a zlib header or trailer"),
+            ZlibError::UnsupportedMethod(cmf) => write!(f, "Unsupported compression method {cmf:#04x}"),
+            ZlibError::BadHeader => write!(f, "Not a zlib stream"),
+            ZlibError::PresetDictionary => write!(f, "Preset dictionaries aren't supported"),
+            ZlibError::Deflate(err) => write!(f, "DEFLATE error: {err}"),
+            ZlibError::ChecksumMismatch { expected, actual } => write!(f, "Adler-32 mismatch: expected {expected:#010x}, got {actual:#010x}")
+        }
+    }
+}
+
+impl From<DeflateError> for ZlibError {
+    fn from(err: DeflateError) -> Self {
+        ZlibError::Deflate(err)
+    }
+}
+
+/// Compresses bytes into a zlib stream, which is what `zlib.decompress` in
+/// Python (or `inflate` anywhere else) reads.
+///
+/// It's the same DEFLATE stream as `gzip_compress`, with a smaller wrapper:
+/// two header bytes, then an Adler-32 of the bytes at the end, big endian.
+pub fn zlib_compress(bytes: &[u8]) -> Vec<u8> {
+    let cmf = (ZLIB_WINDOW_BITS << 4) | ZLIB_METHOD_DEFLATE;
+    let flags = ZLIB_LEVEL_DEFAULT << 6;
+    //The check bits make the header a multiple of 31
+    let check = 31 - ((cmf as u16) << 8 | flags as u16) % 31;
+    let flags = flags | (check % 31) as u8;
+
+    let mut compressed = Vec::with_capacity(ZLIB_HEADER_LEN + bytes.len() / 2 + ZLIB_TRAILER_LEN);
+    compressed.push(cmf);
+    compressed.push(flags);
+    compressed.extend_from_slice(&deflate_compress(bytes));
+    compressed.extend_from_slice(&adler32(bytes).to_be_bytes());
+    compressed
+}
+
+/// Decompresses a zlib stream, checking the header and the Adler-32 in the
+/// trailer. Anything after the trailer is ignored.
+pub fn zlib_decompress(bytes: &[u8]) -> Result<Vec<u8>, ZlibError> {
+    let header = bytes.get(..ZLIB_HEADER_LEN).ok_or(ZlibError::UnexpectedEnd)?;
+    let (cmf, flags) = (header[0], header[1]);
+    if !((cmf as u16) << 8 | flags as u16).is_multiple_of(31) {
+        return Err(ZlibError::BadHeader);
+    }
+    if cmf & 0x0F != ZLIB_METHOD_DEFLATE || cmf >> 4 > ZLIB_WINDOW_BITS {
+        return Err(ZlibError::UnsupportedMethod(cmf));
+    }
+    if flags & ZLIB_FLAG_DICT != 0 {
+        return Err(ZlibError::PresetDictionary);
+    }
+
+    let (decompressed, deflate_len) = deflate_decompress_with_size(&bytes[ZLIB_HEADER_LEN..])?;
+    let trailer_pos = ZLIB_HEADER_LEN + deflate_len;
+    let trailer = bytes.get(trailer_pos..(trailer_pos + ZLIB_TRAILER_LEN)).ok_or(ZlibError::UnexpectedEnd)?;
+
+    let expected = u32::from_be_bytes(trailer.try_into().unwrap());
+    let actual = adler32(&decompressed);
+    if expected != actual {
+        return Err(ZlibError::ChecksumMismatch { expected, actual });
+    }
+
+    Ok(decompressed)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::zlib::{zlib_compress, zlib_decompress, ZlibError};
+
+    #[test]
+    fn zlib_test() {
+        use std::fs;
+
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let compressed = zlib_compress(&bytes);
+        println!("lorem_ipsum: [{}] -> [{}]", bytes.len(), compressed.len());
+        assert!(compressed[..2] == [0x78, 0x9c], "Wrong header [{:02x?}]", &compressed[..2]);
+        assert!(zlib_decompress(&compressed) == Ok(bytes), "lorem_ipsum different after compressing and decompressing");
+
+        assert!(zlib_decompress(&zlib_compress(&[])) == Ok(Vec::new()), "Nothing wasn't empty after compressing and decompressing");
+    }
+
+    #[test]
+    fn zlib_python_fixture_test() {
+        use std::fs;
+
+        //Made with Python's `zlib.compress(lorem_ipsum, 9)`
+        let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+        let fixture = fs::read("lorem_ipsum.zz").expect("File could not be opened and/or read");
+        assert!(zlib_decompress(&fixture) == Ok(bytes), "Python zlib fixture different after decompressing");
+
+        //`zlib.compress(b'hello hello hello')` and `zlib.compress(b'stored bytes', 0)`
+        let fixed = [0x78, 0x9c, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x90, 0x00, 0x3a, 0x2e, 0x06, 0x7d];
+        assert!(zlib_decompress(&fixed) == Ok(b"hello hello hello".to_vec()), "Fixed block fixture different after decompressing [{:?}]", zlib_decompress(&fixed));
+        let stored = [0x78, 0x01, 0x01, 0x0c, 0x00, 0xf3, 0xff, 0x73, 0x74, 0x6f, 0x72, 0x65, 0x64, 0x20, 0x62, 0x79, 0x74, 0x65, 0x73, 0x1f, 0xcf, 0x04, 0xd9];
+        assert!(zlib_decompress(&stored) == Ok(b"stored bytes".to_vec()), "Stored block fixture different after decompressing [{:?}]", zlib_decompress(&stored));
+    }
+
+    #[test]
+    fn zlib_corrupt_test() {
+        let compressed = zlib_compress(b"Some bytes to compress, some bytes to compress");
+
+        let mut bad_adler = compressed.clone();
+        let adler_pos = bad_adler.len() - 1;
+        bad_adler[adler_pos] ^= 1;
+        assert!(matches!(zlib_decompress(&bad_adler), Err(ZlibError::ChecksumMismatch {..})), "Wrong Adler-32 wasn't caught");
+
+        let mut bad_header = compressed.clone();
+        bad_header[1] ^= 1;
+        assert!(zlib_decompress(&bad_header) == Err(ZlibError::BadHeader), "Wrong header check bits weren't caught");
+
+        //Still a multiple of 31, but with a dictionary or a method that isn't DEFLATE
+        assert!(zlib_decompress(&[0x78, 0xbb, 0, 0, 0, 0]) == Err(ZlibError::PresetDictionary), "Preset dictionary wasn't caught");
+        assert!(zlib_decompress(&[0x79, 0x18]) == Err(ZlibError::UnsupportedMethod(0x79)), "Wrong method wasn't caught");
+
+        assert!(zlib_decompress(&compressed[..(compressed.len() - 2)]) == Err(ZlibError::UnexpectedEnd), "Truncated trailer wasn't caught");
+        assert!(zlib_decompress(&compressed[..1]) == Err(ZlibError::UnexpectedEnd), "Truncated header wasn't caught");
+    }
+}
@@ -0,0 +1,171 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_lz_compression")).args(args).output().expect("Binary could not be run")
+}
+
+fn path_str(path: &Path) -> &str {
+    path.to_str().unwrap()
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("lz_compression_cli_{name}_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn cli_roundtrip_test() {
+    let dir = temp_dir("roundtrip");
+    let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+    let input = dir.join("input.txt");
+    fs::write(&input, &bytes).unwrap();
+
+    for codec in ["huffman", "lzw", "lz77", "deflate"] {
+        let compressed = dir.join(format!("input.{codec}.lz"));
+        let decompressed = dir.join(format!("output.{codec}.txt"));
+
+        let output = run(&["compress", "--codec", codec, "--input", path_str(&input), "--output", path_str(&compressed)]);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(output.status.success(), "Compressing with {codec} failed [{stderr}]");
+        assert!(stderr.contains("Ratio: [") && stderr.contains("Time: ["), "Compressing with {codec} didn't print the ratio and time [{stderr}]");
+
+        let output = run(&["decompress", "--codec", codec, "--input", path_str(&compressed), "--output", path_str(&decompressed)]);
+        assert!(output.status.success(), "Decompressing with {codec} failed [{}]", String::from_utf8_lossy(&output.stderr));
+        assert!(fs::read(&decompressed).unwrap() == bytes, "Bytes different after compressing and decompressing with {codec}");
+    }
+
+    //The container knows which codec it used
+    let compressed = dir.join("input.lzw.lz");
+    let output = run(&["decompress", "--codec", "lz77", "--input", path_str(&compressed), "--output", path_str(&dir.join("wrong.txt"))]);
+    assert!(!output.status.success(), "Decompressing with the wrong codec succeeded");
+    assert!(!run(&["compress", "--codec", "zip", "--input", path_str(&input)]).status.success(), "Compressing with an unknown codec succeeded");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn cli_checksum_test() {
+    let dir = temp_dir("checksum");
+    let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+    let input = dir.join("input.txt");
+    fs::write(&input, &bytes[..20000]).unwrap();
+
+    for checksum in ["none", "crc32", "adler32"] {
+        let compressed = dir.join(format!("input.{checksum}.lz"));
+        let output = run(&["compress", "--codec", "lzw", "--checksum", checksum, "--input", path_str(&input), "--output", path_str(&compressed)]);
+        assert!(output.status.success(), "Compressing with {checksum} failed [{}]", String::from_utf8_lossy(&output.stderr));
+        let output = run(&["decompress", "--input", path_str(&compressed), "--output", path_str(&dir.join("output.txt"))]);
+        assert!(output.status.success(), "Decompressing with {checksum} failed [{}]", String::from_utf8_lossy(&output.stderr));
+    }
+
+    //Corrupting the stored checksum makes decompressing fail with a mismatch
+    let compressed = dir.join("input.adler32.lz");
+    let mut corrupted = fs::read(&compressed).unwrap();
+    corrupted[17] ^= 0xFF;
+    fs::write(&compressed, &corrupted).unwrap();
+    let output = run(&["decompress", "--input", path_str(&compressed), "--output", path_str(&dir.join("output.txt"))]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success() && stderr.contains("adler32 mismatch"), "Corrupted checksum wasn't reported [{stderr}]");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn cli_stats_test() {
+    let dir = temp_dir("stats");
+    let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+    let input = dir.join("input.txt");
+    fs::write(&input, &bytes[..50000]).unwrap();
+
+    let codec_fields:[(&str, &[&str]); 3] = [("huffman", &["Chunks: [", "Average code length: ["]), ("lz77", &["Literals: [", "Matches: ["]), ("lzw", &["Resets: ["])];
+    for (codec, fields) in codec_fields {
+        let output = run(&["compress", "--codec", codec, "--input", path_str(&input), "--stats"]);
+        assert!(output.status.success(), "Compressing with {codec} failed [{}]", String::from_utf8_lossy(&output.stderr));
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        println!("{stdout}");
+        for field in ["Input size: [", "Output size: [", "Ratio: [", "Time: ["].iter().chain(fields.iter()) {
+            assert!(stdout.contains(field), "{codec} stats missing field [{field}]");
+        }
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn cli_batch_test() {
+    let dir = temp_dir("batch");
+    let sub_dir = dir.join("sub");
+    fs::create_dir_all(&sub_dir).unwrap();
+
+    let contents = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+    let mut originals:Vec<(PathBuf, Vec<u8>)> = Vec::new();
+    for i in 0..5 {
+        let path = if i % 2 == 0 {dir.join(format!("file{i}.txt"))} else {sub_dir.join(format!("file{i}.txt"))};
+        let bytes = contents[(i * 1000)..((i + 2) * 10000)].to_vec();
+        fs::write(&path, &bytes).unwrap();
+        originals.push((path, bytes));
+    }
+
+    assert!(!run(&["compress", "--input", path_str(&dir)]).status.success(), "Directory was compressed without --recursive");
+
+    let output = run(&["compress", "--codec", "deflate", "--input", path_str(&dir), "--recursive", "--threads", "3"]);
+    assert!(output.status.success(), "Compressing the directory failed [{}]", String::from_utf8_lossy(&output.stderr));
+
+    for (path, bytes) in &originals {
+        let mut compressed = path.clone().into_os_string();
+        compressed.push(".lz");
+        let decompressed = path.with_extension("out");
+
+        let output = run(&["decompress", "--input", compressed.to_str().unwrap(), "--output", path_str(&decompressed)]);
+        assert!(output.status.success(), "Decompressing [{}] failed [{}]", path.display(), String::from_utf8_lossy(&output.stderr));
+        assert!(fs::read(&decompressed).unwrap() == *bytes, "[{}] different after compressing and decompressing", path.display());
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn cli_bench_test() {
+    let dir = temp_dir("bench");
+    let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+    let input = dir.join("input.txt");
+    fs::write(&input, &bytes[..20000]).unwrap();
+
+    let output = run(&["bench", "--input", path_str(&input)]);
+    assert!(output.status.success(), "Benchmarking failed [{}]", String::from_utf8_lossy(&output.stderr));
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    println!("{stdout}");
+    assert!(stdout.contains("Encode MB/s") && stdout.contains("Decode MB/s"), "Benchmark didn't print the table header [{stdout}]");
+    for codec in ["huffman", "lzw", "lz77", "deflate"] {
+        assert!(stdout.lines().any(|line| line.starts_with(codec)), "Benchmark didn't print a row for {codec} [{stdout}]");
+    }
+
+    assert!(!run(&["bench", "--input", path_str(&dir.join("missing.txt"))]).status.success(), "Benchmarking a missing file succeeded");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn cli_verify_test() {
+    let dir = temp_dir("verify");
+    let bytes = fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+    let input = dir.join("input.txt");
+    fs::write(&input, &bytes[..20000]).unwrap();
+
+    for codec in ["huffman", "lzw", "lz77", "deflate"] {
+        let output = run(&["verify", "--codec", codec, "--input", path_str(&input)]);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(output.status.success(), "Verifying {codec} failed [{stderr}]");
+        assert!(stderr.contains("roundtrips") && stderr.contains("Ratio: ["), "Verifying {codec} didn't print the ratio [{stderr}]");
+    }
+
+    assert!(!run(&["verify", "--codec", "lz77", "--input", path_str(&dir.join("missing.txt"))]).status.success(), "Verifying a missing file succeeded");
+    assert!(!run(&["verify", "--input", path_str(&input)]).status.success(), "Verifying without a codec succeeded");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
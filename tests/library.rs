@@ -0,0 +1,34 @@
+use lz_compression::{compress, decompress, Codec, BitWriter, BitReader, HuffmanEncoder, HuffmanDecoder, compress_lzw, decompress_lzw, lz77_compress_simple, lz77_decompress};
+
+#[test]
+fn library_test() {
+    let bytes = b"Lorem ipsum dolor sit amet, consectetur adipiscing elit. Lorem ipsum dolor sit amet.".to_vec();
+
+    for codec in Codec::ALL {
+        let compressed = compress(codec, &bytes);
+        assert!(decompress(codec, &compressed) == Ok(bytes.clone()), "Bytes different after compressing and decompressing with {codec:?}");
+    }
+
+    let mut writer = BitWriter::new();
+    HuffmanEncoder::new(256).encode_all_bytes(&bytes, 1 << 10, &mut writer);
+    let encoded = writer.get_bytes();
+    assert!(HuffmanDecoder::new().decode_all_bytes(&mut BitReader::new(&encoded)) == Ok(bytes.clone()), "Bytes different after Huffman encoding and decoding");
+
+    assert!(decompress_lzw(&compress_lzw(&bytes)) == Ok(bytes.clone()), "Bytes different after LZW compression and decompression");
+    assert!(lz77_decompress(lz77_compress_simple(&bytes, 0xFFFF, 3, 256, 16, 256)).unwrap() == bytes, "Bytes different after LZ77 compression and decompression");
+}
+
+#[test]
+fn fuzz_seeds_test() {
+    let contents = std::fs::read("lorem_ipsum").expect("File could not be opened and/or read");
+
+    //The seeds have to be valid streams, so they need making again whenever the format changes
+    for (name, len) in [("lorem_ipsum_small", 256), ("lorem_ipsum_medium", 4096)] {
+        let huffman = std::fs::read(format!("fuzz/corpus/huffman_decode_all/{name}")).expect("Seed could not be opened and/or read");
+        let decoded = HuffmanDecoder::new().decode_all_bytes(&mut BitReader::new(&huffman));
+        assert!(decoded.as_deref() == Ok(&contents[..len]), "Huffman seed [{name}] isn't a valid stream [{:?}]", decoded.map(|bytes| bytes.len()));
+
+        let lzw = std::fs::read(format!("fuzz/corpus/decompress_lzw/{name}")).expect("Seed could not be opened and/or read");
+        assert!(decompress_lzw(&lzw).as_deref() == Ok(&contents[..len]), "LZW seed [{name}] isn't a valid stream");
+    }
+}
@@ -0,0 +1,37 @@
+//! Uses the library the way a `no_std` crate would, with only `alloc`. Run it
+//! with `cargo test --no-default-features --test no_std` to check that the
+//! codecs still work with the `std` feature off.
+
+#![no_std]
+
+extern crate alloc;
+//The test harness itself still needs `std`
+extern crate std;
+
+use alloc::vec::Vec;
+use lz_compression::{compress, decompress, Codec, BitWriter, BitReader, HuffmanEncoder, HuffmanDecoder, compress_lzw, decompress_lzw, lz77_compress_simple, lz77_decompress, zlib_compress, zlib_decompress};
+
+#[test]
+fn no_std_test() {
+    //Made in memory, since there's no file system to read from
+    let mut bytes = Vec::new();
+    for i in 0..4096u32 {
+        bytes.extend_from_slice(b"Lorem ipsum dolor sit amet, ");
+        bytes.push((i.wrapping_mul(2654435761) >> 24) as u8);
+    }
+
+    for codec in Codec::ALL {
+        let compressed = compress(codec, &bytes);
+        assert!(compressed.len() < bytes.len(), "{codec:?} didn't compress [{}] -> [{}]", bytes.len(), compressed.len());
+        assert!(decompress(codec, &compressed) == Ok(bytes.clone()), "Bytes different after compressing and decompressing with {codec:?}");
+    }
+
+    let mut writer = BitWriter::new();
+    HuffmanEncoder::new(256).encode_all_bytes(&bytes, 1 << 12, &mut writer);
+    let encoded = writer.get_bytes();
+    assert!(HuffmanDecoder::new().decode_all_bytes(&mut BitReader::new(&encoded)) == Ok(bytes.clone()), "Bytes different after Huffman encoding and decoding");
+
+    assert!(decompress_lzw(&compress_lzw(&bytes)) == Ok(bytes.clone()), "Bytes different after LZW compression and decompression");
+    assert!(lz77_decompress(lz77_compress_simple(&bytes, 0xFFFF, 3, 256, 16, 256)).unwrap() == bytes, "Bytes different after LZ77 compression and decompression");
+    assert!(zlib_decompress(&zlib_compress(&bytes)) == Ok(bytes.clone()), "Bytes different after zlib compression and decompression");
+}